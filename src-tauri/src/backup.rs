@@ -0,0 +1,591 @@
+use crate::database::{self, Database, Settings};
+use crate::encryption;
+use crate::error::{AppError, AppResult};
+use crate::migrations;
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use chrono::{Datelike, NaiveDateTime};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+// Exports start with this line, then the manifest as one line of JSON, then
+// the SQLite file itself. Plain SQLite files are still accepted on import.
+const BACKUP_MAGIC: &[u8] = b"RMS-BACKUP\n";
+const BACKUP_FORMAT: u32 = 1;
+const MAX_MANIFEST_LEN: u64 = 64 * 1024;
+
+const SNAPSHOT_PAGES_PER_STEP: i32 = 256;
+const COPY_CHUNK_LEN: usize = 256 * 1024;
+const PROGRESS_STEP: u64 = 1024 * 1024;
+
+// Automatic snapshots are named restaurant-YYYYMMDD-HHMMSS.rmsbackup in local
+// time. They are exports rather than plain SQLite files, so they don't get a
+// .db name that invites opening them as a database.
+const SNAPSHOT_PREFIX: &str = "restaurant-";
+const SNAPSHOT_SUFFIX: &str = ".rmsbackup";
+const SNAPSHOT_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+const DEFAULT_KEEP_DAILY: i64 = 7;
+const DEFAULT_KEEP_WEEKLY: i64 = 4;
+
+// Tables every restaurant database has had since the first release
+const REQUIRED_TABLES: &[&str] = &["settings", "products", "sales", "sale_items"];
+
+// A scratch file that is deleted when dropped, unless it was moved into place
+struct StagedFile(PathBuf);
+
+impl Drop for StagedFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+        remove_sidecars(&self.0);
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format: u32,
+    pub app_version: String,
+    pub schema_version: i64,
+    pub created_at: String,
+    pub record_counts: BTreeMap<String, i64>,
+    pub size: u64,
+    pub sha256: String,
+}
+
+// Reported to the frontend while a backup is written or read
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupStage {
+    Snapshot,
+    Writing,
+    Reading,
+    Verifying,
+    Replacing,
+    Done,
+}
+
+// Called with the current stage, the bytes processed so far and the total
+// expected for that stage (0 when it isn't known)
+pub type ProgressFn<'a> = &'a mut dyn FnMut(BackupStage, u64, u64);
+
+#[derive(Debug, Serialize)]
+pub struct BackupSnapshot {
+    pub file_name: String,
+    pub created_at: String,
+    pub size: u64,
+}
+
+fn sidecar_path(db_path: &Path, suffix: &str) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn remove_sidecars(db_path: &Path) {
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let _ = fs::remove_file(sidecar_path(db_path, suffix));
+    }
+}
+
+fn staging_path(db_path: &Path, purpose: &str) -> PathBuf {
+    let stamp = chrono::Local::now().format("%Y%m%d%H%M%S%3f");
+    db_path.with_extension(format!("db.{}-{}", purpose, stamp))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Passes reads through, reporting the running byte count every
+// PROGRESS_STEP bytes and once more at the end of the stream
+struct ProgressReader<'a, R> {
+    inner: R,
+    stage: BackupStage,
+    done: u64,
+    total: u64,
+    reported: u64,
+    progress: ProgressFn<'a>,
+}
+
+impl<'a, R: Read> ProgressReader<'a, R> {
+    fn new(inner: R, stage: BackupStage, total: u64, progress: ProgressFn<'a>) -> Self {
+        progress(stage, 0, total);
+        ProgressReader {
+            inner,
+            stage,
+            done: 0,
+            total,
+            reported: 0,
+            progress,
+        }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.done += n as u64;
+        if (n == 0 && self.reported != self.done) || self.done - self.reported >= PROGRESS_STEP {
+            self.reported = self.done;
+            (self.progress)(self.stage, self.done, self.total);
+        }
+        Ok(n)
+    }
+}
+
+// Writes a consistent copy of the live database to `dest` while the app keeps
+// running. `compact` uses VACUUM INTO, which also drops free pages; otherwise
+// the online backup API copies the pages as they are. Either way the copy
+// includes anything still sitting in the WAL.
+fn write_snapshot(conn: &Connection, dest: &Path, compact: bool, progress: ProgressFn) -> AppResult<()> {
+    progress(BackupStage::Snapshot, 0, 0);
+    if compact {
+        conn.execute("VACUUM INTO ?1", [dest.to_string_lossy()])?;
+    } else {
+        // Sales committed on the shared connection would restart a page copy
+        // taken step by step; a read transaction held throughout keeps this
+        // one on the version it started from, which WAL leaves in place
+        conn.execute_batch("BEGIN")?;
+        conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))?;
+        let page_size: u64 = conn.pragma_query_value(None, "page_size", |row| row.get(0))?;
+        let mut dest_conn = Connection::open(dest)?;
+        let backup = Backup::new(conn, &mut dest_conn)?;
+        loop {
+            match backup.step(SNAPSHOT_PAGES_PER_STEP)? {
+                StepResult::Done => break,
+                StepResult::More => {}
+                _ => thread::sleep(Duration::from_millis(5)),
+            }
+            let pages = backup.progress();
+            let total = pages.pagecount.max(0) as u64 * page_size;
+            let remaining = pages.remaining.max(0) as u64 * page_size;
+            progress(BackupStage::Snapshot, total - remaining, total);
+        }
+        drop(backup);
+        conn.execute_batch("COMMIT")?;
+    }
+
+    // The copy inherits WAL mode from the live file; make it self-contained
+    let dest_conn = Connection::open(dest)?;
+    dest_conn.pragma_update_and_check(None, "journal_mode", "DELETE", |row| row.get::<_, String>(0))?;
+    Ok(())
+}
+
+fn record_counts(conn: &Connection) -> rusqlite::Result<BTreeMap<String, i64>> {
+    let tables = conn
+        .prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut counts = BTreeMap::new();
+    for table in tables {
+        let count = conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| row.get(0))?;
+        counts.insert(table, count);
+    }
+    Ok(counts)
+}
+
+fn snapshot_manifest(snapshot: &Path) -> AppResult<BackupManifest> {
+    let conn = Connection::open_with_flags(snapshot, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let schema_version = migrations::current_version(&conn)?;
+    let record_counts = record_counts(&conn)?;
+    drop(conn);
+
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut File::open(snapshot)?, &mut hasher)?;
+    Ok(BackupManifest {
+        format: BACKUP_FORMAT,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version,
+        created_at: chrono::Local::now().to_rfc3339(),
+        record_counts,
+        size,
+        sha256: hex(&hasher.finalize()),
+    })
+}
+
+// A read-only connection of its own for taking a snapshot, so sales and
+// lookups on the shared connection carry on meanwhile. It is opened while
+// holding the shared lock, so never halfway through an import replacing the
+// file.
+fn open_snapshot_source(db: &Database) -> AppResult<Connection> {
+    let _live = db.conn();
+    let conn = Connection::open_with_flags(
+        db.path(),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    conn.busy_timeout(Duration::from_secs(5))?;
+    Ok(conn)
+}
+
+// Streams a backup of the live database into `out`: a snapshot with a
+// manifest describing where it came from and how to verify it. With a
+// password the whole stream, manifest included, is encrypted. The snapshot
+// is read through its own connection, so the app is never locked by it.
+pub fn export_to_writer(
+    db: &Database,
+    out: impl Write,
+    compact: bool,
+    password: Option<&str>,
+    progress: ProgressFn,
+) -> AppResult<()> {
+    if let Some(password) = password {
+        encryption::check_password(password)?;
+    }
+
+    let snapshot = StagedFile(staging_path(db.path(), "export"));
+    write_snapshot(&open_snapshot_source(db)?, &snapshot.0, compact, progress)?;
+    let manifest = snapshot_manifest(&snapshot.0)?;
+
+    let mut header = BACKUP_MAGIC.to_vec();
+    serde_json::to_writer(&mut header, &manifest)
+        .map_err(|e| AppError::validation(format!("Could not write backup manifest: {}", e)))?;
+    header.push(b'\n');
+
+    let total = header.len() as u64 + manifest.size;
+    let body = header.as_slice().chain(File::open(&snapshot.0)?);
+    let mut reader = ProgressReader::new(body, BackupStage::Writing, total, progress);
+    match password {
+        Some(password) => encryption::encrypt(reader, out, password),
+        None => {
+            let mut out = out;
+            io::copy(&mut reader, &mut out)?;
+            out.flush()?;
+            Ok(())
+        }
+    }
+}
+
+// Exports to `dest`, writing to a temporary file next to it first so an
+// interrupted export never leaves a truncated backup under the chosen name.
+pub fn export_to_file(
+    db: &Database,
+    dest: &Path,
+    compact: bool,
+    password: Option<&str>,
+    progress: ProgressFn,
+) -> AppResult<()> {
+    let partial = StagedFile(sidecar_path(dest, ".partial"));
+    let mut out = BufWriter::new(File::create(&partial.0)?);
+    export_to_writer(db, &mut out, compact, password, progress)?;
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    fs::rename(&partial.0, dest)?;
+    progress(BackupStage::Done, 0, 0);
+    Ok(())
+}
+
+// Copies a backup stream (without encryption) to `dest` as a plain SQLite
+// file, checking it against its manifest on the way. Plain SQLite files have
+// no manifest and are copied as they are.
+fn unpack_backup(mut input: impl BufRead, dest: &Path) -> AppResult<()> {
+    let mut out = BufWriter::new(File::create(dest)?);
+    if !input.fill_buf()?.starts_with(BACKUP_MAGIC) {
+        io::copy(&mut input, &mut out)?;
+        out.flush()?;
+        return Ok(());
+    }
+
+    let corrupt = |reason: &str| {
+        AppError::validation_with("The backup file is damaged", json!({ "reason": reason }))
+    };
+
+    input.consume(BACKUP_MAGIC.len());
+    let mut line = Vec::new();
+    input
+        .by_ref()
+        .take(MAX_MANIFEST_LEN)
+        .read_until(b'\n', &mut line)?;
+    if line.pop() != Some(b'\n') {
+        return Err(corrupt("missing manifest"));
+    }
+    let manifest: BackupManifest =
+        serde_json::from_slice(&line).map_err(|_| corrupt("unreadable manifest"))?;
+
+    if manifest.format > BACKUP_FORMAT {
+        return Err(AppError::Conflict {
+            message: "This backup was made by a newer version of the app. Please update the app."
+                .to_string(),
+            details: Some(json!({ "format": manifest.format, "app_version": manifest.app_version })),
+        });
+    }
+
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; COPY_CHUNK_LEN];
+    let mut size = 0u64;
+    loop {
+        let n = input.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        out.write_all(&buf[..n])?;
+        size += n as u64;
+    }
+    out.flush()?;
+
+    if size != manifest.size {
+        return Err(corrupt("size does not match the manifest"));
+    }
+    if hex(&hasher.finalize()) != manifest.sha256 {
+        return Err(corrupt("checksum does not match the manifest"));
+    }
+    Ok(())
+}
+
+// Replaces the live database with the backup read from `input`. The file is
+// staged next to the live one, checked and migrated there, and only then
+// swapped in. If the swap fails the previous database is put back; it is
+// also kept as `restaurant.db.backup`. `total` is the input size if known,
+// for progress reporting.
+pub fn import_from_reader(
+    db: &Database,
+    input: impl Read,
+    total: u64,
+    password: Option<&str>,
+    progress: ProgressFn,
+) -> AppResult<()> {
+    let staged = StagedFile(staging_path(db.path(), "import"));
+    {
+        let reader = ProgressReader::new(input, BackupStage::Reading, total, &mut *progress);
+        let mut input = BufReader::with_capacity(COPY_CHUNK_LEN, reader);
+        if encryption::is_encrypted(input.fill_buf()?) {
+            let password = password.ok_or_else(encryption::password_required)?;
+            let decrypted = StagedFile(staging_path(db.path(), "decrypt"));
+            let mut plain = BufWriter::new(File::create(&decrypted.0)?);
+            encryption::decrypt(input, &mut plain, password)?;
+            drop(plain);
+            unpack_backup(BufReader::new(File::open(&decrypted.0)?), &staged.0)?;
+        } else {
+            unpack_backup(input, &staged.0)?;
+        }
+    }
+
+    progress(BackupStage::Verifying, 0, 0);
+    let mut header = [0u8; 100];
+    let is_sqlite = File::open(&staged.0)?.read_exact(&mut header).is_ok()
+        && header.starts_with(SQLITE_HEADER);
+    if !is_sqlite {
+        return Err(AppError::validation(
+            "The selected file is not a SQLite database",
+        ));
+    }
+    prepare_import(&staged.0)?;
+
+    progress(BackupStage::Replacing, 0, 0);
+    swap_in(db, &staged)?;
+    progress(BackupStage::Done, 0, 0);
+    Ok(())
+}
+
+pub fn import_from_file(
+    db: &Database,
+    src: &Path,
+    password: Option<&str>,
+    progress: ProgressFn,
+) -> AppResult<()> {
+    let file = File::open(src)?;
+    let total = file.metadata()?.len();
+    import_from_reader(db, file, total, password, progress)
+}
+
+// Checks the staged copy and brings its schema up to the current version
+fn prepare_import(path: &Path) -> AppResult<()> {
+    let mut conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+
+    let problems = conn
+        .prepare("PRAGMA integrity_check")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if problems != ["ok"] {
+        return Err(AppError::validation_with(
+            "The selected database is damaged and cannot be imported",
+            json!({ "problems": problems.iter().take(10).collect::<Vec<_>>() }),
+        ));
+    }
+
+    let mut stmt = conn.prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1")?;
+    let missing: Vec<&str> = REQUIRED_TABLES
+        .iter()
+        .copied()
+        .filter(|table| !stmt.exists([table]).unwrap_or(false))
+        .collect();
+    drop(stmt);
+    if !missing.is_empty() {
+        return Err(AppError::validation_with(
+            "The selected database is not a Restaurant Management System database",
+            json!({ "missing_tables": missing }),
+        ));
+    }
+
+    migrations::run(&mut conn)?;
+
+    // Leave a single self-contained file behind, whatever mode it was saved in
+    conn.pragma_update_and_check(None, "journal_mode", "DELETE", |row| row.get::<_, String>(0))?;
+    conn.close().map_err(|(_, e)| e)?;
+    Ok(())
+}
+
+fn swap_in(db: &Database, staged: &StagedFile) -> AppResult<()> {
+    let db_path = db.path();
+    let backup_path = db_path.with_extension("db.backup");
+    let mut conn = db.conn();
+
+    // Release the live file (and its WAL) before replacing it. Other commands
+    // wait on the lock we hold until the new connection is in place.
+    database::checkpoint(&conn)?;
+    let old = std::mem::replace(&mut *conn, Connection::open_in_memory()?);
+    if let Err((old, e)) = old.close() {
+        *conn = old;
+        return Err(e.into());
+    }
+
+    let mut replaced = false;
+    let result = (|| -> AppResult<Connection> {
+        fs::copy(db_path, &backup_path)?;
+        remove_sidecars(db_path);
+        fs::rename(&staged.0, db_path)?;
+        replaced = true;
+        reopen(db_path)
+    })();
+
+    match result {
+        Ok(new_conn) => {
+            *conn = new_conn;
+            Ok(())
+        }
+        Err(e) => {
+            if replaced {
+                remove_sidecars(db_path);
+                fs::rename(&backup_path, db_path)?;
+            }
+            *conn = reopen(db_path)?;
+            Err(e)
+        }
+    }
+}
+
+fn reopen(db_path: &Path) -> AppResult<Connection> {
+    let mut conn = database::open_connection(db_path)?;
+    migrations::run(&mut conn)?;
+    Ok(conn)
+}
+
+// Where automatic snapshots go: the configured directory, or a `backups`
+// folder next to the database
+pub fn backup_directory(db_path: &Path, settings: &Settings) -> PathBuf {
+    match settings.backup_directory.as_deref().map(str::trim) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => db_path.with_file_name("backups"),
+    }
+}
+
+fn snapshot_time(file_name: &str) -> Option<NaiveDateTime> {
+    let stamp = file_name
+        .strip_prefix(SNAPSHOT_PREFIX)?
+        .strip_suffix(SNAPSHOT_SUFFIX)?;
+    NaiveDateTime::parse_from_str(stamp, SNAPSHOT_TIME_FORMAT).ok()
+}
+
+// Snapshots in `dir`, newest first. Files we didn't name are left alone.
+fn snapshots(dir: &Path) -> AppResult<Vec<(NaiveDateTime, PathBuf)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut found = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if let Some(time) = path.file_name().and_then(|n| n.to_str()).and_then(snapshot_time) {
+            found.push((time, path));
+        }
+    }
+    found.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+    Ok(found)
+}
+
+pub fn latest_snapshot_time(dir: &Path) -> AppResult<Option<NaiveDateTime>> {
+    Ok(snapshots(dir)?.first().map(|(time, _)| *time))
+}
+
+pub fn list_snapshots(dir: &Path) -> AppResult<Vec<BackupSnapshot>> {
+    snapshots(dir)?
+        .into_iter()
+        .map(|(time, path)| {
+            Ok(BackupSnapshot {
+                file_name: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+                created_at: time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                size: fs::metadata(&path)?.len(),
+            })
+        })
+        .collect()
+}
+
+// Writes a new snapshot in the export format, so it can also be imported by
+// hand.
+pub fn create_snapshot(db: &Database, dir: &Path) -> AppResult<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let name = format!(
+        "{}{}{}",
+        SNAPSHOT_PREFIX,
+        chrono::Local::now().format(SNAPSHOT_TIME_FORMAT),
+        SNAPSHOT_SUFFIX
+    );
+    let path = dir.join(name);
+    export_to_file(db, &path, true, None, &mut |_, _, _| {})?;
+    Ok(path)
+}
+
+// Keeps the newest snapshot of each of the last `keep_daily` days and of each
+// of the last `keep_weekly` ISO weeks that have snapshots, plus the newest
+// snapshot overall. Returns how many were deleted.
+pub fn prune_snapshots(dir: &Path, settings: &Settings) -> AppResult<usize> {
+    let keep_daily = settings.backup_keep_daily.unwrap_or(DEFAULT_KEEP_DAILY).max(0) as usize;
+    let keep_weekly = settings.backup_keep_weekly.unwrap_or(DEFAULT_KEEP_WEEKLY).max(0) as usize;
+
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut removed = 0;
+    for (index, (time, path)) in snapshots(dir)?.into_iter().enumerate() {
+        let day = time.date();
+        let week = (day.iso_week().year(), day.iso_week().week());
+        let mut keep = index == 0;
+        if days.len() < keep_daily && days.insert(day) {
+            keep = true;
+        }
+        if weeks.len() < keep_weekly && weeks.insert(week) {
+            keep = true;
+        }
+        if !keep {
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+pub fn restore_snapshot(
+    db: &Database,
+    dir: &Path,
+    file_name: &str,
+    progress: ProgressFn,
+) -> AppResult<()> {
+    // Only names we listed ourselves, so this can't be used to read other files
+    let (_, path) = snapshots(dir)?
+        .into_iter()
+        .find(|(_, path)| path.file_name().and_then(|n| n.to_str()) == Some(file_name))
+        .ok_or_else(|| AppError::not_found(format!("Backup {} not found", file_name)))?;
+    import_from_file(db, &path, None, progress)
+}
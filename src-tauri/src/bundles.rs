@@ -0,0 +1,320 @@
+use crate::error::{AppError, AppResult};
+use rusqlite::{Connection, OptionalExtension, Result, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BundleProduct {
+    pub product_id: i64,
+    pub name: String,
+}
+
+// One slot of a bundle, e.g. "1 × Fries", optionally swappable for one of
+// `substitutes`. `quantity` is per bundle sold.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BundleComponent {
+    pub id: i64,
+    pub product_id: i64,
+    pub name: String,
+    pub quantity: f64,
+    pub substitutes: Vec<BundleProduct>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewBundleComponent {
+    pub product_id: i64,
+    pub quantity: f64,
+    #[serde(default)]
+    pub substitutes: Vec<i64>,
+}
+
+// Serves `product_id` instead of the default product of slot `component_id`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Substitution {
+    pub component_id: i64,
+    pub product_id: i64,
+}
+
+// A product served as part of a bundle line. `quantity` covers the whole
+// line; `substituted_for` is the slot's default product when it was swapped.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LineComponent {
+    pub product_id: Option<i64>,
+    pub name: String,
+    pub quantity: f64,
+    pub substituted_for: Option<i64>,
+}
+
+// The slots of a bundle in display order; empty for ordinary products
+pub fn get_bundle_components(conn: &Connection, bundle_id: i64) -> Result<Vec<BundleComponent>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT c.id, c.product_id, p.name, c.quantity FROM bundle_components c
+         JOIN products p ON p.id = c.product_id
+         WHERE c.bundle_id = ?1 ORDER BY c.sort_order, c.id",
+    )?;
+    let components = stmt
+        .query_map([bundle_id], |row| {
+            Ok(BundleComponent {
+                id: row.get(0)?,
+                product_id: row.get(1)?,
+                name: row.get(2)?,
+                quantity: row.get(3)?,
+                substitutes: Vec::new(),
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut substitutes_stmt = conn.prepare_cached(
+        "SELECT s.product_id, p.name FROM bundle_substitutes s
+         JOIN products p ON p.id = s.product_id
+         WHERE s.component_id = ?1 ORDER BY p.name COLLATE NOCASE",
+    )?;
+    components
+        .into_iter()
+        .map(|component| {
+            let substitutes = substitutes_stmt
+                .query_map([component.id], |row| {
+                    Ok(BundleProduct {
+                        product_id: row.get(0)?,
+                        name: row.get(1)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>>>()?;
+            Ok(BundleComponent {
+                substitutes,
+                ..component
+            })
+        })
+        .collect()
+}
+
+fn is_bundle(conn: &Connection, product_id: i64) -> Result<bool> {
+    conn.prepare_cached("SELECT EXISTS (SELECT 1 FROM bundle_components WHERE bundle_id = ?1)")?
+        .query_row([product_id], |row| row.get(0))
+}
+
+// Names of the bundles a product is served in, as a default or a substitute
+pub fn bundles_using(conn: &Connection, product_id: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT DISTINCT p.name FROM bundle_components c
+         JOIN products p ON p.id = c.bundle_id
+         LEFT JOIN bundle_substitutes s ON s.component_id = c.id
+         WHERE c.product_id = ?1 OR s.product_id = ?1
+         ORDER BY p.name COLLATE NOCASE",
+    )?;
+    let names = stmt
+        .query_map([product_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(names)
+}
+
+// A product that can fill a slot: it must exist, and bundles don't nest
+fn check_component_product(tx: &Transaction, index: usize, bundle_id: i64, product_id: i64) -> AppResult<()> {
+    let exists: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM products WHERE id = ?1)",
+        [product_id],
+        |row| row.get(0),
+    )?;
+    let reason = if !exists {
+        "product does not exist"
+    } else if product_id == bundle_id {
+        "a bundle cannot contain itself"
+    } else if is_bundle(tx, product_id)? {
+        "a bundle cannot contain another bundle"
+    } else {
+        return Ok(());
+    };
+    Err(AppError::validation_with(
+        format!("Component #{}: {}", index + 1, reason),
+        json!({ "component": index, "product_id": product_id }),
+    ))
+}
+
+// Replaces the components of a product. An empty list turns it back into an
+// ordinary product. Past sales keep the components they were sold with.
+pub fn set_bundle_components(
+    conn: &mut Connection,
+    bundle_id: i64,
+    components: &[NewBundleComponent],
+) -> AppResult<Vec<BundleComponent>> {
+    let tx = conn.transaction()?;
+    let name: String = tx
+        .query_row("SELECT name FROM products WHERE id = ?1", [bundle_id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Product {} not found", bundle_id)))?;
+
+    if !components.is_empty() {
+        let used_in = bundles_using(&tx, bundle_id)?;
+        if !used_in.is_empty() {
+            return Err(AppError::validation_with(
+                format!(
+                    "{} is part of {}, so it cannot be a bundle itself",
+                    name,
+                    used_in.join(", ")
+                ),
+                json!({ "bundles": used_in }),
+            ));
+        }
+    }
+    for (index, component) in components.iter().enumerate() {
+        if !component.quantity.is_finite() || component.quantity <= 0.0 {
+            return Err(AppError::validation_with(
+                format!("Component #{}: quantity must be greater than zero", index + 1),
+                json!({ "component": index }),
+            ));
+        }
+        check_component_product(&tx, index, bundle_id, component.product_id)?;
+        for &substitute in &component.substitutes {
+            check_component_product(&tx, index, bundle_id, substitute)?;
+        }
+    }
+
+    tx.execute("DELETE FROM bundle_components WHERE bundle_id = ?1", [bundle_id])?;
+    {
+        let mut insert_component = tx.prepare_cached(
+            "INSERT INTO bundle_components (bundle_id, product_id, quantity, sort_order)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        let mut insert_substitute = tx.prepare_cached(
+            "INSERT OR IGNORE INTO bundle_substitutes (component_id, product_id) VALUES (?1, ?2)",
+        )?;
+        for (sort_order, component) in components.iter().enumerate() {
+            insert_component.execute((
+                bundle_id,
+                component.product_id,
+                component.quantity,
+                sort_order as i64,
+            ))?;
+            let component_id = tx.last_insert_rowid();
+            for &substitute in &component.substitutes {
+                if substitute != component.product_id {
+                    insert_substitute.execute((component_id, substitute))?;
+                }
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(get_bundle_components(conn, bundle_id)?)
+}
+
+// Works out what is served for sale line `index`: each slot's default
+// product, or the substitute chosen for it. Returns nothing for ordinary
+// products, which may not carry substitutions.
+pub fn resolve_line_components(
+    conn: &Connection,
+    index: usize,
+    product_id: Option<i64>,
+    quantity: f64,
+    substitutions: &[Substitution],
+) -> AppResult<Vec<LineComponent>> {
+    let invalid = |reason: String, details: serde_json::Value| {
+        AppError::validation_with(format!("Invalid item #{}: {}", index + 1, reason), details)
+    };
+
+    let components = match product_id {
+        Some(product_id) => get_bundle_components(conn, product_id)?,
+        None => Vec::new(),
+    };
+    if components.is_empty() {
+        if substitutions.is_empty() {
+            return Ok(Vec::new());
+        }
+        return Err(invalid(
+            "substitutions can only be made on a bundle".to_string(),
+            json!({ "item": index }),
+        ));
+    }
+
+    for (position, substitution) in substitutions.iter().enumerate() {
+        if substitutions[..position]
+            .iter()
+            .any(|s| s.component_id == substitution.component_id)
+        {
+            return Err(invalid(
+                "the same component was substituted twice".to_string(),
+                json!({ "item": index, "component_id": substitution.component_id }),
+            ));
+        }
+        let Some(component) = components.iter().find(|c| c.id == substitution.component_id) else {
+            return Err(invalid(
+                format!("component {} is not part of this bundle", substitution.component_id),
+                json!({ "item": index, "component_id": substitution.component_id }),
+            ));
+        };
+        if substitution.product_id != component.product_id
+            && !component
+                .substitutes
+                .iter()
+                .any(|s| s.product_id == substitution.product_id)
+        {
+            return Err(invalid(
+                format!("{} cannot be swapped for product {}", component.name, substitution.product_id),
+                json!({ "item": index, "component_id": component.id, "product_id": substitution.product_id }),
+            ));
+        }
+    }
+
+    let mut on_menu = conn.prepare_cached("SELECT archived_at IS NULL FROM products WHERE id = ?1")?;
+    let mut served = Vec::with_capacity(components.len());
+    for component in &components {
+        let (chosen, name) = match substitutions.iter().find(|s| s.component_id == component.id) {
+            Some(s) if s.product_id != component.product_id => {
+                let substitute = component
+                    .substitutes
+                    .iter()
+                    .find(|p| p.product_id == s.product_id)
+                    .map_or(String::new(), |p| p.name.clone());
+                (s.product_id, substitute)
+            }
+            _ => (component.product_id, component.name.clone()),
+        };
+        if !on_menu.query_row([chosen], |row| row.get::<_, bool>(0))? {
+            return Err(invalid(
+                format!("{} is no longer on the menu", name),
+                json!({ "item": index, "component_id": component.id, "product_id": chosen }),
+            ));
+        }
+        served.push(LineComponent {
+            product_id: Some(chosen),
+            name,
+            quantity: component.quantity * quantity,
+            substituted_for: (chosen != component.product_id).then_some(component.product_id),
+        });
+    }
+    Ok(served)
+}
+
+pub fn record_line_components(tx: &Transaction, sale_item_id: i64, components: &[LineComponent]) -> Result<()> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO sale_item_components (sale_item_id, product_id, product_name, quantity, substituted_for)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for component in components {
+        stmt.execute((
+            sale_item_id,
+            component.product_id,
+            &component.name,
+            component.quantity,
+            component.substituted_for,
+        ))?;
+    }
+    Ok(())
+}
+
+pub fn get_line_components(conn: &Connection, sale_item_id: i64) -> Result<Vec<LineComponent>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT product_id, product_name, quantity, substituted_for FROM sale_item_components
+         WHERE sale_item_id = ?1 ORDER BY id",
+    )?;
+    let components = stmt
+        .query_map([sale_item_id], |row| {
+            Ok(LineComponent {
+                product_id: row.get(0)?,
+                name: row.get(1)?,
+                quantity: row.get(2)?,
+                substituted_for: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(components)
+}
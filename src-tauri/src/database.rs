@@ -1,21 +1,73 @@
-use rusqlite::{Connection, Result};
+use crate::error::{AppError, AppResult};
+use crate::migrations::{self, MigrationError};
+use crate::bundles::{self, LineComponent, Substitution};
+use crate::discounts::{self, AppliedCoupon, AppliedDiscount, DiscountKind, NewDiscount};
+use crate::inventory::{self, Ingredient};
+use crate::modifiers::{self, LineModifier};
+use crate::money::Money;
+use crate::purchasing;
+use crate::tax::{self, TaxBreakdown, TaxClass, TaxRates};
+use crate::units::{self, Unit};
+use serde_json::json;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use rusqlite::{Connection, OptionalExtension, Params, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
 use tauri::{AppHandle, Manager};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Product {
     pub id: i64,
     pub name: String,
-    pub price: f64,
+    pub price: Money,
     pub unit: String,
+    pub category_id: Option<i64>,
+    pub tax_class: TaxClass,
+    pub archived_at: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NewProduct {
     pub name: String,
-    pub price: f64,
+    pub price: Money,
     pub unit: String,
+    pub category_id: Option<i64>,
+    #[serde(default)]
+    pub tax_class: TaxClass,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Category {
+    pub id: i64,
+    pub name: String,
+    pub sort_order: i64,
+    pub color: Option<String>,
+    pub parent_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewCategory {
+    pub name: String,
+    pub sort_order: Option<i64>,
+    pub color: Option<String>,
+    pub parent_id: Option<i64>,
+}
+
+// One menu section and its products. `category` is None for the trailing
+// group of products that have no category.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryGroup {
+    pub category: Option<Category>,
+    pub products: Vec<Product>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PriceChange {
+    pub price: Money,
+    pub effective_from: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,73 +76,153 @@ pub struct Settings {
     pub address: Option<String>,
     pub phone: Option<String>,
     pub email: Option<String>,
+    // Standard and reduced tax rates, as percentages
     pub tax_rate: Option<String>,
+    pub reduced_tax_rate: Option<String>,
+    // Whether prices already include tax; they do unless this is false
+    pub prices_include_tax: Option<bool>,
     pub currency: Option<String>,
     pub opening_time: Option<String>,
     pub closing_time: Option<String>,
     pub receipt_footer: Option<String>,
     pub logo: Option<String>,
+    pub backup_directory: Option<String>,
+    pub backup_keep_daily: Option<i64>,
+    pub backup_keep_weekly: Option<i64>,
 }
 
+// A line of a sale. `quantity` is in `unit`, which for a product may be any
+// unit of the same kind as the product's own, e.g. 250 g of something sold
+// by the kilogram; `price` stays per the product's unit.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SaleItem {
+    pub product_id: Option<i64>,
     pub name: String,
-    pub price: f64,
+    pub price: Money,
     pub quantity: f64,
     pub unit: String,
+    // Options chosen from the product's modifier groups
+    #[serde(default)]
+    pub modifier_ids: Vec<i64>,
+    // Swaps made to the components of a bundle product
+    #[serde(default)]
+    pub substitutions: Vec<Substitution>,
+    #[serde(default)]
+    pub discount: Option<NewDiscount>,
 }
 
+// `discount` is taken off the whole order after any line discounts, then
+// the coupon named by `coupon_code` off what is left
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Sale {
     pub products: Vec<SaleItem>,
-    pub total_amount: f64,
-    pub currency: String,
+    pub total_amount: Money,
+    #[serde(default)]
+    pub discount: Option<NewDiscount>,
+    #[serde(default)]
+    pub coupon_code: Option<String>,
 }
 
+// A recorded sale, with the ingredients it took to or below their reorder
+// point
+#[derive(Debug)]
+pub struct SaleOutcome {
+    pub id: i64,
+    pub low_stock: Vec<Ingredient>,
+}
+
+// A recorded sale. `tax` is the tax on all of its lines, included in
+// `total_amount` either way: within the prices when `prices_include_tax`,
+// added on top of them otherwise. `tax_breakdown` splits it by class and
+// rate; sales from before tax was worked out have none. `discount` is
+// everything taken off its lines and the order, coupon included. A refund
+// names the sale it gives money back on in `refund_of`, and its amounts are
+// negative.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Transaction {
     pub id: i64,
+    pub refund_of: Option<i64>,
+    pub refund_reason: Option<String>,
     pub items: Vec<TransactionItem>,
-    pub total_amount: f64,
+    pub total_amount: Money,
+    pub discount: Money,
+    pub order_discount: Option<AppliedDiscount>,
+    pub coupon: Option<AppliedCoupon>,
+    pub tax: Money,
+    pub prices_include_tax: bool,
+    pub tax_breakdown: Vec<TaxBreakdown>,
     pub currency: String,
     pub date: String,
     pub time: String,
 }
 
+// `subtotal` is the line at its prices, before its own `discount` and its
+// `order_discount` share of the order's discounts; its tax is on what is
+// left. `tax_rate` is a percentage. `refunded_quantity` is how much of the
+// line later refunds have given back.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TransactionItem {
+    pub id: i64,
+    pub product_id: Option<i64>,
     pub name: String,
-    pub price: f64,
+    pub price: Money,
     pub quantity: f64,
     pub unit: String,
-    pub subtotal: f64,
+    pub modifiers: Vec<LineModifier>,
+    pub components: Vec<LineComponent>,
+    pub subtotal: Money,
+    pub discount: Option<AppliedDiscount>,
+    pub order_discount: Money,
+    pub tax_class: Option<TaxClass>,
+    pub tax_rate: Option<f64>,
+    pub tax: Money,
+    pub refunded_quantity: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DailyRevenue {
     pub date: String,
-    pub revenue: f64,
+    pub revenue: Money,
     pub orders: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TopProduct {
+    pub product_id: Option<i64>,
     pub name: String,
     pub sales: i64,
-    pub revenue: f64,
+    pub revenue: Money,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProductDistribution {
+    pub product_id: Option<i64>,
     pub name: String,
     pub value: i64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModifierUsage {
+    pub modifier_id: Option<i64>,
+    pub group: String,
+    pub name: String,
+    pub sales: i64,
+    pub revenue: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryRevenue {
+    pub category_id: Option<i64>,
+    pub name: String,
+    pub sales: i64,
+    pub revenue: Money,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AnalyticsSummary {
     pub total_orders: i64,
-    pub total_revenue: f64,
-    pub average_order_value: f64,
+    pub total_revenue: Money,
+    pub average_order_value: Money,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -98,6 +230,8 @@ pub struct AnalyticsData {
     pub daily_revenue: Vec<DailyRevenue>,
     pub top_products: Vec<TopProduct>,
     pub product_distribution: Vec<ProductDistribution>,
+    pub category_revenue: Vec<CategoryRevenue>,
+    pub top_modifiers: Vec<ModifierUsage>,
     pub summary: AnalyticsSummary,
 }
 
@@ -113,77 +247,65 @@ pub fn get_db_path(app: &AppHandle) -> Result<PathBuf, String> {
     Ok(app_data_dir.join("restaurant.db"))
 }
 
-pub fn initialize_database(db_path: &PathBuf) -> Result<()> {
+// Shared handle stored in Tauri managed state. Commands lock the connection
+// for the duration of one call instead of opening the file every time.
+pub struct Database {
+    path: PathBuf,
+    conn: Mutex<Connection>,
+}
+
+impl Database {
+    pub fn open(path: PathBuf) -> std::result::Result<Self, MigrationError> {
+        let mut conn = open_connection(&path)?;
+        migrations::run(&mut conn)?;
+        Ok(Database {
+            path,
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn conn(&self) -> MutexGuard<'_, Connection> {
+        // A panic in one command must not lock every later command out
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub fn open_connection(db_path: &Path) -> Result<Connection> {
     let conn = Connection::open(db_path)?;
-    
-    conn.execute_batch(
-        "
-        CREATE TABLE IF NOT EXISTS settings (
-            id INTEGER PRIMARY KEY CHECK (id = 1),
-            restaurant_name TEXT,
-            address TEXT,
-            phone TEXT,
-            email TEXT,
-            tax_rate TEXT,
-            currency TEXT,
-            opening_time TEXT,
-            closing_time TEXT,
-            receipt_footer TEXT,
-            logo TEXT
-        );
-
-        CREATE TABLE IF NOT EXISTS products (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            name TEXT NOT NULL,
-            price REAL NOT NULL,
-            unit TEXT NOT NULL DEFAULT 'item',
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        );
-
-        CREATE TABLE IF NOT EXISTS sales (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            total_amount REAL NOT NULL,
-            currency TEXT NOT NULL,
-            date TEXT DEFAULT (date('now')),
-            time TEXT DEFAULT (time('now')),
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        );
-
-        CREATE TABLE IF NOT EXISTS sale_items (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            sale_id INTEGER NOT NULL,
-            product_name TEXT NOT NULL,
-            price REAL NOT NULL,
-            quantity REAL NOT NULL,
-            unit TEXT NOT NULL,
-            FOREIGN KEY (sale_id) REFERENCES sales (id)
-        );
-
-        INSERT OR IGNORE INTO settings (
-            id, restaurant_name, currency, receipt_footer
-        ) VALUES (
-            1, 
-            'Restaurant Management System',
-            'PKR',
-            'Thank you for your business!'
-        );
-        "
-    )?;
-    
-    Ok(())
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    conn.pragma_update(None, "foreign_keys", true)?;
+    conn.busy_timeout(Duration::from_secs(5))?;
+    conn.set_prepared_statement_cache_capacity(64);
+    Ok(conn)
+}
+
+// Folds the WAL back into the main file so it can be copied on its own
+pub fn checkpoint(conn: &Connection) -> Result<()> {
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
 }
 
 // Product operations
-pub fn get_products(db_path: &PathBuf) -> Result<Vec<Product>> {
-    let conn = Connection::open(db_path)?;
-    let mut stmt = conn.prepare("SELECT id, name, price, unit FROM products ORDER BY id DESC")?;
+// Archived products are left out unless `include_archived` is set
+pub fn get_products(conn: &Connection, include_archived: bool) -> Result<Vec<Product>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, name, price, currency, unit, category_id, archived_at, tax_class FROM products
+         WHERE ?1 OR archived_at IS NULL ORDER BY id DESC"
+    )?;
     
-    let products = stmt.query_map([], |row| {
+    let products = stmt.query_map([include_archived], |row| {
         Ok(Product {
             id: row.get(0)?,
             name: row.get(1)?,
-            price: row.get(2)?,
-            unit: row.get(3)?,
+            price: Money::new(row.get(2)?, row.get::<_, String>(3)?),
+            unit: row.get(4)?,
+            category_id: row.get(5)?,
+            tax_class: TaxClass::from_column(&row.get::<_, String>(7)?),
+            archived_at: row.get(6)?,
         })
     })?
     .collect::<Result<Vec<_>>>()?;
@@ -191,34 +313,434 @@ pub fn get_products(db_path: &PathBuf) -> Result<Vec<Product>> {
     Ok(products)
 }
 
-pub fn add_product(db_path: &PathBuf, product: NewProduct) -> Result<Product> {
-    let conn = Connection::open(db_path)?;
-    conn.execute(
-        "INSERT INTO products (name, price, unit) VALUES (?1, ?2, ?3)",
-        (&product.name, &product.price, &product.unit),
+// Products split into menu sections, in the same order as `get_categories`.
+// Every category gets a group, even an empty one, so tabs stay stable.
+pub fn get_products_by_category(conn: &Connection, include_archived: bool) -> Result<Vec<CategoryGroup>> {
+    let mut groups: Vec<CategoryGroup> = get_categories(conn)?
+        .into_iter()
+        .map(|category| CategoryGroup {
+            category: Some(category),
+            products: Vec::new(),
+        })
+        .collect();
+    let index: HashMap<i64, usize> = groups
+        .iter()
+        .enumerate()
+        .filter_map(|(i, group)| group.category.as_ref().map(|c| (c.id, i)))
+        .collect();
+
+    let mut uncategorized = Vec::new();
+    for product in get_products(conn, include_archived)? {
+        match product.category_id.and_then(|id| index.get(&id)) {
+            Some(&i) => groups[i].products.push(product),
+            None => uncategorized.push(product),
+        }
+    }
+    if !uncategorized.is_empty() {
+        groups.push(CategoryGroup {
+            category: None,
+            products: uncategorized,
+        });
+    }
+    Ok(groups)
+}
+
+// Returns the registry unit the product is sold by
+fn validate_product(product: &NewProduct) -> AppResult<&'static Unit> {
+    let (field, reason) = if product.name.trim().is_empty() {
+        ("name", "Product name is required")
+    } else if product.price.is_negative() {
+        ("price", "Price must be zero or more")
+    } else {
+        return units::parse_unit(&product.unit, "unit");
+    };
+    Err(AppError::validation_with(reason, json!({ "field": field })))
+}
+
+fn check_category_exists(tx: &rusqlite::Transaction, category_id: Option<i64>, field: &str) -> AppResult<()> {
+    let Some(category_id) = category_id else {
+        return Ok(());
+    };
+    let exists: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM categories WHERE id = ?1)",
+        [category_id],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Err(AppError::validation_with(
+            format!("Category {} does not exist", category_id),
+            json!({ "field": field }),
+        ));
+    }
+    Ok(())
+}
+
+fn record_price(tx: &rusqlite::Transaction, product_id: i64, price: &Money) -> Result<()> {
+    tx.execute(
+        "INSERT INTO product_price_history (product_id, price, currency) VALUES (?1, ?2, ?3)",
+        (product_id, price.minor, &price.currency),
+    )?;
+    Ok(())
+}
+
+pub fn add_product(conn: &mut Connection, product: NewProduct) -> AppResult<Product> {
+    let unit = validate_product(&product)?;
+
+    let tx = conn.transaction()?;
+    check_category_exists(&tx, product.category_id, "category_id")?;
+    tx.execute(
+        "INSERT INTO products (name, price, currency, unit, category_id, tax_class)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        (
+            &product.name,
+            product.price.minor,
+            &product.price.currency,
+            unit.code,
+            product.category_id,
+            product.tax_class.as_str(),
+        ),
     )?;
     
-    let id = conn.last_insert_rowid();
+    let id = tx.last_insert_rowid();
+    record_price(&tx, id, &product.price)?;
+    tx.commit()?;
     Ok(Product {
         id,
         name: product.name,
         price: product.price,
-        unit: product.unit,
+        unit: unit.code.to_string(),
+        category_id: product.category_id,
+        tax_class: product.tax_class,
+        archived_at: None,
     })
 }
 
-pub fn delete_product(db_path: &PathBuf, id: i64) -> Result<()> {
-    let conn = Connection::open(db_path)?;
-    conn.execute("DELETE FROM products WHERE id = ?1", [id])?;
+// Edits a product in place, keeping its id and created_at. A new price
+// history entry is written only when the price actually changes, and its
+// recipe is converted when it changes unit.
+pub fn update_product(conn: &mut Connection, id: i64, product: NewProduct) -> AppResult<Product> {
+    let unit = validate_product(&product)?;
+
+    let tx = conn.transaction()?;
+    let (old_minor, old_currency, old_unit, archived_at): (i64, String, String, Option<String>) = tx
+        .query_row(
+            "SELECT price, currency, unit, archived_at FROM products WHERE id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Product {} not found", id)))?;
+    check_category_exists(&tx, product.category_id, "category_id")?;
+    // A unit from before the registry is only being relabelled
+    if let Some(old) = units::find_unit(&old_unit).filter(|old| old.code != unit.code) {
+        inventory::convert_recipe_quantities(&tx, "product_id", id, old, unit, &product.name)?;
+    }
+
+    tx.execute(
+        "UPDATE products SET name = ?1, price = ?2, currency = ?3, unit = ?4, category_id = ?5, tax_class = ?6
+         WHERE id = ?7",
+        (
+            &product.name,
+            product.price.minor,
+            &product.price.currency,
+            unit.code,
+            product.category_id,
+            product.tax_class.as_str(),
+            id,
+        ),
+    )?;
+    if old_minor != product.price.minor || old_currency != product.price.currency {
+        record_price(&tx, id, &product.price)?;
+    }
+    tx.commit()?;
+
+    Ok(Product {
+        id,
+        name: product.name,
+        price: product.price,
+        unit: unit.code.to_string(),
+        category_id: product.category_id,
+        tax_class: product.tax_class,
+        archived_at,
+    })
+}
+
+// Takes a product off the menu. It keeps its id and history and can be
+// brought back with `restore_product`; archiving twice is harmless.
+pub fn delete_product(conn: &Connection, id: i64) -> AppResult<()> {
+    let updated = conn.execute(
+        "UPDATE products SET archived_at = COALESCE(archived_at, CURRENT_TIMESTAMP) WHERE id = ?1",
+        [id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::not_found(format!("Product {} not found", id)));
+    }
+    Ok(())
+}
+
+pub fn restore_product(conn: &Connection, id: i64) -> AppResult<()> {
+    if conn.execute("UPDATE products SET archived_at = NULL WHERE id = ?1", [id])? == 0 {
+        return Err(AppError::not_found(format!("Product {} not found", id)));
+    }
+    Ok(())
+}
+
+// Permanently removes a product together with its price history. Only
+// archived products can be purged, so a single mis-click can't do it.
+pub fn purge_product(conn: &mut Connection, id: i64) -> AppResult<()> {
+    let tx = conn.transaction()?;
+    let archived: bool = tx
+        .query_row(
+            "SELECT archived_at IS NOT NULL FROM products WHERE id = ?1",
+            [id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Product {} not found", id)))?;
+    if !archived {
+        return Err(AppError::Conflict {
+            message: "Only archived products can be deleted permanently. Archive it first."
+                .to_string(),
+            details: Some(json!({ "id": id })),
+        });
+    }
+    let used_in = bundles::bundles_using(&tx, id)?;
+    if !used_in.is_empty() {
+        return Err(AppError::Conflict {
+            message: format!(
+                "This product is part of {}. Remove it from the bundle first.",
+                used_in.join(", ")
+            ),
+            details: Some(json!({ "id": id, "bundles": used_in })),
+        });
+    }
+    purchasing::check_not_ordered(&tx, None, Some(id), "product")?;
+
+    tx.execute("DELETE FROM products WHERE id = ?1", [id])?;
+    tx.commit()?;
+    Ok(())
+}
+
+// Every price the product has had, newest first. Timestamps are UTC, like
+// the rest of the database.
+pub fn get_product_price_history(conn: &Connection, product_id: i64) -> AppResult<Vec<PriceChange>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT price, currency, effective_from FROM product_price_history
+         WHERE product_id = ?1 ORDER BY effective_from DESC, id DESC",
+    )?;
+    let history = stmt
+        .query_map([product_id], |row| {
+            Ok(PriceChange {
+                price: Money::new(row.get(0)?, row.get::<_, String>(1)?),
+                effective_from: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    if history.is_empty() {
+        return Err(AppError::not_found(format!("Product {} not found", product_id)));
+    }
+    Ok(history)
+}
+
+// Accepts "YYYY-MM-DD" (meaning the end of that day), "YYYY-MM-DD HH:MM:SS",
+// the same with a "T" separator, or RFC 3339 with an offset. Times without an
+// offset are taken as UTC.
+fn parse_as_of(at: &str) -> AppResult<String> {
+    let at = at.trim();
+    let timestamp = if let Ok(date) = NaiveDate::parse_from_str(at, "%Y-%m-%d") {
+        date.and_hms_opt(23, 59, 59)
+    } else if let Ok(time) = DateTime::parse_from_rfc3339(at) {
+        Some(time.naive_utc())
+    } else {
+        NaiveDateTime::parse_from_str(at, "%Y-%m-%d %H:%M:%S")
+            .or_else(|_| NaiveDateTime::parse_from_str(at, "%Y-%m-%dT%H:%M:%S"))
+            .ok()
+    };
+    timestamp
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .ok_or_else(|| {
+            AppError::validation_with(
+                format!("Invalid date: {}", at),
+                json!({ "field": "at", "expected": "YYYY-MM-DD or YYYY-MM-DD HH:MM:SS" }),
+            )
+        })
+}
+
+// The price that was in effect for a product at `at`
+pub fn get_product_price_at(conn: &Connection, product_id: i64, at: &str) -> AppResult<PriceChange> {
+    let at = parse_as_of(at)?;
+    conn.prepare_cached(
+        "SELECT price, currency, effective_from FROM product_price_history
+         WHERE product_id = ?1 AND effective_from <= ?2
+         ORDER BY effective_from DESC, id DESC LIMIT 1",
+    )?
+    .query_row((product_id, &at), |row| {
+        Ok(PriceChange {
+            price: Money::new(row.get(0)?, row.get::<_, String>(1)?),
+            effective_from: row.get(2)?,
+        })
+    })
+    .optional()?
+    .ok_or_else(|| {
+        AppError::not_found(format!("Product {} had no price on {}", product_id, at))
+    })
+}
+
+// Category operations
+
+// Parents come before their sub-sections, siblings by sort order then name
+pub fn get_categories(conn: &Connection) -> Result<Vec<Category>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, name, sort_order, color, parent_id FROM categories
+         ORDER BY sort_order, name COLLATE NOCASE, id",
+    )?;
+    let categories = stmt
+        .query_map([], |row| {
+            Ok(Category {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                sort_order: row.get(2)?,
+                color: row.get(3)?,
+                parent_id: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut ordered = Vec::with_capacity(categories.len());
+    let mut pending: Vec<&Category> = categories.iter().filter(|c| c.parent_id.is_none()).rev().collect();
+    while let Some(category) = pending.pop() {
+        ordered.push(category.clone());
+        pending.extend(categories.iter().filter(|c| c.parent_id == Some(category.id)).rev());
+    }
+    Ok(ordered)
+}
+
+fn is_hex_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+// Checks a new or edited category. `id` is the category being edited, which
+// may not become its own parent or a sub-section of one of its sub-sections.
+fn validate_category(tx: &rusqlite::Transaction, id: Option<i64>, category: &NewCategory) -> AppResult<()> {
+    if category.name.trim().is_empty() {
+        return Err(AppError::validation_with(
+            "Category name is required",
+            json!({ "field": "name" }),
+        ));
+    }
+    if let Some(color) = category.color.as_deref().filter(|c| !c.is_empty()) {
+        if !is_hex_color(color) {
+            return Err(AppError::validation_with(
+                "Color must be a hex value like #ff8800",
+                json!({ "field": "color" }),
+            ));
+        }
+    }
+    check_category_exists(tx, category.parent_id, "parent_id")?;
+
+    if let (Some(id), Some(parent_id)) = (id, category.parent_id) {
+        let cycle: bool = tx.query_row(
+            "WITH RECURSIVE ancestors (id) AS (
+                 SELECT ?1
+                 UNION
+                 SELECT c.parent_id FROM categories c JOIN ancestors a ON c.id = a.id
+                 WHERE c.parent_id IS NOT NULL
+             )
+             SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = ?2)",
+            (parent_id, id),
+            |row| row.get(0),
+        )?;
+        if cycle {
+            return Err(AppError::validation_with(
+                "A category cannot be placed inside itself",
+                json!({ "field": "parent_id" }),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Without an explicit sort order a new category goes after its siblings
+pub fn add_category(conn: &mut Connection, category: NewCategory) -> AppResult<Category> {
+    let tx = conn.transaction()?;
+    validate_category(&tx, None, &category)?;
+    let sort_order = match category.sort_order {
+        Some(sort_order) => sort_order,
+        None => tx.query_row(
+            "SELECT COALESCE(MAX(sort_order) + 1, 0) FROM categories WHERE parent_id IS ?1",
+            [category.parent_id],
+            |row| row.get(0),
+        )?,
+    };
+    let color = category.color.filter(|c| !c.is_empty());
+    tx.execute(
+        "INSERT INTO categories (name, sort_order, color, parent_id) VALUES (?1, ?2, ?3, ?4)",
+        (category.name.trim(), sort_order, &color, category.parent_id),
+    )?;
+    let id = tx.last_insert_rowid();
+    tx.commit()?;
+
+    Ok(Category {
+        id,
+        name: category.name.trim().to_string(),
+        sort_order,
+        color,
+        parent_id: category.parent_id,
+    })
+}
+
+// Leaving out the sort order keeps the category where it is
+pub fn update_category(conn: &mut Connection, id: i64, category: NewCategory) -> AppResult<Category> {
+    let tx = conn.transaction()?;
+    let current_order: i64 = tx
+        .query_row("SELECT sort_order FROM categories WHERE id = ?1", [id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Category {} not found", id)))?;
+    validate_category(&tx, Some(id), &category)?;
+
+    let sort_order = category.sort_order.unwrap_or(current_order);
+    let color = category.color.filter(|c| !c.is_empty());
+    tx.execute(
+        "UPDATE categories SET name = ?1, sort_order = ?2, color = ?3, parent_id = ?4 WHERE id = ?5",
+        (category.name.trim(), sort_order, &color, category.parent_id, id),
+    )?;
+    tx.commit()?;
+
+    Ok(Category {
+        id,
+        name: category.name.trim().to_string(),
+        sort_order,
+        color,
+        parent_id: category.parent_id,
+    })
+}
+
+// Sub-sections and products of a deleted category move up to its parent,
+// or become top-level / uncategorized if it had none.
+pub fn delete_category(conn: &mut Connection, id: i64) -> AppResult<()> {
+    let tx = conn.transaction()?;
+    let parent_id: Option<i64> = tx
+        .query_row("SELECT parent_id FROM categories WHERE id = ?1", [id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Category {} not found", id)))?;
+
+    tx.execute("UPDATE categories SET parent_id = ?1 WHERE parent_id = ?2", (parent_id, id))?;
+    tx.execute("UPDATE products SET category_id = ?1 WHERE category_id = ?2", (parent_id, id))?;
+    tx.execute("DELETE FROM categories WHERE id = ?1", [id])?;
+    tx.commit()?;
     Ok(())
 }
 
 // Settings operations
-pub fn get_settings(db_path: &PathBuf) -> Result<Settings> {
-    let conn = Connection::open(db_path)?;
-    let mut stmt = conn.prepare(
+pub fn get_settings(conn: &Connection) -> Result<Settings> {
+    let mut stmt = conn.prepare_cached(
         "SELECT restaurant_name, address, phone, email, tax_rate, currency, 
-         opening_time, closing_time, receipt_footer, logo FROM settings WHERE id = 1"
+         opening_time, closing_time, receipt_footer, logo, backup_directory,
+         backup_keep_daily, backup_keep_weekly, reduced_tax_rate, prices_include_tax
+         FROM settings WHERE id = 1"
     )?;
     
     let settings = stmt.query_row([], |row| {
@@ -228,141 +750,714 @@ pub fn get_settings(db_path: &PathBuf) -> Result<Settings> {
             phone: row.get(2)?,
             email: row.get(3)?,
             tax_rate: row.get(4)?,
+            reduced_tax_rate: row.get(13)?,
+            prices_include_tax: row.get(14)?,
             currency: row.get(5)?,
             opening_time: row.get(6)?,
             closing_time: row.get(7)?,
             receipt_footer: row.get(8)?,
             logo: row.get(9)?,
+            backup_directory: row.get(10)?,
+            backup_keep_daily: row.get(11)?,
+            backup_keep_weekly: row.get(12)?,
         })
     })?;
     
     Ok(settings)
 }
 
-pub fn save_settings(db_path: &PathBuf, settings: Settings) -> Result<()> {
-    let conn = Connection::open(db_path)?;
+// Tax rates are checked and stored in a standard form, e.g. "17.50" as "17.5"
+pub fn save_settings(conn: &Connection, settings: Settings) -> AppResult<()> {
+    let tax_rate = tax::parse_rate(settings.tax_rate.as_deref(), "tax_rate")?.map(tax::format_rate);
+    let reduced_tax_rate =
+        tax::parse_rate(settings.reduced_tax_rate.as_deref(), "reduced_tax_rate")?.map(tax::format_rate);
     conn.execute(
         "INSERT OR REPLACE INTO settings (
             id, restaurant_name, address, phone, email, tax_rate, 
-            currency, opening_time, closing_time, receipt_footer, logo
-        ) VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            currency, opening_time, closing_time, receipt_footer, logo,
+            backup_directory, backup_keep_daily, backup_keep_weekly,
+            reduced_tax_rate, prices_include_tax
+        ) VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
         (
             &settings.restaurant_name,
             &settings.address,
             &settings.phone,
             &settings.email,
-            &settings.tax_rate,
+            &tax_rate,
             &settings.currency,
             &settings.opening_time,
             &settings.closing_time,
             &settings.receipt_footer,
             &settings.logo,
+            &settings.backup_directory,
+            &settings.backup_keep_daily,
+            &settings.backup_keep_weekly,
+            &reduced_tax_rate,
+            &settings.prices_include_tax,
         ),
     )?;
     Ok(())
 }
 
 // Sales operations
-pub fn add_sale(db_path: &PathBuf, sale: Sale) -> Result<i64> {
-    let conn = Connection::open(db_path)?;
-    
-    conn.execute(
-        "INSERT INTO sales (total_amount, currency) VALUES (?1, ?2)",
-        (&sale.total_amount, &sale.currency),
+fn validate_sale_items(items: &[SaleItem], currency: &str) -> AppResult<()> {
+    if items.is_empty() {
+        return Err(AppError::validation("A sale must contain at least one item"));
+    }
+    for (index, item) in items.iter().enumerate() {
+        if !item.price.currency.eq_ignore_ascii_case(currency) {
+            return Err(AppError::validation_with(
+                format!(
+                    "Item #{} is priced in {} but the sale is in {}",
+                    index + 1,
+                    item.price.currency,
+                    currency
+                ),
+                json!({ "item": index, "expected": currency, "found": item.price.currency }),
+            ));
+        }
+        let reason = if item.name.trim().is_empty() {
+            "name is empty"
+        } else if item.price.is_negative() {
+            "price must be zero or more"
+        } else if !item.quantity.is_finite() || item.quantity <= 0.0 {
+            "quantity must be greater than zero"
+        } else {
+            continue;
+        };
+        return Err(AppError::validation_with(
+            format!("Invalid item #{}: {}", index + 1, reason),
+            json!({ "item": index }),
+        ));
+    }
+    Ok(())
+}
+
+// How a line was sold: the unit it was sold in, its quantity in the unit of
+// its product, which its price and recipe are per, and the tax class of the
+// product. Lines without a product are priced in the unit they were sold in
+// and taxed at the standard rate.
+struct LineUnits {
+    unit: String,
+    quantity: f64,
+    tax_class: TaxClass,
+}
+
+// Lines that name a product must point at one that is still on the menu,
+// and every line must be sold in a known unit; a product can be sold in
+// any unit of the same kind as its own. Lines are charged at their
+// product's price, so a stale cart or a UI bug can't sell at some other
+// price; lines without a product id go by name, and products may share a
+// name, so any of them will do.
+fn resolve_sale_units(tx: &rusqlite::Transaction, items: &[SaleItem]) -> AppResult<Vec<LineUnits>> {
+    let mut stmt = tx.prepare_cached(
+        "SELECT archived_at IS NULL, unit, tax_class, price, currency FROM products WHERE id = ?1",
+    )?;
+    let mut by_name = tx.prepare_cached("SELECT price, currency FROM products WHERE name = ?1 ORDER BY id")?;
+    let mut lines = Vec::with_capacity(items.len());
+    for (index, item) in items.iter().enumerate() {
+        let invalid = |reason: String| {
+            AppError::validation_with(
+                format!("Invalid item #{}: {}", index + 1, reason),
+                json!({ "item": index, "product_id": item.product_id, "unit": item.unit }),
+            )
+        };
+        let sold = units::find_unit(&item.unit);
+        let Some(product_id) = item.product_id else {
+            let prices = by_name
+                .query_map([&item.name], |row| Ok(Money::new(row.get(0)?, row.get::<_, String>(1)?)))?
+                .collect::<Result<Vec<_>>>()?;
+            if prices.is_empty() {
+                return Err(invalid(format!("there is no product named {}", item.name)));
+            }
+            check_sale_price(index, item, &prices)?;
+            let sold = sold.ok_or_else(|| invalid(format!("unknown unit {}", item.unit.trim())))?;
+            lines.push(LineUnits {
+                unit: sold.code.to_string(),
+                quantity: item.quantity,
+                tax_class: TaxClass::Standard,
+            });
+            continue;
+        };
+        let product = stmt
+            .query_row([product_id], |row| {
+                Ok((
+                    row.get::<_, bool>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    Money::new(row.get(3)?, row.get::<_, String>(4)?),
+                ))
+            })
+            .optional()?;
+        let (product_unit, tax_class) = match product {
+            Some((true, unit, class, price)) => {
+                check_sale_price(index, item, &[price])?;
+                (unit, TaxClass::from_column(&class))
+            }
+            Some((false, ..)) => return Err(invalid("product is no longer on the menu".to_string())),
+            None => return Err(invalid("product does not exist".to_string())),
+        };
+        let line = match (sold, units::find_unit(&product_unit)) {
+            (Some(sold), Some(own)) => LineUnits {
+                unit: sold.code.to_string(),
+                quantity: units::convert(item.quantity, sold, own).ok_or_else(|| {
+                    invalid(format!(
+                        "{} is sold by the {} and can't be sold in {}",
+                        item.name.trim(),
+                        own.name,
+                        sold.code
+                    ))
+                })?,
+                tax_class,
+            },
+            // A product with a unit from before the registry sells in it as is
+            (_, None) if item.unit.trim().eq_ignore_ascii_case(product_unit.trim()) => LineUnits {
+                unit: product_unit,
+                quantity: item.quantity,
+                tax_class,
+            },
+            (None, _) => return Err(invalid(format!("unknown unit {}", item.unit.trim()))),
+            (Some(sold), None) => {
+                return Err(invalid(format!(
+                    "{} is sold by the {} and can't be sold in {}",
+                    item.name.trim(),
+                    product_unit,
+                    sold.code
+                )))
+            }
+        };
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+fn check_sale_price(index: usize, item: &SaleItem, prices: &[Money]) -> AppResult<()> {
+    let matches = |price: &Money| {
+        price.minor == item.price.minor && price.currency.eq_ignore_ascii_case(&item.price.currency)
+    };
+    if prices.iter().any(matches) {
+        return Ok(());
+    }
+    Err(AppError::validation_with(
+        format!(
+            "Invalid item #{}: price {} does not match the product price {}",
+            index + 1,
+            item.price,
+            prices[0]
+        ),
+        json!({ "item": index, "submitted": item.price, "expected": prices[0] }),
+    ))
+}
+
+// Price of one unit of a line: the product price plus the price changes of
+// the options chosen on it
+fn unit_price(item: &SaleItem, modifiers: &[LineModifier]) -> Money {
+    let deltas: i64 = modifiers.iter().map(|m| m.price_delta.minor).sum();
+    Money::new(item.price.minor + deltas, &*item.price.currency)
+}
+
+// `quantity` is in the unit the line is priced per
+fn line_subtotal(item: &SaleItem, quantity: f64, modifiers: &[LineModifier]) -> Money {
+    unit_price(item, modifiers).times_quantity(quantity)
+}
+
+// Tax on one line, at the rate of its class when the sale was made
+struct LineTax {
+    class: TaxClass,
+    rate: i64,
+    tax: i64,
+}
+
+fn line_tax(amount: i64, class: TaxClass, rates: &TaxRates) -> LineTax {
+    let rate = rates.rate(class);
+    LineTax {
+        class,
+        rate,
+        tax: tax::line_tax(amount, rate, rates.prices_include_tax),
+    }
+}
+
+// Discounts on a sale, in item order where they are per line. Line
+// discounts come off each line first, then the order discount off what the
+// lines come to, then the coupon off what is left. The order discount and
+// coupon are shared across the lines by value, so each line is taxed on
+// what was actually paid for it.
+struct SaleDiscounts {
+    lines: Vec<Option<AppliedDiscount>>,
+    order: Option<AppliedDiscount>,
+    coupon: Option<(i64, String, i64)>,
+    // Each line's share of the order discount and coupon
+    shares: Vec<i64>,
+}
+
+fn resolve_sale_discounts(
+    conn: &Connection,
+    sale: &Sale,
+    subtotals: &[Money],
+    currency: &str,
+) -> AppResult<SaleDiscounts> {
+    let lines = sale
+        .products
+        .iter()
+        .zip(subtotals)
+        .enumerate()
+        .map(|(index, (item, subtotal))| {
+            item.discount
+                .as_ref()
+                .map(|discount| discounts::resolve_discount(conn, discount, subtotal.minor, currency, Some(index)))
+                .transpose()
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+    let nets: Vec<i64> = subtotals
+        .iter()
+        .zip(&lines)
+        .map(|(subtotal, discount)| subtotal.minor - discount.as_ref().map_or(0, |d| d.amount.minor))
+        .collect();
+    let items_total: i64 = nets.iter().sum();
+    let order = sale
+        .discount
+        .as_ref()
+        .map(|discount| discounts::resolve_discount(conn, discount, items_total, currency, None))
+        .transpose()?;
+    let after_order = items_total - order.as_ref().map_or(0, |d| d.amount.minor);
+    let coupon = match sale.coupon_code.as_deref().map(str::trim).filter(|code| !code.is_empty()) {
+        Some(code) => {
+            let coupon = discounts::redeemable_coupon(conn, code, currency, items_total)?;
+            let amount = discounts::discount_off(coupon.kind, coupon.value, after_order);
+            Some((coupon.id, coupon.code, amount))
+        }
+        None => None,
+    };
+    let order_total = (items_total - after_order) + coupon.as_ref().map_or(0, |c| c.2);
+    Ok(SaleDiscounts {
+        shares: discounts::allocate(order_total, &nets),
+        lines,
+        order,
+        coupon,
+    })
+}
+
+// The authoritative sale total: the sum of what each line comes to after
+// its discounts, plus the tax on each line when prices don't already
+// include it. Lines are taxed one by one, so the total is the same however
+// a receipt adds them up.
+pub fn compute_sale_total(amounts: &[i64], taxes: &[i64], prices_include_tax: bool, currency: &str) -> Money {
+    let mut minor: i64 = amounts.iter().sum();
+    if !prices_include_tax {
+        minor += taxes.iter().sum::<i64>();
+    }
+    Money::new(minor, currency)
+}
+
+// Resolves the options chosen on every line. A discount option may lower a
+// line's price, but never below zero.
+fn resolve_sale_modifiers(conn: &Connection, items: &[SaleItem], currency: &str) -> AppResult<Vec<Vec<LineModifier>>> {
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let chosen = modifiers::resolve_line_modifiers(
+                conn,
+                index,
+                item.product_id,
+                &item.modifier_ids,
+                currency,
+            )?;
+            if unit_price(item, &chosen).is_negative() {
+                return Err(AppError::validation_with(
+                    format!("Invalid item #{}: options make the price negative", index + 1),
+                    json!({ "item": index }),
+                ));
+            }
+            Ok(chosen)
+        })
+        .collect()
+}
+
+// What is served for every line, in item order. Lines for ordinary products
+// resolve to no components.
+fn resolve_sale_components(
+    conn: &Connection,
+    items: &[SaleItem],
+    quantities: &[f64],
+) -> AppResult<Vec<Vec<LineComponent>>> {
+    items
+        .iter()
+        .zip(quantities)
+        .enumerate()
+        .map(|(index, (item, &quantity))| {
+            bundles::resolve_line_components(
+                conn,
+                index,
+                item.product_id,
+                quantity,
+                &item.substitutions,
+            )
+        })
+        .collect()
+}
+
+pub fn add_sale(conn: &mut Connection, sale: Sale) -> AppResult<SaleOutcome> {
+    let currency = &sale.total_amount.currency;
+    validate_sale_items(&sale.products, currency)?;
+
+    // Either the sale and all of its lines are written, or nothing is
+    let tx = conn.transaction()?;
+    let line_units = resolve_sale_units(&tx, &sale.products)?;
+    let quantities: Vec<f64> = line_units.iter().map(|line| line.quantity).collect();
+    let line_modifiers = resolve_sale_modifiers(&tx, &sale.products, currency)?;
+    let line_components = resolve_sale_components(&tx, &sale.products, &quantities)?;
+
+    let rates = tax::tax_rates(&tx)?;
+    let subtotals: Vec<Money> = sale
+        .products
+        .iter()
+        .zip(&quantities)
+        .zip(&line_modifiers)
+        .map(|((item, &quantity), chosen)| line_subtotal(item, quantity, chosen))
+        .collect();
+    let sale_discounts = resolve_sale_discounts(&tx, &sale, &subtotals, currency)?;
+    let line_discounts: Vec<i64> = sale_discounts
+        .lines
+        .iter()
+        .map(|discount| discount.as_ref().map_or(0, |d| d.amount.minor))
+        .collect();
+    let amounts: Vec<i64> = subtotals
+        .iter()
+        .zip(&line_discounts)
+        .zip(&sale_discounts.shares)
+        .map(|((subtotal, discount), share)| subtotal.minor - discount - share)
+        .collect();
+    let line_taxes: Vec<LineTax> = amounts
+        .iter()
+        .zip(&line_units)
+        .map(|(&amount, units)| line_tax(amount, units.tax_class, &rates))
+        .collect();
+    let taxes: Vec<i64> = line_taxes.iter().map(|line| line.tax).collect();
+    let tax_amount: i64 = taxes.iter().sum();
+    let discount_amount: i64 =
+        line_discounts.iter().sum::<i64>() + sale_discounts.shares.iter().sum::<i64>();
+    let computed = compute_sale_total(&amounts, &taxes, rates.prices_include_tax, currency);
+    if computed.minor != sale.total_amount.minor {
+        return Err(AppError::validation_with(
+            format!(
+                "Sale total {} does not match the items, expected {}",
+                sale.total_amount, computed
+            ),
+            json!({ "submitted": sale.total_amount, "computed": computed }),
+        ));
+    }
+
+    let order = sale_discounts.order.as_ref();
+    let coupon = sale_discounts.coupon.as_ref();
+    tx.execute(
+        "INSERT INTO sales (total_amount, currency, tax_amount, prices_include_tax, discount_amount,
+                            discount_kind, discount_value, discount_reason, order_discount,
+                            coupon_id, coupon_code, coupon_discount)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        (
+            computed.minor,
+            currency,
+            tax_amount,
+            rates.prices_include_tax,
+            discount_amount,
+            order.map(|d| d.kind.as_str()),
+            order.map(|d| d.value),
+            order.map(|d| &d.reason),
+            order.map_or(0, |d| d.amount.minor),
+            coupon.map(|c| c.0),
+            coupon.map(|c| &c.1),
+            coupon.map_or(0, |c| c.2),
+        ),
     )?;
     
-    let sale_id = conn.last_insert_rowid();
+    let sale_id = tx.last_insert_rowid();
     
-    for item in sale.products {
-        conn.execute(
-            "INSERT INTO sale_items (sale_id, product_name, price, quantity, unit) 
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            (sale_id, &item.name, &item.price, &item.quantity, &item.unit),
+    {
+        let mut item_stmt = tx.prepare_cached(
+            "INSERT INTO sale_items (sale_id, product_id, product_name, price, quantity, unit, subtotal,
+                                     tax_class, tax_rate, tax, discount_kind, discount_value, discount_reason,
+                                     discount, order_discount) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
         )?;
+        let lines = sale
+            .products
+            .iter()
+            .zip(&line_units)
+            .zip(&line_modifiers)
+            .zip(&line_components)
+            .zip(subtotals.iter().zip(&line_taxes))
+            .zip(sale_discounts.lines.iter().zip(&sale_discounts.shares));
+        for (((((item, units), chosen), components), (subtotal, taxed)), (discount, share)) in lines {
+            let discount = discount.as_ref();
+            item_stmt.execute(rusqlite::params![
+                sale_id,
+                item.product_id,
+                &item.name,
+                item.price.minor,
+                item.quantity,
+                &units.unit,
+                subtotal.minor,
+                taxed.class.as_str(),
+                taxed.rate,
+                taxed.tax,
+                discount.map(|d| d.kind.as_str()),
+                discount.map(|d| d.value),
+                discount.map(|d| &d.reason),
+                discount.map_or(0, |d| d.amount.minor),
+                share,
+            ])?;
+            let item_id = tx.last_insert_rowid();
+            modifiers::record_line_modifiers(&tx, item_id, chosen)?;
+            bundles::record_line_components(&tx, item_id, components)?;
+        }
     }
+
+    let served: Vec<(i64, f64)> = sale
+        .products
+        .iter()
+        .zip(&quantities)
+        .zip(&line_components)
+        .flat_map(|((item, &quantity), components)| {
+            let line = item.product_id.map(|id| (id, quantity));
+            line.into_iter()
+                .chain(components.iter().filter_map(|c| c.product_id.map(|id| (id, c.quantity))))
+        })
+        .collect();
+    let low_stock = inventory::deduct_sale_stock(&tx, sale_id, &served)?;
     
-    Ok(sale_id)
+    tx.commit()?;
+    Ok(SaleOutcome { id: sale_id, low_stock })
 }
 
 // Transaction operations
-pub fn get_transactions(db_path: &PathBuf) -> Result<Vec<Transaction>> {
-    let conn = Connection::open(db_path)?;
-    
-    let mut stmt = conn.prepare(
-        "SELECT id, total_amount, currency, date, time FROM sales ORDER BY id DESC"
-    )?;
+pub fn get_transactions(conn: &Connection) -> Result<Vec<Transaction>> {
+    transactions_where(conn, "1", [])
+}
+
+pub fn get_transaction(conn: &Connection, id: i64) -> AppResult<Transaction> {
+    transactions_where(conn, "id = ?1", [id])?
+        .pop()
+        .ok_or_else(|| AppError::not_found(format!("Transaction {} not found", id)))
+}
+
+// Sales matching `condition`, newest first
+fn transactions_where(conn: &Connection, condition: &str, params: impl Params) -> Result<Vec<Transaction>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT id, total_amount, currency, date, time, tax_amount, prices_include_tax, discount_amount,
+                discount_kind, discount_value, discount_reason, order_discount,
+                coupon_id, coupon_code, coupon_discount, refund_of, refund_reason
+         FROM sales WHERE {} ORDER BY id DESC",
+        condition
+    ))?;
     
-    let sales: Vec<(i64, f64, String, String, String)> = stmt
-        .query_map([], |row| {
-            Ok((
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?,
-                row.get(3)?,
-                row.get(4)?,
-            ))
+    // Items are filled in below, once the sale's currency is known
+    let sales: Vec<Transaction> = stmt
+        .query_map(params, |row| {
+            let currency: String = row.get(2)?;
+            let money = |minor: i64| Money::new(minor, &*currency);
+            let order_discount = match row.get::<_, Option<String>>(8)? {
+                Some(kind) => Some(AppliedDiscount {
+                    kind: DiscountKind::from_column(&kind),
+                    value: row.get(9)?,
+                    reason: row.get::<_, Option<String>>(10)?.unwrap_or_default(),
+                    amount: money(row.get(11)?),
+                }),
+                None => None,
+            };
+            let coupon = match row.get::<_, Option<String>>(13)? {
+                Some(code) => Some(AppliedCoupon {
+                    coupon_id: row.get(12)?,
+                    code,
+                    amount: money(row.get(14)?),
+                }),
+                None => None,
+            };
+            Ok(Transaction {
+                id: row.get(0)?,
+                refund_of: row.get(15)?,
+                refund_reason: row.get(16)?,
+                items: Vec::new(),
+                total_amount: money(row.get(1)?),
+                discount: money(row.get(7)?),
+                order_discount,
+                coupon,
+                tax: money(row.get(5)?),
+                prices_include_tax: row.get(6)?,
+                tax_breakdown: Vec::new(),
+                date: row.get(3)?,
+                time: row.get(4)?,
+                currency,
+            })
         })?
         .collect::<Result<Vec<_>>>()?;
     
+    let mut item_stmt = conn.prepare_cached(
+        "SELECT id, product_id, product_name, price, quantity, unit, subtotal, tax_class, tax_rate, tax,
+                discount_kind, discount_value, discount_reason, discount, order_discount,
+                (SELECT COALESCE(-SUM(r.quantity), 0) FROM sale_items r WHERE r.refund_of = sale_items.id)
+         FROM sale_items WHERE sale_id = ?1 ORDER BY id"
+    )?;
     let mut transactions = Vec::new();
     
-    for (id, total_amount, currency, date, time) in sales {
-        let mut item_stmt = conn.prepare(
-            "SELECT product_name, price, quantity, unit FROM sale_items WHERE sale_id = ?1"
-        )?;
-        
+    for sale in sales {
+        let currency = &sale.currency;
         let items: Vec<TransactionItem> = item_stmt
-            .query_map([id], |row| {
-                let price: f64 = row.get(1)?;
-                let quantity: f64 = row.get(2)?;
+            .query_map([sale.id], |row| {
+                let discount = match row.get::<_, Option<String>>(10)? {
+                    Some(kind) => Some(AppliedDiscount {
+                        kind: DiscountKind::from_column(&kind),
+                        value: row.get(11)?,
+                        reason: row.get::<_, Option<String>>(12)?.unwrap_or_default(),
+                        amount: Money::new(row.get(13)?, &**currency),
+                    }),
+                    None => None,
+                };
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    TransactionItem {
+                        id: row.get(0)?,
+                        product_id: row.get(1)?,
+                        name: row.get(2)?,
+                        price: Money::new(row.get(3)?, &**currency),
+                        quantity: row.get(4)?,
+                        unit: row.get(5)?,
+                        modifiers: Vec::new(),
+                        components: Vec::new(),
+                        subtotal: Money::new(row.get(6)?, &**currency),
+                        discount,
+                        order_discount: Money::new(row.get(14)?, &**currency),
+                        tax_class: row.get::<_, Option<String>>(7)?.as_deref().map(TaxClass::from_column),
+                        tax_rate: row.get::<_, Option<i64>>(8)?.map(tax::rate_percent),
+                        tax: Money::new(row.get(9)?, &**currency),
+                        refunded_quantity: row.get(15)?,
+                    },
+                ))
+            })?
+            .map(|row| {
+                let (item_id, item) = row?;
                 Ok(TransactionItem {
-                    name: row.get(0)?,
-                    price,
-                    quantity,
-                    unit: row.get(3)?,
-                    subtotal: price * quantity,
+                    modifiers: modifiers::get_line_modifiers(conn, item_id, currency)?,
+                    components: bundles::get_line_components(conn, item_id)?,
+                    ..item
                 })
-            })?
+            })
             .collect::<Result<Vec<_>>>()?;
         
+        let tax_breakdown = transaction_tax_breakdown(&items, sale.prices_include_tax, currency);
         transactions.push(Transaction {
-            id,
             items,
-            total_amount,
-            currency,
-            date,
-            time,
+            tax_breakdown,
+            ..sale
         });
     }
     
     Ok(transactions)
 }
 
-pub fn delete_transaction(db_path: &PathBuf, id: i64) -> Result<()> {
-    let conn = Connection::open(db_path)?;
-    conn.execute("DELETE FROM sale_items WHERE sale_id = ?1", [id])?;
-    conn.execute("DELETE FROM sales WHERE id = ?1", [id])?;
+// Groups the taxed lines of a sale by class and rate, in that order
+fn transaction_tax_breakdown(items: &[TransactionItem], prices_include_tax: bool, currency: &str) -> Vec<TaxBreakdown> {
+    let mut groups: BTreeMap<(TaxClass, i64), (i64, i64)> = BTreeMap::new();
+    for item in items {
+        let (Some(class), Some(rate)) = (item.tax_class, item.tax_rate) else {
+            continue;
+        };
+        let paid = item.subtotal.minor
+            - item.discount.as_ref().map_or(0, |d| d.amount.minor)
+            - item.order_discount.minor;
+        let taxable = if prices_include_tax { paid - item.tax.minor } else { paid };
+        let group = groups.entry((class, (rate * 100.0).round() as i64)).or_default();
+        group.0 += taxable;
+        group.1 += item.tax.minor;
+    }
+    groups
+        .into_iter()
+        .map(|((tax_class, rate), (taxable, tax))| TaxBreakdown {
+            tax_class,
+            rate: tax::rate_percent(rate),
+            taxable: Money::new(taxable, currency),
+            tax: Money::new(tax, currency),
+        })
+        .collect()
+}
+
+pub fn delete_transaction(conn: &mut Connection, id: i64) -> AppResult<()> {
+    let tx = conn.transaction()?;
+    let refunds: i64 = tx.query_row("SELECT COUNT(*) FROM sales WHERE refund_of = ?1", [id], |row| row.get(0))?;
+    if refunds > 0 {
+        return Err(AppError::Conflict {
+            message: format!("Transaction {} has been refunded. Delete its refunds first.", id),
+            details: Some(json!({ "id": id, "refunds": refunds })),
+        });
+    }
+    inventory::restore_sale_stock(&tx, id)?;
+    tx.execute("DELETE FROM sale_items WHERE sale_id = ?1", [id])?;
+    if tx.execute("DELETE FROM sales WHERE id = ?1", [id])? == 0 {
+        return Err(AppError::not_found(format!("Transaction {} not found", id)));
+    }
+    tx.commit()?;
     Ok(())
 }
 
 // Analytics operations
-pub fn get_analytics(db_path: &PathBuf) -> Result<AnalyticsData> {
-    let conn = Connection::open(db_path)?;
-    
-    // Get daily revenue (last 30 days, oldest first for proper chart display)
-    let mut daily_stmt = conn.prepare(
-        "SELECT date, COALESCE(SUM(total_amount), 0.0) as revenue, COUNT(*) as orders 
+
+// Checks the inclusive "YYYY-MM-DD" range of a report, returning the dates
+// trimmed. Dates are UTC, like the rest of the database.
+pub fn parse_date_range(start_date: &str, end_date: &str) -> AppResult<(String, String)> {
+    let parse = |value: &str, field: &str| {
+        NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").map_err(|_| {
+            AppError::validation_with(
+                format!("Invalid date: {}", value.trim()),
+                json!({ "field": field, "expected": "YYYY-MM-DD" }),
+            )
+        })
+    };
+    let start = parse(start_date, "start_date")?;
+    let end = parse(end_date, "end_date")?;
+    if end < start {
+        return Err(AppError::validation_with(
+            "The end date cannot be before the start date",
+            json!({ "field": "end_date" }),
+        ));
+    }
+    Ok((start.to_string(), end.to_string()))
+}
+
+// Currency reports are expressed in. Sales recorded in any other currency
+// are left out, since adding amounts across currencies is meaningless.
+pub fn report_currency(conn: &Connection) -> Result<String> {
+    let currency: Option<String> = conn
+        .prepare_cached("SELECT currency FROM settings WHERE id = 1")?
+        .query_row([], |row| row.get(0))?;
+    Ok(currency
+        .filter(|c| !c.trim().is_empty())
+        .unwrap_or_else(|| "PKR".to_string()))
+}
+
+pub fn get_analytics(conn: &Connection) -> Result<AnalyticsData> {
+    let currency = report_currency(conn)?;
+    let money = |minor: i64| Money::new(minor, &*currency);
+    // Quantities of sale lines `i` of products `p` are counted in the unit
+    // the product is sold by now, whatever unit each line was sold in
+    let sold_quantity = units::convert_sql("i.quantity", "i.unit", "p.unit");
+
+    // Get daily revenue (last 30 days, oldest first for proper chart display).
+    // Refunds take their amounts off the day they were given but are not orders.
+    let mut daily_stmt = conn.prepare_cached(
+        "SELECT date, COALESCE(SUM(total_amount), 0) as revenue,
+                COUNT(CASE WHEN refund_of IS NULL THEN 1 END) as orders
          FROM sales 
+         WHERE currency = ?1 
          GROUP BY date 
          ORDER BY date ASC 
          LIMIT 30"
     )?;
     
     let mut daily_revenue: Vec<DailyRevenue> = daily_stmt
-        .query_map([], |row| {
+        .query_map([&currency], |row| {
             Ok(DailyRevenue {
                 date: row.get(0)?,
-                revenue: row.get(1)?,
+                revenue: money(row.get(1)?),
                 orders: row.get(2)?,
             })
         })?
@@ -371,92 +1466,166 @@ pub fn get_analytics(db_path: &PathBuf) -> Result<AnalyticsData> {
     // Reverse to get most recent dates last
     daily_revenue.reverse();
     
-    // Get top products
-    let mut top_stmt = conn.prepare(
-        "SELECT product_name, 
-                CAST(SUM(quantity) AS INTEGER) as total_sales, 
-                COALESCE(SUM(price * quantity), 0.0) as revenue 
-         FROM sale_items 
-         GROUP BY product_name 
+    // Get top products. Lines linked to a product are grouped by its id and
+    // shown under its current name, so a rename doesn't split its history;
+    // older unlinked lines fall back to grouping by the name they were sold as.
+    // Products served inside a bundle count towards its sales, while the
+    // revenue stays with the bundle. Revenue is after discounts.
+    let mut top_stmt = conn.prepare_cached(&format!(
+        "WITH lines AS (
+             SELECT i.sale_id, i.product_id, i.product_name, {} AS quantity,
+                    i.subtotal - i.discount - i.order_discount AS subtotal 
+             FROM sale_items i LEFT JOIN products p ON p.id = i.product_id 
+             UNION ALL 
+             SELECT i.sale_id, c.product_id, c.product_name, c.quantity, 0 
+             FROM sale_item_components c JOIN sale_items i ON i.id = c.sale_item_id
+         )
+         SELECT i.product_id, 
+                COALESCE(p.name, i.product_name) as name, 
+                CAST(SUM(i.quantity) AS INTEGER) as total_sales, 
+                COALESCE(SUM(i.subtotal), 0) as revenue 
+         FROM lines i 
+         JOIN sales s ON s.id = i.sale_id 
+         LEFT JOIN products p ON p.id = i.product_id 
+         WHERE s.currency = ?1 
+         GROUP BY i.product_id, CASE WHEN i.product_id IS NULL THEN i.product_name END 
          ORDER BY total_sales DESC 
-         LIMIT 10"
-    )?;
+         LIMIT 10",
+        sold_quantity
+    ))?;
     
     let top_products: Vec<TopProduct> = top_stmt
-        .query_map([], |row| {
+        .query_map([&currency], |row| {
             Ok(TopProduct {
-                name: row.get(0)?,
-                sales: row.get(1)?,
-                revenue: row.get(2)?,
+                product_id: row.get(0)?,
+                name: row.get(1)?,
+                sales: row.get(2)?,
+                revenue: money(row.get(3)?),
             })
         })?
         .collect::<Result<Vec<_>>>()?;
     
-    // Get product distribution
-    let mut dist_stmt = conn.prepare(
-        "SELECT product_name, CAST(SUM(quantity) AS INTEGER) as total 
-         FROM sale_items 
-         GROUP BY product_name 
+    // Get product distribution, counting bundle components like above
+    let mut dist_stmt = conn.prepare_cached(&format!(
+        "WITH lines AS (
+             SELECT i.product_id, i.product_name, {} AS quantity 
+             FROM sale_items i LEFT JOIN products p ON p.id = i.product_id 
+             UNION ALL 
+             SELECT product_id, product_name, quantity FROM sale_item_components
+         )
+         SELECT i.product_id, 
+                COALESCE(p.name, i.product_name) as name, 
+                CAST(SUM(i.quantity) AS INTEGER) as total 
+         FROM lines i 
+         LEFT JOIN products p ON p.id = i.product_id 
+         GROUP BY i.product_id, CASE WHEN i.product_id IS NULL THEN i.product_name END 
          ORDER BY total DESC 
-         LIMIT 5"
-    )?;
+         LIMIT 5",
+        sold_quantity
+    ))?;
     
     let product_distribution: Vec<ProductDistribution> = dist_stmt
         .query_map([], |row| {
             Ok(ProductDistribution {
-                name: row.get(0)?,
-                value: row.get(1)?,
+                product_id: row.get(0)?,
+                name: row.get(1)?,
+                value: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    
+    // Revenue per category, after discounts. Products are counted under the
+    // category they are in now; lines for unknown or uncategorized products
+    // are grouped together.
+    let mut category_stmt = conn.prepare_cached(&format!(
+        "SELECT c.id, 
+                COALESCE(c.name, 'Uncategorized') as name, 
+                CAST(SUM({}) AS INTEGER) as total_sales, 
+                COALESCE(SUM(i.subtotal - i.discount - i.order_discount), 0) as revenue 
+         FROM sale_items i 
+         JOIN sales s ON s.id = i.sale_id 
+         LEFT JOIN products p ON p.id = i.product_id 
+         LEFT JOIN categories c ON c.id = p.category_id 
+         WHERE s.currency = ?1 
+         GROUP BY c.id 
+         ORDER BY revenue DESC",
+        sold_quantity
+    ))?;
+    
+    let category_revenue: Vec<CategoryRevenue> = category_stmt
+        .query_map([&currency], |row| {
+            Ok(CategoryRevenue {
+                category_id: row.get(0)?,
+                name: row.get(1)?,
+                sales: row.get(2)?,
+                revenue: money(row.get(3)?),
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    
+    // Most popular options. Like products, options still on the menu are
+    // grouped by id and shown under their current name.
+    let mut modifier_stmt = conn.prepare_cached(&format!(
+        "SELECT m.modifier_id, 
+                COALESCE(g.name, m.group_name) as group_name, 
+                COALESCE(cur.name, m.name) as name, 
+                CAST(SUM({q}) AS INTEGER) as total_sales, 
+                CAST(ROUND(SUM(m.price_delta * {q})) AS INTEGER) as revenue 
+         FROM sale_item_modifiers m 
+         JOIN sale_items i ON i.id = m.sale_item_id 
+         JOIN sales s ON s.id = i.sale_id 
+         LEFT JOIN products p ON p.id = i.product_id 
+         LEFT JOIN modifiers cur ON cur.id = m.modifier_id 
+         LEFT JOIN modifier_groups g ON g.id = cur.group_id 
+         WHERE s.currency = ?1 
+         GROUP BY m.modifier_id, 
+                  CASE WHEN m.modifier_id IS NULL THEN m.group_name END, 
+                  CASE WHEN m.modifier_id IS NULL THEN m.name END 
+         ORDER BY total_sales DESC 
+         LIMIT 10",
+        q = sold_quantity
+    ))?;
+    
+    let top_modifiers: Vec<ModifierUsage> = modifier_stmt
+        .query_map([&currency], |row| {
+            Ok(ModifierUsage {
+                modifier_id: row.get(0)?,
+                group: row.get(1)?,
+                name: row.get(2)?,
+                sales: row.get(3)?,
+                revenue: money(row.get(4)?),
             })
         })?
         .collect::<Result<Vec<_>>>()?;
     
     // Get summary
-    let mut summary_stmt = conn.prepare(
-        "SELECT COUNT(*) as total_orders, 
-                COALESCE(SUM(total_amount), 0.0) as total_revenue 
-         FROM sales"
+    let mut summary_stmt = conn.prepare_cached(
+        "SELECT COUNT(CASE WHEN refund_of IS NULL THEN 1 END) as total_orders,
+                COALESCE(SUM(total_amount), 0) as total_revenue 
+         FROM sales 
+         WHERE currency = ?1"
     )?;
     
-    let (total_orders, total_revenue): (i64, f64) = summary_stmt.query_row([], |row| {
+    let (total_orders, total_revenue): (i64, i64) = summary_stmt.query_row([&currency], |row| {
         Ok((row.get(0)?, row.get(1)?))
     })?;
     
     let average_order_value = if total_orders > 0 {
-        total_revenue / total_orders as f64
+        (total_revenue as f64 / total_orders as f64).round() as i64
     } else {
-        0.0
+        0
     };
     
     Ok(AnalyticsData {
         daily_revenue,
         top_products,
         product_distribution,
+        category_revenue,
+        top_modifiers,
         summary: AnalyticsSummary {
             total_orders,
-            total_revenue,
-            average_order_value,
+            total_revenue: money(total_revenue),
+            average_order_value: money(average_order_value),
         },
     })
 }
-
-// Database import/export
-pub fn export_database(db_path: &PathBuf) -> Result<Vec<u8>> {
-    std::fs::read(db_path)
-        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
-}
-
-pub fn import_database(db_path: &PathBuf, data: Vec<u8>) -> Result<()> {
-    // Backup existing database
-    if db_path.exists() {
-        let backup_path = db_path.with_extension("db.backup");
-        std::fs::copy(db_path, backup_path)
-            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
-    }
-    
-    // Write new database
-    std::fs::write(db_path, data)
-        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
-    
-    Ok(())
-}
-
@@ -0,0 +1,472 @@
+use crate::error::{AppError, AppResult};
+use crate::money::Money;
+use chrono::NaiveDate;
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+// A percentage off, in basis points so 12.5% is 1250, or a fixed amount off
+// in minor units
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscountKind {
+    Percentage,
+    Fixed,
+}
+
+impl DiscountKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DiscountKind::Percentage => "percentage",
+            DiscountKind::Fixed => "fixed",
+        }
+    }
+
+    pub fn from_column(value: &str) -> Self {
+        match value {
+            "fixed" => DiscountKind::Fixed,
+            _ => DiscountKind::Percentage,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiscountReason {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewDiscountReason {
+    pub name: String,
+}
+
+// A discount given at the till on a line or a whole order. A fixed `value`
+// is in the currency of the sale. Every discount needs one of the reasons.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NewDiscount {
+    pub kind: DiscountKind,
+    pub value: i64,
+    pub reason_id: i64,
+}
+
+// A discount as it was given, with its reason as it was named then and the
+// amount it took off
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppliedDiscount {
+    pub kind: DiscountKind,
+    pub value: i64,
+    pub reason: String,
+    pub amount: Money,
+}
+
+// A coupon used on a sale. `coupon_id` is None once the coupon is deleted.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppliedCoupon {
+    pub coupon_id: Option<i64>,
+    pub code: String,
+    pub amount: Money,
+}
+
+// A code customers can redeem for a discount on an order. A fixed `value`
+// and `min_spend` are in `currency`, and the coupon only applies to sales in
+// it. Dates are inclusive and either end can be open; `usage_limit` of None
+// means no limit. `times_used` counts the sales that used it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Coupon {
+    pub id: i64,
+    pub code: String,
+    pub description: Option<String>,
+    pub kind: DiscountKind,
+    pub value: i64,
+    pub currency: String,
+    pub min_spend: Option<Money>,
+    pub valid_from: Option<String>,
+    pub valid_until: Option<String>,
+    pub usage_limit: Option<i64>,
+    pub times_used: i64,
+    pub active: bool,
+    pub created_at: String,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewCoupon {
+    pub code: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub kind: DiscountKind,
+    pub value: i64,
+    pub currency: String,
+    #[serde(default)]
+    pub min_spend: Option<Money>,
+    #[serde(default)]
+    pub valid_from: Option<String>,
+    #[serde(default)]
+    pub valid_until: Option<String>,
+    #[serde(default)]
+    pub usage_limit: Option<i64>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn clean(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+// What a discount takes off `base` minor units, never more than all of it.
+// Percentages round half away from zero like Money::times_quantity.
+pub fn discount_off(kind: DiscountKind, value: i64, base: i64) -> i64 {
+    let base = base.max(0);
+    let off = match kind {
+        DiscountKind::Percentage => ((base as i128 * value as i128 + 5_000) / 10_000) as i64,
+        DiscountKind::Fixed => value,
+    };
+    off.clamp(0, base)
+}
+
+// Splits `total` minor units across lines in proportion to `weights`,
+// handing the units left over by rounding down to the largest remainders,
+// so the shares always add up to `total`
+pub fn allocate(total: i64, weights: &[i64]) -> Vec<i64> {
+    let sum: i128 = weights.iter().map(|&w| w.max(0) as i128).sum();
+    if sum == 0 {
+        return vec![0; weights.len()];
+    }
+    let exact: Vec<(i64, i128)> = weights
+        .iter()
+        .map(|&w| {
+            let scaled = total as i128 * w.max(0) as i128;
+            ((scaled / sum) as i64, scaled % sum)
+        })
+        .collect();
+    let mut shares: Vec<i64> = exact.iter().map(|(share, _)| *share).collect();
+    let left = total - shares.iter().sum::<i64>();
+    let mut order: Vec<usize> = (0..weights.len()).collect();
+    order.sort_by(|&a, &b| exact[b].1.cmp(&exact[a].1).then(a.cmp(&b)));
+    for &index in order.iter().take(left.max(0) as usize) {
+        shares[index] += 1;
+    }
+    shares
+}
+
+fn validate_value(kind: DiscountKind, value: i64, details: serde_json::Value) -> AppResult<()> {
+    let reason = match kind {
+        DiscountKind::Percentage if !(1..=10_000).contains(&value) => "a percentage must be above 0 and at most 100",
+        DiscountKind::Fixed if value <= 0 => "a fixed discount must be greater than zero",
+        _ => return Ok(()),
+    };
+    Err(AppError::validation_with(format!("Invalid discount: {}", reason), details))
+}
+
+// Checks a discount given at the till and works out what it takes off
+// `base` minor units. `item` is the line it is on, if it isn't on the order.
+pub fn resolve_discount(
+    conn: &Connection,
+    discount: &NewDiscount,
+    base: i64,
+    currency: &str,
+    item: Option<usize>,
+) -> AppResult<AppliedDiscount> {
+    let details = json!({ "item": item, "reason_id": discount.reason_id });
+    validate_value(discount.kind, discount.value, details.clone())?;
+    let reason: String = conn
+        .prepare_cached("SELECT name FROM discount_reasons WHERE id = ?1")?
+        .query_row([discount.reason_id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| AppError::validation_with("Invalid discount: choose a reason for it", details))?;
+    Ok(AppliedDiscount {
+        kind: discount.kind,
+        value: discount.value,
+        reason,
+        amount: Money::new(discount_off(discount.kind, discount.value, base), currency),
+    })
+}
+
+// Discount reasons
+
+pub fn get_discount_reasons(conn: &Connection) -> Result<Vec<DiscountReason>> {
+    let mut stmt = conn.prepare_cached("SELECT id, name FROM discount_reasons ORDER BY name COLLATE NOCASE")?;
+    let reasons = stmt
+        .query_map([], |row| Ok(DiscountReason { id: row.get(0)?, name: row.get(1)? }))?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(reasons)
+}
+
+pub fn add_discount_reason(conn: &Connection, reason: NewDiscountReason) -> AppResult<DiscountReason> {
+    let name = reason.name.trim();
+    if name.is_empty() {
+        return Err(AppError::validation_with("Reason is required", json!({ "field": "name" })));
+    }
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM discount_reasons WHERE name = ?1)",
+        [name],
+        |row| row.get(0),
+    )?;
+    if taken {
+        return Err(AppError::Conflict {
+            message: format!("A discount reason named {} already exists", name),
+            details: Some(json!({ "field": "name" })),
+        });
+    }
+    conn.execute("INSERT INTO discount_reasons (name) VALUES (?1)", [name])?;
+    Ok(DiscountReason {
+        id: conn.last_insert_rowid(),
+        name: name.to_string(),
+    })
+}
+
+// Sales keep the name of the reason they were given, so reasons can always
+// be removed
+pub fn delete_discount_reason(conn: &Connection, id: i64) -> AppResult<()> {
+    if conn.execute("DELETE FROM discount_reasons WHERE id = ?1", [id])? == 0 {
+        return Err(AppError::not_found(format!("Discount reason {} not found", id)));
+    }
+    Ok(())
+}
+
+// Coupons
+
+const COUPON_COLUMNS: &str = "c.id, c.code, c.description, c.kind, c.value, c.currency, c.min_spend,
+     c.valid_from, c.valid_until, c.usage_limit,
+     (SELECT COUNT(*) FROM sales s WHERE s.coupon_id = c.id), c.active, c.created_at";
+
+fn coupon_from_row(row: &Row) -> Result<Coupon> {
+    let currency: String = row.get(5)?;
+    Ok(Coupon {
+        id: row.get(0)?,
+        code: row.get(1)?,
+        description: row.get(2)?,
+        kind: DiscountKind::from_column(&row.get::<_, String>(3)?),
+        value: row.get(4)?,
+        min_spend: row.get::<_, Option<i64>>(6)?.map(|minor| Money::new(minor, &*currency)),
+        currency,
+        valid_from: row.get(7)?,
+        valid_until: row.get(8)?,
+        usage_limit: row.get(9)?,
+        times_used: row.get(10)?,
+        active: row.get(11)?,
+        created_at: row.get(12)?,
+    })
+}
+
+pub fn get_coupons(conn: &Connection) -> Result<Vec<Coupon>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM coupons c ORDER BY c.active DESC, c.code COLLATE NOCASE",
+        COUPON_COLUMNS
+    ))?;
+    let coupons = stmt.query_map([], coupon_from_row)?.collect::<Result<Vec<_>>>()?;
+    Ok(coupons)
+}
+
+fn get_coupon(conn: &Connection, id: i64) -> AppResult<Coupon> {
+    conn.prepare_cached(&format!("SELECT {} FROM coupons c WHERE c.id = ?1", COUPON_COLUMNS))?
+        .query_row([id], coupon_from_row)
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Coupon {} not found", id)))
+}
+
+// Checks a coupon, returning its code, currency and dates cleaned up
+fn validate_coupon(
+    conn: &Connection,
+    id: Option<i64>,
+    coupon: &NewCoupon,
+) -> AppResult<(String, String, Option<String>, Option<String>)> {
+    let code = coupon.code.trim().to_string();
+    if code.is_empty() {
+        return Err(AppError::validation_with("Coupon code is required", json!({ "field": "code" })));
+    }
+    let currency = coupon.currency.trim().to_uppercase();
+    if currency.is_empty() {
+        return Err(AppError::validation_with("Currency is required", json!({ "field": "currency" })));
+    }
+    validate_value(coupon.kind, coupon.value, json!({ "field": "value" }))?;
+    if let Some(min_spend) = &coupon.min_spend {
+        if min_spend.is_negative() || !min_spend.currency.eq_ignore_ascii_case(&currency) {
+            return Err(AppError::validation_with(
+                format!("Minimum spend must be zero or more, in {}", currency),
+                json!({ "field": "min_spend" }),
+            ));
+        }
+    }
+    if coupon.usage_limit.is_some_and(|limit| limit <= 0) {
+        return Err(AppError::validation_with(
+            "Usage limit must be at least 1, or left empty for no limit",
+            json!({ "field": "usage_limit" }),
+        ));
+    }
+    let date = |value: &Option<String>, field: &str| -> AppResult<Option<NaiveDate>> {
+        clean(value.clone())
+            .map(|value| {
+                NaiveDate::parse_from_str(&value, "%Y-%m-%d").map_err(|_| {
+                    AppError::validation_with(
+                        format!("Invalid date: {}", value),
+                        json!({ "field": field, "expected": "YYYY-MM-DD" }),
+                    )
+                })
+            })
+            .transpose()
+    };
+    let valid_from = date(&coupon.valid_from, "valid_from")?;
+    let valid_until = date(&coupon.valid_until, "valid_until")?;
+    if let (Some(from), Some(until)) = (valid_from, valid_until) {
+        if until < from {
+            return Err(AppError::validation_with(
+                "The coupon can't end before it starts",
+                json!({ "field": "valid_until" }),
+            ));
+        }
+    }
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM coupons WHERE code = ?1 AND id IS NOT ?2)",
+        (&code, id),
+        |row| row.get(0),
+    )?;
+    if taken {
+        return Err(AppError::Conflict {
+            message: format!("A coupon with the code {} already exists", code),
+            details: Some(json!({ "field": "code" })),
+        });
+    }
+    Ok((
+        code,
+        currency,
+        valid_from.map(|d| d.to_string()),
+        valid_until.map(|d| d.to_string()),
+    ))
+}
+
+pub fn add_coupon(conn: &Connection, coupon: NewCoupon) -> AppResult<Coupon> {
+    let (code, currency, valid_from, valid_until) = validate_coupon(conn, None, &coupon)?;
+    conn.execute(
+        "INSERT INTO coupons (code, description, kind, value, currency, min_spend, valid_from, valid_until,
+                              usage_limit, active)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        (
+            &code,
+            clean(coupon.description),
+            coupon.kind.as_str(),
+            coupon.value,
+            &currency,
+            coupon.min_spend.map(|m| m.minor),
+            valid_from,
+            valid_until,
+            coupon.usage_limit,
+            coupon.active,
+        ),
+    )?;
+    get_coupon(conn, conn.last_insert_rowid())
+}
+
+pub fn update_coupon(conn: &Connection, id: i64, coupon: NewCoupon) -> AppResult<Coupon> {
+    let (code, currency, valid_from, valid_until) = validate_coupon(conn, Some(id), &coupon)?;
+    if conn.execute(
+        "UPDATE coupons SET code = ?1, description = ?2, kind = ?3, value = ?4, currency = ?5, min_spend = ?6,
+                            valid_from = ?7, valid_until = ?8, usage_limit = ?9, active = ?10
+         WHERE id = ?11",
+        (
+            &code,
+            clean(coupon.description),
+            coupon.kind.as_str(),
+            coupon.value,
+            &currency,
+            coupon.min_spend.map(|m| m.minor),
+            valid_from,
+            valid_until,
+            coupon.usage_limit,
+            coupon.active,
+            id,
+        ),
+    )? == 0
+    {
+        return Err(AppError::not_found(format!("Coupon {} not found", id)));
+    }
+    get_coupon(conn, id)
+}
+
+// Sales keep the code of the coupon they used, and lose only the link to
+// it, so coupons can always be removed
+pub fn delete_coupon(conn: &Connection, id: i64) -> AppResult<()> {
+    if conn.execute("DELETE FROM coupons WHERE id = ?1", [id])? == 0 {
+        return Err(AppError::not_found(format!("Coupon {} not found", id)));
+    }
+    Ok(())
+}
+
+// The coupon `code` names, if it can be used today on an order in
+// `currency` whose items come to `spend` minor units. Today is the UTC date
+// the sale is recorded on, which the reports go by as well, so a coupon is
+// valid on exactly the days its sales are counted in.
+pub fn redeemable_coupon(conn: &Connection, code: &str, currency: &str, spend: i64) -> AppResult<Coupon> {
+    let code = code.trim();
+    let invalid = |message: String| AppError::validation_with(message, json!({ "field": "coupon_code", "code": code }));
+    let coupon = conn
+        .prepare_cached(&format!("SELECT {} FROM coupons c WHERE c.code = ?1", COUPON_COLUMNS))?
+        .query_row([code], coupon_from_row)
+        .optional()?
+        .ok_or_else(|| invalid(format!("Unknown coupon code: {}", code)))?;
+    let today: String = conn.query_row("SELECT date('now')", [], |row| row.get(0))?;
+    if !coupon.active {
+        return Err(invalid(format!("Coupon {} is switched off", coupon.code)));
+    }
+    if let Some(from) = coupon.valid_from.as_deref().filter(|from| *from > today.as_str()) {
+        return Err(invalid(format!("Coupon {} can't be used before {}", coupon.code, from)));
+    }
+    if let Some(until) = coupon.valid_until.as_deref().filter(|until| *until < today.as_str()) {
+        return Err(invalid(format!("Coupon {} expired on {}", coupon.code, until)));
+    }
+    if coupon.usage_limit.is_some_and(|limit| coupon.times_used >= limit) {
+        return Err(invalid(format!("Coupon {} has been used up", coupon.code)));
+    }
+    if !coupon.currency.eq_ignore_ascii_case(currency) {
+        return Err(invalid(format!("Coupon {} is for sales in {}", coupon.code, coupon.currency)));
+    }
+    if let Some(min_spend) = coupon.min_spend.as_ref().filter(|min| spend < min.minor) {
+        return Err(invalid(format!("Coupon {} needs a spend of at least {}", coupon.code, min_spend)));
+    }
+    Ok(coupon)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentage_discounts_round_half_up() {
+        // 12.5% of 1.00 and 10% of 0.05
+        assert_eq!(discount_off(DiscountKind::Percentage, 1_250, 100), 13);
+        assert_eq!(discount_off(DiscountKind::Percentage, 1_000, 5), 1);
+        assert_eq!(discount_off(DiscountKind::Percentage, 1_000, 4), 0);
+        assert_eq!(discount_off(DiscountKind::Percentage, 10_000, 999), 999);
+    }
+
+    #[test]
+    fn discounts_take_no_more_than_the_base() {
+        assert_eq!(discount_off(DiscountKind::Fixed, 500, 300), 300);
+        assert_eq!(discount_off(DiscountKind::Fixed, 500, 1_000), 500);
+        assert_eq!(discount_off(DiscountKind::Fixed, 500, -100), 0);
+    }
+
+    #[test]
+    fn allocated_shares_add_up_to_the_total() {
+        assert_eq!(allocate(100, &[1, 1, 1]), vec![34, 33, 33]);
+        assert_eq!(allocate(10, &[300, 200, 100]), vec![5, 3, 2]);
+        assert_eq!(allocate(7, &[0, 0]), vec![0, 0]);
+        assert_eq!(allocate(5, &[-10, 10]), vec![0, 5]);
+        for (total, weights) in [(1_001, vec![333, 333, 334]), (99, vec![1, 2, 3, 4, 5, 6, 7]), (1, vec![5, 5])] {
+            assert_eq!(allocate(total, &weights).iter().sum::<i64>(), total);
+        }
+    }
+
+    #[test]
+    fn leftover_units_go_to_the_largest_remainders() {
+        // Exact shares 1.5, 2.5 and 6.0 leave one unit over, which goes to
+        // the first of the equal remainders
+        assert_eq!(allocate(10, &[3, 5, 12]), vec![2, 2, 6]);
+        // Exact shares 0.6, 0.4 and 1.0 leave one over for the 0.6
+        assert_eq!(allocate(2, &[3, 2, 5]), vec![1, 0, 1]);
+    }
+}
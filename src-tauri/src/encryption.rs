@@ -0,0 +1,272 @@
+use crate::error::{AppError, AppResult};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{KeyInit, OsRng, Payload};
+use chacha20poly1305::XChaCha20Poly1305;
+use serde_json::json;
+use std::io::{self, Read, Write};
+
+// Password-protected backups. The file is a fixed header followed by the
+// backup split into chunks, each sealed with XChaCha20-Poly1305 using the
+// STREAM construction so chunks can't be reordered, dropped or truncated.
+//
+//   magic "RMSCRYPT" | version u8 | argon2id m_cost, t_cost, p_cost (u32 LE)
+//   | salt [16] | stream nonce [19] | chunks...
+//
+// The whole header is authenticated with every chunk, so changing the KDF
+// parameters or the version is detected like any other tampering.
+const MAGIC: &[u8] = b"RMSCRYPT";
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 19;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 12 + SALT_LEN + NONCE_LEN;
+const CHUNK_LEN: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+
+// Argon2id settings for new files (the OWASP minimum: 19 MiB, 2 passes)
+const M_COST: u32 = 19 * 1024;
+const T_COST: u32 = 2;
+const P_COST: u32 = 1;
+
+// Limits on what a file may ask for, so a crafted header can't make us
+// allocate gigabytes or spin for minutes before the password is even checked
+const MAX_M_COST: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 16;
+
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn derive_key(password: &str, salt: &[u8], m_cost: u32, t_cost: u32, p_cost: u32) -> AppResult<[u8; 32]> {
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32))
+        .map_err(|_| unsupported("invalid key derivation parameters"))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|_| unsupported("invalid key derivation parameters"))?;
+    Ok(key)
+}
+
+fn unsupported(reason: &str) -> AppError {
+    AppError::validation_with(
+        "The encrypted backup could not be read",
+        json!({ "reason": reason }),
+    )
+}
+
+// The chunk counter is 32 bits, so this only happens past 256 TiB
+fn too_large() -> AppError {
+    AppError::validation("The backup is too large to encrypt")
+}
+
+fn decryption_failed() -> AppError {
+    AppError::validation_with(
+        "Wrong password, or the backup file has been changed or damaged",
+        json!({ "reason": "decryption_failed" }),
+    )
+}
+
+pub fn password_required() -> AppError {
+    AppError::validation_with(
+        "This backup is encrypted. Enter its password to import it.",
+        json!({ "reason": "password_required" }),
+    )
+}
+
+pub fn check_password(password: &str) -> AppResult<()> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(AppError::validation_with(
+            format!("Backup password must be at least {} characters", MIN_PASSWORD_LEN),
+            json!({ "field": "password", "min_length": MIN_PASSWORD_LEN }),
+        ));
+    }
+    Ok(())
+}
+
+// Reads until `buf` is full or the input ends, returning how much was read
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+pub fn encrypt(mut reader: impl Read, mut writer: impl Write, password: &str) -> AppResult<()> {
+    check_password(password)?;
+
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(VERSION);
+    for cost in [M_COST, T_COST, P_COST] {
+        header.extend_from_slice(&cost.to_le_bytes());
+    }
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce);
+    writer.write_all(&header)?;
+
+    let key = derive_key(password, &salt, M_COST, T_COST, P_COST)?;
+    let cipher = XChaCha20Poly1305::new(&key.into());
+    let mut encryptor = EncryptorBE32::from_aead(cipher, &nonce.into());
+
+    // Every chunk but the last is exactly CHUNK_LEN, which is how the reader
+    // tells the last one apart. An input that is a multiple of CHUNK_LEN ends
+    // with an empty last chunk.
+    let mut buf = vec![0u8; CHUNK_LEN];
+    loop {
+        let n = read_full(&mut reader, &mut buf)?;
+        let payload = Payload { msg: &buf[..n], aad: &header };
+        if n < CHUNK_LEN {
+            let sealed = encryptor.encrypt_last(payload).map_err(|_| too_large())?;
+            writer.write_all(&sealed)?;
+            break;
+        }
+        let sealed = encryptor.encrypt_next(payload).map_err(|_| too_large())?;
+        writer.write_all(&sealed)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn decrypt(mut reader: impl Read, mut writer: impl Write, password: &str) -> AppResult<()> {
+    let mut header = [0u8; HEADER_LEN];
+    if read_full(&mut reader, &mut header)? < HEADER_LEN || !header.starts_with(MAGIC) {
+        return Err(unsupported("not an encrypted backup"));
+    }
+
+    let version = header[MAGIC.len()];
+    if version > VERSION {
+        return Err(AppError::Conflict {
+            message: "This backup was encrypted by a newer version of the app. Please update the app."
+                .to_string(),
+            details: Some(json!({ "version": version })),
+        });
+    }
+    if version != VERSION {
+        return Err(unsupported("unsupported format version"));
+    }
+
+    let mut offset = MAGIC.len() + 1;
+    let mut next_u32 = || {
+        let value = u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+        offset += 4;
+        value
+    };
+    let (m_cost, t_cost, p_cost) = (next_u32(), next_u32(), next_u32());
+    if m_cost > MAX_M_COST || t_cost > MAX_T_COST || p_cost > MAX_P_COST {
+        return Err(unsupported("key derivation parameters out of range"));
+    }
+    let salt = &header[offset..offset + SALT_LEN];
+    let nonce: [u8; NONCE_LEN] = header[offset + SALT_LEN..].try_into().unwrap();
+
+    let key = derive_key(password, salt, m_cost, t_cost, p_cost)?;
+    let cipher = XChaCha20Poly1305::new(&key.into());
+    let mut decryptor = DecryptorBE32::from_aead(cipher, &nonce.into());
+
+    let mut buf = vec![0u8; CHUNK_LEN + TAG_LEN];
+    loop {
+        let n = read_full(&mut reader, &mut buf)?;
+        let payload = Payload { msg: &buf[..n], aad: &header };
+        if n < buf.len() {
+            let plain = decryptor.decrypt_last(payload).map_err(|_| decryption_failed())?;
+            writer.write_all(&plain)?;
+            break;
+        }
+        let plain = decryptor.decrypt_next(payload).map_err(|_| decryption_failed())?;
+        writer.write_all(&plain)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSWORD: &str = "correct horse battery";
+
+    fn encrypted(plain: &[u8]) -> Vec<u8> {
+        let mut sealed = Vec::new();
+        encrypt(plain, &mut sealed, PASSWORD).unwrap();
+        sealed
+    }
+
+    fn reason(err: &AppError) -> Option<String> {
+        match err {
+            AppError::Validation { details, .. } => details
+                .as_ref()
+                .and_then(|d| d["reason"].as_str())
+                .map(str::to_string),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn round_trips_and_rejects_the_wrong_password() {
+        // Two full chunks and a partial one
+        let plain: Vec<u8> = (0..2 * CHUNK_LEN + 1000).map(|i| (i % 251) as u8).collect();
+        let sealed = encrypted(&plain);
+        assert!(is_encrypted(&sealed));
+        assert_ne!(&sealed[HEADER_LEN..HEADER_LEN + 64], &plain[..64]);
+
+        let mut opened = Vec::new();
+        decrypt(&sealed[..], &mut opened, PASSWORD).unwrap();
+        assert_eq!(opened, plain);
+
+        let err = decrypt(&sealed[..], Vec::new(), "wrong password").unwrap_err();
+        assert_eq!(reason(&err).as_deref(), Some("decryption_failed"));
+    }
+
+    #[test]
+    fn detects_tampering_and_truncation() {
+        let plain = vec![7u8; CHUNK_LEN];
+        let sealed = encrypted(&plain);
+
+        let mut flipped = sealed.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        let err = decrypt(&flipped[..], Vec::new(), PASSWORD).unwrap_err();
+        assert_eq!(reason(&err).as_deref(), Some("decryption_failed"));
+
+        // Dropping the empty last chunk makes the full one look like the last
+        let truncated = &sealed[..sealed.len() - TAG_LEN];
+        let err = decrypt(truncated, Vec::new(), PASSWORD).unwrap_err();
+        assert_eq!(reason(&err).as_deref(), Some("decryption_failed"));
+    }
+
+    #[test]
+    fn tells_newer_formats_from_unreadable_ones() {
+        let mut header = [0u8; HEADER_LEN];
+        header[..MAGIC.len()].copy_from_slice(MAGIC);
+
+        header[MAGIC.len()] = VERSION + 1;
+        let err = decrypt(&header[..], Vec::new(), PASSWORD).unwrap_err();
+        assert!(matches!(err, AppError::Conflict { .. }), "{:?}", err);
+
+        header[MAGIC.len()] = 0;
+        let err = decrypt(&header[..], Vec::new(), PASSWORD).unwrap_err();
+        assert_eq!(reason(&err).as_deref(), Some("unsupported format version"));
+
+        let err = decrypt(&b"SQLite format 3\0"[..], Vec::new(), PASSWORD).unwrap_err();
+        assert_eq!(reason(&err).as_deref(), Some("not an encrypted backup"));
+    }
+
+    #[test]
+    fn refuses_short_passwords() {
+        let err = encrypt(&b"data"[..], Vec::new(), "short").unwrap_err();
+        assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::migrations::MigrationError;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+use std::fmt;
+
+// Error returned by every Tauri command. It reaches the frontend as
+// `{ code, message, details }` where `code` is one of "database",
+// "validation", "not_found", "io", "email", "permission" or "conflict"
+// and `details` is either null or an object with extra context.
+#[derive(Debug)]
+pub enum AppError {
+    Database(rusqlite::Error),
+    Migration(MigrationError),
+    Validation { message: String, details: Option<Value> },
+    NotFound(String),
+    Io(std::io::Error),
+    Email(String),
+    Permission(String),
+    Conflict { message: String, details: Option<Value> },
+}
+
+pub type AppResult<T> = std::result::Result<T, AppError>;
+
+impl AppError {
+    pub fn validation(message: impl Into<String>) -> Self {
+        AppError::Validation {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn validation_with(message: impl Into<String>, details: Value) -> Self {
+        AppError::Validation {
+            message: message.into(),
+            details: Some(details),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::NotFound(message.into())
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Database(_) => "database",
+            AppError::Migration(MigrationError::SchemaTooNew { .. }) => "conflict",
+            AppError::Migration(_) => "database",
+            AppError::Validation { .. } => "validation",
+            AppError::NotFound(_) => "not_found",
+            AppError::Io(_) => "io",
+            AppError::Email(_) => "email",
+            AppError::Permission(_) => "permission",
+            AppError::Conflict { .. } => "conflict",
+        }
+    }
+
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            AppError::Database(e) => e
+                .sqlite_error_code()
+                .map(|code| json!({ "sqlite_code": format!("{:?}", code) })),
+            AppError::Migration(MigrationError::SchemaTooNew { found, supported }) => {
+                Some(json!({ "found": found, "supported": supported }))
+            }
+            AppError::Migration(MigrationError::Failed { version, name, .. }) => {
+                Some(json!({ "version": version, "name": name }))
+            }
+            AppError::Io(e) => Some(json!({ "kind": format!("{:?}", e.kind()) })),
+            AppError::Validation { details, .. } | AppError::Conflict { details, .. } => {
+                details.clone()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Database(e) => write!(f, "Database error: {}", e),
+            AppError::Migration(e) => write!(f, "{}", e),
+            AppError::Validation { message, .. } => write!(f, "{}", message),
+            AppError::NotFound(message) => write!(f, "{}", message),
+            AppError::Io(e) => write!(f, "File error: {}", e),
+            AppError::Email(message) => write!(f, "{}", message),
+            AppError::Permission(message) => write!(f, "{}", message),
+            AppError::Conflict { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::Database(e) => Some(e),
+            AppError::Migration(e) => Some(e),
+            AppError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        if let rusqlite::Error::QueryReturnedNoRows = e {
+            return AppError::not_found("Record not found");
+        }
+        match e.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::ConstraintViolation) => AppError::Conflict {
+                message: format!("Change conflicts with existing data: {}", e),
+                details: None,
+            },
+            _ => AppError::Database(e),
+        }
+    }
+}
+
+impl From<MigrationError> for AppError {
+    fn from(e: MigrationError) -> Self {
+        match e {
+            MigrationError::Sqlite(e) => e.into(),
+            e => AppError::Migration(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::PermissionDenied => AppError::Permission(format!(
+                "Permission denied: {}",
+                e
+            )),
+            _ => AppError::Io(e),
+        }
+    }
+}
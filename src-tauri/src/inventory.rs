@@ -0,0 +1,688 @@
+use crate::error::{AppError, AppResult};
+use crate::money::Money;
+use crate::purchasing;
+use crate::units::{self, Unit};
+use rusqlite::{Connection, OptionalExtension, Result, Row, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+// A stocked item, with its current level in `unit`. Stock at or below
+// `reorder_point` is low; `par_level` is what a delivery should restore.
+// `cost_per_unit` is what one `unit` cost when last received.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Ingredient {
+    pub id: i64,
+    pub name: String,
+    pub unit: String,
+    pub quantity: f64,
+    pub reorder_point: Option<f64>,
+    pub par_level: Option<f64>,
+    pub cost_per_unit: Option<Money>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewIngredient {
+    pub name: String,
+    pub unit: String,
+    #[serde(default)]
+    pub reorder_point: Option<f64>,
+    #[serde(default)]
+    pub par_level: Option<f64>,
+}
+
+// An ingredient at or below its reorder point. `daily_usage` is the average
+// over the usage window, and `suggested_quantity` what to order.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LowStockItem {
+    pub ingredient: Ingredient,
+    pub daily_usage: f64,
+    pub suggested_quantity: f64,
+}
+
+// How much of an ingredient one unit of a product uses, in the ingredient's unit
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecipeItem {
+    pub ingredient_id: i64,
+    pub name: String,
+    pub unit: String,
+    pub quantity: f64,
+}
+
+// `unit` defaults to the ingredient's own; a quantity in any other unit of
+// the same kind is converted into it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewRecipeItem {
+    pub ingredient_id: i64,
+    pub quantity: f64,
+    #[serde(default)]
+    pub unit: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MovementKind {
+    Sale,
+    Receive,
+    Adjust,
+    Waste,
+}
+
+impl MovementKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MovementKind::Sale => "sale",
+            MovementKind::Receive => "receive",
+            MovementKind::Adjust => "adjust",
+            MovementKind::Waste => "waste",
+        }
+    }
+
+    fn from_column(value: &str) -> Self {
+        match value {
+            "sale" => MovementKind::Sale,
+            "receive" => MovementKind::Receive,
+            "waste" => MovementKind::Waste,
+            _ => MovementKind::Adjust,
+        }
+    }
+}
+
+// One change to an ingredient's level. `change` is negative for stock used;
+// `unit_cost` is set on receipts with a known cost.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockMovement {
+    pub id: i64,
+    pub ingredient_id: i64,
+    pub kind: MovementKind,
+    pub change: f64,
+    pub quantity_after: f64,
+    pub unit_cost: Option<Money>,
+    pub sale_id: Option<i64>,
+    pub purchase_order_id: Option<i64>,
+    pub stock_count_id: Option<i64>,
+    pub waste_entry_id: Option<i64>,
+    pub note: Option<String>,
+    pub created_at: String,
+}
+
+// A change about to be made to an ingredient's level, with what caused it
+pub struct MovementEntry<'a> {
+    pub kind: MovementKind,
+    pub change: f64,
+    pub unit_cost: Option<&'a Money>,
+    pub sale_id: Option<i64>,
+    pub purchase_order_id: Option<i64>,
+    pub stock_count_id: Option<i64>,
+    pub waste_entry_id: Option<i64>,
+    pub note: Option<&'a str>,
+}
+
+const INGREDIENT_COLUMNS: &str =
+    "id, name, unit, quantity, reorder_point, par_level, cost_per_unit, cost_currency";
+
+fn ingredient_from_row(row: &Row) -> Result<Ingredient> {
+    let cost: Option<i64> = row.get(6)?;
+    let currency: Option<String> = row.get(7)?;
+    Ok(Ingredient {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        unit: row.get(2)?,
+        quantity: row.get(3)?,
+        reorder_point: row.get(4)?,
+        par_level: row.get(5)?,
+        cost_per_unit: cost.zip(currency).map(|(minor, currency)| Money::new(minor, currency)),
+    })
+}
+
+pub fn get_ingredients(conn: &Connection) -> Result<Vec<Ingredient>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM ingredients ORDER BY name COLLATE NOCASE",
+        INGREDIENT_COLUMNS
+    ))?;
+    let ingredients = stmt
+        .query_map([], ingredient_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(ingredients)
+}
+
+pub fn get_ingredient(conn: &Connection, id: i64) -> AppResult<Ingredient> {
+    conn.prepare_cached(&format!("SELECT {} FROM ingredients WHERE id = ?1", INGREDIENT_COLUMNS))?
+        .query_row([id], ingredient_from_row)
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Ingredient {} not found", id)))
+}
+
+fn check_name_free(conn: &Connection, id: Option<i64>, name: &str) -> AppResult<()> {
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM ingredients WHERE name = ?1 AND id IS NOT ?2)",
+        (name, id),
+        |row| row.get(0),
+    )?;
+    if taken {
+        return Err(AppError::Conflict {
+            message: format!("An ingredient named {} already exists", name),
+            details: Some(json!({ "field": "name" })),
+        });
+    }
+    Ok(())
+}
+
+fn validate_ingredient(
+    conn: &Connection,
+    id: Option<i64>,
+    ingredient: &NewIngredient,
+) -> AppResult<&'static Unit> {
+    let (field, reason) = if ingredient.name.trim().is_empty() {
+        ("name", "Ingredient name is required")
+    } else if ingredient.reorder_point.is_some_and(|q| !q.is_finite() || q < 0.0) {
+        ("reorder_point", "Reorder point must be zero or more")
+    } else if ingredient.par_level.is_some_and(|q| !q.is_finite() || q < 0.0) {
+        ("par_level", "Par level must be zero or more")
+    } else if matches!(
+        (ingredient.reorder_point, ingredient.par_level),
+        (Some(reorder), Some(par)) if par < reorder
+    ) {
+        ("par_level", "Par level cannot be below the reorder point")
+    } else {
+        let unit = units::parse_unit(&ingredient.unit, "unit")?;
+        check_name_free(conn, id, ingredient.name.trim())?;
+        return Ok(unit);
+    };
+    Err(AppError::validation_with(reason, json!({ "field": field })))
+}
+
+// New ingredients start with no stock; receive or count it in afterwards
+pub fn add_ingredient(conn: &Connection, ingredient: NewIngredient) -> AppResult<Ingredient> {
+    let unit = validate_ingredient(conn, None, &ingredient)?;
+    conn.execute(
+        "INSERT INTO ingredients (name, unit, reorder_point, par_level) VALUES (?1, ?2, ?3, ?4)",
+        (
+            ingredient.name.trim(),
+            unit.code,
+            ingredient.reorder_point,
+            ingredient.par_level,
+        ),
+    )?;
+    get_ingredient(conn, conn.last_insert_rowid())
+}
+
+// Changes an ingredient's details and reorder levels. Stock levels are
+// changed through receive_stock and adjust_stock only, so they stay in the
+// movement log. Once stock has been recorded the unit is fixed, as the log
+// is kept in it; before that, recipes using the ingredient are converted.
+pub fn update_ingredient(conn: &mut Connection, id: i64, ingredient: NewIngredient) -> AppResult<Ingredient> {
+    let unit = validate_ingredient(conn, Some(id), &ingredient)?;
+
+    let tx = conn.transaction()?;
+    let current = get_ingredient(&tx, id)?;
+    // A unit from before the registry is only being relabelled
+    if let Some(old) = units::find_unit(&current.unit).filter(|old| old.code != unit.code) {
+        let has_history: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM stock_movements WHERE ingredient_id = ?1)",
+            [id],
+            |row| row.get(0),
+        )?;
+        if has_history {
+            return Err(AppError::Conflict {
+                message: format!(
+                    "Stock of {} is recorded in {}, so its unit can no longer be changed",
+                    current.name, old.code
+                ),
+                details: Some(json!({ "field": "unit", "unit": old.code })),
+            });
+        }
+        convert_recipe_quantities(&tx, "ingredient_id", id, old, unit, &current.name)?;
+    }
+    tx.execute(
+        "UPDATE ingredients SET name = ?1, unit = ?2, reorder_point = ?3, par_level = ?4 WHERE id = ?5",
+        (
+            ingredient.name.trim(),
+            unit.code,
+            ingredient.reorder_point,
+            ingredient.par_level,
+            id,
+        ),
+    )?;
+    tx.commit()?;
+    get_ingredient(conn, id)
+}
+
+// Converts the recipe lines of a product or an ingredient (`column` is
+// "product_id" or "ingredient_id") when its unit changes from `from` to
+// `to`. A product's lines are per one of its units, an ingredient's in its
+// unit. Units measuring different things can't be converted, so a change
+// between them is refused while recipes use the unit.
+pub fn convert_recipe_quantities(
+    tx: &Transaction,
+    column: &str,
+    id: i64,
+    from: &Unit,
+    to: &Unit,
+    name: &str,
+) -> AppResult<()> {
+    let ratio = if column == "product_id" {
+        units::convert(1.0, to, from)
+    } else {
+        units::convert(1.0, from, to)
+    };
+    match ratio {
+        Some(ratio) => {
+            tx.execute(
+                &format!("UPDATE recipe_items SET quantity = quantity * ?2 WHERE {} = ?1", column),
+                (id, ratio),
+            )?;
+        }
+        None => {
+            let in_recipes: bool = tx.query_row(
+                &format!("SELECT EXISTS (SELECT 1 FROM recipe_items WHERE {} = ?1)", column),
+                [id],
+                |row| row.get(0),
+            )?;
+            if in_recipes {
+                let used = if column == "product_id" {
+                    format!("{} has a recipe per {}", name, from.name)
+                } else {
+                    format!("Recipes use {} by the {}", name, from.name)
+                };
+                return Err(AppError::validation_with(
+                    format!(
+                        "{}, so its unit can only change to another {} unit",
+                        used,
+                        from.dimension.as_str()
+                    ),
+                    json!({ "field": "unit", "dimension": from.dimension }),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn delete_ingredient(conn: &Connection, id: i64) -> AppResult<()> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT p.name FROM recipe_items r JOIN products p ON p.id = r.product_id
+         WHERE r.ingredient_id = ?1 ORDER BY p.name COLLATE NOCASE",
+    )?;
+    let used_in = stmt
+        .query_map([id], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>>>()?;
+    if !used_in.is_empty() {
+        return Err(AppError::Conflict {
+            message: format!(
+                "This ingredient is used in the recipe of {}. Remove it from the recipe first.",
+                used_in.join(", ")
+            ),
+            details: Some(json!({ "id": id, "products": used_in })),
+        });
+    }
+    purchasing::check_not_ordered(conn, Some(id), None, "ingredient")?;
+    if conn.execute("DELETE FROM ingredients WHERE id = ?1", [id])? == 0 {
+        return Err(AppError::not_found(format!("Ingredient {} not found", id)));
+    }
+    Ok(())
+}
+
+pub fn get_recipe(conn: &Connection, product_id: i64) -> Result<Vec<RecipeItem>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT r.ingredient_id, i.name, i.unit, r.quantity FROM recipe_items r
+         JOIN ingredients i ON i.id = r.ingredient_id
+         WHERE r.product_id = ?1 ORDER BY i.name COLLATE NOCASE",
+    )?;
+    let items = stmt
+        .query_map([product_id], |row| {
+            Ok(RecipeItem {
+                ingredient_id: row.get(0)?,
+                name: row.get(1)?,
+                unit: row.get(2)?,
+                quantity: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(items)
+}
+
+// Replaces a product's recipe. An empty recipe means selling the product
+// doesn't touch stock.
+pub fn set_recipe(conn: &mut Connection, product_id: i64, items: &[NewRecipeItem]) -> AppResult<Vec<RecipeItem>> {
+    let tx = conn.transaction()?;
+    let exists: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM products WHERE id = ?1)",
+        [product_id],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Err(AppError::not_found(format!("Product {} not found", product_id)));
+    }
+
+    let mut quantities = Vec::with_capacity(items.len());
+    for (index, item) in items.iter().enumerate() {
+        let invalid = |reason: &str| {
+            AppError::validation_with(
+                format!("Recipe line #{}: {}", index + 1, reason),
+                json!({ "line": index, "ingredient_id": item.ingredient_id }),
+            )
+        };
+        if !item.quantity.is_finite() || item.quantity <= 0.0 {
+            return Err(invalid("quantity must be greater than zero"));
+        }
+        if items[..index].iter().any(|i| i.ingredient_id == item.ingredient_id) {
+            return Err(invalid("ingredient is listed twice"));
+        }
+        let unit: Option<String> = tx
+            .query_row("SELECT unit FROM ingredients WHERE id = ?1", [item.ingredient_id], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        let Some(unit) = unit else {
+            return Err(invalid("ingredient does not exist"));
+        };
+        // Stored in the ingredient's unit
+        let quantity = match item.unit.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+            None => item.quantity,
+            Some(given) => {
+                let from = units::find_unit(given)
+                    .ok_or_else(|| invalid(&format!("unknown unit {}", given)))?;
+                units::find_unit(&unit)
+                    .and_then(|to| units::convert(item.quantity, from, to))
+                    .ok_or_else(|| invalid(&format!("{} can't be converted into {}", from.code, unit)))?
+            }
+        };
+        quantities.push(quantity);
+    }
+
+    tx.execute("DELETE FROM recipe_items WHERE product_id = ?1", [product_id])?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO recipe_items (product_id, ingredient_id, quantity) VALUES (?1, ?2, ?3)",
+        )?;
+        for (item, quantity) in items.iter().zip(quantities) {
+            stmt.execute((product_id, item.ingredient_id, quantity))?;
+        }
+    }
+    tx.commit()?;
+    Ok(get_recipe(conn, product_id)?)
+}
+
+// Applies a change to an ingredient's level and logs it, returning the new
+// level. A receipt with a cost also becomes the ingredient's cost per unit.
+pub fn record_movement(conn: &Connection, ingredient_id: i64, entry: &MovementEntry) -> Result<f64> {
+    let quantity_after: f64 = conn
+        .prepare_cached("UPDATE ingredients SET quantity = quantity + ?2 WHERE id = ?1 RETURNING quantity")?
+        .query_row((ingredient_id, entry.change), |row| row.get(0))?;
+    if let (MovementKind::Receive, Some(cost)) = (entry.kind, entry.unit_cost) {
+        conn.prepare_cached("UPDATE ingredients SET cost_per_unit = ?2, cost_currency = ?3 WHERE id = ?1")?
+            .execute((ingredient_id, cost.minor, &cost.currency))?;
+    }
+    conn.prepare_cached(
+        "INSERT INTO stock_movements
+             (ingredient_id, kind, change, quantity_after, unit_cost, sale_id, purchase_order_id,
+              stock_count_id, waste_entry_id, note)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?
+    .execute((
+        ingredient_id,
+        entry.kind.as_str(),
+        entry.change,
+        quantity_after,
+        entry.unit_cost.map(|cost| cost.minor),
+        entry.sale_id,
+        entry.purchase_order_id,
+        entry.stock_count_id,
+        entry.waste_entry_id,
+        entry.note,
+    ))?;
+    Ok(quantity_after)
+}
+
+fn clean_note(note: Option<String>) -> Option<String> {
+    note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty())
+}
+
+// Adds a delivery to stock. `unit_cost`, if known, is per unit of the
+// ingredient.
+pub fn receive_stock(
+    conn: &mut Connection,
+    ingredient_id: i64,
+    quantity: f64,
+    unit_cost: Option<Money>,
+    note: Option<String>,
+) -> AppResult<Ingredient> {
+    if !quantity.is_finite() || quantity <= 0.0 {
+        return Err(AppError::validation_with(
+            "Quantity received must be greater than zero",
+            json!({ "field": "quantity" }),
+        ));
+    }
+    if unit_cost.as_ref().is_some_and(Money::is_negative) {
+        return Err(AppError::validation_with(
+            "Cost must be zero or more",
+            json!({ "field": "unit_cost" }),
+        ));
+    }
+    let tx = conn.transaction()?;
+    get_ingredient(&tx, ingredient_id)?;
+    let note = clean_note(note);
+    record_movement(
+        &tx,
+        ingredient_id,
+        &MovementEntry {
+            kind: MovementKind::Receive,
+            change: quantity,
+            unit_cost: unit_cost.as_ref(),
+            sale_id: None,
+            purchase_order_id: None,
+            stock_count_id: None,
+            waste_entry_id: None,
+            note: note.as_deref(),
+        },
+    )?;
+    tx.commit()?;
+    get_ingredient(conn, ingredient_id)
+}
+
+// Sets the level to what was actually counted, logging the difference
+pub fn adjust_stock(
+    conn: &mut Connection,
+    ingredient_id: i64,
+    counted: f64,
+    note: Option<String>,
+) -> AppResult<Ingredient> {
+    if !counted.is_finite() || counted < 0.0 {
+        return Err(AppError::validation_with(
+            "Counted quantity must be zero or more",
+            json!({ "field": "quantity" }),
+        ));
+    }
+    let tx = conn.transaction()?;
+    let current = get_ingredient(&tx, ingredient_id)?;
+    let change = counted - current.quantity;
+    if change != 0.0 {
+        let note = clean_note(note);
+        record_movement(
+            &tx,
+            ingredient_id,
+            &MovementEntry {
+                kind: MovementKind::Adjust,
+                change,
+                unit_cost: None,
+                sale_id: None,
+                purchase_order_id: None,
+                stock_count_id: None,
+                waste_entry_id: None,
+                note: note.as_deref(),
+            },
+        )?;
+    }
+    tx.commit()?;
+    get_ingredient(conn, ingredient_id)
+}
+
+// Stock history of an ingredient, newest first. Timestamps are UTC.
+pub fn get_stock_movements(conn: &Connection, ingredient_id: i64) -> AppResult<Vec<StockMovement>> {
+    get_ingredient(conn, ingredient_id)?;
+    // Costs are recorded in the currency the ingredient was last costed in
+    let mut stmt = conn.prepare_cached(
+        "SELECT m.id, m.ingredient_id, m.kind, m.change, m.quantity_after, m.unit_cost,
+                i.cost_currency, m.sale_id, m.purchase_order_id, m.stock_count_id,
+                m.waste_entry_id, m.note, m.created_at
+         FROM stock_movements m JOIN ingredients i ON i.id = m.ingredient_id
+         WHERE m.ingredient_id = ?1 ORDER BY m.id DESC",
+    )?;
+    let movements = stmt
+        .query_map([ingredient_id], |row| {
+            let unit_cost: Option<i64> = row.get(5)?;
+            let currency: Option<String> = row.get(6)?;
+            Ok(StockMovement {
+                id: row.get(0)?,
+                ingredient_id: row.get(1)?,
+                kind: MovementKind::from_column(&row.get::<_, String>(2)?),
+                change: row.get(3)?,
+                quantity_after: row.get(4)?,
+                unit_cost: unit_cost.zip(currency).map(|(minor, currency)| Money::new(minor, currency)),
+                sale_id: row.get(7)?,
+                purchase_order_id: row.get(8)?,
+                stock_count_id: row.get(9)?,
+                waste_entry_id: row.get(10)?,
+                note: row.get(11)?,
+                created_at: row.get(12)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(movements)
+}
+
+// How much of each ingredient serving `products` uses, from their current
+// recipes. `products` lists product ids with the quantity served.
+pub fn recipe_usage(conn: &Connection, products: &[(i64, f64)]) -> Result<BTreeMap<i64, f64>> {
+    let mut used: BTreeMap<i64, f64> = BTreeMap::new();
+    let mut recipe = conn.prepare_cached(
+        "SELECT ingredient_id, quantity FROM recipe_items WHERE product_id = ?1",
+    )?;
+    for &(product_id, quantity) in products {
+        let rows = recipe
+            .query_map([product_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?)))?;
+        for row in rows {
+            let (ingredient_id, per_unit) = row?;
+            *used.entry(ingredient_id).or_default() += per_unit * quantity;
+        }
+    }
+    Ok(used)
+}
+
+// Takes the ingredients used by a sale out of stock. `products` lists every
+// product served with its quantity, bundle components included. Stock may
+// go negative: the sale happened, and the count is what needs fixing.
+// Returns the ingredients this sale took to or below their reorder point.
+pub fn deduct_sale_stock(tx: &Transaction, sale_id: i64, products: &[(i64, f64)]) -> AppResult<Vec<Ingredient>> {
+    let mut crossed = Vec::new();
+    for (ingredient_id, quantity) in recipe_usage(tx, products)? {
+        let after = record_movement(
+            tx,
+            ingredient_id,
+            &MovementEntry {
+                kind: MovementKind::Sale,
+                change: -quantity,
+                unit_cost: None,
+                sale_id: Some(sale_id),
+                purchase_order_id: None,
+                stock_count_id: None,
+                waste_entry_id: None,
+                note: None,
+            },
+        )?;
+        let ingredient = get_ingredient(tx, ingredient_id)?;
+        if ingredient
+            .reorder_point
+            .is_some_and(|reorder| after + quantity > reorder && after <= reorder)
+        {
+            crossed.push(ingredient);
+        }
+    }
+    Ok(crossed)
+}
+
+// Puts back the stock moved by the movements whose `column` is `id`, before
+// the sale or waste entry they belong to is deleted. The movements go with it.
+fn restore_stock(tx: &Transaction, column: &str, id: i64) -> Result<()> {
+    tx.execute(
+        &format!(
+            "UPDATE ingredients SET quantity = quantity - (
+                 SELECT SUM(m.change) FROM stock_movements m
+                 WHERE m.{column} = ?1 AND m.ingredient_id = ingredients.id
+             )
+             WHERE id IN (SELECT ingredient_id FROM stock_movements WHERE {column} = ?1)"
+        ),
+        [id],
+    )?;
+    Ok(())
+}
+
+pub fn restore_sale_stock(tx: &Transaction, sale_id: i64) -> Result<()> {
+    restore_stock(tx, "sale_id", sale_id)
+}
+
+pub fn restore_waste_stock(tx: &Transaction, waste_entry_id: i64) -> Result<()> {
+    restore_stock(tx, "waste_entry_id", waste_entry_id)
+}
+
+// Ingredients at or below their reorder point, with a suggested order
+// quantity. Usage is worked out from the last `days` days of sales (today
+// included) and the current recipes; the suggestion covers another `days`
+// days of that usage on top of the reorder point, and at least brings stock
+// back up to par.
+pub fn get_low_stock(conn: &Connection, days: i64) -> AppResult<Vec<LowStockItem>> {
+    if days < 1 {
+        return Err(AppError::validation_with(
+            "Usage window must be at least one day",
+            json!({ "field": "days" }),
+        ));
+    }
+    // Lines are stored in the unit they were sold in and recipes are per unit
+    // of the product, so lines are converted; components already are. Refunds
+    // leave stock alone, so they don't take anything off usage either.
+    let mut usage_stmt = conn.prepare_cached(&format!(
+        "WITH served AS (
+             SELECT i.product_id, {} AS quantity FROM sale_items i
+             JOIN products p ON p.id = i.product_id
+             JOIN sales s ON s.id = i.sale_id
+             WHERE s.date >= date('now', ?1) AND s.refund_of IS NULL
+             UNION ALL
+             SELECT c.product_id, c.quantity FROM sale_item_components c
+             JOIN sale_items i ON i.id = c.sale_item_id
+             JOIN sales s ON s.id = i.sale_id
+             WHERE s.date >= date('now', ?1) AND s.refund_of IS NULL
+         )
+         SELECT r.ingredient_id, SUM(r.quantity * served.quantity) FROM served
+         JOIN recipe_items r ON r.product_id = served.product_id
+         GROUP BY r.ingredient_id",
+        units::convert_sql("i.quantity", "i.unit", "p.unit")
+    ))?;
+    let usage = usage_stmt
+        .query_map([format!("-{} days", days - 1)], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?))
+        })?
+        .collect::<Result<BTreeMap<_, _>>>()?;
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM ingredients
+         WHERE reorder_point IS NOT NULL AND quantity <= reorder_point
+         ORDER BY name COLLATE NOCASE",
+        INGREDIENT_COLUMNS
+    ))?;
+    let low = stmt
+        .query_map([], ingredient_from_row)?
+        .map(|row| {
+            let ingredient = row?;
+            let daily_usage = usage.get(&ingredient.id).copied().unwrap_or(0.0) / days as f64;
+            let reorder_point = ingredient.reorder_point.unwrap_or(0.0);
+            let target = (reorder_point + daily_usage * days as f64).max(ingredient.par_level.unwrap_or(0.0));
+            Ok(LowStockItem {
+                suggested_quantity: (target - ingredient.quantity).max(0.0),
+                daily_usage,
+                ingredient,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(low)
+}
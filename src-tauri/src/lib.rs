@@ -1,11 +1,28 @@
+mod backup;
+mod bundles;
 mod database;
+mod discounts;
 mod email;
+mod encryption;
+mod error;
+mod inventory;
+mod migrations;
+mod modifiers;
+mod money;
+mod purchasing;
+mod refunds;
+mod scheduler;
+mod stock_counts;
+mod tax;
+mod units;
+mod waste;
 
 use database::*;
-use tauri::AppHandle;
+use error::{AppError, AppResult};
+use serde::Serialize;
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager, State};
 
-#[cfg(not(any(target_os = "android", target_os = "ios")))]
-use tauri::Manager;
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 use std::fs;
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
@@ -19,73 +36,542 @@ fn load_env() {
 }
 
 #[tauri::command]
-fn get_products(app: AppHandle) -> Result<Vec<Product>, String> {
-    let db_path = database::get_db_path(&app)?;
-    database::get_products(&db_path).map_err(|e| e.to_string())
+fn get_units() -> &'static [units::Unit] {
+    units::UNITS
+}
+
+#[tauri::command]
+fn get_products(db: State<'_, Database>, include_archived: Option<bool>) -> AppResult<Vec<Product>> {
+    Ok(database::get_products(&db.conn(), include_archived.unwrap_or(false))?)
+}
+
+#[tauri::command]
+fn get_products_by_category(
+    db: State<'_, Database>,
+    include_archived: Option<bool>,
+) -> AppResult<Vec<CategoryGroup>> {
+    Ok(database::get_products_by_category(&db.conn(), include_archived.unwrap_or(false))?)
+}
+
+#[tauri::command]
+fn add_product(db: State<'_, Database>, product: NewProduct) -> AppResult<Product> {
+    database::add_product(&mut db.conn(), product)
+}
+
+#[tauri::command]
+fn update_product(db: State<'_, Database>, id: i64, product: NewProduct) -> AppResult<Product> {
+    database::update_product(&mut db.conn(), id, product)
+}
+
+#[tauri::command]
+fn delete_product(db: State<'_, Database>, id: i64) -> AppResult<()> {
+    database::delete_product(&db.conn(), id)
+}
+
+#[tauri::command]
+fn restore_product(db: State<'_, Database>, id: i64) -> AppResult<()> {
+    database::restore_product(&db.conn(), id)
+}
+
+#[tauri::command]
+fn purge_product(db: State<'_, Database>, id: i64) -> AppResult<()> {
+    database::purge_product(&mut db.conn(), id)
+}
+
+#[tauri::command]
+fn get_product_price_history(db: State<'_, Database>, product_id: i64) -> AppResult<Vec<PriceChange>> {
+    database::get_product_price_history(&db.conn(), product_id)
+}
+
+#[tauri::command]
+fn get_product_price_at(db: State<'_, Database>, product_id: i64, at: String) -> AppResult<PriceChange> {
+    database::get_product_price_at(&db.conn(), product_id, &at)
+}
+
+#[tauri::command]
+fn get_categories(db: State<'_, Database>) -> AppResult<Vec<Category>> {
+    Ok(database::get_categories(&db.conn())?)
+}
+
+#[tauri::command]
+fn add_category(db: State<'_, Database>, category: NewCategory) -> AppResult<Category> {
+    database::add_category(&mut db.conn(), category)
+}
+
+#[tauri::command]
+fn update_category(db: State<'_, Database>, id: i64, category: NewCategory) -> AppResult<Category> {
+    database::update_category(&mut db.conn(), id, category)
+}
+
+#[tauri::command]
+fn delete_category(db: State<'_, Database>, id: i64) -> AppResult<()> {
+    database::delete_category(&mut db.conn(), id)
+}
+
+#[tauri::command]
+fn get_modifier_groups(db: State<'_, Database>) -> AppResult<Vec<modifiers::ModifierGroup>> {
+    Ok(modifiers::get_modifier_groups(&db.conn())?)
+}
+
+#[tauri::command]
+fn get_product_modifier_groups(
+    db: State<'_, Database>,
+    product_id: i64,
+) -> AppResult<Vec<modifiers::ModifierGroup>> {
+    Ok(modifiers::get_product_modifier_groups(&db.conn(), product_id)?)
+}
+
+#[tauri::command]
+fn add_modifier_group(
+    db: State<'_, Database>,
+    group: modifiers::NewModifierGroup,
+) -> AppResult<modifiers::ModifierGroup> {
+    modifiers::add_modifier_group(&mut db.conn(), group)
+}
+
+#[tauri::command]
+fn update_modifier_group(
+    db: State<'_, Database>,
+    id: i64,
+    group: modifiers::NewModifierGroup,
+) -> AppResult<modifiers::ModifierGroup> {
+    modifiers::update_modifier_group(&mut db.conn(), id, group)
+}
+
+#[tauri::command]
+fn delete_modifier_group(db: State<'_, Database>, id: i64) -> AppResult<()> {
+    modifiers::delete_modifier_group(&db.conn(), id)
+}
+
+#[tauri::command]
+fn set_product_modifier_groups(
+    db: State<'_, Database>,
+    product_id: i64,
+    group_ids: Vec<i64>,
+) -> AppResult<()> {
+    modifiers::set_product_modifier_groups(&mut db.conn(), product_id, &group_ids)
+}
+
+#[tauri::command]
+fn get_bundle_components(
+    db: State<'_, Database>,
+    product_id: i64,
+) -> AppResult<Vec<bundles::BundleComponent>> {
+    Ok(bundles::get_bundle_components(&db.conn(), product_id)?)
+}
+
+#[tauri::command]
+fn set_bundle_components(
+    db: State<'_, Database>,
+    product_id: i64,
+    components: Vec<bundles::NewBundleComponent>,
+) -> AppResult<Vec<bundles::BundleComponent>> {
+    bundles::set_bundle_components(&mut db.conn(), product_id, &components)
+}
+
+#[tauri::command]
+fn get_ingredients(db: State<'_, Database>) -> AppResult<Vec<inventory::Ingredient>> {
+    Ok(inventory::get_ingredients(&db.conn())?)
+}
+
+#[tauri::command]
+fn add_ingredient(
+    db: State<'_, Database>,
+    ingredient: inventory::NewIngredient,
+) -> AppResult<inventory::Ingredient> {
+    inventory::add_ingredient(&db.conn(), ingredient)
+}
+
+#[tauri::command]
+fn update_ingredient(
+    db: State<'_, Database>,
+    id: i64,
+    ingredient: inventory::NewIngredient,
+) -> AppResult<inventory::Ingredient> {
+    inventory::update_ingredient(&mut db.conn(), id, ingredient)
+}
+
+#[tauri::command]
+fn delete_ingredient(db: State<'_, Database>, id: i64) -> AppResult<()> {
+    inventory::delete_ingredient(&db.conn(), id)
+}
+
+#[tauri::command]
+fn get_recipe(db: State<'_, Database>, product_id: i64) -> AppResult<Vec<inventory::RecipeItem>> {
+    Ok(inventory::get_recipe(&db.conn(), product_id)?)
+}
+
+#[tauri::command]
+fn set_recipe(
+    db: State<'_, Database>,
+    product_id: i64,
+    items: Vec<inventory::NewRecipeItem>,
+) -> AppResult<Vec<inventory::RecipeItem>> {
+    inventory::set_recipe(&mut db.conn(), product_id, &items)
+}
+
+#[tauri::command]
+fn receive_stock(
+    db: State<'_, Database>,
+    ingredient_id: i64,
+    quantity: f64,
+    unit_cost: Option<money::Money>,
+    note: Option<String>,
+) -> AppResult<inventory::Ingredient> {
+    inventory::receive_stock(&mut db.conn(), ingredient_id, quantity, unit_cost, note)
+}
+
+#[tauri::command]
+fn adjust_stock(
+    db: State<'_, Database>,
+    ingredient_id: i64,
+    counted: f64,
+    note: Option<String>,
+) -> AppResult<inventory::Ingredient> {
+    inventory::adjust_stock(&mut db.conn(), ingredient_id, counted, note)
+}
+
+// `days` is the usage window, a week unless given
+#[tauri::command]
+fn get_low_stock(db: State<'_, Database>, days: Option<i64>) -> AppResult<Vec<inventory::LowStockItem>> {
+    inventory::get_low_stock(&db.conn(), days.unwrap_or(7))
+}
+
+#[tauri::command]
+fn get_stock_movements(
+    db: State<'_, Database>,
+    ingredient_id: i64,
+) -> AppResult<Vec<inventory::StockMovement>> {
+    inventory::get_stock_movements(&db.conn(), ingredient_id)
+}
+
+#[tauri::command]
+fn get_stock_counts(db: State<'_, Database>) -> AppResult<Vec<stock_counts::StockCount>> {
+    Ok(stock_counts::get_stock_counts(&db.conn())?)
+}
+
+#[tauri::command]
+fn open_stock_count(db: State<'_, Database>, note: Option<String>) -> AppResult<stock_counts::StockCount> {
+    stock_counts::open_stock_count(&db.conn(), note)
 }
 
 #[tauri::command]
-fn add_product(app: AppHandle, product: NewProduct) -> Result<Product, String> {
-    let db_path = database::get_db_path(&app)?;
-    database::add_product(&db_path, product).map_err(|e| e.to_string())
+fn set_counted_quantities(
+    db: State<'_, Database>,
+    id: i64,
+    entries: Vec<stock_counts::CountEntry>,
+) -> AppResult<stock_counts::StockCount> {
+    stock_counts::set_counted_quantities(&mut db.conn(), id, &entries)
 }
 
 #[tauri::command]
-fn delete_product(app: AppHandle, id: i64) -> Result<(), String> {
-    let db_path = database::get_db_path(&app)?;
-    database::delete_product(&db_path, id).map_err(|e| e.to_string())
+fn close_stock_count(db: State<'_, Database>, id: i64) -> AppResult<stock_counts::VarianceReport> {
+    stock_counts::close_stock_count(&mut db.conn(), id)
 }
 
 #[tauri::command]
-fn get_settings(app: AppHandle) -> Result<Settings, String> {
-    let db_path = database::get_db_path(&app)?;
-    database::get_settings(&db_path).map_err(|e| e.to_string())
+fn get_variance_report(db: State<'_, Database>, id: i64) -> AppResult<stock_counts::VarianceReport> {
+    stock_counts::get_variance_report(&db.conn(), id)
 }
 
 #[tauri::command]
-fn save_settings(app: AppHandle, settings: Settings) -> Result<(), String> {
-    let db_path = database::get_db_path(&app)?;
-    database::save_settings(&db_path, settings).map_err(|e| e.to_string())
+fn apply_stock_count(db: State<'_, Database>, id: i64) -> AppResult<stock_counts::VarianceReport> {
+    stock_counts::apply_stock_count(&mut db.conn(), id)
 }
 
 #[tauri::command]
-fn add_sale(app: AppHandle, sale: Sale) -> Result<i64, String> {
-    let db_path = database::get_db_path(&app)?;
-    database::add_sale(&db_path, sale).map_err(|e| e.to_string())
+fn delete_stock_count(db: State<'_, Database>, id: i64) -> AppResult<()> {
+    stock_counts::delete_stock_count(&db.conn(), id)
 }
 
 #[tauri::command]
-fn get_transactions(app: AppHandle) -> Result<Vec<Transaction>, String> {
-    let db_path = database::get_db_path(&app)?;
-    database::get_transactions(&db_path).map_err(|e| e.to_string())
+fn log_waste(db: State<'_, Database>, entry: waste::NewWasteEntry) -> AppResult<waste::WasteEntry> {
+    waste::log_waste(&mut db.conn(), entry)
 }
 
 #[tauri::command]
-fn delete_transaction(app: AppHandle, id: i64) -> Result<(), String> {
-    let db_path = database::get_db_path(&app)?;
-    database::delete_transaction(&db_path, id).map_err(|e| e.to_string())
+fn get_waste_entries(
+    db: State<'_, Database>,
+    start_date: String,
+    end_date: String,
+) -> AppResult<Vec<waste::WasteEntry>> {
+    waste::get_waste_entries(&db.conn(), &start_date, &end_date)
 }
 
 #[tauri::command]
-fn get_analytics(app: AppHandle) -> Result<AnalyticsData, String> {
-    let db_path = database::get_db_path(&app)?;
-    database::get_analytics(&db_path).map_err(|e| e.to_string())
+fn delete_waste_entry(db: State<'_, Database>, id: i64) -> AppResult<()> {
+    waste::delete_waste_entry(&mut db.conn(), id)
+}
+
+#[tauri::command]
+fn get_waste_report(db: State<'_, Database>, start_date: String, end_date: String) -> AppResult<waste::WasteReport> {
+    waste::get_waste_report(&db.conn(), &start_date, &end_date)
+}
+
+#[tauri::command]
+fn get_tax_report(db: State<'_, Database>, start_date: String, end_date: String) -> AppResult<tax::TaxReport> {
+    tax::get_tax_report(&db.conn(), &start_date, &end_date)
+}
+
+#[tauri::command]
+fn export_tax_report_csv(
+    db: State<'_, Database>,
+    start_date: String,
+    end_date: String,
+    path: String,
+) -> AppResult<()> {
+    tax::export_tax_report_csv(&db.conn(), &start_date, &end_date, Path::new(&path))
+}
+
+#[tauri::command]
+fn get_discount_reasons(db: State<'_, Database>) -> AppResult<Vec<discounts::DiscountReason>> {
+    Ok(discounts::get_discount_reasons(&db.conn())?)
+}
+
+#[tauri::command]
+fn add_discount_reason(
+    db: State<'_, Database>,
+    reason: discounts::NewDiscountReason,
+) -> AppResult<discounts::DiscountReason> {
+    discounts::add_discount_reason(&db.conn(), reason)
+}
+
+#[tauri::command]
+fn delete_discount_reason(db: State<'_, Database>, id: i64) -> AppResult<()> {
+    discounts::delete_discount_reason(&db.conn(), id)
+}
+
+#[tauri::command]
+fn get_coupons(db: State<'_, Database>) -> AppResult<Vec<discounts::Coupon>> {
+    Ok(discounts::get_coupons(&db.conn())?)
+}
+
+#[tauri::command]
+fn add_coupon(db: State<'_, Database>, coupon: discounts::NewCoupon) -> AppResult<discounts::Coupon> {
+    discounts::add_coupon(&db.conn(), coupon)
+}
+
+#[tauri::command]
+fn update_coupon(db: State<'_, Database>, id: i64, coupon: discounts::NewCoupon) -> AppResult<discounts::Coupon> {
+    discounts::update_coupon(&db.conn(), id, coupon)
+}
+
+#[tauri::command]
+fn delete_coupon(db: State<'_, Database>, id: i64) -> AppResult<()> {
+    discounts::delete_coupon(&db.conn(), id)
+}
+
+// Checks a coupon code at the till before the sale is made. `spend` is what
+// the order's items come to after line discounts.
+#[tauri::command]
+fn check_coupon(db: State<'_, Database>, code: String, spend: money::Money) -> AppResult<discounts::Coupon> {
+    discounts::redeemable_coupon(&db.conn(), &code, &spend.currency, spend.minor)
+}
+
+#[tauri::command]
+fn get_suppliers(db: State<'_, Database>) -> AppResult<Vec<purchasing::Supplier>> {
+    Ok(purchasing::get_suppliers(&db.conn())?)
+}
+
+#[tauri::command]
+fn add_supplier(db: State<'_, Database>, supplier: purchasing::NewSupplier) -> AppResult<purchasing::Supplier> {
+    purchasing::add_supplier(&db.conn(), supplier)
+}
+
+#[tauri::command]
+fn update_supplier(
+    db: State<'_, Database>,
+    id: i64,
+    supplier: purchasing::NewSupplier,
+) -> AppResult<purchasing::Supplier> {
+    purchasing::update_supplier(&db.conn(), id, supplier)
+}
+
+#[tauri::command]
+fn delete_supplier(db: State<'_, Database>, id: i64) -> AppResult<()> {
+    purchasing::delete_supplier(&db.conn(), id)
+}
+
+#[tauri::command]
+fn get_purchase_orders(db: State<'_, Database>) -> AppResult<Vec<purchasing::PurchaseOrder>> {
+    Ok(purchasing::get_purchase_orders(&db.conn())?)
+}
+
+#[tauri::command]
+fn add_purchase_order(
+    db: State<'_, Database>,
+    order: purchasing::NewPurchaseOrder,
+) -> AppResult<purchasing::PurchaseOrder> {
+    purchasing::add_purchase_order(&mut db.conn(), order)
+}
+
+#[tauri::command]
+fn update_purchase_order(
+    db: State<'_, Database>,
+    id: i64,
+    order: purchasing::NewPurchaseOrder,
+) -> AppResult<purchasing::PurchaseOrder> {
+    purchasing::update_purchase_order(&mut db.conn(), id, order)
+}
+
+#[tauri::command]
+fn send_purchase_order(db: State<'_, Database>, id: i64) -> AppResult<purchasing::PurchaseOrder> {
+    purchasing::send_purchase_order(&db.conn(), id)
+}
+
+#[tauri::command]
+fn receive_purchase_order(
+    db: State<'_, Database>,
+    id: i64,
+    receipts: Vec<purchasing::LineReceipt>,
+    note: Option<String>,
+) -> AppResult<purchasing::PurchaseOrder> {
+    purchasing::receive_purchase_order(&mut db.conn(), id, &receipts, note)
+}
+
+#[tauri::command]
+fn delete_purchase_order(db: State<'_, Database>, id: i64) -> AppResult<()> {
+    purchasing::delete_purchase_order(&db.conn(), id)
+}
+
+#[tauri::command]
+fn get_settings(db: State<'_, Database>) -> AppResult<Settings> {
+    Ok(database::get_settings(&db.conn())?)
+}
+
+#[tauri::command]
+fn save_settings(db: State<'_, Database>, settings: Settings) -> AppResult<()> {
+    database::save_settings(&db.conn(), settings)
+}
+
+// Payload of the `stock-low` event, sent when a sale takes ingredients to or
+// below their reorder point
+#[derive(Clone, Serialize)]
+struct StockLowAlert {
+    sale_id: i64,
+    ingredients: Vec<inventory::Ingredient>,
+}
+
+#[tauri::command]
+fn add_sale(app: AppHandle, db: State<'_, Database>, sale: Sale) -> AppResult<i64> {
+    let outcome = database::add_sale(&mut db.conn(), sale)?;
+    if !outcome.low_stock.is_empty() {
+        let _ = app.emit(
+            "stock-low",
+            StockLowAlert {
+                sale_id: outcome.id,
+                ingredients: outcome.low_stock,
+            },
+        );
+    }
+    Ok(outcome.id)
+}
+
+#[tauri::command]
+fn get_transactions(db: State<'_, Database>) -> AppResult<Vec<Transaction>> {
+    Ok(database::get_transactions(&db.conn())?)
+}
+
+#[tauri::command]
+fn delete_transaction(db: State<'_, Database>, id: i64) -> AppResult<()> {
+    database::delete_transaction(&mut db.conn(), id)
+}
+
+#[tauri::command]
+fn refund_sale(db: State<'_, Database>, id: i64, refund: refunds::NewRefund) -> AppResult<Transaction> {
+    refunds::refund_sale(&mut db.conn(), id, refund)
+}
+
+#[tauri::command]
+fn get_analytics(db: State<'_, Database>) -> AppResult<AnalyticsData> {
+    Ok(database::get_analytics(&db.conn())?)
+}
+
+#[derive(Clone, Serialize)]
+struct BackupProgress {
+    operation: &'static str,
+    stage: backup::BackupStage,
+    bytes_done: u64,
+    bytes_total: u64,
+}
+
+// Runs a long backup job on a blocking thread so the UI stays responsive,
+// reporting progress to the frontend as `backup-progress` events. Exports read
+// through a connection of their own, so other commands aren't held up; an
+// import has to hold the shared connection while it swaps the file.
+async fn run_backup_job<F>(app: AppHandle, operation: &'static str, job: F) -> AppResult<()>
+where
+    F: FnOnce(&Database, backup::ProgressFn) -> AppResult<()> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<Database>();
+        let mut progress = |stage, bytes_done, bytes_total| {
+            let _ = app.emit(
+                "backup-progress",
+                BackupProgress {
+                    operation,
+                    stage,
+                    bytes_done,
+                    bytes_total,
+                },
+            );
+        };
+        job(&db, &mut progress)
+    })
+    .await
+    .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))?
+}
+
+#[tauri::command]
+async fn export_database_to_file(
+    app: AppHandle,
+    path: String,
+    compact: Option<bool>,
+    password: Option<String>,
+) -> AppResult<()> {
+    run_backup_job(app, "export", move |db, progress| {
+        backup::export_to_file(
+            db,
+            Path::new(&path),
+            compact.unwrap_or(false),
+            non_empty(&password),
+            progress,
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+async fn import_database_from_file(
+    app: AppHandle,
+    path: String,
+    password: Option<String>,
+) -> AppResult<()> {
+    run_backup_job(app, "import", move |db, progress| {
+        backup::import_from_file(db, Path::new(&path), non_empty(&password), progress)
+    })
+    .await
+}
+
+// The frontend sends an empty string when no password was entered
+fn non_empty(password: &Option<String>) -> Option<&str> {
+    password.as_deref().filter(|p| !p.is_empty())
 }
 
 #[tauri::command]
-fn export_database_cmd(app: AppHandle) -> Result<String, String> {
-    let db_path = database::get_db_path(&app)?;
-    let data = database::export_database(&db_path).map_err(|e| e.to_string())?;
-    use base64::Engine;
-    Ok(base64::engine::general_purpose::STANDARD.encode(&data))
+fn list_backups(db: State<'_, Database>) -> AppResult<Vec<backup::BackupSnapshot>> {
+    let settings = database::get_settings(&db.conn())?;
+    backup::list_snapshots(&backup::backup_directory(db.path(), &settings))
 }
 
 #[tauri::command]
-fn import_database_cmd(app: AppHandle, data: String) -> Result<(), String> {
-    let db_path = database::get_db_path(&app)?;
-    use base64::Engine;
-    let decoded = base64::engine::general_purpose::STANDARD.decode(&data).map_err(|e| e.to_string())?;
-    database::import_database(&db_path, decoded).map_err(|e| e.to_string())
+async fn restore_backup(app: AppHandle, file_name: String) -> AppResult<()> {
+    run_backup_job(app, "restore", move |db, progress| {
+        let settings = database::get_settings(&db.conn())?;
+        let dir = backup::backup_directory(db.path(), &settings);
+        backup::restore_snapshot(db, &dir, &file_name, progress)
+    })
+    .await
 }
 
 #[tauri::command]
@@ -94,8 +580,8 @@ fn send_support_email(
     email: String,
     subject: String,
     message: String,
-) -> Result<(), String> {
-    email::send_support_email(name, email, subject, message)
+) -> AppResult<()> {
+    email::send_support_email(name, email, subject, message).map_err(AppError::Email)
 }
 
 // Helper function to get window state file path (desktop only)
@@ -140,8 +626,10 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .setup(|app| {
-            let db_path = database::get_db_path(&app.handle())?;
-            database::initialize_database(&db_path).map_err(|e| e.to_string())?;
+            let db_path = database::get_db_path(app.handle())?;
+            let db = Database::open(db_path).map_err(|e| e.to_string())?;
+            app.manage(db);
+            scheduler::start(app.handle().clone());
             
             // Setup window state management (desktop only)
             #[cfg(not(any(target_os = "android", target_os = "ios")))]
@@ -149,7 +637,7 @@ pub fn run() {
                 let window = app.get_webview_window("main").unwrap();
                 
                 // Restore maximize state
-                let is_maximized = load_window_state(&app.handle()).unwrap_or(false);
+                let is_maximized = load_window_state(app.handle()).unwrap_or(false);
                 if is_maximized {
                     let _ = window.maximize();
                 }
@@ -170,17 +658,80 @@ pub fn run() {
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            get_units,
             get_products,
+            get_products_by_category,
             add_product,
+            update_product,
             delete_product,
+            restore_product,
+            purge_product,
+            get_product_price_history,
+            get_product_price_at,
+            get_categories,
+            add_category,
+            update_category,
+            delete_category,
+            get_modifier_groups,
+            get_product_modifier_groups,
+            add_modifier_group,
+            update_modifier_group,
+            delete_modifier_group,
+            set_product_modifier_groups,
+            get_bundle_components,
+            set_bundle_components,
+            get_ingredients,
+            add_ingredient,
+            update_ingredient,
+            delete_ingredient,
+            get_recipe,
+            set_recipe,
+            receive_stock,
+            adjust_stock,
+            get_stock_movements,
+            get_stock_counts,
+            open_stock_count,
+            set_counted_quantities,
+            close_stock_count,
+            get_variance_report,
+            apply_stock_count,
+            delete_stock_count,
+            log_waste,
+            get_waste_entries,
+            delete_waste_entry,
+            get_waste_report,
+            get_tax_report,
+            export_tax_report_csv,
+            get_discount_reasons,
+            add_discount_reason,
+            delete_discount_reason,
+            get_coupons,
+            add_coupon,
+            update_coupon,
+            delete_coupon,
+            check_coupon,
+            get_suppliers,
+            add_supplier,
+            update_supplier,
+            delete_supplier,
+            get_purchase_orders,
+            add_purchase_order,
+            update_purchase_order,
+            send_purchase_order,
+            receive_purchase_order,
+            delete_purchase_order,
+            get_low_stock,
             get_settings,
             save_settings,
             add_sale,
             get_transactions,
             delete_transaction,
+            refund_sale,
             get_analytics,
-            export_database_cmd,
-            import_database_cmd,
+            export_database_to_file,
+            import_database_from_file,
+            list_backups,
+            restore_backup,
             send_support_email,
         ])
         .run(tauri::generate_context!())
@@ -0,0 +1,1088 @@
+use crate::money::CURRENCY_DECIMALS;
+use rusqlite::{Connection, OptionalExtension, Transaction};
+use std::fmt;
+
+// A single schema change. Versions must be unique and strictly increasing;
+// once released a migration must never be edited, only followed by a new one.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        up: initial_schema,
+    },
+    Migration {
+        version: 2,
+        name: "money_minor_units",
+        up: money_minor_units,
+    },
+    Migration {
+        version: 3,
+        name: "backup_settings",
+        up: backup_settings,
+    },
+    Migration {
+        version: 4,
+        name: "product_price_history",
+        up: product_price_history,
+    },
+    Migration {
+        version: 5,
+        name: "product_archiving",
+        up: product_archiving,
+    },
+    Migration {
+        version: 6,
+        name: "sale_item_product_ids",
+        up: sale_item_product_ids,
+    },
+    Migration {
+        version: 7,
+        name: "categories",
+        up: categories,
+    },
+    Migration {
+        version: 8,
+        name: "modifiers",
+        up: modifiers,
+    },
+    Migration {
+        version: 9,
+        name: "bundles",
+        up: bundles,
+    },
+    Migration {
+        version: 10,
+        name: "inventory",
+        up: inventory,
+    },
+    Migration {
+        version: 11,
+        name: "reorder_levels",
+        up: reorder_levels,
+    },
+    Migration {
+        version: 12,
+        name: "purchasing",
+        up: purchasing,
+    },
+    Migration {
+        version: 13,
+        name: "stock_counts",
+        up: stock_counts,
+    },
+    Migration {
+        version: 14,
+        name: "waste_log",
+        up: waste_log,
+    },
+    Migration {
+        version: 15,
+        name: "unit_registry",
+        up: unit_registry,
+    },
+    Migration {
+        version: 16,
+        name: "tax",
+        up: tax,
+    },
+    Migration {
+        version: 17,
+        name: "refunds",
+        up: refunds,
+    },
+    Migration {
+        version: 18,
+        name: "discounts",
+        up: discounts,
+    },
+];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Sqlite(rusqlite::Error),
+    // The database was written by a newer build of the app
+    SchemaTooNew { found: i64, supported: i64 },
+    Failed {
+        version: i64,
+        name: &'static str,
+        source: rusqlite::Error,
+    },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Sqlite(e) => write!(f, "Database error: {}", e),
+            MigrationError::SchemaTooNew { found, supported } => write!(
+                f,
+                "Database schema version {} is newer than this app supports ({}). Please update the app.",
+                found, supported
+            ),
+            MigrationError::Failed {
+                version,
+                name,
+                source,
+            } => write!(
+                f,
+                "Migration {} ({}) failed and was rolled back: {}",
+                version, name, source
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MigrationError::Sqlite(e) => Some(e),
+            MigrationError::SchemaTooNew { .. } => None,
+            MigrationError::Failed { source, .. } => Some(source),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(e)
+    }
+}
+
+pub fn current_version(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+// Brings the database up to the last of `MIGRATIONS`. Each migration runs in
+// its own transaction together with the `user_version` bump and its
+// `schema_migrations` record, so a failing migration leaves the database at
+// the last good version.
+pub fn run(conn: &mut Connection) -> Result<(), MigrationError> {
+    run_migrations(conn, MIGRATIONS)
+}
+
+fn run_migrations(conn: &mut Connection, migrations: &[Migration]) -> Result<(), MigrationError> {
+    debug_assert!(migrations.windows(2).all(|w| w[0].version < w[1].version));
+
+    let current = current_version(conn)?;
+    let latest = migrations.last().map_or(0, |m| m.version);
+    if current > latest {
+        return Err(MigrationError::SchemaTooNew {
+            found: current,
+            supported: latest,
+        });
+    }
+    if current == latest {
+        return Ok(());
+    }
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );",
+    )?;
+
+    // Table rebuilds need foreign key enforcement off, and the pragma is a
+    // no-op inside a transaction, so toggle it around the whole run.
+    let foreign_keys: bool = conn.pragma_query_value(None, "foreign_keys", |row| row.get(0))?;
+    conn.pragma_update(None, "foreign_keys", false)?;
+
+    let result = migrations
+        .iter()
+        .filter(|m| m.version > current)
+        .try_for_each(|m| apply(conn, m));
+
+    conn.pragma_update(None, "foreign_keys", foreign_keys)?;
+    result
+}
+
+fn apply(conn: &mut Connection, migration: &Migration) -> Result<(), MigrationError> {
+    let failed = |source| MigrationError::Failed {
+        version: migration.version,
+        name: migration.name,
+        source,
+    };
+
+    let tx = conn.transaction()?;
+    let violations_before = foreign_key_violations(&tx).map_err(failed)?;
+    (migration.up)(&tx).map_err(failed)?;
+
+    // A migration may not leave new dangling references behind. Ones that
+    // were already there (e.g. in an old imported file) are not its fault.
+    let violations = foreign_key_violations(&tx).map_err(failed)?;
+    if violations > violations_before {
+        return Err(failed(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT_FOREIGNKEY),
+            Some(format!(
+                "{} new foreign key violation(s)",
+                violations - violations_before
+            )),
+        )));
+    }
+
+    tx.execute(
+        "INSERT OR REPLACE INTO schema_migrations (version, name) VALUES (?1, ?2)",
+        (migration.version, migration.name),
+    )
+    .map_err(failed)?;
+    tx.pragma_update(None, "user_version", migration.version)
+        .map_err(failed)?;
+    tx.commit().map_err(failed)?;
+    Ok(())
+}
+
+fn foreign_key_violations(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| row.get(0))
+}
+
+fn initial_schema(tx: &Transaction) -> rusqlite::Result<()> {
+    // Matches the schema shipped before migrations existed, hence IF NOT EXISTS
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS settings (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            restaurant_name TEXT,
+            address TEXT,
+            phone TEXT,
+            email TEXT,
+            tax_rate TEXT,
+            currency TEXT,
+            opening_time TEXT,
+            closing_time TEXT,
+            receipt_footer TEXT,
+            logo TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS products (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            price REAL NOT NULL,
+            unit TEXT NOT NULL DEFAULT 'item',
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE IF NOT EXISTS sales (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            total_amount REAL NOT NULL,
+            currency TEXT NOT NULL,
+            date TEXT DEFAULT (date('now')),
+            time TEXT DEFAULT (time('now')),
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE IF NOT EXISTS sale_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            sale_id INTEGER NOT NULL,
+            product_name TEXT NOT NULL,
+            price REAL NOT NULL,
+            quantity REAL NOT NULL,
+            unit TEXT NOT NULL,
+            FOREIGN KEY (sale_id) REFERENCES sales (id)
+        );
+
+        INSERT OR IGNORE INTO settings (
+            id, restaurant_name, currency, receipt_footer
+        ) VALUES (
+            1,
+            'Restaurant Management System',
+            'PKR',
+            'Thank you for your business!'
+        );
+        ",
+    )
+}
+
+// SQLite can't change a column's type in place, so the table is recreated as
+// described in https://www.sqlite.org/lang_altertable.html#otheralter.
+// `select` reads the old rows from `table` in the new column order. The
+// AUTOINCREMENT counter is carried over so deleted ids are never reused.
+fn rebuild_table(tx: &Transaction, table: &str, columns: &str, select: &str) -> rusqlite::Result<()> {
+    let seq: Option<i64> = tx
+        .query_row("SELECT seq FROM sqlite_sequence WHERE name = ?1", [table], |row| row.get(0))
+        .optional()?;
+
+    tx.execute_batch(&format!(
+        "CREATE TABLE {table}_new ({columns});
+         INSERT INTO {table}_new {select};
+         DROP TABLE {table};
+         ALTER TABLE {table}_new RENAME TO {table};"
+    ))?;
+
+    if let Some(seq) = seq {
+        tx.execute("DELETE FROM sqlite_sequence WHERE name = ?1", [table])?;
+        tx.execute(
+            &format!(
+                "INSERT INTO sqlite_sequence (name, seq)
+                 SELECT ?1, MAX(?2, COALESCE((SELECT MAX(id) FROM {table}), 0))"
+            ),
+            (table, seq),
+        )?;
+    }
+    Ok(())
+}
+
+// Converts every REAL money column to integer minor units using each row's
+// currency, and stores the rounded line subtotal on sale_items so reports
+// only ever add integers.
+fn money_minor_units(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TEMP TABLE currency_factors (
+            code TEXT PRIMARY KEY COLLATE NOCASE,
+            factor INTEGER NOT NULL
+        );",
+    )?;
+    for (code, decimals) in CURRENCY_DECIMALS {
+        tx.execute(
+            "INSERT INTO currency_factors (code, factor) VALUES (?1, ?2)",
+            (code, 10i64.pow(*decimals)),
+        )?;
+    }
+    let factor = |currency: &str| {
+        format!(
+            "COALESCE((SELECT factor FROM currency_factors WHERE code = {}), 100)",
+            currency
+        )
+    };
+    let default_currency =
+        "COALESCE((SELECT NULLIF(currency, '') FROM settings WHERE id = 1), 'PKR')";
+
+    rebuild_table(
+        tx,
+        "products",
+        "id INTEGER PRIMARY KEY AUTOINCREMENT,
+         name TEXT NOT NULL,
+         price INTEGER NOT NULL,
+         currency TEXT NOT NULL,
+         unit TEXT NOT NULL DEFAULT 'item',
+         created_at DATETIME DEFAULT CURRENT_TIMESTAMP",
+        &format!(
+            "SELECT id, name, CAST(ROUND(price * {}) AS INTEGER), {}, unit, created_at
+             FROM products",
+            factor(default_currency),
+            default_currency
+        ),
+    )?;
+
+    rebuild_table(
+        tx,
+        "sales",
+        "id INTEGER PRIMARY KEY AUTOINCREMENT,
+         total_amount INTEGER NOT NULL,
+         currency TEXT NOT NULL,
+         date TEXT DEFAULT (date('now')),
+         time TEXT DEFAULT (time('now')),
+         created_at DATETIME DEFAULT CURRENT_TIMESTAMP",
+        &format!(
+            "SELECT id, CAST(ROUND(total_amount * {}) AS INTEGER), currency, date, time, created_at
+             FROM sales",
+            factor("currency")
+        ),
+    )?;
+
+    // Lines whose sale no longer exists were never visible anywhere and
+    // would fail the foreign key check, so they are not carried over
+    rebuild_table(
+        tx,
+        "sale_items",
+        "id INTEGER PRIMARY KEY AUTOINCREMENT,
+         sale_id INTEGER NOT NULL,
+         product_name TEXT NOT NULL,
+         price INTEGER NOT NULL,
+         quantity REAL NOT NULL,
+         unit TEXT NOT NULL,
+         subtotal INTEGER NOT NULL,
+         FOREIGN KEY (sale_id) REFERENCES sales (id)",
+        &format!(
+            "SELECT id, sale_id, product_name, price, quantity, unit,
+                    CAST(ROUND(price * quantity) AS INTEGER)
+             FROM (
+                SELECT i.id, i.sale_id, i.product_name,
+                       CAST(ROUND(i.price * {}) AS INTEGER) AS price, i.quantity, i.unit
+                FROM sale_items i
+                JOIN sales s ON s.id = i.sale_id
+             )",
+            factor("s.currency")
+        ),
+    )?;
+
+    tx.execute_batch("DROP TABLE currency_factors;")
+}
+
+fn backup_settings(tx: &Transaction) -> rusqlite::Result<()> {
+    // NULL means "use the default" for all three
+    tx.execute_batch(
+        "ALTER TABLE settings ADD COLUMN backup_directory TEXT;
+         ALTER TABLE settings ADD COLUMN backup_keep_daily INTEGER;
+         ALTER TABLE settings ADD COLUMN backup_keep_weekly INTEGER;",
+    )
+}
+
+// One row per price a product has had, starting with its price at the time
+// of this migration (dated to when the product was created, the best guess)
+fn product_price_history(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE product_price_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            product_id INTEGER NOT NULL,
+            price INTEGER NOT NULL,
+            currency TEXT NOT NULL,
+            effective_from DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (product_id) REFERENCES products (id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX idx_product_price_history_product
+            ON product_price_history (product_id, effective_from);
+
+        INSERT INTO product_price_history (product_id, price, currency, effective_from)
+        SELECT id, price, currency, COALESCE(created_at, CURRENT_TIMESTAMP) FROM products;",
+    )
+}
+
+// NULL for products on the menu, otherwise when the product was archived
+fn product_archiving(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch("ALTER TABLE products ADD COLUMN archived_at DATETIME;")
+}
+
+// Links sale lines to the product sold. product_name stays as the name at
+// the time of sale. Old lines are matched by exact name, but only where that
+// name belongs to exactly one product; the rest stay NULL rather than guess.
+fn sale_item_product_ids(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE sale_items ADD COLUMN product_id INTEGER
+            REFERENCES products (id) ON DELETE SET NULL;
+
+        UPDATE sale_items SET product_id = (
+            SELECT MIN(p.id) FROM products p
+            WHERE p.name = sale_items.product_name
+            HAVING COUNT(*) = 1
+        );
+
+        CREATE INDEX idx_sale_items_product ON sale_items (product_id);",
+    )
+}
+
+// Menu sections. A category with a parent is a sub-section of it; products
+// without a category are shown as uncategorized.
+fn categories(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE categories (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            color TEXT,
+            parent_id INTEGER REFERENCES categories (id) ON DELETE SET NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX idx_categories_parent ON categories (parent_id, sort_order);
+
+        ALTER TABLE products ADD COLUMN category_id INTEGER
+            REFERENCES categories (id) ON DELETE SET NULL;
+
+        CREATE INDEX idx_products_category ON products (category_id);",
+    )
+}
+
+// Option groups such as "Size" or "Add-ons", shared between products.
+// Sale lines keep their own copy of each chosen modifier's name and price
+// change, so editing or deleting a modifier doesn't rewrite old receipts.
+fn modifiers(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE modifier_groups (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            selection TEXT NOT NULL DEFAULT 'single' CHECK (selection IN ('single', 'multiple')),
+            min_choices INTEGER NOT NULL DEFAULT 0,
+            max_choices INTEGER,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE modifiers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            group_id INTEGER NOT NULL REFERENCES modifier_groups (id) ON DELETE CASCADE,
+            name TEXT NOT NULL,
+            price_delta INTEGER NOT NULL DEFAULT 0,
+            currency TEXT NOT NULL,
+            sort_order INTEGER NOT NULL DEFAULT 0
+        );
+
+        CREATE INDEX idx_modifiers_group ON modifiers (group_id, sort_order);
+
+        CREATE TABLE product_modifier_groups (
+            product_id INTEGER NOT NULL REFERENCES products (id) ON DELETE CASCADE,
+            group_id INTEGER NOT NULL REFERENCES modifier_groups (id) ON DELETE CASCADE,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (product_id, group_id)
+        );
+
+        CREATE TABLE sale_item_modifiers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            sale_item_id INTEGER NOT NULL REFERENCES sale_items (id) ON DELETE CASCADE,
+            modifier_id INTEGER REFERENCES modifiers (id) ON DELETE SET NULL,
+            group_name TEXT NOT NULL,
+            name TEXT NOT NULL,
+            price_delta INTEGER NOT NULL
+        );
+
+        CREATE INDEX idx_sale_item_modifiers_item ON sale_item_modifiers (sale_item_id);
+        CREATE INDEX idx_sale_item_modifiers_modifier ON sale_item_modifiers (modifier_id);",
+    )
+}
+
+// Meal deals: a bundle product is made of component products, each of which
+// may be swapped for one of its listed substitutes when sold. Sale lines
+// record the components actually served so they count towards per-product
+// analytics.
+fn bundles(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE bundle_components (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            bundle_id INTEGER NOT NULL REFERENCES products (id) ON DELETE CASCADE,
+            product_id INTEGER NOT NULL REFERENCES products (id),
+            quantity REAL NOT NULL DEFAULT 1,
+            sort_order INTEGER NOT NULL DEFAULT 0
+        );
+
+        CREATE INDEX idx_bundle_components_bundle ON bundle_components (bundle_id, sort_order);
+        CREATE INDEX idx_bundle_components_product ON bundle_components (product_id);
+
+        CREATE TABLE bundle_substitutes (
+            component_id INTEGER NOT NULL REFERENCES bundle_components (id) ON DELETE CASCADE,
+            product_id INTEGER NOT NULL REFERENCES products (id) ON DELETE CASCADE,
+            PRIMARY KEY (component_id, product_id)
+        );
+
+        CREATE TABLE sale_item_components (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            sale_item_id INTEGER NOT NULL REFERENCES sale_items (id) ON DELETE CASCADE,
+            product_id INTEGER REFERENCES products (id) ON DELETE SET NULL,
+            product_name TEXT NOT NULL,
+            quantity REAL NOT NULL,
+            substituted_for INTEGER REFERENCES products (id) ON DELETE SET NULL
+        );
+
+        CREATE INDEX idx_sale_item_components_item ON sale_item_components (sale_item_id);
+        CREATE INDEX idx_sale_item_components_product ON sale_item_components (product_id);",
+    )
+}
+
+// Ingredient stock. `quantity` on an ingredient is the current level in its
+// unit; every change to it is written to `stock_movements` as well, so levels
+// can be explained and usage measured. Sale movements belong to their sale.
+fn inventory(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE ingredients (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            unit TEXT NOT NULL,
+            quantity REAL NOT NULL DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE recipe_items (
+            product_id INTEGER NOT NULL REFERENCES products (id) ON DELETE CASCADE,
+            ingredient_id INTEGER NOT NULL REFERENCES ingredients (id),
+            quantity REAL NOT NULL,
+            PRIMARY KEY (product_id, ingredient_id)
+        );
+
+        CREATE INDEX idx_recipe_items_ingredient ON recipe_items (ingredient_id);
+
+        CREATE TABLE stock_movements (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+            kind TEXT NOT NULL,
+            change REAL NOT NULL,
+            quantity_after REAL NOT NULL,
+            sale_id INTEGER REFERENCES sales (id) ON DELETE CASCADE,
+            note TEXT,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX idx_stock_movements_ingredient ON stock_movements (ingredient_id, created_at);
+        CREATE INDEX idx_stock_movements_sale ON stock_movements (sale_id);",
+    )
+}
+
+// Stock at or below `reorder_point` needs ordering; `par_level` is the level
+// a delivery should bring it back up to. Both are optional, in the
+// ingredient's unit.
+fn reorder_levels(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE ingredients ADD COLUMN reorder_point REAL;
+        ALTER TABLE ingredients ADD COLUMN par_level REAL;",
+    )
+}
+
+// Suppliers and purchase orders. An order line is for either an ingredient
+// or a product; products are received into the ingredients of their recipe.
+// Costs are in minor units: what was paid per unit is kept on the order line
+// and the stock movement, and the latest cost per unit on the ingredient.
+fn purchasing(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE suppliers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            contact_name TEXT,
+            phone TEXT,
+            email TEXT,
+            notes TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE purchase_orders (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            supplier_id INTEGER NOT NULL REFERENCES suppliers (id),
+            status TEXT NOT NULL DEFAULT 'draft'
+                CHECK (status IN ('draft', 'sent', 'partially_received', 'received')),
+            currency TEXT NOT NULL,
+            notes TEXT,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            sent_at DATETIME,
+            received_at DATETIME
+        );
+
+        CREATE INDEX idx_purchase_orders_supplier ON purchase_orders (supplier_id);
+        CREATE INDEX idx_purchase_orders_status ON purchase_orders (status);
+
+        CREATE TABLE purchase_order_lines (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            order_id INTEGER NOT NULL REFERENCES purchase_orders (id) ON DELETE CASCADE,
+            ingredient_id INTEGER REFERENCES ingredients (id),
+            product_id INTEGER REFERENCES products (id),
+            quantity REAL NOT NULL,
+            unit_cost INTEGER NOT NULL,
+            received_quantity REAL NOT NULL DEFAULT 0,
+            received_cost INTEGER NOT NULL DEFAULT 0,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            CHECK ((ingredient_id IS NULL) <> (product_id IS NULL))
+        );
+
+        CREATE INDEX idx_purchase_order_lines_order ON purchase_order_lines (order_id, sort_order);
+        CREATE INDEX idx_purchase_order_lines_ingredient ON purchase_order_lines (ingredient_id);
+        CREATE INDEX idx_purchase_order_lines_product ON purchase_order_lines (product_id);
+
+        ALTER TABLE ingredients ADD COLUMN cost_per_unit INTEGER;
+        ALTER TABLE ingredients ADD COLUMN cost_currency TEXT;
+
+        ALTER TABLE stock_movements ADD COLUMN unit_cost INTEGER;
+        ALTER TABLE stock_movements ADD COLUMN purchase_order_id INTEGER
+            REFERENCES purchase_orders (id) ON DELETE SET NULL;",
+    )
+}
+
+// Stock takes. While a count is open only the counted quantities are known;
+// closing it snapshots the expected level and cost of each counted
+// ingredient, and the last stock movement so far so usage can be broken down
+// by kind. The adjustments made when a count is applied point back at it.
+// Only one count may be open at a time.
+fn stock_counts(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE stock_counts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'closed', 'applied')),
+            note TEXT,
+            opened_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            closed_at DATETIME,
+            applied_at DATETIME,
+            closed_movement_id INTEGER
+        );
+
+        CREATE UNIQUE INDEX idx_stock_counts_open ON stock_counts (status) WHERE status = 'open';
+
+        CREATE TABLE stock_count_lines (
+            count_id INTEGER NOT NULL REFERENCES stock_counts (id) ON DELETE CASCADE,
+            ingredient_id INTEGER NOT NULL REFERENCES ingredients (id) ON DELETE CASCADE,
+            counted_quantity REAL NOT NULL,
+            expected_quantity REAL,
+            unit_cost INTEGER,
+            cost_currency TEXT,
+            PRIMARY KEY (count_id, ingredient_id)
+        );
+
+        CREATE INDEX idx_stock_count_lines_ingredient ON stock_count_lines (ingredient_id);
+
+        ALTER TABLE stock_movements ADD COLUMN stock_count_id INTEGER
+            REFERENCES stock_counts (id) ON DELETE SET NULL;",
+    )
+}
+
+// Waste and spoilage. An entry names an ingredient or a product, and keeps
+// the name and unit it was logged under in case either is deleted later.
+// `cost` is the value of the stock lost, in minor units of `currency`, when
+// the ingredients had a cost. Its stock movements are removed with it.
+fn waste_log(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE waste_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            ingredient_id INTEGER REFERENCES ingredients (id) ON DELETE SET NULL,
+            product_id INTEGER REFERENCES products (id) ON DELETE SET NULL,
+            name TEXT NOT NULL,
+            unit TEXT NOT NULL,
+            quantity REAL NOT NULL,
+            reason TEXT NOT NULL
+                CHECK (reason IN ('spoiled', 'dropped', 'comped', 'staff_meal', 'other')),
+            recorded_by TEXT NOT NULL,
+            note TEXT,
+            cost INTEGER,
+            currency TEXT,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            CHECK (ingredient_id IS NULL OR product_id IS NULL)
+        );
+
+        CREATE INDEX idx_waste_entries_created_at ON waste_entries (created_at);
+        CREATE INDEX idx_waste_entries_ingredient ON waste_entries (ingredient_id);
+        CREATE INDEX idx_waste_entries_product ON waste_entries (product_id);
+
+        ALTER TABLE stock_movements ADD COLUMN waste_entry_id INTEGER
+            REFERENCES waste_entries (id) ON DELETE CASCADE;",
+    )
+}
+
+// The unit registry as it stood at this migration: each code with every
+// spelling of it, in lower case
+const REGISTRY_UNITS: &[(&str, &[&str])] = &[
+    ("mg", &["mg", "milligram", "milligrams", "milligramme", "milligrammes"]),
+    ("g", &["g", "gram", "grams", "gm", "gms", "gr", "gramme", "grammes"]),
+    ("kg", &["kg", "kilogram", "kilograms", "kilo", "kilos", "kgs", "kilogramme", "kilogrammes"]),
+    ("oz", &["oz", "ounce", "ounces"]),
+    ("lb", &["lb", "pound", "pounds", "lbs"]),
+    ("ml", &["ml", "millilitre", "millilitres", "milliliter", "milliliters"]),
+    ("cl", &["cl", "centilitre", "centilitres", "centiliter", "centiliters"]),
+    ("l", &["l", "litre", "litres", "liter", "liters", "ltr", "ltrs"]),
+    ("tsp", &["tsp", "teaspoon", "teaspoons"]),
+    ("tbsp", &["tbsp", "tablespoon", "tablespoons"]),
+    ("fl oz", &["fl oz", "fluid ounce", "fluid ounces", "floz"]),
+    ("cup", &["cup", "cups"]),
+    ("gal", &["gal", "gallon", "gallons"]),
+    ("item", &["item", "items", "each", "ea", "unit", "units", "nos"]),
+    ("piece", &["piece", "pieces", "pc", "pcs"]),
+    ("portion", &["portion", "portions", "serving", "servings"]),
+    ("plate", &["plate", "plates"]),
+    ("slice", &["slice", "slices"]),
+    ("bottle", &["bottle", "bottles"]),
+    ("can", &["can", "cans", "tin", "tins"]),
+    ("pack", &["pack", "packs", "packet", "packets"]),
+    ("box", &["box", "boxes"]),
+    ("dozen", &["dozen", "dozens", "doz"]),
+];
+
+// Rewrites every unit recognised by the unit registry as its code, so "Kg",
+// "kilo" and "kilogram" all become "kg". Units it doesn't know are kept as
+// they are until the product or ingredient is next saved.
+fn unit_registry(tx: &Transaction) -> rusqlite::Result<()> {
+    for table in ["products", "ingredients", "sale_items", "waste_entries"] {
+        let found = tx
+            .prepare(&format!("SELECT DISTINCT unit FROM {}", table))?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for unit in found {
+            let spelling = unit.trim().to_ascii_lowercase();
+            let known = REGISTRY_UNITS
+                .iter()
+                .find(|(_, spellings)| spellings.contains(&spelling.as_str()));
+            if let Some((code, _)) = known.filter(|(code, _)| *code != unit) {
+                tx.execute(
+                    &format!("UPDATE {} SET unit = ?1 WHERE unit = ?2", table),
+                    (code, &unit),
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Tax worked out per sale line. Products have a tax class, and settings a
+// reduced rate next to the standard `tax_rate` and whether prices include
+// tax. Each line keeps its class, its rate in basis points and its tax in
+// minor units, and each sale its total tax and how prices were entered;
+// sales from before have no class or rate and no tax. Standard rates that
+// aren't a valid percentage are cleared, as they were never applied.
+fn tax(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE settings ADD COLUMN reduced_tax_rate TEXT;
+        ALTER TABLE settings ADD COLUMN prices_include_tax INTEGER;
+
+        ALTER TABLE products ADD COLUMN tax_class TEXT NOT NULL DEFAULT 'standard'
+            CHECK (tax_class IN ('standard', 'reduced', 'exempt'));
+
+        ALTER TABLE sales ADD COLUMN tax_amount INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE sales ADD COLUMN prices_include_tax INTEGER NOT NULL DEFAULT 1;
+
+        ALTER TABLE sale_items ADD COLUMN tax_class TEXT;
+        ALTER TABLE sale_items ADD COLUMN tax_rate INTEGER;
+        ALTER TABLE sale_items ADD COLUMN tax INTEGER NOT NULL DEFAULT 0;",
+    )?;
+    let rate: Option<String> = tx
+        .query_row("SELECT tax_rate FROM settings WHERE id = 1", [], |row| row.get(0))
+        .optional()?
+        .flatten();
+    if let Some(rate) = rate {
+        let normalized = tax_rate_as_written(&rate);
+        tx.execute("UPDATE settings SET tax_rate = ?1 WHERE id = 1", [normalized])?;
+    }
+    Ok(())
+}
+
+// A percentage such as "17.50" as settings write it from here on, "17.5",
+// or None when it isn't one: 0 to 100 with at most two decimals. This is the
+// rate parsing of the time, kept here as it was.
+fn tax_rate_as_written(rate: &str) -> Option<String> {
+    let rate = rate.trim();
+    let (whole, fraction) = rate.split_once('.').unwrap_or((rate, ""));
+    if whole.is_empty() && fraction.is_empty()
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !fraction.chars().all(|c| c.is_ascii_digit())
+        || fraction.len() > 2
+        || whole.len() > 3
+    {
+        return None;
+    }
+    let whole: i64 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+    let fraction: i64 = format!("{:0<2}", fraction).parse().ok()?;
+    let basis_points = whole * 100 + fraction;
+    if basis_points > 10_000 {
+        return None;
+    }
+    let (whole, fraction) = (basis_points / 100, basis_points % 100);
+    Some(match fraction {
+        0 => whole.to_string(),
+        f if f % 10 == 0 => format!("{}.{}", whole, f / 10),
+        f => format!("{}.{:02}", whole, f),
+    })
+}
+
+// Refunds are sales of their own, dated the day they are given, with negative
+// lines that each name the line of the original sale they give back. Totals
+// and reports over a period then net out the refunds given in it, and the
+// original sale stays as it was sold.
+fn refunds(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE sales ADD COLUMN refund_of INTEGER REFERENCES sales (id);
+        ALTER TABLE sales ADD COLUMN refund_reason TEXT;
+        CREATE INDEX idx_sales_refund_of ON sales (refund_of);
+
+        ALTER TABLE sale_items ADD COLUMN refund_of INTEGER REFERENCES sale_items (id);
+        CREATE INDEX idx_sale_items_refund_of ON sale_items (refund_of);",
+    )
+}
+
+// Named reasons for discounts given at the till, and coupon codes. A coupon
+// takes a percentage in basis points or a fixed amount in minor units off an
+// order, within optional dates, a minimum spend and a number of uses; its
+// uses are the sales that name it. Sales and their lines keep the discount
+// they were given with its reason as text, so a reason can be removed later,
+// and each line its share of the order's discounts, which its tax is on.
+fn discounts(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE discount_reasons (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE coupons (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            code TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            description TEXT,
+            kind TEXT NOT NULL CHECK (kind IN ('percentage', 'fixed')),
+            value INTEGER NOT NULL CHECK (value > 0),
+            currency TEXT NOT NULL,
+            min_spend INTEGER,
+            valid_from TEXT,
+            valid_until TEXT,
+            usage_limit INTEGER CHECK (usage_limit IS NULL OR usage_limit > 0),
+            active INTEGER NOT NULL DEFAULT 1,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+
+        ALTER TABLE sales ADD COLUMN discount_amount INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE sales ADD COLUMN discount_kind TEXT;
+        ALTER TABLE sales ADD COLUMN discount_value INTEGER;
+        ALTER TABLE sales ADD COLUMN discount_reason TEXT;
+        ALTER TABLE sales ADD COLUMN order_discount INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE sales ADD COLUMN coupon_id INTEGER REFERENCES coupons (id) ON DELETE SET NULL;
+        ALTER TABLE sales ADD COLUMN coupon_code TEXT;
+        ALTER TABLE sales ADD COLUMN coupon_discount INTEGER NOT NULL DEFAULT 0;
+        CREATE INDEX idx_sales_coupon ON sales (coupon_id);
+
+        ALTER TABLE sale_items ADD COLUMN discount_kind TEXT;
+        ALTER TABLE sale_items ADD COLUMN discount_value INTEGER;
+        ALTER TABLE sale_items ADD COLUMN discount_reason TEXT;
+        ALTER TABLE sale_items ADD COLUMN discount INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE sale_items ADD COLUMN order_discount INTEGER NOT NULL DEFAULT 0;",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn latest_version() -> i64 {
+        MIGRATIONS.last().unwrap().version
+    }
+
+    fn table_exists(conn: &Connection, name: &str) -> bool {
+        conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            [name],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn fail(_: &Transaction) -> rusqlite::Result<()> {
+        Err(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
+            Some("boom".to_string()),
+        ))
+    }
+
+    fn create_then_fail(tx: &Transaction) -> rusqlite::Result<()> {
+        tx.execute_batch("CREATE TABLE half_done (id INTEGER PRIMARY KEY);")?;
+        fail(tx)
+    }
+
+    #[test]
+    fn migrates_a_fresh_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run(&mut conn).unwrap();
+
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        let applied: Vec<i64> = conn
+            .prepare("SELECT version FROM schema_migrations ORDER BY version")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(applied, MIGRATIONS.iter().map(|m| m.version).collect::<Vec<_>>());
+        for table in ["settings", "products", "sales", "sale_items"] {
+            assert!(table_exists(&conn, table), "{} is missing", table);
+        }
+
+        // Running again has nothing left to do
+        run(&mut conn).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn migrates_a_database_from_before_migrations() {
+        // Databases from before migrations have the initial schema and no
+        // user_version
+        let mut conn = Connection::open_in_memory().unwrap();
+        let tx = conn.transaction().unwrap();
+        initial_schema(&tx).unwrap();
+        tx.commit().unwrap();
+        conn.execute_batch(
+            "INSERT INTO products (name, price, unit) VALUES ('Tea', 50.0, 'cup');
+            INSERT INTO sales (total_amount, currency) VALUES (100.0, 'PKR');
+            INSERT INTO sale_items (sale_id, product_name, price, quantity, unit)
+                VALUES (1, 'Tea', 50.0, 2.0, 'cup');",
+        )
+        .unwrap();
+        assert_eq!(current_version(&conn).unwrap(), 0);
+
+        run(&mut conn).unwrap();
+
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        let name: String = conn.query_row("SELECT name FROM products", [], |row| row.get(0)).unwrap();
+        assert_eq!(name, "Tea");
+        let (sales, lines): (i64, i64) = conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM sales), (SELECT COUNT(*) FROM sale_items)",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((sales, lines), (1, 1));
+    }
+
+    #[test]
+    fn rolls_back_a_failing_migration() {
+        let migrations = [
+            Migration {
+                version: 1,
+                name: "initial_schema",
+                up: initial_schema,
+            },
+            Migration {
+                version: 2,
+                name: "create_then_fail",
+                up: create_then_fail,
+            },
+            Migration {
+                version: 3,
+                name: "never_run",
+                up: fail,
+            },
+        ];
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        let err = run_migrations(&mut conn, &migrations).unwrap_err();
+
+        assert!(
+            matches!(err, MigrationError::Failed { version: 2, name: "create_then_fail", .. }),
+            "{:?}",
+            err
+        );
+        assert_eq!(current_version(&conn).unwrap(), 1);
+        assert!(table_exists(&conn, "products"));
+        assert!(!table_exists(&conn, "half_done"));
+        let recorded: i64 = conn
+            .query_row("SELECT MAX(version) FROM schema_migrations", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(recorded, 1);
+    }
+
+    #[test]
+    fn unit_registry_rewrites_known_spellings_as_codes() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let before = MIGRATIONS.iter().position(|m| m.name == "unit_registry").unwrap();
+        run_migrations(&mut conn, &MIGRATIONS[..before]).unwrap();
+        conn.execute_batch(
+            "INSERT INTO products (name, price, currency, unit) VALUES ('Rice', 300, 'PKR', ' Kilo ');
+            INSERT INTO products (name, price, currency, unit) VALUES ('Tea', 50, 'PKR', 'Cups');
+            INSERT INTO products (name, price, currency, unit) VALUES ('Herbs', 20, 'PKR', 'bunch');",
+        )
+        .unwrap();
+
+        run(&mut conn).unwrap();
+
+        let units: Vec<String> = conn
+            .prepare("SELECT unit FROM products ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(units, ["kg", "cup", "bunch"]);
+    }
+
+    #[test]
+    fn tax_rates_are_rewritten_as_settings_write_them() {
+        assert_eq!(tax_rate_as_written("17.50").as_deref(), Some("17.5"));
+        assert_eq!(tax_rate_as_written(" 5.25 ").as_deref(), Some("5.25"));
+        assert_eq!(tax_rate_as_written("017").as_deref(), Some("17"));
+        assert_eq!(tax_rate_as_written("").as_deref(), None);
+        assert_eq!(tax_rate_as_written("17%").as_deref(), None);
+        assert_eq!(tax_rate_as_written("120").as_deref(), None);
+    }
+
+    #[test]
+    fn refuses_a_database_from_a_newer_app() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1).unwrap();
+
+        let err = run(&mut conn).unwrap_err();
+
+        assert!(matches!(err, MigrationError::SchemaTooNew { .. }), "{:?}", err);
+        assert!(!table_exists(&conn, "settings"));
+    }
+}
@@ -0,0 +1,435 @@
+use crate::error::{AppError, AppResult};
+use crate::money::Money;
+use rusqlite::{Connection, Params, Result, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Selection {
+    Single,
+    Multiple,
+}
+
+impl Selection {
+    fn as_str(self) -> &'static str {
+        match self {
+            Selection::Single => "single",
+            Selection::Multiple => "multiple",
+        }
+    }
+
+    fn from_column(value: &str) -> Self {
+        if value == "multiple" {
+            Selection::Multiple
+        } else {
+            Selection::Single
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Modifier {
+    pub id: i64,
+    pub name: String,
+    pub price_delta: Money,
+}
+
+// A set of options offered on a product, e.g. "Size" (single, exactly one)
+// or "Add-ons" (multiple, up to three). `max_choices` of None means no limit.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModifierGroup {
+    pub id: i64,
+    pub name: String,
+    pub selection: Selection,
+    pub min_choices: i64,
+    pub max_choices: Option<i64>,
+    pub modifiers: Vec<Modifier>,
+}
+
+// `id` refers to an existing modifier of the group being edited; modifiers
+// without one are created
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewModifier {
+    pub id: Option<i64>,
+    pub name: String,
+    pub price_delta: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewModifierGroup {
+    pub name: String,
+    pub selection: Selection,
+    pub min_choices: Option<i64>,
+    pub max_choices: Option<i64>,
+    pub modifiers: Vec<NewModifier>,
+}
+
+// A modifier as it was chosen on a sale line. The name and price change are
+// copied at the time of sale; `modifier_id` is None once it has been deleted.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LineModifier {
+    pub modifier_id: Option<i64>,
+    pub group: String,
+    pub name: String,
+    pub price_delta: Money,
+}
+
+fn modifiers_of(conn: &Connection, group_id: i64) -> Result<Vec<Modifier>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, name, price_delta, currency FROM modifiers
+         WHERE group_id = ?1 ORDER BY sort_order, id",
+    )?;
+    let modifiers = stmt
+        .query_map([group_id], |row| {
+            Ok(Modifier {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                price_delta: Money::new(row.get(2)?, row.get::<_, String>(3)?),
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(modifiers)
+}
+
+fn groups_from<P: Params>(conn: &Connection, sql: &str, params: P) -> Result<Vec<ModifierGroup>> {
+    let mut stmt = conn.prepare_cached(sql)?;
+    let groups = stmt
+        .query_map(params, |row| {
+            Ok(ModifierGroup {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                selection: Selection::from_column(&row.get::<_, String>(2)?),
+                min_choices: row.get(3)?,
+                max_choices: row.get(4)?,
+                modifiers: Vec::new(),
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    groups
+        .into_iter()
+        .map(|group| {
+            Ok(ModifierGroup {
+                modifiers: modifiers_of(conn, group.id)?,
+                ..group
+            })
+        })
+        .collect()
+}
+
+pub fn get_modifier_groups(conn: &Connection) -> Result<Vec<ModifierGroup>> {
+    groups_from(
+        conn,
+        "SELECT id, name, selection, min_choices, max_choices FROM modifier_groups
+         ORDER BY name COLLATE NOCASE, id",
+        [],
+    )
+}
+
+// The groups offered on a product, in the order they should be asked
+pub fn get_product_modifier_groups(conn: &Connection, product_id: i64) -> Result<Vec<ModifierGroup>> {
+    groups_from(
+        conn,
+        "SELECT g.id, g.name, g.selection, g.min_choices, g.max_choices
+         FROM product_modifier_groups pg
+         JOIN modifier_groups g ON g.id = pg.group_id
+         WHERE pg.product_id = ?1 ORDER BY pg.sort_order, g.id",
+        [product_id],
+    )
+}
+
+// Checks a group definition and fills in the limits implied by its
+// selection: a single-select group allows at most one choice.
+fn normalize_group(group: &mut NewModifierGroup) -> AppResult<()> {
+    let invalid = |message: &str, field: &str| {
+        Err(AppError::validation_with(message, json!({ "field": field })))
+    };
+
+    if group.name.trim().is_empty() {
+        return invalid("Group name is required", "name");
+    }
+    if group.modifiers.is_empty() {
+        return invalid("A group needs at least one option", "modifiers");
+    }
+    if let Some(index) = group.modifiers.iter().position(|m| m.name.trim().is_empty()) {
+        return Err(AppError::validation_with(
+            format!("Option #{} needs a name", index + 1),
+            json!({ "field": "modifiers", "modifier": index }),
+        ));
+    }
+
+    let min = group.min_choices.unwrap_or(0);
+    let max = match group.selection {
+        Selection::Single => Some(1),
+        Selection::Multiple => group.max_choices,
+    };
+    if min < 0 {
+        return invalid("Minimum choices cannot be negative", "min_choices");
+    }
+    if max.is_some_and(|max| max < 1) {
+        return invalid("Maximum choices must be at least 1", "max_choices");
+    }
+    if max.is_some_and(|max| min > max) {
+        return invalid("Minimum choices cannot be more than the maximum", "min_choices");
+    }
+    if min > group.modifiers.len() as i64 {
+        return invalid("Minimum choices is more than the number of options", "min_choices");
+    }
+
+    group.min_choices = Some(min);
+    group.max_choices = max;
+    Ok(())
+}
+
+// Writes the group's options in list order. Existing options keep their id
+// so sales stay linked to them; options left out of the list are deleted.
+fn save_modifiers(tx: &Transaction, group_id: i64, modifiers: &[NewModifier]) -> AppResult<()> {
+    let existing: Vec<i64> = tx
+        .prepare("SELECT id FROM modifiers WHERE group_id = ?1")?
+        .query_map([group_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(id) = modifiers
+        .iter()
+        .filter_map(|m| m.id)
+        .find(|id| !existing.contains(id))
+    {
+        return Err(AppError::validation_with(
+            format!("Option {} does not belong to this group", id),
+            json!({ "field": "modifiers", "modifier_id": id }),
+        ));
+    }
+
+    for id in existing {
+        if !modifiers.iter().any(|m| m.id == Some(id)) {
+            tx.execute("DELETE FROM modifiers WHERE id = ?1", [id])?;
+        }
+    }
+    for (sort_order, modifier) in modifiers.iter().enumerate() {
+        let delta = &modifier.price_delta;
+        match modifier.id {
+            Some(id) => tx.execute(
+                "UPDATE modifiers SET name = ?1, price_delta = ?2, currency = ?3, sort_order = ?4
+                 WHERE id = ?5",
+                (modifier.name.trim(), delta.minor, &delta.currency, sort_order as i64, id),
+            )?,
+            None => tx.execute(
+                "INSERT INTO modifiers (group_id, name, price_delta, currency, sort_order)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                (group_id, modifier.name.trim(), delta.minor, &delta.currency, sort_order as i64),
+            )?,
+        };
+    }
+    Ok(())
+}
+
+fn group_by_id(conn: &Connection, id: i64) -> AppResult<ModifierGroup> {
+    groups_from(
+        conn,
+        "SELECT id, name, selection, min_choices, max_choices FROM modifier_groups WHERE id = ?1",
+        [id],
+    )?
+    .pop()
+    .ok_or_else(|| AppError::not_found(format!("Modifier group {} not found", id)))
+}
+
+pub fn add_modifier_group(conn: &mut Connection, mut group: NewModifierGroup) -> AppResult<ModifierGroup> {
+    normalize_group(&mut group)?;
+    if let Some(index) = group.modifiers.iter().position(|m| m.id.is_some()) {
+        return Err(AppError::validation_with(
+            "New options cannot have an id",
+            json!({ "field": "modifiers", "modifier": index }),
+        ));
+    }
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO modifier_groups (name, selection, min_choices, max_choices) VALUES (?1, ?2, ?3, ?4)",
+        (group.name.trim(), group.selection.as_str(), group.min_choices, group.max_choices),
+    )?;
+    let id = tx.last_insert_rowid();
+    save_modifiers(&tx, id, &group.modifiers)?;
+    tx.commit()?;
+    group_by_id(conn, id)
+}
+
+pub fn update_modifier_group(conn: &mut Connection, id: i64, mut group: NewModifierGroup) -> AppResult<ModifierGroup> {
+    normalize_group(&mut group)?;
+
+    let tx = conn.transaction()?;
+    let updated = tx.execute(
+        "UPDATE modifier_groups SET name = ?1, selection = ?2, min_choices = ?3, max_choices = ?4
+         WHERE id = ?5",
+        (group.name.trim(), group.selection.as_str(), group.min_choices, group.max_choices, id),
+    )?;
+    if updated == 0 {
+        return Err(AppError::not_found(format!("Modifier group {} not found", id)));
+    }
+    save_modifiers(&tx, id, &group.modifiers)?;
+    tx.commit()?;
+    group_by_id(conn, id)
+}
+
+// Removes the group, its options and its links to products. Past sales keep
+// their copies of the options that were chosen.
+pub fn delete_modifier_group(conn: &Connection, id: i64) -> AppResult<()> {
+    if conn.execute("DELETE FROM modifier_groups WHERE id = ?1", [id])? == 0 {
+        return Err(AppError::not_found(format!("Modifier group {} not found", id)));
+    }
+    Ok(())
+}
+
+// Replaces the groups offered on a product; `group_ids` is in display order
+pub fn set_product_modifier_groups(conn: &mut Connection, product_id: i64, group_ids: &[i64]) -> AppResult<()> {
+    let tx = conn.transaction()?;
+    let product_exists: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM products WHERE id = ?1)",
+        [product_id],
+        |row| row.get(0),
+    )?;
+    if !product_exists {
+        return Err(AppError::not_found(format!("Product {} not found", product_id)));
+    }
+
+    tx.execute("DELETE FROM product_modifier_groups WHERE product_id = ?1", [product_id])?;
+    {
+        let mut group_exists =
+            tx.prepare_cached("SELECT EXISTS (SELECT 1 FROM modifier_groups WHERE id = ?1)")?;
+        let mut insert = tx.prepare_cached(
+            "INSERT INTO product_modifier_groups (product_id, group_id, sort_order) VALUES (?1, ?2, ?3)",
+        )?;
+        for (sort_order, &group_id) in group_ids.iter().enumerate() {
+            if group_ids[..sort_order].contains(&group_id) {
+                continue;
+            }
+            if !group_exists.query_row([group_id], |row| row.get::<_, bool>(0))? {
+                return Err(AppError::validation_with(
+                    format!("Modifier group {} does not exist", group_id),
+                    json!({ "field": "group_ids", "group_id": group_id }),
+                ));
+            }
+            insert.execute((product_id, group_id, sort_order as i64))?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+// Looks up the options chosen on sale line `index` and checks them against
+// the groups offered on its product: every option must belong to one of
+// them, and each group's min/max choices must be respected. The result is
+// in the product's group order, ready to be stored with the line.
+pub fn resolve_line_modifiers(
+    conn: &Connection,
+    index: usize,
+    product_id: Option<i64>,
+    modifier_ids: &[i64],
+    currency: &str,
+) -> AppResult<Vec<LineModifier>> {
+    let invalid = |reason: String, details: serde_json::Value| {
+        AppError::validation_with(format!("Invalid item #{}: {}", index + 1, reason), details)
+    };
+
+    let Some(product_id) = product_id else {
+        if modifier_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        return Err(invalid(
+            "options can only be chosen for a menu product".to_string(),
+            json!({ "item": index }),
+        ));
+    };
+    let groups = get_product_modifier_groups(conn, product_id)?;
+
+    for (position, id) in modifier_ids.iter().enumerate() {
+        if modifier_ids[..position].contains(id) {
+            return Err(invalid(
+                "the same option was chosen twice".to_string(),
+                json!({ "item": index, "modifier_id": id }),
+            ));
+        }
+        if !groups.iter().any(|g| g.modifiers.iter().any(|m| m.id == *id)) {
+            return Err(invalid(
+                format!("option {} is not offered on this product", id),
+                json!({ "item": index, "modifier_id": id }),
+            ));
+        }
+    }
+
+    let mut chosen = Vec::new();
+    for group in &groups {
+        let picked: Vec<&Modifier> = group
+            .modifiers
+            .iter()
+            .filter(|m| modifier_ids.contains(&m.id))
+            .collect();
+        let count = picked.len() as i64;
+        if count < group.min_choices {
+            return Err(invalid(
+                format!("choose at least {} from {}", group.min_choices, group.name),
+                json!({ "item": index, "group_id": group.id }),
+            ));
+        }
+        if let Some(max) = group.max_choices.filter(|&max| count > max) {
+            return Err(invalid(
+                format!("choose at most {} from {}", max, group.name),
+                json!({ "item": index, "group_id": group.id }),
+            ));
+        }
+
+        for modifier in picked {
+            if !modifier.price_delta.currency.eq_ignore_ascii_case(currency) {
+                return Err(invalid(
+                    format!(
+                        "{} is priced in {} but the sale is in {}",
+                        modifier.name, modifier.price_delta.currency, currency
+                    ),
+                    json!({ "item": index, "modifier_id": modifier.id }),
+                ));
+            }
+            chosen.push(LineModifier {
+                modifier_id: Some(modifier.id),
+                group: group.name.clone(),
+                name: modifier.name.clone(),
+                price_delta: Money::new(modifier.price_delta.minor, currency),
+            });
+        }
+    }
+    Ok(chosen)
+}
+
+pub fn record_line_modifiers(tx: &Transaction, sale_item_id: i64, modifiers: &[LineModifier]) -> Result<()> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO sale_item_modifiers (sale_item_id, modifier_id, group_name, name, price_delta)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for modifier in modifiers {
+        stmt.execute((
+            sale_item_id,
+            modifier.modifier_id,
+            &modifier.group,
+            &modifier.name,
+            modifier.price_delta.minor,
+        ))?;
+    }
+    Ok(())
+}
+
+pub fn get_line_modifiers(conn: &Connection, sale_item_id: i64, currency: &str) -> Result<Vec<LineModifier>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT modifier_id, group_name, name, price_delta FROM sale_item_modifiers
+         WHERE sale_item_id = ?1 ORDER BY id",
+    )?;
+    let modifiers = stmt
+        .query_map([sale_item_id], |row| {
+            Ok(LineModifier {
+                modifier_id: row.get(0)?,
+                group: row.get(1)?,
+                name: row.get(2)?,
+                price_delta: Money::new(row.get(3)?, currency),
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(modifiers)
+}
@@ -0,0 +1,148 @@
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+// ISO 4217 currencies whose minor unit is not 1/100. Everything else,
+// including codes we don't recognise, uses two decimal places.
+pub const CURRENCY_DECIMALS: &[(&str, u32)] = &[
+    ("BHD", 3),
+    ("BIF", 0),
+    ("CLP", 0),
+    ("DJF", 0),
+    ("GNF", 0),
+    ("IQD", 3),
+    ("ISK", 0),
+    ("JOD", 3),
+    ("JPY", 0),
+    ("KMF", 0),
+    ("KRW", 0),
+    ("KWD", 3),
+    ("LYD", 3),
+    ("OMR", 3),
+    ("PYG", 0),
+    ("RWF", 0),
+    ("TND", 3),
+    ("UGX", 0),
+    ("VND", 0),
+    ("VUV", 0),
+    ("XAF", 0),
+    ("XOF", 0),
+    ("XPF", 0),
+];
+
+const DEFAULT_DECIMALS: u32 = 2;
+
+pub fn currency_decimals(currency: &str) -> u32 {
+    CURRENCY_DECIMALS
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(currency))
+        .map_or(DEFAULT_DECIMALS, |(_, decimals)| *decimals)
+}
+
+// An amount of money in integer minor units (paisa, cents, fils...).
+//
+// Serialized as `{ "minor": 1250, "currency": "PKR", "amount": "12.50" }`.
+// `amount` is a display string for convenience and is ignored on input;
+// arithmetic on the frontend should use `minor`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Money {
+    pub minor: i64,
+    pub currency: String,
+}
+
+impl Money {
+    pub fn new(minor: i64, currency: impl Into<String>) -> Self {
+        Money {
+            minor,
+            currency: currency.into(),
+        }
+    }
+
+    pub fn decimals(&self) -> u32 {
+        currency_decimals(&self.currency)
+    }
+
+    // Price of `quantity` units, rounded half away from zero to a whole minor unit
+    pub fn times_quantity(&self, quantity: f64) -> Money {
+        Money::new((self.minor as f64 * quantity).round() as i64, &*self.currency)
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.minor < 0
+    }
+
+    pub fn amount_string(&self) -> String {
+        let decimals = self.decimals();
+        let sign = if self.minor < 0 { "-" } else { "" };
+        let minor = self.minor.unsigned_abs();
+        if decimals == 0 {
+            return format!("{}{}", sign, minor);
+        }
+        let factor = 10u64.pow(decimals);
+        format!(
+            "{}{}.{:0width$}",
+            sign,
+            minor / factor,
+            minor % factor,
+            width = decimals as usize
+        )
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.currency, self.amount_string())
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Money", 3)?;
+        state.serialize_field("minor", &self.minor)?;
+        state.serialize_field("currency", &self.currency)?;
+        state.serialize_field("amount", &self.amount_string())?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_with_the_currency_decimal_places() {
+        assert_eq!(Money::new(1250, "PKR").to_string(), "PKR 12.50");
+        assert_eq!(Money::new(5, "usd").amount_string(), "0.05");
+        assert_eq!(Money::new(1500, "JPY").amount_string(), "1500");
+        assert_eq!(Money::new(12345, "KWD").amount_string(), "12.345");
+        assert_eq!(Money::new(7, "KWD").amount_string(), "0.007");
+        assert_eq!(Money::new(-1250, "PKR").amount_string(), "-12.50");
+        assert_eq!(Money::new(-5, "PKR").amount_string(), "-0.05");
+        assert_eq!(Money::new(i64::MIN, "JPY").amount_string(), "-9223372036854775808");
+    }
+
+    #[test]
+    fn unknown_currencies_have_two_decimals() {
+        assert_eq!(currency_decimals("XYZ"), 2);
+        assert_eq!(currency_decimals("jpy"), 0);
+    }
+
+    #[test]
+    fn times_quantity_rounds_half_away_from_zero() {
+        let price = Money::new(333, "PKR");
+        assert_eq!(price.times_quantity(3.0), Money::new(999, "PKR"));
+        assert_eq!(price.times_quantity(0.5), Money::new(167, "PKR"));
+        assert_eq!(Money::new(-333, "PKR").times_quantity(0.5), Money::new(-167, "PKR"));
+        assert_eq!(Money::new(1000, "PKR").times_quantity(0.25), Money::new(250, "PKR"));
+        assert_eq!(Money::new(199, "PKR").times_quantity(1.0 / 3.0), Money::new(66, "PKR"));
+        assert_eq!(price.times_quantity(0.0), Money::new(0, "PKR"));
+    }
+
+    #[test]
+    fn serializes_the_display_amount() {
+        let json = serde_json::to_value(Money::new(1250, "PKR")).unwrap();
+        assert_eq!(json, serde_json::json!({ "minor": 1250, "currency": "PKR", "amount": "12.50" }));
+        let back: Money = serde_json::from_value(json).unwrap();
+        assert_eq!(back, Money::new(1250, "PKR"));
+    }
+}
@@ -0,0 +1,588 @@
+use crate::error::{AppError, AppResult};
+use crate::inventory::{self, MovementEntry, MovementKind};
+use crate::money::Money;
+use rusqlite::{Connection, OptionalExtension, Result, Row, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Supplier {
+    pub id: i64,
+    pub name: String,
+    pub contact_name: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewSupplier {
+    pub name: String,
+    #[serde(default)]
+    pub contact_name: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+// Orders are drafted, sent to the supplier, then received in one or more
+// deliveries. Only drafts can be edited.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PurchaseOrderStatus {
+    Draft,
+    Sent,
+    PartiallyReceived,
+    Received,
+}
+
+impl PurchaseOrderStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            PurchaseOrderStatus::Draft => "draft",
+            PurchaseOrderStatus::Sent => "sent",
+            PurchaseOrderStatus::PartiallyReceived => "partially_received",
+            PurchaseOrderStatus::Received => "received",
+        }
+    }
+
+    fn from_column(value: &str) -> Self {
+        match value {
+            "sent" => PurchaseOrderStatus::Sent,
+            "partially_received" => PurchaseOrderStatus::PartiallyReceived,
+            "received" => PurchaseOrderStatus::Received,
+            _ => PurchaseOrderStatus::Draft,
+        }
+    }
+}
+
+// A line orders either an ingredient or a product with a recipe, such as
+// bottled drinks. `quantity` is in the ingredient's or product's unit and
+// `unit_cost` is per one of those units. `received_cost` is the total paid
+// for what has arrived so far.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PurchaseOrderLine {
+    pub id: i64,
+    pub ingredient_id: Option<i64>,
+    pub product_id: Option<i64>,
+    pub name: String,
+    pub unit: String,
+    pub quantity: f64,
+    pub unit_cost: Money,
+    pub received_quantity: f64,
+    pub received_cost: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PurchaseOrder {
+    pub id: i64,
+    pub supplier_id: i64,
+    pub supplier_name: String,
+    pub status: PurchaseOrderStatus,
+    pub currency: String,
+    pub notes: Option<String>,
+    pub lines: Vec<PurchaseOrderLine>,
+    pub created_at: String,
+    pub sent_at: Option<String>,
+    pub received_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewPurchaseOrderLine {
+    #[serde(default)]
+    pub ingredient_id: Option<i64>,
+    #[serde(default)]
+    pub product_id: Option<i64>,
+    pub quantity: f64,
+    pub unit_cost: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewPurchaseOrder {
+    pub supplier_id: i64,
+    #[serde(default)]
+    pub notes: Option<String>,
+    pub lines: Vec<NewPurchaseOrderLine>,
+}
+
+// What arrived for one line. `unit_cost` overrides the ordered cost when the
+// invoice differs.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LineReceipt {
+    pub line_id: i64,
+    pub quantity: f64,
+    #[serde(default)]
+    pub unit_cost: Option<Money>,
+}
+
+fn clean(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+// Suppliers
+
+const SUPPLIER_COLUMNS: &str = "id, name, contact_name, phone, email, notes";
+
+fn supplier_from_row(row: &Row) -> Result<Supplier> {
+    Ok(Supplier {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        contact_name: row.get(2)?,
+        phone: row.get(3)?,
+        email: row.get(4)?,
+        notes: row.get(5)?,
+    })
+}
+
+pub fn get_suppliers(conn: &Connection) -> Result<Vec<Supplier>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM suppliers ORDER BY name COLLATE NOCASE",
+        SUPPLIER_COLUMNS
+    ))?;
+    let suppliers = stmt
+        .query_map([], supplier_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(suppliers)
+}
+
+fn get_supplier(conn: &Connection, id: i64) -> AppResult<Supplier> {
+    conn.prepare_cached(&format!("SELECT {} FROM suppliers WHERE id = ?1", SUPPLIER_COLUMNS))?
+        .query_row([id], supplier_from_row)
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Supplier {} not found", id)))
+}
+
+fn validate_supplier(conn: &Connection, id: Option<i64>, name: &str) -> AppResult<()> {
+    if name.is_empty() {
+        return Err(AppError::validation_with(
+            "Supplier name is required",
+            json!({ "field": "name" }),
+        ));
+    }
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM suppliers WHERE name = ?1 AND id IS NOT ?2)",
+        (name, id),
+        |row| row.get(0),
+    )?;
+    if taken {
+        return Err(AppError::Conflict {
+            message: format!("A supplier named {} already exists", name),
+            details: Some(json!({ "field": "name" })),
+        });
+    }
+    Ok(())
+}
+
+pub fn add_supplier(conn: &Connection, supplier: NewSupplier) -> AppResult<Supplier> {
+    let name = supplier.name.trim();
+    validate_supplier(conn, None, name)?;
+    conn.execute(
+        "INSERT INTO suppliers (name, contact_name, phone, email, notes) VALUES (?1, ?2, ?3, ?4, ?5)",
+        (
+            name,
+            clean(supplier.contact_name),
+            clean(supplier.phone),
+            clean(supplier.email),
+            clean(supplier.notes),
+        ),
+    )?;
+    get_supplier(conn, conn.last_insert_rowid())
+}
+
+pub fn update_supplier(conn: &Connection, id: i64, supplier: NewSupplier) -> AppResult<Supplier> {
+    let name = supplier.name.trim();
+    validate_supplier(conn, Some(id), name)?;
+    if conn.execute(
+        "UPDATE suppliers SET name = ?1, contact_name = ?2, phone = ?3, email = ?4, notes = ?5 WHERE id = ?6",
+        (
+            name,
+            clean(supplier.contact_name),
+            clean(supplier.phone),
+            clean(supplier.email),
+            clean(supplier.notes),
+            id,
+        ),
+    )? == 0
+    {
+        return Err(AppError::not_found(format!("Supplier {} not found", id)));
+    }
+    get_supplier(conn, id)
+}
+
+// Suppliers with orders are kept so the order history stays complete
+pub fn delete_supplier(conn: &Connection, id: i64) -> AppResult<()> {
+    let orders: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM purchase_orders WHERE supplier_id = ?1",
+        [id],
+        |row| row.get(0),
+    )?;
+    if orders > 0 {
+        return Err(AppError::Conflict {
+            message: format!("This supplier has {} purchase order(s) and cannot be deleted", orders),
+            details: Some(json!({ "id": id, "orders": orders })),
+        });
+    }
+    if conn.execute("DELETE FROM suppliers WHERE id = ?1", [id])? == 0 {
+        return Err(AppError::not_found(format!("Supplier {} not found", id)));
+    }
+    Ok(())
+}
+
+// Purchase orders
+
+fn get_order_lines(conn: &Connection, order_id: i64, currency: &str) -> Result<Vec<PurchaseOrderLine>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT l.id, l.ingredient_id, l.product_id, COALESCE(i.name, p.name), COALESCE(i.unit, p.unit),
+                l.quantity, l.unit_cost, l.received_quantity, l.received_cost
+         FROM purchase_order_lines l
+         LEFT JOIN ingredients i ON i.id = l.ingredient_id
+         LEFT JOIN products p ON p.id = l.product_id
+         WHERE l.order_id = ?1 ORDER BY l.sort_order, l.id",
+    )?;
+    let lines = stmt
+        .query_map([order_id], |row| {
+            Ok(PurchaseOrderLine {
+                id: row.get(0)?,
+                ingredient_id: row.get(1)?,
+                product_id: row.get(2)?,
+                name: row.get(3)?,
+                unit: row.get(4)?,
+                quantity: row.get(5)?,
+                unit_cost: Money::new(row.get(6)?, currency),
+                received_quantity: row.get(7)?,
+                received_cost: Money::new(row.get(8)?, currency),
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(lines)
+}
+
+const ORDER_QUERY: &str = "SELECT o.id, o.supplier_id, s.name, o.status, o.currency, o.notes,
+        o.created_at, o.sent_at, o.received_at
+     FROM purchase_orders o JOIN suppliers s ON s.id = o.supplier_id";
+
+fn order_from_row(row: &Row) -> Result<PurchaseOrder> {
+    Ok(PurchaseOrder {
+        id: row.get(0)?,
+        supplier_id: row.get(1)?,
+        supplier_name: row.get(2)?,
+        status: PurchaseOrderStatus::from_column(&row.get::<_, String>(3)?),
+        currency: row.get(4)?,
+        notes: row.get(5)?,
+        lines: Vec::new(),
+        created_at: row.get(6)?,
+        sent_at: row.get(7)?,
+        received_at: row.get(8)?,
+    })
+}
+
+fn with_lines(conn: &Connection, order: PurchaseOrder) -> Result<PurchaseOrder> {
+    let lines = get_order_lines(conn, order.id, &order.currency)?;
+    Ok(PurchaseOrder { lines, ..order })
+}
+
+// Newest first
+pub fn get_purchase_orders(conn: &Connection) -> Result<Vec<PurchaseOrder>> {
+    let mut stmt = conn.prepare_cached(&format!("{} ORDER BY o.id DESC", ORDER_QUERY))?;
+    let orders = stmt
+        .query_map([], order_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    orders.into_iter().map(|order| with_lines(conn, order)).collect()
+}
+
+pub fn get_purchase_order(conn: &Connection, id: i64) -> AppResult<PurchaseOrder> {
+    let order = conn
+        .prepare_cached(&format!("{} WHERE o.id = ?1", ORDER_QUERY))?
+        .query_row([id], order_from_row)
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Purchase order {} not found", id)))?;
+    Ok(with_lines(conn, order)?)
+}
+
+fn require_status(order: &PurchaseOrder, allowed: &[PurchaseOrderStatus], action: &str) -> AppResult<()> {
+    if allowed.contains(&order.status) {
+        return Ok(());
+    }
+    Err(AppError::Conflict {
+        message: format!(
+            "Purchase order {} is {} and cannot be {}",
+            order.id,
+            order.status.as_str().replace('_', " "),
+            action
+        ),
+        details: Some(json!({ "id": order.id, "status": order.status })),
+    })
+}
+
+// Checks the supplier and lines of an order, returning its currency
+fn validate_order(tx: &Transaction, order: &NewPurchaseOrder) -> AppResult<String> {
+    get_supplier(tx, order.supplier_id)?;
+    let Some(first) = order.lines.first() else {
+        return Err(AppError::validation("A purchase order must contain at least one line"));
+    };
+    let currency = first.unit_cost.currency.clone();
+
+    for (index, line) in order.lines.iter().enumerate() {
+        let invalid = |reason: String| {
+            AppError::validation_with(format!("Line #{}: {}", index + 1, reason), json!({ "line": index }))
+        };
+        if !line.unit_cost.currency.eq_ignore_ascii_case(&currency) {
+            return Err(invalid(format!(
+                "cost is in {} but the order is in {}",
+                line.unit_cost.currency, currency
+            )));
+        }
+        if line.unit_cost.is_negative() {
+            return Err(invalid("cost must be zero or more".to_string()));
+        }
+        if !line.quantity.is_finite() || line.quantity <= 0.0 {
+            return Err(invalid("quantity must be greater than zero".to_string()));
+        }
+        match (line.ingredient_id, line.product_id) {
+            (Some(ingredient_id), None) => {
+                let known: bool = tx.query_row(
+                    "SELECT EXISTS (SELECT 1 FROM ingredients WHERE id = ?1)",
+                    [ingredient_id],
+                    |row| row.get(0),
+                )?;
+                if !known {
+                    return Err(invalid("ingredient does not exist".to_string()));
+                }
+            }
+            (None, Some(product_id)) => {
+                let name: Option<String> = tx
+                    .query_row("SELECT name FROM products WHERE id = ?1", [product_id], |row| row.get(0))
+                    .optional()?;
+                let Some(name) = name else {
+                    return Err(invalid("product does not exist".to_string()));
+                };
+                if inventory::get_recipe(tx, product_id)?.is_empty() {
+                    return Err(invalid(format!(
+                        "{} has no recipe, so receiving it would not change stock",
+                        name
+                    )));
+                }
+            }
+            _ => return Err(invalid("choose either an ingredient or a product".to_string())),
+        }
+    }
+    Ok(currency)
+}
+
+fn insert_lines(tx: &Transaction, order_id: i64, lines: &[NewPurchaseOrderLine]) -> Result<()> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO purchase_order_lines (order_id, ingredient_id, product_id, quantity, unit_cost, sort_order)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for (sort_order, line) in lines.iter().enumerate() {
+        stmt.execute((
+            order_id,
+            line.ingredient_id,
+            line.product_id,
+            line.quantity,
+            line.unit_cost.minor,
+            sort_order as i64,
+        ))?;
+    }
+    Ok(())
+}
+
+pub fn add_purchase_order(conn: &mut Connection, order: NewPurchaseOrder) -> AppResult<PurchaseOrder> {
+    let tx = conn.transaction()?;
+    let currency = validate_order(&tx, &order)?;
+    tx.execute(
+        "INSERT INTO purchase_orders (supplier_id, currency, notes) VALUES (?1, ?2, ?3)",
+        (order.supplier_id, &currency, clean(order.notes)),
+    )?;
+    let id = tx.last_insert_rowid();
+    insert_lines(&tx, id, &order.lines)?;
+    tx.commit()?;
+    get_purchase_order(conn, id)
+}
+
+// Replaces the supplier, notes and lines of a draft
+pub fn update_purchase_order(conn: &mut Connection, id: i64, order: NewPurchaseOrder) -> AppResult<PurchaseOrder> {
+    let tx = conn.transaction()?;
+    require_status(&get_purchase_order(&tx, id)?, &[PurchaseOrderStatus::Draft], "edited")?;
+    let currency = validate_order(&tx, &order)?;
+    tx.execute(
+        "UPDATE purchase_orders SET supplier_id = ?1, currency = ?2, notes = ?3 WHERE id = ?4",
+        (order.supplier_id, &currency, clean(order.notes), id),
+    )?;
+    tx.execute("DELETE FROM purchase_order_lines WHERE order_id = ?1", [id])?;
+    insert_lines(&tx, id, &order.lines)?;
+    tx.commit()?;
+    get_purchase_order(conn, id)
+}
+
+pub fn send_purchase_order(conn: &Connection, id: i64) -> AppResult<PurchaseOrder> {
+    require_status(&get_purchase_order(conn, id)?, &[PurchaseOrderStatus::Draft], "sent")?;
+    conn.execute(
+        "UPDATE purchase_orders SET status = 'sent', sent_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        [id],
+    )?;
+    get_purchase_order(conn, id)
+}
+
+// Orders that have received stock are kept, as the stock log points at them
+pub fn delete_purchase_order(conn: &Connection, id: i64) -> AppResult<()> {
+    require_status(
+        &get_purchase_order(conn, id)?,
+        &[PurchaseOrderStatus::Draft, PurchaseOrderStatus::Sent],
+        "deleted",
+    )?;
+    conn.execute("DELETE FROM purchase_orders WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+// Books a delivery against a sent order. Ingredient lines go straight into
+// stock; product lines are broken down through the product's recipe, and
+// their cost only carries over when the recipe has a single ingredient.
+// The order is received once every line has arrived in full.
+pub fn receive_purchase_order(
+    conn: &mut Connection,
+    id: i64,
+    receipts: &[LineReceipt],
+    note: Option<String>,
+) -> AppResult<PurchaseOrder> {
+    let tx = conn.transaction()?;
+    let order = get_purchase_order(&tx, id)?;
+    require_status(
+        &order,
+        &[PurchaseOrderStatus::Sent, PurchaseOrderStatus::PartiallyReceived],
+        "received",
+    )?;
+    if receipts.is_empty() {
+        return Err(AppError::validation("Enter what arrived for at least one line"));
+    }
+    let note = clean(note);
+
+    for (index, receipt) in receipts.iter().enumerate() {
+        let invalid = |reason: String| {
+            AppError::validation_with(
+                format!("Receipt #{}: {}", index + 1, reason),
+                json!({ "receipt": index, "line_id": receipt.line_id }),
+            )
+        };
+        if receipts[..index].iter().any(|r| r.line_id == receipt.line_id) {
+            return Err(invalid("the same line was received twice".to_string()));
+        }
+        let Some(line) = order.lines.iter().find(|l| l.id == receipt.line_id) else {
+            return Err(invalid(format!("line {} is not part of this order", receipt.line_id)));
+        };
+        if !receipt.quantity.is_finite() || receipt.quantity <= 0.0 {
+            return Err(invalid("quantity must be greater than zero".to_string()));
+        }
+        let unit_cost = receipt.unit_cost.as_ref().unwrap_or(&line.unit_cost);
+        if !unit_cost.currency.eq_ignore_ascii_case(&order.currency) {
+            return Err(invalid(format!(
+                "cost is in {} but the order is in {}",
+                unit_cost.currency, order.currency
+            )));
+        }
+        if unit_cost.is_negative() {
+            return Err(invalid("cost must be zero or more".to_string()));
+        }
+        let unit_cost = Money::new(unit_cost.minor, &*order.currency);
+
+        match (line.ingredient_id, line.product_id) {
+            (Some(ingredient_id), _) => {
+                inventory::record_movement(
+                    &tx,
+                    ingredient_id,
+                    &MovementEntry {
+                        kind: MovementKind::Receive,
+                        change: receipt.quantity,
+                        unit_cost: Some(&unit_cost),
+                        sale_id: None,
+                        purchase_order_id: Some(id),
+                        stock_count_id: None,
+                        waste_entry_id: None,
+                        note: note.as_deref(),
+                    },
+                )?;
+            }
+            (None, Some(product_id)) => {
+                let recipe = inventory::get_recipe(&tx, product_id)?;
+                if recipe.is_empty() {
+                    return Err(invalid(format!("{} no longer has a recipe", line.name)));
+                }
+                let single = recipe.len() == 1;
+                for item in &recipe {
+                    let cost = single.then(|| {
+                        Money::new((unit_cost.minor as f64 / item.quantity).round() as i64, &*order.currency)
+                    });
+                    inventory::record_movement(
+                        &tx,
+                        item.ingredient_id,
+                        &MovementEntry {
+                            kind: MovementKind::Receive,
+                            change: receipt.quantity * item.quantity,
+                            unit_cost: cost.as_ref(),
+                            sale_id: None,
+                            purchase_order_id: Some(id),
+                            stock_count_id: None,
+                            waste_entry_id: None,
+                            note: note.as_deref(),
+                        },
+                    )?;
+                }
+            }
+            (None, None) => return Err(invalid(format!("{} no longer exists", line.name))),
+        }
+
+        tx.execute(
+            "UPDATE purchase_order_lines
+             SET received_quantity = received_quantity + ?2, received_cost = received_cost + ?3
+             WHERE id = ?1",
+            (line.id, receipt.quantity, unit_cost.times_quantity(receipt.quantity).minor),
+        )?;
+    }
+
+    let outstanding: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM purchase_order_lines WHERE order_id = ?1 AND received_quantity < quantity)",
+        [id],
+        |row| row.get(0),
+    )?;
+    let status = if outstanding {
+        PurchaseOrderStatus::PartiallyReceived
+    } else {
+        PurchaseOrderStatus::Received
+    };
+    tx.execute(
+        "UPDATE purchase_orders
+         SET status = ?2, received_at = CASE WHEN ?2 = 'received' THEN CURRENT_TIMESTAMP END
+         WHERE id = ?1",
+        (id, status.as_str()),
+    )?;
+    tx.commit()?;
+    get_purchase_order(conn, id)
+}
+
+// Ingredients and products on a purchase order can't be deleted, so the
+// order still says what was bought. `what` names the thing for the message.
+pub fn check_not_ordered(
+    conn: &Connection,
+    ingredient_id: Option<i64>,
+    product_id: Option<i64>,
+    what: &str,
+) -> AppResult<()> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT DISTINCT order_id FROM purchase_order_lines
+         WHERE ingredient_id = ?1 OR product_id = ?2 ORDER BY order_id",
+    )?;
+    let orders = stmt
+        .query_map((ingredient_id, product_id), |row| row.get::<_, i64>(0))?
+        .collect::<Result<Vec<_>>>()?;
+    if orders.is_empty() {
+        return Ok(());
+    }
+    let numbers: Vec<String> = orders.iter().map(|id| format!("#{}", id)).collect();
+    Err(AppError::Conflict {
+        message: format!("This {} is on purchase order {} and cannot be deleted", what, numbers.join(", ")),
+        details: Some(json!({ "orders": orders })),
+    })
+}
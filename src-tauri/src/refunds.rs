@@ -0,0 +1,268 @@
+use crate::database::{self, Transaction};
+use crate::error::{AppError, AppResult};
+use rusqlite::{Connection, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+// How much of a line to give back, in the unit it was sold in
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefundLine {
+    pub item_id: i64,
+    pub quantity: f64,
+}
+
+// No lines gives back everything of the sale not refunded yet
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewRefund {
+    #[serde(default)]
+    pub lines: Vec<RefundLine>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+// Quantities are compared with this much slack, so a line refunded in
+// parts that add up to all of it is refunded in full
+const QUANTITY_EPSILON: f64 = 1e-9;
+
+struct SoldLine {
+    id: i64,
+    product_id: Option<i64>,
+    name: String,
+    price: i64,
+    quantity: f64,
+    unit: String,
+    subtotal: i64,
+    tax_class: Option<String>,
+    tax_rate: Option<i64>,
+    tax: i64,
+    discount_kind: Option<String>,
+    discount_value: Option<i64>,
+    discount_reason: Option<String>,
+    discount: i64,
+    order_discount: i64,
+    refunded: f64,
+}
+
+// The share of `value` that `quantity` of `sold` comes to, rounded half
+// away from zero
+fn part(value: i64, quantity: f64, sold: f64) -> i64 {
+    if quantity >= sold {
+        value
+    } else {
+        (value as f64 * quantity / sold).round() as i64
+    }
+}
+
+// Giving back `quantity` more of a line of `sold` of which `refunded` has
+// been given back already: the quantity given back, capped at what is left,
+// and each of the line's `amounts` share of it. Shares are the part of all
+// that has been refunded less the part refunded before, so the parts of a
+// line refunded bit by bit add up to exactly its amounts.
+fn refund_shares<const N: usize>(amounts: [i64; N], sold: f64, refunded: f64, quantity: f64) -> (f64, [i64; N]) {
+    let after = (refunded + quantity).min(sold);
+    let after = if sold - after <= QUANTITY_EPSILON { sold } else { after };
+    (after - refunded, amounts.map(|value| part(value, after, sold) - part(value, refunded, sold)))
+}
+
+// Gives money back on a sale as a sale of its own, dated today, so the
+// refund comes off the takings and tax of the day it is given rather than
+// rewriting the period of the original sale. Each line gives back its share
+// of the line's amount, discounts and tax. Stock is left alone: whether refunded goods go back on
+// the shelf is for a stock adjustment to say.
+pub fn refund_sale(conn: &mut Connection, sale_id: i64, refund: NewRefund) -> AppResult<Transaction> {
+    let tx = conn.transaction()?;
+    let (currency, prices_include_tax, refund_of): (String, bool, Option<i64>) = tx
+        .query_row(
+            "SELECT currency, prices_include_tax, refund_of FROM sales WHERE id = ?1",
+            [sale_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Transaction {} not found", sale_id)))?;
+    if refund_of.is_some() {
+        return Err(AppError::validation_with(
+            format!("Transaction {} is a refund and can't be refunded", sale_id),
+            json!({ "id": sale_id }),
+        ));
+    }
+
+    let sold: Vec<SoldLine> = {
+        let mut stmt = tx.prepare_cached(
+            "SELECT id, product_id, product_name, price, quantity, unit, subtotal, tax_class, tax_rate, tax,
+                    discount_kind, discount_value, discount_reason, discount, order_discount,
+                    (SELECT COALESCE(-SUM(r.quantity), 0) FROM sale_items r WHERE r.refund_of = sale_items.id)
+             FROM sale_items WHERE sale_id = ?1 ORDER BY id",
+        )?;
+        let lines = stmt.query_map([sale_id], |row| {
+            Ok(SoldLine {
+                id: row.get(0)?,
+                product_id: row.get(1)?,
+                name: row.get(2)?,
+                price: row.get(3)?,
+                quantity: row.get(4)?,
+                unit: row.get(5)?,
+                subtotal: row.get(6)?,
+                tax_class: row.get(7)?,
+                tax_rate: row.get(8)?,
+                tax: row.get(9)?,
+                discount_kind: row.get(10)?,
+                discount_value: row.get(11)?,
+                discount_reason: row.get(12)?,
+                discount: row.get(13)?,
+                order_discount: row.get(14)?,
+                refunded: row.get(15)?,
+            })
+        })?;
+        lines.collect::<Result<_>>()?
+    };
+    let remaining = |line: &SoldLine| line.quantity - line.refunded;
+
+    // (line sold, quantity to give back)
+    let mut chosen: Vec<(&SoldLine, f64)> = Vec::new();
+    if refund.lines.is_empty() {
+        chosen.extend(
+            sold.iter()
+                .filter(|line| remaining(line) > QUANTITY_EPSILON)
+                .map(|line| (line, remaining(line))),
+        );
+        if chosen.is_empty() {
+            return Err(AppError::Conflict {
+                message: format!("Transaction {} has already been refunded in full", sale_id),
+                details: Some(json!({ "id": sale_id })),
+            });
+        }
+    } else {
+        for (index, line) in refund.lines.iter().enumerate() {
+            let invalid = |reason: &str| {
+                AppError::validation_with(
+                    format!("Invalid refund line #{}: {}", index + 1, reason),
+                    json!({ "line": index }),
+                )
+            };
+            let Some(item) = sold.iter().find(|item| item.id == line.item_id) else {
+                return Err(invalid("not a line of this sale"));
+            };
+            if chosen.iter().any(|(other, _)| other.id == item.id) {
+                return Err(invalid("the line is refunded twice"));
+            }
+            if !line.quantity.is_finite() || line.quantity <= 0.0 {
+                return Err(invalid("quantity must be greater than zero"));
+            }
+            if line.quantity > remaining(item) + QUANTITY_EPSILON {
+                return Err(AppError::validation_with(
+                    format!(
+                        "Invalid refund line #{}: only {} {} of {} is left to refund",
+                        index + 1,
+                        remaining(item).max(0.0),
+                        item.unit,
+                        item.name
+                    ),
+                    json!({ "line": index, "remaining": remaining(item).max(0.0) }),
+                ));
+            }
+            chosen.push((item, line.quantity.min(remaining(item))));
+        }
+    }
+
+    let reason = refund.reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    // (line sold, quantity given back, [subtotal, discount, order discount, tax] given back)
+    let lines: Vec<(&SoldLine, f64, [i64; 4])> = chosen
+        .into_iter()
+        .map(|(line, quantity)| {
+            let amounts = [line.subtotal, line.discount, line.order_discount, line.tax];
+            let (quantity, amounts) = refund_shares(amounts, line.quantity, line.refunded, quantity);
+            (line, quantity, amounts)
+        })
+        .collect();
+
+    let paid: i64 = lines.iter().map(|(_, _, [subtotal, discount, order, _])| subtotal - discount - order).sum();
+    let tax_amount: i64 = lines.iter().map(|(_, _, amounts)| amounts[3]).sum();
+    let discount_amount: i64 = lines.iter().map(|(_, _, amounts)| amounts[1] + amounts[2]).sum();
+    let total = if prices_include_tax { paid } else { paid + tax_amount };
+
+    tx.execute(
+        "INSERT INTO sales (total_amount, currency, tax_amount, prices_include_tax, discount_amount,
+                            refund_of, refund_reason)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        (-total, &currency, -tax_amount, prices_include_tax, -discount_amount, sale_id, reason),
+    )?;
+    let refund_id = tx.last_insert_rowid();
+    {
+        let mut item_stmt = tx.prepare_cached(
+            "INSERT INTO sale_items (sale_id, product_id, product_name, price, quantity, unit, subtotal,
+                                     tax_class, tax_rate, tax, discount_kind, discount_value, discount_reason,
+                                     discount, order_discount, refund_of)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+        )?;
+        for (line, quantity, [subtotal, discount, order_discount, tax]) in &lines {
+            item_stmt.execute(rusqlite::params![
+                refund_id,
+                line.product_id,
+                line.name,
+                line.price,
+                -quantity,
+                line.unit,
+                -subtotal,
+                line.tax_class,
+                line.tax_rate,
+                -tax,
+                line.discount_kind,
+                line.discount_value,
+                line.discount_reason,
+                -discount,
+                -order_discount,
+                line.id,
+            ])?;
+        }
+    }
+    tx.commit()?;
+
+    database::get_transaction(conn, refund_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_round_half_away_from_zero() {
+        // A third of 1000 is 333.33, and half of 5 tax is 2.5
+        assert_eq!(refund_shares([1000, 5], 3.0, 0.0, 1.0), (1.0, [333, 2]));
+        assert_eq!(refund_shares([1000, 5], 2.0, 0.0, 1.0), (1.0, [500, 3]));
+        assert_eq!(refund_shares([-5], 2.0, 0.0, 1.0), (1.0, [-3]));
+    }
+
+    #[test]
+    fn shares_of_a_partly_refunded_line_add_up_to_the_line() {
+        let amounts = [1000, 150];
+        let (first, first_shares) = refund_shares(amounts, 3.0, 0.0, 1.0);
+        let (second, second_shares) = refund_shares(amounts, 3.0, 1.0, 1.0);
+        let (third, third_shares) = refund_shares(amounts, 3.0, 2.0, 1.0);
+        assert_eq!(first_shares, [333, 50]);
+        assert_eq!(second_shares, [334, 50]);
+        assert_eq!(third_shares, [333, 50]);
+        assert_eq!(first + second + third, 3.0);
+        for i in 0..amounts.len() {
+            assert_eq!(first_shares[i] + second_shares[i] + third_shares[i], amounts[i]);
+        }
+    }
+
+    #[test]
+    fn discount_shares_round_with_the_line() {
+        // [subtotal, discount, order discount, tax] of two sold, one given back
+        let amounts = [1000, 100, 45, 127];
+        let (_, first) = refund_shares(amounts, 2.0, 0.0, 1.0);
+        let (_, second) = refund_shares(amounts, 2.0, 1.0, 1.0);
+        assert_eq!(first, [500, 50, 23, 64]);
+        assert_eq!(second, [500, 50, 22, 63]);
+    }
+
+    #[test]
+    fn shares_give_back_no_more_than_is_left() {
+        assert_eq!(refund_shares([1000, 150], 3.0, 2.0, 5.0), (1.0, [333, 50]));
+        // What is left within the slack is the rest of the line
+        let (quantity, shares) = refund_shares([1000], 1.0, 0.1 + 0.2, 0.7);
+        assert_eq!(shares, [700]);
+        assert!((quantity - 0.7).abs() < QUANTITY_EPSILON);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::backup;
+use crate::database::{self, Database, Settings};
+use crate::error::AppResult;
+use chrono::{Duration as ChronoDuration, NaiveDateTime, NaiveTime};
+use serde::Serialize;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+// How often the scheduler wakes up to see whether a snapshot is due. Settings
+// are re-read every time, so changes apply without a restart.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// Payload of the `backup-failed` event, sent when automatic backups stop
+// working. A failing backup is retried on every check, but only reported
+// again once one has succeeded in between.
+#[derive(Clone, Serialize)]
+struct BackupFailed {
+    message: String,
+}
+
+fn report_failure(app: &AppHandle, message: String) {
+    let _ = app.emit("backup-failed", BackupFailed { message });
+}
+
+// Starts the automatic backup thread. It runs for the life of the app.
+pub fn start(app: AppHandle) {
+    let handle = app.clone();
+    let spawned = thread::Builder::new()
+        .name("backup-scheduler".into())
+        .spawn(move || {
+            let mut failing = false;
+            loop {
+                match run_if_due(&app) {
+                    Ok(()) => failing = false,
+                    Err(e) => {
+                        if !failing {
+                            report_failure(&app, e.to_string());
+                        }
+                        failing = true;
+                    }
+                }
+                thread::sleep(CHECK_INTERVAL);
+            }
+        });
+    if let Err(e) = spawned {
+        report_failure(&handle, format!("Could not start the backup scheduler: {}", e));
+    }
+}
+
+fn run_if_due(app: &AppHandle) -> AppResult<()> {
+    let db = app.state::<Database>();
+    let settings = database::get_settings(&db.conn())?;
+    let dir = backup::backup_directory(db.path(), &settings);
+
+    let now = chrono::Local::now().naive_local();
+    let last = backup::latest_snapshot_time(&dir)?;
+    if !is_due(&settings, last, now) {
+        return Ok(());
+    }
+
+    backup::create_snapshot(&db, &dir)?;
+    backup::prune_snapshots(&dir, &settings)?;
+    Ok(())
+}
+
+// Hourly while the restaurant is open, daily otherwise
+fn is_due(settings: &Settings, last: Option<NaiveDateTime>, now: NaiveDateTime) -> bool {
+    let interval = if is_open(settings, now.time()) {
+        ChronoDuration::hours(1)
+    } else {
+        ChronoDuration::days(1)
+    };
+    match last {
+        Some(last) => now - last >= interval,
+        None => true,
+    }
+}
+
+fn is_open(settings: &Settings, now: NaiveTime) -> bool {
+    let parse = |time: &Option<String>| {
+        time.as_deref()
+            .and_then(|t| NaiveTime::parse_from_str(t.trim(), "%H:%M").ok())
+    };
+    match (parse(&settings.opening_time), parse(&settings.closing_time)) {
+        (Some(open), Some(close)) if open < close => open <= now && now < close,
+        // Open past midnight, e.g. 18:00 to 02:00
+        (Some(open), Some(close)) if open > close => now >= open || now < close,
+        _ => false,
+    }
+}
@@ -0,0 +1,397 @@
+use crate::error::{AppError, AppResult};
+use crate::inventory::{self, MovementEntry, MovementKind};
+use crate::money::Money;
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+
+// A count is open while quantities are entered, closed once the variance
+// has been worked out, and applied when the stock levels have been corrected
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StockCountStatus {
+    Open,
+    Closed,
+    Applied,
+}
+
+impl StockCountStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            StockCountStatus::Open => "open",
+            StockCountStatus::Closed => "closed",
+            StockCountStatus::Applied => "applied",
+        }
+    }
+
+    fn from_column(value: &str) -> Self {
+        match value {
+            "closed" => StockCountStatus::Closed,
+            "applied" => StockCountStatus::Applied,
+            _ => StockCountStatus::Open,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StockCountLine {
+    pub ingredient_id: i64,
+    pub name: String,
+    pub unit: String,
+    pub counted_quantity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StockCount {
+    pub id: i64,
+    pub status: StockCountStatus,
+    pub note: Option<String>,
+    pub lines: Vec<StockCountLine>,
+    pub opened_at: String,
+    pub closed_at: Option<String>,
+    pub applied_at: Option<String>,
+}
+
+// A quantity counted on the shelf. No quantity takes the ingredient back
+// out of the count.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CountEntry {
+    pub ingredient_id: i64,
+    #[serde(default)]
+    pub counted: Option<f64>,
+}
+
+// How an ingredient's expected level came about since it was last counted:
+// `opening` plus `received`, less `used` by sales and `wasted`, plus other
+// `adjusted` changes. `variance` is counted less expected, and is valued at the
+// ingredient's cost when the count was closed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VarianceLine {
+    pub ingredient_id: i64,
+    pub name: String,
+    pub unit: String,
+    pub opening: f64,
+    pub received: f64,
+    pub used: f64,
+    pub wasted: f64,
+    pub adjusted: f64,
+    pub expected: f64,
+    pub counted: f64,
+    pub variance: f64,
+    pub unit_cost: Option<Money>,
+    pub variance_value: Option<Money>,
+}
+
+// `total_variance_value` has one entry per currency costs were recorded in,
+// normally just one. Lines without a cost are left out of it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VarianceReport {
+    pub count: StockCount,
+    pub lines: Vec<VarianceLine>,
+    pub total_variance_value: Vec<Money>,
+}
+
+const COUNT_COLUMNS: &str = "id, status, note, opened_at, closed_at, applied_at";
+
+// What line `l` of count `c` expects while the count is closed: the level now
+// less every movement since it closed. This is the level at the close, unless
+// stock taken out before it has been put back since, as when an earlier sale
+// is deleted, which is then expected too rather than corrected as variance.
+const LIVE_EXPECTED_SQL: &str = "(SELECT i.quantity - COALESCE(
+         (SELECT SUM(m.change) FROM stock_movements m
+          WHERE m.ingredient_id = i.id AND m.id > c.closed_movement_id), 0)
+     FROM ingredients i WHERE i.id = l.ingredient_id)";
+
+fn count_from_row(row: &Row) -> Result<StockCount> {
+    Ok(StockCount {
+        id: row.get(0)?,
+        status: StockCountStatus::from_column(&row.get::<_, String>(1)?),
+        note: row.get(2)?,
+        lines: Vec::new(),
+        opened_at: row.get(3)?,
+        closed_at: row.get(4)?,
+        applied_at: row.get(5)?,
+    })
+}
+
+fn with_lines(conn: &Connection, count: StockCount) -> Result<StockCount> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT l.ingredient_id, i.name, i.unit, l.counted_quantity FROM stock_count_lines l
+         JOIN ingredients i ON i.id = l.ingredient_id
+         WHERE l.count_id = ?1 ORDER BY i.name COLLATE NOCASE",
+    )?;
+    let lines = stmt
+        .query_map([count.id], |row| {
+            Ok(StockCountLine {
+                ingredient_id: row.get(0)?,
+                name: row.get(1)?,
+                unit: row.get(2)?,
+                counted_quantity: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(StockCount { lines, ..count })
+}
+
+// Newest first
+pub fn get_stock_counts(conn: &Connection) -> Result<Vec<StockCount>> {
+    let mut stmt = conn.prepare_cached(&format!("SELECT {} FROM stock_counts ORDER BY id DESC", COUNT_COLUMNS))?;
+    let counts = stmt
+        .query_map([], count_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    counts.into_iter().map(|count| with_lines(conn, count)).collect()
+}
+
+pub fn get_stock_count(conn: &Connection, id: i64) -> AppResult<StockCount> {
+    let count = conn
+        .prepare_cached(&format!("SELECT {} FROM stock_counts WHERE id = ?1", COUNT_COLUMNS))?
+        .query_row([id], count_from_row)
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Stock count {} not found", id)))?;
+    Ok(with_lines(conn, count)?)
+}
+
+fn require_status(count: &StockCount, allowed: StockCountStatus, action: &str) -> AppResult<()> {
+    if count.status == allowed {
+        return Ok(());
+    }
+    Err(AppError::Conflict {
+        message: format!(
+            "Stock count {} is {} and cannot be {}",
+            count.id,
+            count.status.as_str(),
+            action
+        ),
+        details: Some(json!({ "id": count.id, "status": count.status })),
+    })
+}
+
+fn last_movement_id(conn: &Connection) -> Result<i64> {
+    conn.query_row("SELECT COALESCE(MAX(id), 0) FROM stock_movements", [], |row| row.get(0))
+}
+
+pub fn open_stock_count(conn: &Connection, note: Option<String>) -> AppResult<StockCount> {
+    let open: Option<i64> = conn
+        .query_row("SELECT id FROM stock_counts WHERE status = 'open'", [], |row| row.get(0))
+        .optional()?;
+    if let Some(open) = open {
+        return Err(AppError::Conflict {
+            message: format!("Stock count {} is still open. Close or discard it first.", open),
+            details: Some(json!({ "id": open })),
+        });
+    }
+    let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    conn.execute("INSERT INTO stock_counts (note) VALUES (?1)", [note])?;
+    get_stock_count(conn, conn.last_insert_rowid())
+}
+
+// Records counted quantities on an open count, replacing any entered before
+pub fn set_counted_quantities(conn: &mut Connection, id: i64, entries: &[CountEntry]) -> AppResult<StockCount> {
+    let tx = conn.transaction()?;
+    require_status(&get_stock_count(&tx, id)?, StockCountStatus::Open, "changed")?;
+    for (index, entry) in entries.iter().enumerate() {
+        let invalid = |reason: &str| {
+            AppError::validation_with(
+                format!("Count #{}: {}", index + 1, reason),
+                json!({ "entry": index, "ingredient_id": entry.ingredient_id }),
+            )
+        };
+        if entry.counted.is_some_and(|q| !q.is_finite() || q < 0.0) {
+            return Err(invalid("counted quantity must be zero or more"));
+        }
+        let known: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM ingredients WHERE id = ?1)",
+            [entry.ingredient_id],
+            |row| row.get(0),
+        )?;
+        if !known {
+            return Err(invalid("ingredient does not exist"));
+        }
+        match entry.counted {
+            Some(counted) => tx.execute(
+                "INSERT INTO stock_count_lines (count_id, ingredient_id, counted_quantity) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (count_id, ingredient_id) DO UPDATE SET counted_quantity = excluded.counted_quantity",
+                (id, entry.ingredient_id, counted),
+            )?,
+            None => tx.execute(
+                "DELETE FROM stock_count_lines WHERE count_id = ?1 AND ingredient_id = ?2",
+                (id, entry.ingredient_id),
+            )?,
+        };
+    }
+    tx.commit()?;
+    get_stock_count(conn, id)
+}
+
+// Freezes the count: each counted ingredient's current level becomes its
+// expected quantity, alongside its cost per unit. Until the count is applied,
+// the expected quantity follows deletions of earlier sales and waste.
+pub fn close_stock_count(conn: &mut Connection, id: i64) -> AppResult<VarianceReport> {
+    let tx = conn.transaction()?;
+    let count = get_stock_count(&tx, id)?;
+    require_status(&count, StockCountStatus::Open, "closed")?;
+    if count.lines.is_empty() {
+        return Err(AppError::validation("Enter at least one counted quantity before closing the count"));
+    }
+    tx.execute(
+        "UPDATE stock_count_lines
+         SET (expected_quantity, unit_cost, cost_currency) =
+             (SELECT quantity, cost_per_unit, cost_currency FROM ingredients WHERE id = ingredient_id)
+         WHERE count_id = ?1",
+        [id],
+    )?;
+    tx.execute(
+        "UPDATE stock_counts SET status = 'closed', closed_at = CURRENT_TIMESTAMP, closed_movement_id = ?2
+         WHERE id = ?1",
+        (id, last_movement_id(&tx)?),
+    )?;
+    tx.commit()?;
+    get_variance_report(conn, id)
+}
+
+// Counted less expected for every line of a closed or applied count. The
+// breakdown covers the stock movements between the close of the
+// ingredient's last applied count and the close of this one, leaving out the
+// adjustments made by counts, so `opening` is what was last counted.
+pub fn get_variance_report(conn: &Connection, id: i64) -> AppResult<VarianceReport> {
+    let count = get_stock_count(conn, id)?;
+    if count.status == StockCountStatus::Open {
+        return Err(AppError::Conflict {
+            message: format!("Stock count {} is still open. Close it to see the variance.", id),
+            details: Some(json!({ "id": id, "status": count.status })),
+        });
+    }
+    let closed_movement_id: i64 = conn.query_row(
+        "SELECT COALESCE(closed_movement_id, 0) FROM stock_counts WHERE id = ?1",
+        [id],
+        |row| row.get(0),
+    )?;
+
+    let mut lines_stmt = conn.prepare_cached(&format!(
+        "SELECT l.ingredient_id, i.name, i.unit, l.counted_quantity,
+                CASE WHEN c.status = 'closed' THEN {} ELSE l.expected_quantity END,
+                l.unit_cost, l.cost_currency,
+                (SELECT COALESCE(MAX(a.closed_movement_id), 0) FROM stock_counts a
+                 JOIN stock_count_lines p ON p.count_id = a.id
+                 WHERE a.status = 'applied' AND a.id < l.count_id AND p.ingredient_id = l.ingredient_id)
+         FROM stock_count_lines l
+         JOIN stock_counts c ON c.id = l.count_id
+         JOIN ingredients i ON i.id = l.ingredient_id
+         WHERE l.count_id = ?1 ORDER BY i.name COLLATE NOCASE",
+        LIVE_EXPECTED_SQL
+    ))?;
+    let rows = lines_stmt
+        .query_map([id], |row| {
+            let unit_cost: Option<i64> = row.get(5)?;
+            let currency: Option<String> = row.get(6)?;
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, f64>(3)?,
+                row.get::<_, Option<f64>>(4)?.unwrap_or_default(),
+                unit_cost.zip(currency).map(|(minor, currency)| Money::new(minor, currency)),
+                row.get::<_, i64>(7)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut movements_stmt = conn.prepare_cached(
+        "SELECT kind, SUM(change) FROM stock_movements
+         WHERE ingredient_id = ?1 AND id > ?2 AND id <= ?3 AND stock_count_id IS NULL
+         GROUP BY kind",
+    )?;
+    let mut lines = Vec::with_capacity(rows.len());
+    let mut totals: Vec<Money> = Vec::new();
+    for (ingredient_id, name, unit, counted, expected, unit_cost, since) in rows {
+        let changes: HashMap<String, f64> = movements_stmt
+            .query_map((ingredient_id, since, closed_movement_id), |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_>>()?;
+        let received = changes.get(MovementKind::Receive.as_str()).copied().unwrap_or_default();
+        let used = changes.get(MovementKind::Sale.as_str()).map_or(0.0, |change| -change);
+        let wasted = changes.get(MovementKind::Waste.as_str()).map_or(0.0, |change| -change);
+        let adjusted = changes.get(MovementKind::Adjust.as_str()).copied().unwrap_or_default();
+        let variance = counted - expected;
+        let variance_value = unit_cost.as_ref().map(|cost| cost.times_quantity(variance));
+        if let Some(value) = &variance_value {
+            match totals.iter_mut().find(|t| t.currency == value.currency) {
+                Some(total) => total.minor += value.minor,
+                None => totals.push(value.clone()),
+            }
+        }
+        lines.push(VarianceLine {
+            ingredient_id,
+            name,
+            unit,
+            opening: expected - received + used + wasted - adjusted,
+            received,
+            used,
+            wasted,
+            adjusted,
+            expected,
+            counted,
+            variance,
+            unit_cost,
+            variance_value,
+        });
+    }
+    Ok(VarianceReport {
+        count,
+        lines,
+        total_variance_value: totals,
+    })
+}
+
+// Corrects stock by each line's variance. Sales made since the count was
+// closed stay deducted, so levels are moved by the difference rather than
+// set to the counted quantity. The expected quantities the variance came
+// from are kept with the count.
+pub fn apply_stock_count(conn: &mut Connection, id: i64) -> AppResult<VarianceReport> {
+    let tx = conn.transaction()?;
+    require_status(&get_stock_count(&tx, id)?, StockCountStatus::Closed, "applied")?;
+    tx.execute(
+        &format!(
+            "UPDATE stock_count_lines AS l SET expected_quantity = {}
+             FROM stock_counts c WHERE c.id = l.count_id AND l.count_id = ?1",
+            LIVE_EXPECTED_SQL
+        ),
+        [id],
+    )?;
+    let report = get_variance_report(&tx, id)?;
+    for line in report.lines.iter().filter(|line| line.variance != 0.0) {
+        inventory::record_movement(
+            &tx,
+            line.ingredient_id,
+            &MovementEntry {
+                kind: MovementKind::Adjust,
+                change: line.variance,
+                unit_cost: None,
+                sale_id: None,
+                purchase_order_id: None,
+                stock_count_id: Some(id),
+                waste_entry_id: None,
+                note: None,
+            },
+        )?;
+    }
+    tx.execute(
+        "UPDATE stock_counts SET status = 'applied', applied_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        [id],
+    )?;
+    tx.commit()?;
+    get_variance_report(conn, id)
+}
+
+// Discards a count that hasn't been applied
+pub fn delete_stock_count(conn: &Connection, id: i64) -> AppResult<()> {
+    let count = get_stock_count(conn, id)?;
+    if count.status == StockCountStatus::Applied {
+        return Err(AppError::Conflict {
+            message: format!("Stock count {} has been applied and cannot be deleted", id),
+            details: Some(json!({ "id": id, "status": count.status })),
+        });
+    }
+    conn.execute("DELETE FROM stock_counts WHERE id = ?1", [id])?;
+    Ok(())
+}
@@ -0,0 +1,339 @@
+use crate::database;
+use crate::error::{AppError, AppResult};
+use crate::money::Money;
+use rusqlite::{Connection, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+
+// Which of the rates in settings a product is taxed at
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxClass {
+    #[default]
+    Standard,
+    Reduced,
+    Exempt,
+}
+
+impl TaxClass {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TaxClass::Standard => "standard",
+            TaxClass::Reduced => "reduced",
+            TaxClass::Exempt => "exempt",
+        }
+    }
+
+    pub fn from_column(value: &str) -> Self {
+        match value {
+            "reduced" => TaxClass::Reduced,
+            "exempt" => TaxClass::Exempt,
+            _ => TaxClass::Standard,
+        }
+    }
+}
+
+// The tax settings a sale is made under. Rates are in basis points,
+// hundredths of a percent, so 17.5% is 1750. With `prices_include_tax` the
+// tax is part of each price; otherwise it is added on top.
+#[derive(Debug, Clone, Copy)]
+pub struct TaxRates {
+    pub standard: i64,
+    pub reduced: i64,
+    pub prices_include_tax: bool,
+}
+
+impl TaxRates {
+    pub fn rate(&self, class: TaxClass) -> i64 {
+        match class {
+            TaxClass::Standard => self.standard,
+            TaxClass::Reduced => self.reduced,
+            TaxClass::Exempt => 0,
+        }
+    }
+}
+
+// Tax on the lines of one class at one rate. `taxable` is what they came to
+// without tax.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaxBreakdown {
+    pub tax_class: TaxClass,
+    pub rate: f64,
+    pub taxable: Money,
+    pub tax: Money,
+}
+
+// Reads a percentage such as "17" or "17.5" as basis points. Empty means no
+// rate; anything else must be between 0 and 100 with at most two decimals.
+pub fn parse_rate(value: Option<&str>, field: &str) -> AppResult<Option<i64>> {
+    let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+    let invalid = || {
+        AppError::validation_with(
+            format!("Invalid tax rate: {}", value),
+            json!({ "field": field, "expected": "a percentage between 0 and 100" }),
+        )
+    };
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if whole.is_empty() && fraction.is_empty()
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !fraction.chars().all(|c| c.is_ascii_digit())
+        || fraction.len() > 2
+        || whole.len() > 3
+    {
+        return Err(invalid());
+    }
+    let whole: i64 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| invalid())? };
+    let fraction: i64 = format!("{:0<2}", fraction).parse().map_err(|_| invalid())?;
+    let rate = whole * 100 + fraction;
+    if rate > 10_000 {
+        return Err(invalid());
+    }
+    Ok(Some(rate))
+}
+
+// Basis points as a percentage, e.g. 1750 as 17.5
+pub fn rate_percent(rate: i64) -> f64 {
+    rate as f64 / 100.0
+}
+
+// Basis points as they are written in settings, e.g. 1750 as "17.5"
+pub fn format_rate(rate: i64) -> String {
+    let (whole, fraction) = (rate / 100, rate % 100);
+    match fraction {
+        0 => whole.to_string(),
+        f if f % 10 == 0 => format!("{}.{}", whole, f / 10),
+        f => format!("{}.{:02}", whole, f),
+    }
+}
+
+// The rates sales are taxed at now. A rate left empty is zero, and prices
+// include tax unless settings say otherwise, as they did before tax was
+// worked out here.
+pub fn tax_rates(conn: &Connection) -> Result<TaxRates> {
+    let (standard, reduced, prices_include_tax): (Option<String>, Option<String>, Option<bool>) = conn
+        .prepare_cached("SELECT tax_rate, reduced_tax_rate, prices_include_tax FROM settings WHERE id = 1")?
+        .query_row([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+    // Settings are validated when saved, so a bad rate can only be a blank
+    let rate = |value: Option<String>| parse_rate(value.as_deref(), "").ok().flatten().unwrap_or(0);
+    Ok(TaxRates {
+        standard: rate(standard),
+        reduced: rate(reduced),
+        prices_include_tax: prices_include_tax.unwrap_or(true),
+    })
+}
+
+// Divides, rounding half away from zero like Money::times_quantity
+fn div_round(numerator: i128, denominator: i128) -> i64 {
+    let half = denominator / 2;
+    let rounded = if numerator >= 0 {
+        (numerator + half) / denominator
+    } else {
+        (numerator - half) / denominator
+    };
+    rounded as i64
+}
+
+// Tax on a line that came to `amount` minor units at `rate`. An inclusive
+// amount already contains its tax, which is taken back out of it.
+pub fn line_tax(amount: i64, rate: i64, prices_include_tax: bool) -> i64 {
+    let denominator = if prices_include_tax { 10_000 + rate } else { 10_000 };
+    div_round(amount as i128 * rate as i128, denominator as i128)
+}
+
+// Sales of one class at one rate in a tax report, net of tax and
+// discounts. Sales of the exempt class are `exempt_sales`; everything else
+// is `taxable_sales`, including sales at a rate of zero. Both are what was
+// left after `refunds`, the amount given back net of tax, and `tax` is
+// what was left after `refunded_tax`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaxSummaryLine {
+    pub tax_class: TaxClass,
+    pub rate: f64,
+    pub taxable_sales: Money,
+    pub exempt_sales: Money,
+    pub tax: Money,
+    pub refunds: Money,
+    pub refunded_tax: Money,
+}
+
+// Tax on sales between two dates, inclusive, in the report currency.
+// `untaxed_sales` is what was sold before tax was recorded per line, which
+// no line of the report includes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaxReport {
+    pub start_date: String,
+    pub end_date: String,
+    pub lines: Vec<TaxSummaryLine>,
+    pub taxable_sales: Money,
+    pub exempt_sales: Money,
+    pub tax: Money,
+    pub refunds: Money,
+    pub refunded_tax: Money,
+    pub untaxed_sales: Money,
+}
+
+// Tax collected by class and rate, for filing. Amounts are summed with
+// their sign, so a refund, being a sale of its own with negative lines,
+// nets out of the period it was given in whenever the sale was made.
+pub fn get_tax_report(conn: &Connection, start_date: &str, end_date: &str) -> AppResult<TaxReport> {
+    let (start, end) = database::parse_date_range(start_date, end_date)?;
+    let currency = database::report_currency(conn)?;
+    let money = |minor: i64| Money::new(minor, &*currency);
+
+    let mut stmt = conn.prepare_cached(
+        "WITH lines AS (
+             SELECT i.tax_class, i.tax_rate, s.refund_of IS NOT NULL AS is_refund, i.tax,
+                    i.subtotal - i.discount - i.order_discount - CASE WHEN s.prices_include_tax THEN i.tax ELSE 0 END AS net
+             FROM sale_items i JOIN sales s ON s.id = i.sale_id
+             WHERE s.date BETWEEN ?1 AND ?2 AND s.currency = ?3 AND i.tax_class IS NOT NULL
+         )
+         SELECT tax_class, tax_rate, SUM(net), SUM(tax),
+                -SUM(CASE WHEN is_refund THEN net ELSE 0 END), -SUM(CASE WHEN is_refund THEN tax ELSE 0 END)
+         FROM lines
+         GROUP BY tax_class, tax_rate",
+    )?;
+    let mut lines = stmt
+        .query_map((&start, &end, &currency), |row| {
+            let tax_class = TaxClass::from_column(&row.get::<_, String>(0)?);
+            let net: i64 = row.get(2)?;
+            let (taxable, exempt) = if tax_class == TaxClass::Exempt { (0, net) } else { (net, 0) };
+            Ok(TaxSummaryLine {
+                tax_class,
+                rate: rate_percent(row.get(1)?),
+                taxable_sales: money(taxable),
+                exempt_sales: money(exempt),
+                tax: money(row.get(3)?),
+                refunds: money(row.get(4)?),
+                refunded_tax: money(row.get(5)?),
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    lines.sort_by(|a, b| a.tax_class.cmp(&b.tax_class).then(a.rate.total_cmp(&b.rate)));
+
+    let untaxed_sales: i64 = conn.query_row(
+        "SELECT COALESCE(SUM(i.subtotal - i.discount - i.order_discount), 0)
+         FROM sale_items i JOIN sales s ON s.id = i.sale_id
+         WHERE s.date BETWEEN ?1 AND ?2 AND s.currency = ?3 AND i.tax_class IS NULL",
+        (&start, &end, &currency),
+        |row| row.get(0),
+    )?;
+
+    Ok(TaxReport {
+        taxable_sales: money(lines.iter().map(|l| l.taxable_sales.minor).sum()),
+        exempt_sales: money(lines.iter().map(|l| l.exempt_sales.minor).sum()),
+        tax: money(lines.iter().map(|l| l.tax.minor).sum()),
+        refunds: money(lines.iter().map(|l| l.refunds.minor).sum()),
+        refunded_tax: money(lines.iter().map(|l| l.refunded_tax.minor).sum()),
+        untaxed_sales: money(untaxed_sales),
+        start_date: start,
+        end_date: end,
+        lines,
+    })
+}
+
+// The report as CSV, one row per class and rate followed by a total row.
+// Amounts are plain decimals in the report currency.
+pub fn tax_report_csv(report: &TaxReport) -> String {
+    let mut csv = String::from("tax_class,rate,taxable_sales,exempt_sales,tax,refunds,refunded_tax,currency\n");
+    let currency = &report.tax.currency;
+    for line in &report.lines {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            line.tax_class.as_str(),
+            line.rate,
+            line.taxable_sales.amount_string(),
+            line.exempt_sales.amount_string(),
+            line.tax.amount_string(),
+            line.refunds.amount_string(),
+            line.refunded_tax.amount_string(),
+            currency
+        ));
+    }
+    csv.push_str(&format!(
+        "total,,{},{},{},{},{},{}\n",
+        report.taxable_sales.amount_string(),
+        report.exempt_sales.amount_string(),
+        report.tax.amount_string(),
+        report.refunds.amount_string(),
+        report.refunded_tax.amount_string(),
+        currency
+    ));
+    csv
+}
+
+pub fn export_tax_report_csv(conn: &Connection, start_date: &str, end_date: &str, path: &Path) -> AppResult<()> {
+    let report = get_tax_report(conn, start_date, end_date)?;
+    std::fs::write(path, tax_report_csv(&report))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_percentages_as_basis_points() {
+        assert_eq!(parse_rate(Some("17"), "tax_rate").unwrap(), Some(1700));
+        assert_eq!(parse_rate(Some(" 17.5 "), "tax_rate").unwrap(), Some(1750));
+        assert_eq!(parse_rate(Some("5.25"), "tax_rate").unwrap(), Some(525));
+        assert_eq!(parse_rate(Some(".5"), "tax_rate").unwrap(), Some(50));
+        assert_eq!(parse_rate(Some("100"), "tax_rate").unwrap(), Some(10_000));
+        assert_eq!(parse_rate(Some("  "), "tax_rate").unwrap(), None);
+        assert_eq!(parse_rate(None, "tax_rate").unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_rates_that_are_not_percentages() {
+        for rate in ["abc", ".", "-1", "17.555", "100.01", "1000", "1e2", "17%"] {
+            let err = parse_rate(Some(rate), "tax_rate").unwrap_err();
+            assert!(matches!(err, AppError::Validation { .. }), "{}: {:?}", rate, err);
+        }
+    }
+
+    #[test]
+    fn formats_rates_as_they_are_written() {
+        assert_eq!(format_rate(1700), "17");
+        assert_eq!(format_rate(1750), "17.5");
+        assert_eq!(format_rate(525), "5.25");
+        assert_eq!(format_rate(5), "0.05");
+        for rate in ["0", "8.75", "12.5", "100"] {
+            assert_eq!(format_rate(parse_rate(Some(rate), "").unwrap().unwrap()), rate);
+        }
+    }
+
+    #[test]
+    fn adds_exclusive_tax_on_top() {
+        assert_eq!(line_tax(10_000, 1750, false), 1750);
+        assert_eq!(line_tax(10_000, 0, false), 0);
+        // 0.5 and 1.5 minor units round away from zero
+        assert_eq!(line_tax(10, 500, false), 1);
+        assert_eq!(line_tax(30, 500, false), 2);
+        assert_eq!(line_tax(29, 500, false), 1);
+        assert_eq!(line_tax(-10, 500, false), -1);
+    }
+
+    #[test]
+    fn takes_inclusive_tax_out_of_the_amount() {
+        assert_eq!(line_tax(11_750, 1750, true), 1750);
+        assert_eq!(line_tax(10_500, 500, true), 500);
+        // At 100% half of the amount is tax: 0.5 and 1.5 round away from zero
+        assert_eq!(line_tax(1, 10_000, true), 1);
+        assert_eq!(line_tax(3, 10_000, true), 2);
+        assert_eq!(line_tax(-3, 10_000, true), -2);
+    }
+
+    #[test]
+    fn exempt_lines_have_no_rate() {
+        let rates = TaxRates {
+            standard: 1700,
+            reduced: 500,
+            prices_include_tax: true,
+        };
+        assert_eq!(rates.rate(TaxClass::Standard), 1700);
+        assert_eq!(rates.rate(TaxClass::Reduced), 500);
+        assert_eq!(rates.rate(TaxClass::Exempt), 0);
+    }
+}
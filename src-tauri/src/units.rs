@@ -0,0 +1,198 @@
+use crate::error::{AppError, AppResult};
+use serde::Serialize;
+use serde_json::json;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimension {
+    Mass,
+    Volume,
+    Count,
+}
+
+impl Dimension {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Dimension::Mass => "mass",
+            Dimension::Volume => "volume",
+            Dimension::Count => "count",
+        }
+    }
+}
+
+// A unit quantities can be recorded in. `factor` is the size of one unit in
+// the base unit of its dimension: grams, millilitres or single items.
+// `aliases` are other spellings accepted for it, matched without case.
+#[derive(Debug, Serialize)]
+pub struct Unit {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub dimension: Dimension,
+    pub factor: f64,
+    #[serde(skip)]
+    aliases: &'static [&'static str],
+}
+
+const fn unit(
+    code: &'static str,
+    name: &'static str,
+    dimension: Dimension,
+    factor: f64,
+    aliases: &'static [&'static str],
+) -> Unit {
+    Unit {
+        code,
+        name,
+        dimension,
+        factor,
+        aliases,
+    }
+}
+
+// Every unit products and ingredients can be measured in. Counted units are
+// all one of something, apart from a dozen; they convert into each other
+// one for one.
+pub const UNITS: &[Unit] = &[
+    unit("mg", "milligram", Dimension::Mass, 0.001, &["milligrams", "milligramme", "milligrammes"]),
+    unit("g", "gram", Dimension::Mass, 1.0, &["grams", "gm", "gms", "gr", "gramme", "grammes"]),
+    unit("kg", "kilogram", Dimension::Mass, 1000.0, &["kilograms", "kilo", "kilos", "kgs", "kilogramme", "kilogrammes"]),
+    unit("oz", "ounce", Dimension::Mass, 28.349523125, &["ounces"]),
+    unit("lb", "pound", Dimension::Mass, 453.59237, &["pounds", "lbs"]),
+    unit("ml", "millilitre", Dimension::Volume, 1.0, &["millilitres", "milliliter", "milliliters"]),
+    unit("cl", "centilitre", Dimension::Volume, 10.0, &["centilitres", "centiliter", "centiliters"]),
+    unit("l", "litre", Dimension::Volume, 1000.0, &["litres", "liter", "liters", "ltr", "ltrs"]),
+    unit("tsp", "teaspoon", Dimension::Volume, 4.92892159375, &["teaspoons"]),
+    unit("tbsp", "tablespoon", Dimension::Volume, 14.78676478125, &["tablespoons"]),
+    unit("fl oz", "fluid ounce", Dimension::Volume, 29.5735295625, &["fluid ounces", "floz"]),
+    unit("cup", "cup", Dimension::Volume, 236.5882365, &["cups"]),
+    unit("gal", "gallon", Dimension::Volume, 3785.411784, &["gallons"]),
+    unit("item", "item", Dimension::Count, 1.0, &["items", "each", "ea", "unit", "units", "nos"]),
+    unit("piece", "piece", Dimension::Count, 1.0, &["pieces", "pc", "pcs"]),
+    unit("portion", "portion", Dimension::Count, 1.0, &["portions", "serving", "servings"]),
+    unit("plate", "plate", Dimension::Count, 1.0, &["plates"]),
+    unit("slice", "slice", Dimension::Count, 1.0, &["slices"]),
+    unit("bottle", "bottle", Dimension::Count, 1.0, &["bottles"]),
+    unit("can", "can", Dimension::Count, 1.0, &["cans", "tin", "tins"]),
+    unit("pack", "pack", Dimension::Count, 1.0, &["packs", "packet", "packets"]),
+    unit("box", "box", Dimension::Count, 1.0, &["boxes"]),
+    unit("dozen", "dozen", Dimension::Count, 12.0, &["dozens", "doz"]),
+];
+
+// The registry unit `text` names: its code, name or an alias, ignoring case
+// and surrounding spaces
+pub fn find_unit(text: &str) -> Option<&'static Unit> {
+    let text = text.trim();
+    UNITS.iter().find(|unit| {
+        unit.code.eq_ignore_ascii_case(text)
+            || unit.name.eq_ignore_ascii_case(text)
+            || unit.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(text))
+    })
+}
+
+// Like find_unit, but an unknown unit is a validation error on `field`
+pub fn parse_unit(text: &str, field: &str) -> AppResult<&'static Unit> {
+    find_unit(text).ok_or_else(|| {
+        let message = if text.trim().is_empty() {
+            "Unit is required".to_string()
+        } else {
+            format!("Unknown unit: {}", text.trim())
+        };
+        AppError::validation_with(
+            message,
+            json!({ "field": field, "expected": UNITS.iter().map(|u| u.code).collect::<Vec<_>>() }),
+        )
+    })
+}
+
+// `quantity` of `from` expressed in `to`, or None when they measure
+// different things
+pub fn convert(quantity: f64, from: &Unit, to: &Unit) -> Option<f64> {
+    if from.dimension != to.dimension {
+        return None;
+    }
+    if from.code == to.code {
+        return Some(quantity);
+    }
+    Some(quantity * from.factor / to.factor)
+}
+
+// SQL for `quantity` of the unit in `from` expressed in the unit in `to`,
+// all three being column expressions holding unit codes. Quantities whose
+// units are unknown or measure different things are left as they are.
+pub fn convert_sql(quantity: &str, from: &str, to: &str) -> String {
+    let case = |column: &str, value: &dyn Fn(&Unit) -> String| {
+        let arms: String = UNITS
+            .iter()
+            .map(|unit| format!(" WHEN '{}' THEN {}", unit.code, value(unit)))
+            .collect();
+        format!("(CASE {}{} END)", column, arms)
+    };
+    let factor = |unit: &Unit| format!("{:?}", unit.factor);
+    let dimension = |unit: &Unit| format!("'{}'", unit.dimension.as_str());
+    format!(
+        "({q} * CASE WHEN {fd} = {td} THEN {ff} / {tf} ELSE 1.0 END)",
+        q = quantity,
+        fd = case(from, &dimension),
+        td = case(to, &dimension),
+        ff = case(from, &factor),
+        tf = case(to, &factor),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(text: &str) -> &'static Unit {
+        find_unit(text).unwrap()
+    }
+
+    #[test]
+    fn finds_units_by_code_name_or_alias() {
+        assert_eq!(unit("kg").code, "kg");
+        assert_eq!(unit(" Kilogram ").code, "kg");
+        assert_eq!(unit("KILOS").code, "kg");
+        assert_eq!(unit("fluid ounces").code, "fl oz");
+        assert!(find_unit("bunch").is_none());
+        assert!(find_unit("").is_none());
+    }
+
+    #[test]
+    fn converts_within_a_dimension() {
+        assert_eq!(convert(1.5, unit("kg"), unit("g")), Some(1500.0));
+        assert_eq!(convert(250.0, unit("ml"), unit("l")), Some(0.25));
+        assert_eq!(convert(2.0, unit("dozen"), unit("piece")), Some(24.0));
+        assert_eq!(convert(3.0, unit("plate"), unit("portion")), Some(3.0));
+        let grams = convert(1.0, unit("lb"), unit("g")).unwrap();
+        assert!((grams - 453.59237).abs() < 1e-9);
+        let back = convert(convert(7.0, unit("cup"), unit("ml")).unwrap(), unit("ml"), unit("cup")).unwrap();
+        assert!((back - 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn does_not_convert_across_dimensions() {
+        assert_eq!(convert(1.0, unit("kg"), unit("l")), None);
+        assert_eq!(convert(1.0, unit("item"), unit("g")), None);
+    }
+
+    #[test]
+    fn converts_in_sql_like_in_rust() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let sql = format!("SELECT {}", convert_sql("?1", "?2", "?3"));
+        let convert_in_sql = |quantity: f64, from: &str, to: &str| -> f64 {
+            conn.query_row(&sql, (quantity, from, to), |row| row.get(0)).unwrap()
+        };
+        assert_eq!(convert_in_sql(1.5, "kg", "g"), 1500.0);
+        assert_eq!(convert_in_sql(2.0, "dozen", "item"), 24.0);
+        // Left as it is when the units don't convert
+        assert_eq!(convert_in_sql(2.0, "kg", "l"), 2.0);
+        assert_eq!(convert_in_sql(2.0, "bunch", "g"), 2.0);
+    }
+
+    #[test]
+    fn unknown_units_are_validation_errors() {
+        let err = parse_unit("bunch", "unit").unwrap_err();
+        assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+        assert_eq!(parse_unit(" Grams ", "unit").unwrap().code, "g");
+    }
+}
@@ -0,0 +1,334 @@
+use crate::bundles;
+use crate::database;
+use crate::error::{AppError, AppResult};
+use crate::inventory::{self, MovementEntry, MovementKind};
+use crate::money::Money;
+use crate::units;
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WasteReason {
+    Spoiled,
+    Dropped,
+    Comped,
+    StaffMeal,
+    Other,
+}
+
+impl WasteReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            WasteReason::Spoiled => "spoiled",
+            WasteReason::Dropped => "dropped",
+            WasteReason::Comped => "comped",
+            WasteReason::StaffMeal => "staff_meal",
+            WasteReason::Other => "other",
+        }
+    }
+
+    fn from_column(value: &str) -> Self {
+        match value {
+            "spoiled" => WasteReason::Spoiled,
+            "dropped" => WasteReason::Dropped,
+            "comped" => WasteReason::Comped,
+            "staff_meal" => WasteReason::StaffMeal,
+            _ => WasteReason::Other,
+        }
+    }
+}
+
+// Stock thrown away, given away or eaten by staff. `name` and `unit` are as
+// logged; `cost` is the value of the ingredients lost, when they had a cost.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WasteEntry {
+    pub id: i64,
+    pub ingredient_id: Option<i64>,
+    pub product_id: Option<i64>,
+    pub name: String,
+    pub unit: String,
+    pub quantity: f64,
+    pub reason: WasteReason,
+    pub recorded_by: String,
+    pub note: Option<String>,
+    pub cost: Option<Money>,
+    pub created_at: String,
+}
+
+// Either an ingredient, or a product whose recipe is taken out of stock.
+// `unit` defaults to the item's own; a quantity in any other unit of the
+// same kind is converted into it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewWasteEntry {
+    #[serde(default)]
+    pub ingredient_id: Option<i64>,
+    #[serde(default)]
+    pub product_id: Option<i64>,
+    pub quantity: f64,
+    #[serde(default)]
+    pub unit: Option<String>,
+    pub reason: WasteReason,
+    pub recorded_by: String,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WasteReasonTotal {
+    pub reason: WasteReason,
+    pub entries: i64,
+    pub value: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WasteItemTotal {
+    pub ingredient_id: Option<i64>,
+    pub product_id: Option<i64>,
+    pub name: String,
+    pub unit: String,
+    pub quantity: f64,
+    pub value: Money,
+}
+
+// Waste between two dates, inclusive. Values are in the report currency;
+// `uncosted_entries` counts entries with no cost in it, which add nothing
+// to the values.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WasteReport {
+    pub start_date: String,
+    pub end_date: String,
+    pub total: Money,
+    pub uncosted_entries: i64,
+    pub by_reason: Vec<WasteReasonTotal>,
+    pub by_item: Vec<WasteItemTotal>,
+}
+
+const ENTRY_COLUMNS: &str =
+    "id, ingredient_id, product_id, name, unit, quantity, reason, recorded_by, note, cost, currency, created_at";
+
+fn entry_from_row(row: &Row) -> Result<WasteEntry> {
+    let cost: Option<i64> = row.get(9)?;
+    let currency: Option<String> = row.get(10)?;
+    Ok(WasteEntry {
+        id: row.get(0)?,
+        ingredient_id: row.get(1)?,
+        product_id: row.get(2)?,
+        name: row.get(3)?,
+        unit: row.get(4)?,
+        quantity: row.get(5)?,
+        reason: WasteReason::from_column(&row.get::<_, String>(6)?),
+        recorded_by: row.get(7)?,
+        note: row.get(8)?,
+        cost: cost.zip(currency).map(|(minor, currency)| Money::new(minor, currency)),
+        created_at: row.get(11)?,
+    })
+}
+
+fn get_waste_entry(conn: &Connection, id: i64) -> AppResult<WasteEntry> {
+    conn.prepare_cached(&format!("SELECT {} FROM waste_entries WHERE id = ?1", ENTRY_COLUMNS))?
+        .query_row([id], entry_from_row)
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Waste entry {} not found", id)))
+}
+
+// Newest first
+pub fn get_waste_entries(conn: &Connection, start_date: &str, end_date: &str) -> AppResult<Vec<WasteEntry>> {
+    let (start, end) = database::parse_date_range(start_date, end_date)?;
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM waste_entries WHERE date(created_at) BETWEEN ?1 AND ?2 ORDER BY id DESC",
+        ENTRY_COLUMNS
+    ))?;
+    let entries = stmt
+        .query_map((&start, &end), entry_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(entries)
+}
+
+// Takes what was wasted out of stock and logs it. A product uses up its
+// recipe, and a bundle the recipes of its default components as well; a
+// product without a recipe is logged without touching stock.
+pub fn log_waste(conn: &mut Connection, entry: NewWasteEntry) -> AppResult<WasteEntry> {
+    let recorded_by = entry.recorded_by.trim();
+    if recorded_by.is_empty() {
+        return Err(AppError::validation_with(
+            "Enter who is logging the waste",
+            json!({ "field": "recorded_by" }),
+        ));
+    }
+    if !entry.quantity.is_finite() || entry.quantity <= 0.0 {
+        return Err(AppError::validation_with(
+            "Quantity must be greater than zero",
+            json!({ "field": "quantity" }),
+        ));
+    }
+
+    let in_unit = |unit: &str| -> AppResult<f64> {
+        let Some(given) = entry.unit.as_deref().map(str::trim).filter(|u| !u.is_empty()) else {
+            return Ok(entry.quantity);
+        };
+        let from = units::parse_unit(given, "unit")?;
+        units::find_unit(unit)
+            .and_then(|to| units::convert(entry.quantity, from, to))
+            .ok_or_else(|| {
+                AppError::validation_with(
+                    format!("{} can't be converted into {}", from.code, unit),
+                    json!({ "field": "unit" }),
+                )
+            })
+    };
+
+    let tx = conn.transaction()?;
+    let (name, unit, quantity, used) = match (entry.ingredient_id, entry.product_id) {
+        (Some(ingredient_id), None) => {
+            let ingredient = inventory::get_ingredient(&tx, ingredient_id)?;
+            let quantity = in_unit(&ingredient.unit)?;
+            let used = BTreeMap::from([(ingredient_id, quantity)]);
+            (ingredient.name, ingredient.unit, quantity, used)
+        }
+        (None, Some(product_id)) => {
+            let (name, unit): (String, String) = tx
+                .query_row("SELECT name, unit FROM products WHERE id = ?1", [product_id], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })
+                .optional()?
+                .ok_or_else(|| AppError::not_found(format!("Product {} not found", product_id)))?;
+            let quantity = in_unit(&unit)?;
+            let mut served = vec![(product_id, quantity)];
+            for component in bundles::get_bundle_components(&tx, product_id)? {
+                served.push((component.product_id, component.quantity * quantity));
+            }
+            (name, unit, quantity, inventory::recipe_usage(&tx, &served)?)
+        }
+        _ => {
+            return Err(AppError::validation(
+                "Choose either an ingredient or a product to log as waste",
+            ))
+        }
+    };
+
+    // Valued at the ingredients' latest cost, where it is in the report currency
+    let currency = database::report_currency(&tx)?;
+    let mut cost: Option<i64> = None;
+    for (&ingredient_id, &quantity) in &used {
+        if let Some(unit_cost) = inventory::get_ingredient(&tx, ingredient_id)?
+            .cost_per_unit
+            .filter(|c| c.currency == currency)
+        {
+            *cost.get_or_insert(0) += unit_cost.times_quantity(quantity).minor;
+        }
+    }
+
+    let note = entry.note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    tx.execute(
+        "INSERT INTO waste_entries
+             (ingredient_id, product_id, name, unit, quantity, reason, recorded_by, note, cost, currency)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        (
+            entry.ingredient_id,
+            entry.product_id,
+            &name,
+            &unit,
+            quantity,
+            entry.reason.as_str(),
+            recorded_by,
+            &note,
+            cost,
+            cost.map(|_| &currency),
+        ),
+    )?;
+    let id = tx.last_insert_rowid();
+    for (ingredient_id, quantity) in used {
+        inventory::record_movement(
+            &tx,
+            ingredient_id,
+            &MovementEntry {
+                kind: MovementKind::Waste,
+                change: -quantity,
+                unit_cost: None,
+                sale_id: None,
+                purchase_order_id: None,
+                stock_count_id: None,
+                waste_entry_id: Some(id),
+                note: note.as_deref(),
+            },
+        )?;
+    }
+    tx.commit()?;
+    get_waste_entry(conn, id)
+}
+
+// Removes an entry logged by mistake, putting its stock back
+pub fn delete_waste_entry(conn: &mut Connection, id: i64) -> AppResult<()> {
+    let tx = conn.transaction()?;
+    get_waste_entry(&tx, id)?;
+    inventory::restore_waste_stock(&tx, id)?;
+    tx.execute("DELETE FROM waste_entries WHERE id = ?1", [id])?;
+    tx.commit()?;
+    Ok(())
+}
+
+// Waste by reason and by item between two dates, inclusive. Items still in
+// the database are grouped by id under their current name.
+pub fn get_waste_report(conn: &Connection, start_date: &str, end_date: &str) -> AppResult<WasteReport> {
+    let (start, end) = database::parse_date_range(start_date, end_date)?;
+    let currency = database::report_currency(conn)?;
+    let money = |minor: i64| Money::new(minor, &*currency);
+
+    let mut reason_stmt = conn.prepare_cached(
+        "SELECT reason, COUNT(*), COALESCE(SUM(CASE WHEN currency = ?3 THEN cost END), 0)
+         FROM waste_entries WHERE date(created_at) BETWEEN ?1 AND ?2
+         GROUP BY reason ORDER BY 3 DESC, 2 DESC",
+    )?;
+    let by_reason = reason_stmt
+        .query_map((&start, &end, &currency), |row| {
+            Ok(WasteReasonTotal {
+                reason: WasteReason::from_column(&row.get::<_, String>(0)?),
+                entries: row.get(1)?,
+                value: money(row.get(2)?),
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut item_stmt = conn.prepare_cached(
+        "SELECT w.ingredient_id, w.product_id, COALESCE(i.name, p.name, w.name), COALESCE(i.unit, p.unit, w.unit),
+                SUM(w.quantity), COALESCE(SUM(CASE WHEN w.currency = ?3 THEN w.cost END), 0)
+         FROM waste_entries w
+         LEFT JOIN ingredients i ON i.id = w.ingredient_id
+         LEFT JOIN products p ON p.id = w.product_id
+         WHERE date(w.created_at) BETWEEN ?1 AND ?2
+         GROUP BY w.ingredient_id, w.product_id,
+                  CASE WHEN w.ingredient_id IS NULL AND w.product_id IS NULL THEN w.name END
+         ORDER BY 6 DESC, 3 COLLATE NOCASE",
+    )?;
+    let by_item = item_stmt
+        .query_map((&start, &end, &currency), |row| {
+            Ok(WasteItemTotal {
+                ingredient_id: row.get(0)?,
+                product_id: row.get(1)?,
+                name: row.get(2)?,
+                unit: row.get(3)?,
+                quantity: row.get(4)?,
+                value: money(row.get(5)?),
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    let uncosted_entries: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM waste_entries
+         WHERE date(created_at) BETWEEN ?1 AND ?2 AND (cost IS NULL OR currency IS NOT ?3)",
+        (&start, &end, &currency),
+        |row| row.get(0),
+    )?;
+
+    Ok(WasteReport {
+        total: money(by_reason.iter().map(|r| r.value.minor).sum()),
+        start_date: start,
+        end_date: end,
+        uncosted_entries,
+        by_reason,
+        by_item,
+    })
+}
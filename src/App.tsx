@@ -1,14 +1,34 @@
+import { useEffect } from 'react';
 import { BrowserRouter as Router, Routes, Route } from 'react-router-dom';
+import { listen } from '@tauri-apps/api/event';
 import Home from './pages/Home';
 import ProductManager from './pages/ProductManager';
 import Sale from './pages/Sale';
 import Analytics from './pages/Analytics';
 import Transactions from './pages/Transactions';
+import Inventory from './pages/Inventory';
+import Purchasing from './pages/Purchasing';
+import StockCounts from './pages/StockCounts';
+import Waste from './pages/Waste';
+import TaxReport from './pages/TaxReport';
+import Discounts from './pages/Discounts';
 import Settings from './pages/Settings';
 import About from './pages/About';
 import Support from './pages/Support';
+import { BackupFailed } from './types';
 
 function App() {
+  // Automatic backups run in the background, so a failure is shown on
+  // whichever page is open
+  useEffect(() => {
+    const unlisten = listen<BackupFailed>('backup-failed', (event) => {
+      alert(`Automatic backup failed: ${event.payload.message}`);
+    });
+    return () => {
+      unlisten.then(stop => stop());
+    };
+  }, []);
+
   return (
     <Router>
       <Routes>
@@ -17,6 +37,12 @@ function App() {
         <Route path="/sale" element={<Sale />} />
         <Route path="/analytics" element={<Analytics />} />
         <Route path="/transactions" element={<Transactions />} />
+        <Route path="/inventory" element={<Inventory />} />
+        <Route path="/purchasing" element={<Purchasing />} />
+        <Route path="/stock-counts" element={<StockCounts />} />
+        <Route path="/waste" element={<Waste />} />
+        <Route path="/tax-report" element={<TaxReport />} />
+        <Route path="/discounts" element={<Discounts />} />
         <Route path="/settings" element={<Settings />} />
         <Route path="/about" element={<About />} />
         <Route path="/support" element={<Support />} />
@@ -9,6 +9,11 @@ interface ReceiptProps {
     price: number;
   }[];
   totalAmount: number;
+  // Line, order and coupon discounts, each as a positive amount taken off
+  discounts?: { label: string; amount: number }[];
+  // Tax within the total, or added to it when prices exclude tax
+  taxAmount?: number;
+  pricesIncludeTax?: boolean;
   date: string;
   time: string;
   settings: Settings;
@@ -16,7 +21,7 @@ interface ReceiptProps {
 }
 
 const Receipt = forwardRef<HTMLDivElement, ReceiptProps>(
-  ({ products, totalAmount, date, time, settings, receiptNumber }, ref) => {
+  ({ products, totalAmount, discounts, taxAmount, pricesIncludeTax, date, time, settings, receiptNumber }, ref) => {
     return (
       <div ref={ref} className="receipt-container">
         <div className="receipt-content bg-white text-black p-6 max-w-sm mx-auto rounded-lg">
@@ -73,6 +78,22 @@ const Receipt = forwardRef<HTMLDivElement, ReceiptProps>(
           {/* Separator */}
           <div className="border-t-2 border-dashed border-gray-400 my-3" />
 
+          {/* Discounts */}
+          {discounts?.map((discount, index) => (
+            <div key={index} className="flex justify-between items-center text-xs mb-1">
+              <span className="truncate">{discount.label}</span>
+              <span>-{settings.currency} {discount.amount.toFixed(2)}</span>
+            </div>
+          ))}
+
+          {/* Tax */}
+          {taxAmount !== undefined && taxAmount > 0 && (
+            <div className="flex justify-between items-center text-xs mb-1">
+              <span>{pricesIncludeTax === false ? 'Tax' : 'Tax (included)'}</span>
+              <span>{settings.currency} {taxAmount.toFixed(2)}</span>
+            </div>
+          )}
+
           {/* Total */}
           <div className="flex justify-between items-center font-bold text-base mb-4">
             <span>Total</span>
@@ -0,0 +1,36 @@
+import { Dimension, Unit } from '../types';
+
+interface UnitSelectProps {
+  units: Unit[];
+  value: string;
+  onChange: (value: string) => void;
+  className?: string;
+  disabled?: boolean;
+}
+
+const dimensionLabels: Record<Dimension, string> = {
+  count: 'Count',
+  mass: 'Weight',
+  volume: 'Volume'
+};
+
+export default function UnitSelect({ units, value, onChange, className, disabled }: UnitSelectProps) {
+  // A unit saved before the registry existed stays selectable until it is changed
+  const isLegacy = value !== '' && !units.some(unit => unit.code === value);
+
+  return (
+    <select value={value} onChange={(e) => onChange(e.target.value)} className={className} disabled={disabled}>
+      {value === '' && <option value="">Choose a unit...</option>}
+      {isLegacy && <option value={value}>{value}</option>}
+      {(Object.keys(dimensionLabels) as Dimension[]).map(dimension => (
+        <optgroup key={dimension} label={dimensionLabels[dimension]}>
+          {units.filter(unit => unit.dimension === dimension).map(unit => (
+            <option key={unit.code} value={unit.code}>
+              {unit.code === unit.name ? unit.name : `${unit.name} (${unit.code})`}
+            </option>
+          ))}
+        </optgroup>
+      ))}
+    </select>
+  );
+}
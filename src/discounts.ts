@@ -0,0 +1,52 @@
+import { DiscountKind } from './types';
+import { parseRate } from './tax';
+import { fromMajor } from './money';
+
+// Mirrors src-tauri/src/discounts.rs, so the cart comes to the same total
+// the backend works out
+
+// What a discount takes off `base` minor units, never more than all of it
+export const discountOff = (kind: DiscountKind, value: number, base: number): number => {
+  const whole = Math.max(base, 0);
+  const off = kind === 'percentage' ? Math.round((whole * value) / 10000) : value;
+  return Math.min(Math.max(off, 0), whole);
+};
+
+// Splits `total` minor units across lines in proportion to `weights`, the
+// units left over by rounding down going to the largest remainders
+export const allocate = (total: number, weights: number[]): number[] => {
+  const positive = weights.map(weight => Math.max(weight, 0));
+  const sum = positive.reduce((acc, weight) => acc + weight, 0);
+  if (sum === 0) return weights.map(() => 0);
+  const exact = positive.map(weight => ({
+    share: Math.floor((total * weight) / sum),
+    remainder: (total * weight) % sum
+  }));
+  const shares = exact.map(e => e.share);
+  const left = total - shares.reduce((acc, share) => acc + share, 0);
+  const order = exact
+    .map((e, index) => ({ remainder: e.remainder, index }))
+    .sort((a, b) => b.remainder - a.remainder || a.index - b.index);
+  order.slice(0, Math.max(left, 0)).forEach(({ index }) => { shares[index] += 1; });
+  return shares;
+};
+
+// A percentage typed at the till, such as "12.5", as basis points
+export const parsePercentage = (text: string): number | null => {
+  const rate = parseRate(text);
+  return rate !== null && rate > 0 ? rate : null;
+};
+
+// A discount typed at the till in the units it is stored in: a percentage
+// as basis points, or an amount of `currency` in minor units
+export const parseDiscountValue = (kind: DiscountKind, text: string, currency: string): number | null => {
+  if (kind === 'percentage') return parsePercentage(text);
+  const amount = Number(text.trim());
+  if (text.trim() === '' || !Number.isFinite(amount)) return null;
+  const minor = fromMajor(amount, currency).minor;
+  return minor > 0 ? minor : null;
+};
+
+// "10%" or "PKR 5.00" style label for a discount, given how to show amounts
+export const discountLabel = (kind: DiscountKind, value: number, formatFixed: (minor: number) => string): string =>
+  kind === 'percentage' ? `${value / 100}%` : formatFixed(value);
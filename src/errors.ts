@@ -0,0 +1,11 @@
+import { AppError } from './types';
+
+export const isAppError = (error: unknown): error is AppError =>
+  typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
+
+// Human readable text for anything thrown by `invoke` or by the browser
+export const errorMessage = (error: unknown): string => {
+  if (isAppError(error)) return error.message;
+  if (error instanceof Error) return error.message;
+  return String(error);
+};
@@ -0,0 +1,30 @@
+import { Money } from './types';
+
+// Mirrors CURRENCY_DECIMALS in src-tauri/src/money.rs. Currencies not listed
+// here use two decimal places.
+const CURRENCY_DECIMALS: Record<string, number> = {
+  BHD: 3, BIF: 0, CLP: 0, DJF: 0, GNF: 0, IQD: 3, ISK: 0, JOD: 3,
+  JPY: 0, KMF: 0, KRW: 0, KWD: 3, LYD: 3, OMR: 3, PYG: 0, RWF: 0,
+  TND: 3, UGX: 0, VND: 0, VUV: 0, XAF: 0, XOF: 0, XPF: 0,
+};
+
+export const currencyDecimals = (currency: string): number =>
+  CURRENCY_DECIMALS[currency.toUpperCase()] ?? 2;
+
+// Rounds half away from zero, like the backend
+const roundMinor = (value: number): number =>
+  Math.sign(value) * Math.round(Math.abs(value));
+
+export const money = (minor: number, currency: string): Money => ({ minor, currency });
+
+export const fromMajor = (value: number, currency: string): Money =>
+  money(roundMinor(value * 10 ** currencyDecimals(currency)), currency);
+
+export const toMajor = (value: Money): number =>
+  value.minor / 10 ** currencyDecimals(value.currency);
+
+export const lineTotal = (price: Money, quantity: number): Money =>
+  money(roundMinor(price.minor * quantity), price.currency);
+
+export const formatAmount = (value: Money): string =>
+  toMajor(value).toFixed(currencyDecimals(value.currency));
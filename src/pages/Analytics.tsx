@@ -9,16 +9,25 @@ import {
 } from 'recharts';
 import { invoke } from '@tauri-apps/api/core';
 import { AnalyticsData, Settings } from '../types';
+import { formatAmount, money, toMajor } from '../money';
 
 const COLORS = ['#0088FE', '#00C49F', '#FFBB28', '#FF8042'];
 
+const emptySummary = () => ({
+  total_orders: 0,
+  total_revenue: money(0, 'PKR'),
+  average_order_value: money(0, 'PKR')
+});
+
 export default function Analytics() {
   const navigate = useNavigate();
   const [data, setData] = useState<AnalyticsData>({
     daily_revenue: [],
     top_products: [],
     product_distribution: [],
-    summary: { total_orders: 0, total_revenue: 0, average_order_value: 0 }
+    category_revenue: [],
+    top_modifiers: [],
+    summary: emptySummary()
   });
   const [currency, setCurrency] = useState('PKR');
   const [loading, setLoading] = useState(true);
@@ -44,11 +53,9 @@ export default function Analytics() {
           daily_revenue: analyticsRes?.daily_revenue || [],
           top_products: analyticsRes?.top_products || [],
           product_distribution: analyticsRes?.product_distribution || [],
-          summary: analyticsRes?.summary || { 
-            total_orders: 0, 
-            total_revenue: 0, 
-            average_order_value: 0 
-          }
+          category_revenue: analyticsRes?.category_revenue || [],
+          top_modifiers: analyticsRes?.top_modifiers || [],
+          summary: analyticsRes?.summary || emptySummary()
         });
         setCurrency(settingsRes?.currency || 'PKR');
       } catch (error) {
@@ -58,7 +65,9 @@ export default function Analytics() {
           daily_revenue: [],
           top_products: [],
           product_distribution: [],
-          summary: { total_orders: 0, total_revenue: 0, average_order_value: 0 }
+          category_revenue: [],
+          top_modifiers: [],
+          summary: emptySummary()
         });
       } finally {
         setLoading(false);
@@ -71,11 +80,17 @@ export default function Analytics() {
   // Calculate growth rate
   const growthRate = useMemo(() => {
     if (data.daily_revenue.length < 2) return 0;
-    const first = data.daily_revenue[0].revenue;
-    const last = data.daily_revenue[data.daily_revenue.length - 1].revenue;
+    const first = toMajor(data.daily_revenue[0].revenue);
+    const last = toMajor(data.daily_revenue[data.daily_revenue.length - 1].revenue);
     return ((last - first) / first * 100).toFixed(1);
   }, [data.daily_revenue]);
 
+  // Charts need plain numbers
+  const revenueChartData = useMemo(
+    () => data.daily_revenue.map(day => ({ ...day, revenue: toMajor(day.revenue) })),
+    [data.daily_revenue]
+  );
+
   if (loading) {
     return (
       <div className="min-h-screen bg-slate-100 dark:bg-slate-900 flex items-center justify-center transition-colors duration-300">
@@ -139,7 +154,7 @@ export default function Analytics() {
               <div>
                 <p className="text-slate-600 dark:text-white/60">Total Revenue</p>
                 <p className="text-2xl font-bold text-slate-900 dark:text-white">
-                  {currency} {data.summary.total_revenue.toFixed(2)}
+                  {currency} {formatAmount(data.summary.total_revenue)}
                 </p>
               </div>
             </div>
@@ -192,7 +207,7 @@ export default function Analytics() {
               <div>
                 <p className="text-slate-600 dark:text-white/60">Avg. Order Value</p>
                 <p className="text-2xl font-bold text-slate-900 dark:text-white">
-                  {currency} {data.summary.average_order_value.toFixed(2)}
+                  {currency} {formatAmount(data.summary.average_order_value)}
                 </p>
               </div>
             </div>
@@ -225,7 +240,7 @@ export default function Analytics() {
             <div className="h-[250px] sm:h-[300px]">
               {data.daily_revenue.length > 0 ? (
                 <ResponsiveContainer width="100%" height="100%">
-                  <AreaChart data={data.daily_revenue}>
+                  <AreaChart data={revenueChartData}>
                     <defs>
                       <linearGradient id="colorRevenue" x1="0" y1="0" x2="0" y2="1">
                         <stop offset="5%" stopColor="#0088FE" stopOpacity={0.8} />
@@ -331,6 +346,64 @@ export default function Analytics() {
             </div>
           </motion.div>
 
+          {/* Revenue by Category */}
+          <motion.div
+            initial={{ opacity: 0, y: 20 }}
+            animate={{ opacity: 1, y: 0 }}
+            className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl p-4 sm:p-6 transition-colors duration-300"
+          >
+            <h2 className="text-lg sm:text-xl font-bold text-slate-900 dark:text-white mb-4">Revenue by Category</h2>
+            <div className="h-[250px] sm:h-[300px]">
+              {data.category_revenue.length > 0 ? (
+                <ResponsiveContainer width="100%" height="100%">
+                  <BarChart data={data.category_revenue.map(c => ({ name: c.name, revenue: toMajor(c.revenue) }))}>
+                    <CartesianGrid strokeDasharray="3 3" stroke="#ffffff20" />
+                    <XAxis dataKey="name" stroke="#ffffff60" />
+                    <YAxis stroke="#ffffff60" />
+                    <Tooltip
+                      contentStyle={{ backgroundColor: '#1f2937', border: 'none' }}
+                      labelStyle={{ color: '#ffffff' }}
+                    />
+                    <Bar dataKey="revenue" fill="#0088FE" />
+                  </BarChart>
+                </ResponsiveContainer>
+              ) : (
+                <div className="flex items-center justify-center h-full">
+                  <p className="text-slate-500 dark:text-white/40">No category sales data available</p>
+                </div>
+              )}
+            </div>
+          </motion.div>
+
+          {/* Popular Options */}
+          <motion.div
+            initial={{ opacity: 0, y: 20 }}
+            animate={{ opacity: 1, y: 0 }}
+            className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl p-4 sm:p-6 transition-colors duration-300"
+          >
+            <h2 className="text-lg sm:text-xl font-bold text-slate-900 dark:text-white mb-4">Popular Options</h2>
+            <div className="h-[250px] sm:h-[300px]">
+              {data.top_modifiers.length > 0 ? (
+                <ResponsiveContainer width="100%" height="100%">
+                  <BarChart data={data.top_modifiers.map(m => ({ name: `${m.group}: ${m.name}`, sales: m.sales }))}>
+                    <CartesianGrid strokeDasharray="3 3" stroke="#ffffff20" />
+                    <XAxis dataKey="name" stroke="#ffffff60" />
+                    <YAxis stroke="#ffffff60" />
+                    <Tooltip
+                      contentStyle={{ backgroundColor: '#1f2937', border: 'none' }}
+                      labelStyle={{ color: '#ffffff' }}
+                    />
+                    <Bar dataKey="sales" fill="#FF8042" />
+                  </BarChart>
+                </ResponsiveContainer>
+              ) : (
+                <div className="flex items-center justify-center h-full">
+                  <p className="text-slate-500 dark:text-white/40">No option sales data available</p>
+                </div>
+              )}
+            </div>
+          </motion.div>
+
           {/* Daily Orders Trend */}
           <motion.div
             initial={{ opacity: 0, y: 20 }}
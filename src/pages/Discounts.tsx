@@ -0,0 +1,418 @@
+import { motion } from 'framer-motion';
+import { useState, useEffect } from 'react';
+import { FaPlus, FaTrash, FaEdit } from 'react-icons/fa';
+import { MdArrowBack } from 'react-icons/md';
+import { useNavigate } from 'react-router-dom';
+import { invoke } from '@tauri-apps/api/core';
+import { Coupon, DiscountKind, DiscountReason, NewCoupon, Settings } from '../types';
+import ThemeToggle from '../components/ThemeToggle';
+import { errorMessage } from '../errors';
+import { formatAmount, fromMajor, money } from '../money';
+import { discountLabel, parseDiscountValue } from '../discounts';
+
+// Text fields of the coupon form; the value is a percentage or an amount
+// depending on `kind`
+const emptyCoupon = {
+  code: '',
+  description: '',
+  kind: 'percentage' as DiscountKind,
+  value: '',
+  min_spend: '',
+  valid_from: '',
+  valid_until: '',
+  usage_limit: '',
+  active: true
+};
+
+export default function Discounts() {
+  const navigate = useNavigate();
+  const [isNavigating, setIsNavigating] = useState(false);
+  const [reasons, setReasons] = useState<DiscountReason[]>([]);
+  const [newReason, setNewReason] = useState('');
+  const [coupons, setCoupons] = useState<Coupon[]>([]);
+  const [form, setForm] = useState(emptyCoupon);
+  // Coupon being edited, or null when the form adds a new one
+  const [editingId, setEditingId] = useState<number | null>(null);
+  const [currency, setCurrency] = useState('PKR');
+  const [isSubmitting, setIsSubmitting] = useState(false);
+
+  const handleBackNavigation = () => {
+    setIsNavigating(true);
+    setTimeout(() => {
+      navigate('/manager');
+    }, 300);
+  };
+
+  const loadDiscounts = async () => {
+    try {
+      const [reasonsRes, couponsRes] = await Promise.all([
+        invoke<DiscountReason[]>('get_discount_reasons'),
+        invoke<Coupon[]>('get_coupons')
+      ]);
+      setReasons(reasonsRes);
+      setCoupons(couponsRes);
+    } catch (error) {
+      alert(`Error loading discounts: ${errorMessage(error)}`);
+    }
+  };
+
+  useEffect(() => {
+    invoke<Settings>('get_settings')
+      .then(settings => settings?.currency && setCurrency(settings.currency))
+      .catch(error => console.error('Error loading settings:', error));
+    loadDiscounts();
+  }, []);
+
+  const handleAddReason = async () => {
+    try {
+      await invoke('add_discount_reason', { reason: { name: newReason } });
+      setNewReason('');
+      await loadDiscounts();
+    } catch (error) {
+      alert(`Error adding reason: ${errorMessage(error)}`);
+    }
+  };
+
+  const handleDeleteReason = async (reason: DiscountReason) => {
+    if (!confirm(`Delete the reason "${reason.name}"? Past sales keep it.`)) return;
+    try {
+      await invoke('delete_discount_reason', { id: reason.id });
+      await loadDiscounts();
+    } catch (error) {
+      alert(`Error deleting reason: ${errorMessage(error)}`);
+    }
+  };
+
+  const couponValue = parseDiscountValue(form.kind, form.value, currency);
+
+  const handleSaveCoupon = async () => {
+    if (couponValue === null) return;
+    const minSpend = Number(form.min_spend);
+    const coupon: NewCoupon = {
+      code: form.code,
+      description: form.description || null,
+      kind: form.kind,
+      value: couponValue,
+      currency,
+      min_spend: form.min_spend.trim() !== '' && minSpend > 0 ? fromMajor(minSpend, currency) : null,
+      valid_from: form.valid_from || null,
+      valid_until: form.valid_until || null,
+      usage_limit: form.usage_limit.trim() !== '' ? Number(form.usage_limit) : null,
+      active: form.active
+    };
+    setIsSubmitting(true);
+    try {
+      if (editingId === null) {
+        await invoke('add_coupon', { coupon });
+      } else {
+        await invoke('update_coupon', { id: editingId, coupon });
+      }
+      setForm(emptyCoupon);
+      setEditingId(null);
+      await loadDiscounts();
+    } catch (error) {
+      alert(`Error saving coupon: ${errorMessage(error)}`);
+    } finally {
+      setIsSubmitting(false);
+    }
+  };
+
+  const handleEditCoupon = (coupon: Coupon) => {
+    setEditingId(coupon.id);
+    setForm({
+      code: coupon.code,
+      description: coupon.description ?? '',
+      kind: coupon.kind,
+      value: coupon.kind === 'percentage' ? String(coupon.value / 100) : formatAmount(money(coupon.value, coupon.currency)),
+      min_spend: coupon.min_spend ? formatAmount(coupon.min_spend) : '',
+      valid_from: coupon.valid_from ?? '',
+      valid_until: coupon.valid_until ?? '',
+      usage_limit: coupon.usage_limit !== null ? String(coupon.usage_limit) : '',
+      active: coupon.active
+    });
+  };
+
+  const handleDeleteCoupon = async (coupon: Coupon) => {
+    if (!confirm(`Delete the coupon ${coupon.code}? Past sales keep its code.`)) return;
+    try {
+      await invoke('delete_coupon', { id: coupon.id });
+      await loadDiscounts();
+    } catch (error) {
+      alert(`Error deleting coupon: ${errorMessage(error)}`);
+    }
+  };
+
+  const validity = (coupon: Coupon) => {
+    if (coupon.valid_from && coupon.valid_until) return `${coupon.valid_from} to ${coupon.valid_until}`;
+    if (coupon.valid_from) return `from ${coupon.valid_from}`;
+    if (coupon.valid_until) return `until ${coupon.valid_until}`;
+    return 'no end date';
+  };
+
+  const inputClass = `w-full px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white
+    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none`;
+
+  return (
+    <div className="min-h-screen bg-linear-to-br from-slate-100 via-purple-100 to-slate-100
+      dark:from-slate-900 dark:via-purple-900 dark:to-slate-900 transition-colors duration-300">
+      {/* Theme Toggle */}
+      <div className="absolute safe-top safe-right z-50">
+        <ThemeToggle />
+      </div>
+      <div className="max-w-7xl mx-auto px-4 py-4 sm:py-8">
+        {/* Header */}
+        <div className="flex flex-col sm:flex-row items-center gap-4 sm:gap-0 sm:justify-between mb-6 sm:mb-8">
+          <motion.button
+            whileHover={{ scale: 1.05 }}
+            whileTap={{ scale: 0.95 }}
+            onClick={handleBackNavigation}
+            className="flex items-center text-slate-900 dark:text-white gap-2
+              bg-purple-200 dark:bg-purple-600/30 px-4 py-2 rounded-lg min-w-[120px] justify-center
+              transition-colors duration-300"
+            disabled={isNavigating}
+          >
+            {isNavigating ? (
+              <>
+                <motion.div
+                  animate={{ rotate: 360 }}
+                  transition={{ duration: 1, repeat: Infinity, ease: "linear" }}
+                  className="w-5 h-5 border-2 border-white border-t-transparent rounded-full"
+                />
+                <span>Loading...</span>
+              </>
+            ) : (
+              <>
+                <MdArrowBack className="w-5 h-5" />
+                <span>Products</span>
+              </>
+            )}
+          </motion.button>
+          <motion.h1
+            initial={{ opacity: 0, y: -20 }}
+            animate={{ opacity: 1, y: 0 }}
+            className="text-2xl sm:text-3xl font-bold text-slate-900 dark:text-white order-first sm:order-0 transition-colors duration-300"
+          >
+            Discounts
+          </motion.h1>
+          <div className="hidden sm:block w-24" />
+        </div>
+
+        <div className="grid grid-cols-1 lg:grid-cols-3 gap-6">
+          {/* Reasons */}
+          <motion.div
+            initial={{ opacity: 0 }}
+            animate={{ opacity: 1 }}
+            className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl p-4 sm:p-6
+              text-slate-900 dark:text-white transition-colors duration-300"
+          >
+            <h2 className="text-lg sm:text-xl font-semibold mb-1">Discount reasons</h2>
+            <p className="text-sm text-slate-500 dark:text-white/60 mb-4">
+              Every discount given at the till names one of these.
+            </p>
+            <div className="flex gap-2 mb-4">
+              <input
+                type="text"
+                value={newReason}
+                onChange={(e) => setNewReason(e.target.value)}
+                onKeyDown={(e) => e.key === 'Enter' && newReason.trim() && handleAddReason()}
+                placeholder="e.g. Staff meal"
+                className={inputClass}
+              />
+              <motion.button
+                whileHover={{ scale: 1.02 }}
+                whileTap={{ scale: 0.98 }}
+                onClick={handleAddReason}
+                className="bg-teal-500 text-white rounded-lg py-2 px-4 flex items-center justify-center
+                  hover:bg-teal-400 transition-colors disabled:opacity-50"
+                disabled={!newReason.trim()}
+              >
+                <FaPlus />
+              </motion.button>
+            </div>
+            <div className="space-y-2">
+              {reasons.map(reason => (
+                <div key={reason.id} className="flex justify-between items-center">
+                  <span>{reason.name}</span>
+                  <button
+                    onClick={() => handleDeleteReason(reason)}
+                    className="p-2 text-red-500 hover:text-red-400 transition-colors"
+                  >
+                    <FaTrash />
+                  </button>
+                </div>
+              ))}
+              {reasons.length === 0 && (
+                <p className="text-slate-600 dark:text-white/60">No reasons yet.</p>
+              )}
+            </div>
+          </motion.div>
+
+          {/* Coupons */}
+          <motion.div
+            initial={{ opacity: 0 }}
+            animate={{ opacity: 1 }}
+            className="lg:col-span-2 bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl p-4 sm:p-6
+              text-slate-900 dark:text-white transition-colors duration-300"
+          >
+            <h2 className="text-lg sm:text-xl font-semibold mb-4">
+              {editingId === null ? 'Add a coupon' : `Edit coupon ${form.code}`}
+            </h2>
+            <div className="grid grid-cols-1 sm:grid-cols-6 gap-4 mb-6">
+              <input
+                type="text"
+                value={form.code}
+                onChange={(e) => setForm({ ...form, code: e.target.value.toUpperCase() })}
+                placeholder="Code"
+                className={`${inputClass} sm:col-span-2`}
+                disabled={isSubmitting}
+              />
+              <input
+                type="text"
+                value={form.description}
+                onChange={(e) => setForm({ ...form, description: e.target.value })}
+                placeholder="Description (optional)"
+                className={`${inputClass} sm:col-span-4`}
+                disabled={isSubmitting}
+              />
+              <select
+                value={form.kind}
+                onChange={(e) => setForm({ ...form, kind: e.target.value as DiscountKind })}
+                className={`${inputClass} sm:col-span-2`}
+                disabled={isSubmitting}
+              >
+                <option value="percentage">Percent off</option>
+                <option value="fixed">{currency} off</option>
+              </select>
+              <input
+                type="text"
+                inputMode="decimal"
+                value={form.value}
+                onChange={(e) => setForm({ ...form, value: e.target.value })}
+                placeholder={form.kind === 'percentage' ? 'Percent, e.g. 10' : 'Amount, e.g. 5.00'}
+                className={`${inputClass} sm:col-span-2`}
+                disabled={isSubmitting}
+              />
+              <input
+                type="number"
+                min="0"
+                step="any"
+                value={form.min_spend}
+                onChange={(e) => setForm({ ...form, min_spend: e.target.value })}
+                placeholder="Minimum spend"
+                className={`${inputClass} sm:col-span-2`}
+                disabled={isSubmitting}
+              />
+              <label className="flex items-center gap-2 sm:col-span-2">
+                From
+                <input
+                  type="date"
+                  value={form.valid_from}
+                  onChange={(e) => setForm({ ...form, valid_from: e.target.value })}
+                  className={inputClass}
+                  disabled={isSubmitting}
+                />
+              </label>
+              <label className="flex items-center gap-2 sm:col-span-2">
+                until
+                <input
+                  type="date"
+                  value={form.valid_until}
+                  onChange={(e) => setForm({ ...form, valid_until: e.target.value })}
+                  className={inputClass}
+                  disabled={isSubmitting}
+                />
+              </label>
+              <input
+                type="number"
+                min="1"
+                step="1"
+                value={form.usage_limit}
+                onChange={(e) => setForm({ ...form, usage_limit: e.target.value })}
+                placeholder="Uses allowed (blank for no limit)"
+                className={`${inputClass} sm:col-span-2`}
+                disabled={isSubmitting}
+              />
+              <label className="flex items-center gap-2 sm:col-span-2 cursor-pointer">
+                <input
+                  type="checkbox"
+                  checked={form.active}
+                  onChange={(e) => setForm({ ...form, active: e.target.checked })}
+                  className="accent-teal-500"
+                  disabled={isSubmitting}
+                />
+                Can be used
+              </label>
+              {editingId !== null && (
+                <button
+                  onClick={() => {
+                    setEditingId(null);
+                    setForm(emptyCoupon);
+                  }}
+                  className="sm:col-span-2 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 text-slate-900 dark:text-white
+                    hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+                  disabled={isSubmitting}
+                >
+                  Cancel
+                </button>
+              )}
+              <motion.button
+                whileHover={{ scale: 1.02 }}
+                whileTap={{ scale: 0.98 }}
+                onClick={handleSaveCoupon}
+                className={`${editingId === null ? 'sm:col-span-4' : 'sm:col-span-2'} bg-teal-500 text-white rounded-lg py-2 px-4
+                  flex items-center justify-center gap-2 hover:bg-teal-400 transition-colors disabled:opacity-50`}
+                disabled={isSubmitting || !form.code.trim() || couponValue === null}
+              >
+                <FaPlus />
+                <span>{editingId === null ? 'Add coupon' : 'Save coupon'}</span>
+              </motion.button>
+            </div>
+
+            <div className="space-y-3">
+              {coupons.map(coupon => (
+                <div
+                  key={coupon.id}
+                  className={`flex justify-between items-start gap-4 border-t border-slate-300 dark:border-white/10 pt-3 ${
+                    coupon.active ? '' : 'opacity-60'
+                  }`}
+                >
+                  <div>
+                    <p className="font-semibold">
+                      {coupon.code} · {discountLabel(coupon.kind, coupon.value, minor => `${coupon.currency} ${formatAmount(money(minor, coupon.currency))}`)} off
+                      {!coupon.active && <span className="ml-2 text-sm font-normal">(switched off)</span>}
+                    </p>
+                    {coupon.description && (
+                      <p className="text-sm text-slate-600 dark:text-white/70">{coupon.description}</p>
+                    )}
+                    <p className="text-sm text-slate-500 dark:text-white/60">
+                      {validity(coupon)}
+                      {coupon.min_spend && ` · spend at least ${coupon.min_spend.currency} ${formatAmount(coupon.min_spend)}`}
+                      {` · ${coupon.times_used} used${coupon.usage_limit !== null ? ` of ${coupon.usage_limit}` : ''}`}
+                    </p>
+                  </div>
+                  <div className="flex gap-1">
+                    <button
+                      onClick={() => handleEditCoupon(coupon)}
+                      className="p-2 text-slate-600 dark:text-white/70 hover:text-teal-500 transition-colors"
+                    >
+                      <FaEdit />
+                    </button>
+                    <button
+                      onClick={() => handleDeleteCoupon(coupon)}
+                      className="p-2 text-red-500 hover:text-red-400 transition-colors"
+                    >
+                      <FaTrash />
+                    </button>
+                  </div>
+                </div>
+              ))}
+              {coupons.length === 0 && (
+                <p className="text-slate-600 dark:text-white/60">No coupons yet.</p>
+              )}
+            </div>
+          </motion.div>
+        </div>
+      </div>
+    </div>
+  );
+}
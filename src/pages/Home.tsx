@@ -1,4 +1,4 @@
-import { FaUtensils, FaClipboardList, FaChartBar, FaHistory, FaCog, FaHeart, FaEnvelope } from 'react-icons/fa';
+import { FaUtensils, FaClipboardList, FaChartBar, FaHistory, FaCog, FaHeart, FaEnvelope, FaBoxes } from 'react-icons/fa';
 import { MdPointOfSale } from 'react-icons/md';
 import { motion } from 'framer-motion';
 import { useNavigate } from 'react-router-dom';
@@ -234,6 +234,33 @@ export default function Home() {
             )}
           </motion.button>
 
+          <motion.button
+            variants={item}
+            onClick={() => handleNavigation('/inventory')}
+            whileHover={{ y: -5 }}
+            whileTap={{ scale: 0.98 }}
+            className="flex flex-col items-center p-8 bg-linear-to-br from-amber-400 to-amber-500 
+            dark:from-amber-500 dark:to-amber-600 text-white rounded-2xl transition-all duration-300
+            backdrop-blur-sm bg-opacity-90 h-full relative shadow-lg hover:shadow-xl"
+            disabled={loadingRoute !== null}
+          >
+            {loadingRoute === '/inventory' ? (
+              <>
+                <motion.div
+                  animate={{ rotate: 360 }}
+                  transition={{ duration: 1, repeat: Infinity, ease: "linear" }}
+                  className="w-12 h-12 mb-4 border-4 border-white border-t-transparent rounded-full"
+                />
+                <span className="text-xl font-semibold text-center">Loading...</span>
+              </>
+            ) : (
+              <>
+                <FaBoxes className="w-12 h-12 mb-4" />
+                <span className="text-xl font-semibold text-center">Inventory</span>
+              </>
+            )}
+          </motion.button>
+
           <motion.button
             variants={item}
             onClick={() => handleNavigation('/settings')}
@@ -0,0 +1,567 @@
+import { motion } from 'framer-motion';
+import { useState, useEffect } from 'react';
+import { FaPlus, FaTrash, FaEdit, FaTruck, FaBalanceScale, FaHistory, FaFileInvoice, FaClipboardCheck, FaRecycle } from 'react-icons/fa';
+import { MdArrowBack } from 'react-icons/md';
+import { useNavigate } from 'react-router-dom';
+import { invoke } from '@tauri-apps/api/core';
+import { Ingredient, NewIngredient, StockMovement, MovementKind, LowStockItem, Settings, Unit } from '../types';
+import { formatAmount, fromMajor } from '../money';
+import ThemeToggle from '../components/ThemeToggle';
+import UnitSelect from '../components/UnitSelect';
+import { errorMessage } from '../errors';
+
+const movementLabels: Record<MovementKind, string> = {
+  sale: 'Sale',
+  receive: 'Received',
+  adjust: 'Count',
+  waste: 'Waste'
+};
+
+// Quantities are stored as floats; show them without binary noise
+const formatQuantity = (quantity: number) => String(Number(quantity.toFixed(3)));
+
+const isLow = (ingredient: Ingredient) =>
+  ingredient.reorder_point !== null && ingredient.quantity <= ingredient.reorder_point;
+
+// Empty inputs leave a level unset
+const optionalNumber = (value: string) => (value.trim() === '' ? null : Number(value));
+
+export default function Inventory() {
+  const navigate = useNavigate();
+  const [isNavigating, setIsNavigating] = useState(false);
+  const [ingredients, setIngredients] = useState<Ingredient[]>([]);
+  const [lowStock, setLowStock] = useState<LowStockItem[]>([]);
+  const [isLoading, setIsLoading] = useState(true);
+  const [isSubmitting, setIsSubmitting] = useState(false);
+  const [editing, setEditing] = useState<Ingredient | 'new' | null>(null);
+  const [form, setForm] = useState({ name: '', unit: '', reorder_point: '', par_level: '' });
+  // Receiving adds to the level; counting replaces it with what is on the shelf
+  const [stockAction, setStockAction] = useState<{ ingredient: Ingredient; kind: 'receive' | 'adjust' } | null>(null);
+  const [stockForm, setStockForm] = useState({ quantity: '', unit_cost: '', note: '' });
+  const [currency, setCurrency] = useState('PKR');
+  const [units, setUnits] = useState<Unit[]>([]);
+  const [history, setHistory] = useState<{ ingredient: Ingredient; movements: StockMovement[] } | null>(null);
+
+  const handleBackNavigation = () => {
+    setIsNavigating(true);
+    setTimeout(() => {
+      navigate('/');
+    }, 300);
+  };
+
+  useEffect(() => {
+    const loadCurrency = async () => {
+      try {
+        const response = await invoke<Settings>('get_settings');
+        if (response?.currency) {
+          setCurrency(response.currency);
+        }
+      } catch (error) {
+        console.error('Error loading currency:', error);
+      }
+    };
+
+    const loadUnits = async () => {
+      try {
+        setUnits(await invoke<Unit[]>('get_units'));
+      } catch (error) {
+        console.error('Error loading units:', error);
+      }
+    };
+
+    loadCurrency();
+    loadUnits();
+  }, []);
+
+  const loadIngredients = async () => {
+    try {
+      const [ingredientsRes, lowStockRes] = await Promise.all([
+        invoke<Ingredient[]>('get_ingredients'),
+        invoke<LowStockItem[]>('get_low_stock')
+      ]);
+      setIngredients(ingredientsRes);
+      setLowStock(lowStockRes);
+    } catch (error) {
+      console.error('Error loading ingredients:', error);
+    } finally {
+      setIsLoading(false);
+    }
+  };
+
+  useEffect(() => {
+    loadIngredients();
+  }, []);
+
+  const openIngredientForm = (ingredient: Ingredient | null) => {
+    setEditing(ingredient ?? 'new');
+    setForm({
+      name: ingredient?.name ?? '',
+      unit: ingredient?.unit ?? '',
+      reorder_point: ingredient?.reorder_point != null ? String(ingredient.reorder_point) : '',
+      par_level: ingredient?.par_level != null ? String(ingredient.par_level) : ''
+    });
+  };
+
+  const handleSaveIngredient = async () => {
+    if (!editing) return;
+    setIsSubmitting(true);
+    try {
+      const ingredient: NewIngredient = {
+        name: form.name,
+        unit: form.unit,
+        reorder_point: optionalNumber(form.reorder_point),
+        par_level: optionalNumber(form.par_level)
+      };
+      if (editing === 'new') {
+        await invoke('add_ingredient', { ingredient });
+      } else {
+        await invoke('update_ingredient', { id: editing.id, ingredient });
+      }
+      setEditing(null);
+      await loadIngredients();
+    } catch (error) {
+      alert(`Error saving ingredient: ${errorMessage(error)}`);
+    } finally {
+      setIsSubmitting(false);
+    }
+  };
+
+  const handleDeleteIngredient = async (ingredient: Ingredient) => {
+    if (!confirm(`Delete ${ingredient.name} and its stock history?`)) return;
+    try {
+      await invoke('delete_ingredient', { id: ingredient.id });
+      await loadIngredients();
+    } catch (error) {
+      alert(`Error deleting ingredient: ${errorMessage(error)}`);
+    }
+  };
+
+  const openStockAction = (ingredient: Ingredient, kind: 'receive' | 'adjust') => {
+    setStockAction({ ingredient, kind });
+    setStockForm({ quantity: kind === 'adjust' ? formatQuantity(ingredient.quantity) : '', unit_cost: '', note: '' });
+  };
+
+  const handleStockAction = async () => {
+    if (!stockAction) return;
+    setIsSubmitting(true);
+    try {
+      const quantity = Number(stockForm.quantity);
+      const note = stockForm.note || null;
+      if (stockAction.kind === 'receive') {
+        // Leaving the cost empty keeps the ingredient's last known cost
+        const unitCost = stockForm.unit_cost.trim() === '' ? null : fromMajor(Number(stockForm.unit_cost), currency);
+        await invoke('receive_stock', { ingredientId: stockAction.ingredient.id, quantity, unitCost, note });
+      } else {
+        await invoke('adjust_stock', { ingredientId: stockAction.ingredient.id, counted: quantity, note });
+      }
+      setStockAction(null);
+      await loadIngredients();
+    } catch (error) {
+      alert(`Error updating stock: ${errorMessage(error)}`);
+    } finally {
+      setIsSubmitting(false);
+    }
+  };
+
+  const openHistory = async (ingredient: Ingredient) => {
+    try {
+      const movements = await invoke<StockMovement[]>('get_stock_movements', { ingredientId: ingredient.id });
+      setHistory({ ingredient, movements });
+    } catch (error) {
+      alert(`Error loading stock history: ${errorMessage(error)}`);
+    }
+  };
+
+  const inputClass = `w-full px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white
+    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none`;
+
+  return (
+    <div className="min-h-screen bg-linear-to-br from-slate-100 via-purple-100 to-slate-100
+      dark:from-slate-900 dark:via-purple-900 dark:to-slate-900 transition-colors duration-300">
+      {/* Theme Toggle */}
+      <div className="absolute safe-top safe-right z-50">
+        <ThemeToggle />
+      </div>
+      <div className="max-w-7xl mx-auto px-4 py-4 sm:py-8">
+        {/* Header */}
+        <div className="flex flex-col sm:flex-row items-center gap-4 sm:gap-0 sm:justify-between mb-6 sm:mb-8">
+          <motion.button
+            whileHover={{ scale: 1.05 }}
+            whileTap={{ scale: 0.95 }}
+            onClick={handleBackNavigation}
+            className="flex items-center text-slate-900 dark:text-white gap-2
+              bg-purple-200 dark:bg-purple-600/30 px-4 py-2 rounded-lg min-w-[120px] justify-center
+              transition-colors duration-300"
+            disabled={isNavigating}
+          >
+            {isNavigating ? (
+              <>
+                <motion.div
+                  animate={{ rotate: 360 }}
+                  transition={{ duration: 1, repeat: Infinity, ease: "linear" }}
+                  className="w-5 h-5 border-2 border-white border-t-transparent rounded-full"
+                />
+                <span>Loading...</span>
+              </>
+            ) : (
+              <>
+                <MdArrowBack className="w-5 h-5" />
+                <span>Back</span>
+              </>
+            )}
+          </motion.button>
+          <motion.h1
+            initial={{ opacity: 0, y: -20 }}
+            animate={{ opacity: 1, y: 0 }}
+            className="text-2xl sm:text-3xl font-bold text-slate-900 dark:text-white order-first sm:order-0 transition-colors duration-300"
+          >
+            Inventory
+          </motion.h1>
+          <div className="hidden sm:block w-24" />
+        </div>
+
+        <div className="flex flex-col sm:flex-row gap-4 mb-6 sm:mb-8">
+          {/* Add Ingredient Button */}
+          <motion.button
+            whileHover={{ scale: 1.02 }}
+            whileTap={{ scale: 0.98 }}
+            onClick={() => openIngredientForm(null)}
+            className="flex-1 bg-linear-to-r from-teal-400 to-teal-500
+              dark:from-teal-500 dark:to-teal-600 text-white rounded-xl py-3 sm:py-4 px-4 sm:px-6
+              flex items-center justify-center gap-2 transition-all duration-300 shadow-lg hover:shadow-xl"
+          >
+            <FaPlus />
+            <span>Add Ingredient</span>
+          </motion.button>
+          <motion.button
+            whileHover={{ scale: 1.02 }}
+            whileTap={{ scale: 0.98 }}
+            onClick={() => navigate('/purchasing')}
+            className="flex-1 bg-linear-to-r from-amber-400 to-amber-500
+              dark:from-amber-500 dark:to-amber-600 text-white rounded-xl py-3 sm:py-4 px-4 sm:px-6
+              flex items-center justify-center gap-2 transition-all duration-300 shadow-lg hover:shadow-xl"
+          >
+            <FaFileInvoice />
+            <span>Suppliers &amp; Purchase Orders</span>
+          </motion.button>
+          <motion.button
+            whileHover={{ scale: 1.02 }}
+            whileTap={{ scale: 0.98 }}
+            onClick={() => navigate('/stock-counts')}
+            className="flex-1 bg-linear-to-r from-sky-400 to-sky-500
+              dark:from-sky-500 dark:to-sky-600 text-white rounded-xl py-3 sm:py-4 px-4 sm:px-6
+              flex items-center justify-center gap-2 transition-all duration-300 shadow-lg hover:shadow-xl"
+          >
+            <FaClipboardCheck />
+            <span>Stock Take</span>
+          </motion.button>
+          <motion.button
+            whileHover={{ scale: 1.02 }}
+            whileTap={{ scale: 0.98 }}
+            onClick={() => navigate('/waste')}
+            className="flex-1 bg-linear-to-r from-rose-400 to-rose-500
+              dark:from-rose-500 dark:to-rose-600 text-white rounded-xl py-3 sm:py-4 px-4 sm:px-6
+              flex items-center justify-center gap-2 transition-all duration-300 shadow-lg hover:shadow-xl"
+          >
+            <FaRecycle />
+            <span>Waste Log</span>
+          </motion.button>
+        </div>
+
+        {/* Low Stock */}
+        {lowStock.length > 0 && (
+          <motion.div
+            initial={{ opacity: 0 }}
+            animate={{ opacity: 1 }}
+            className="bg-red-100 dark:bg-red-500/20 rounded-xl p-4 sm:p-6 mb-6 sm:mb-8 text-slate-900 dark:text-white"
+          >
+            <h2 className="text-lg sm:text-xl font-semibold mb-3">Low stock ({lowStock.length})</h2>
+            <div className="space-y-2">
+              {lowStock.map(item => (
+                <div key={item.ingredient.id} className="flex flex-col sm:flex-row sm:justify-between gap-1">
+                  <span className="font-medium">
+                    {item.ingredient.name}: {formatQuantity(item.ingredient.quantity)} {item.ingredient.unit} left
+                  </span>
+                  <span className="text-sm text-slate-600 dark:text-white/70">
+                    Using about {formatQuantity(item.daily_usage)} {item.ingredient.unit}/day · order{' '}
+                    <span className="font-semibold">{formatQuantity(item.suggested_quantity)} {item.ingredient.unit}</span>
+                  </span>
+                </div>
+              ))}
+            </div>
+          </motion.div>
+        )}
+
+        {/* Stock Levels */}
+        <motion.div
+          initial={{ opacity: 0 }}
+          animate={{ opacity: 1 }}
+          className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl overflow-hidden transition-colors duration-300"
+        >
+          <div className="hidden sm:grid grid-cols-12 gap-4 p-4 border-b border-slate-300 dark:border-white/10
+            text-slate-900 dark:text-white font-semibold">
+            <div className="col-span-5">Ingredient</div>
+            <div className="col-span-3">In stock</div>
+            <div className="col-span-4">Action</div>
+          </div>
+
+          {ingredients.map(ingredient => (
+            <div
+              key={ingredient.id}
+              className="sm:grid sm:grid-cols-12 gap-4 p-4 border-b border-slate-300 dark:border-white/10
+                text-slate-900 dark:text-white hover:bg-slate-100 dark:hover:bg-white/5 transition-colors"
+            >
+              <div className="col-span-5 font-medium">
+                {ingredient.name}
+                {ingredient.cost_per_unit && (
+                  <span className="ml-2 text-xs font-normal text-slate-500 dark:text-white/60">
+                    {ingredient.cost_per_unit.currency} {formatAmount(ingredient.cost_per_unit)}/{ingredient.unit}
+                  </span>
+                )}
+              </div>
+              <div className={`col-span-3 ${isLow(ingredient) || ingredient.quantity < 0 ? 'text-red-500' : 'text-teal-600 dark:text-teal-400'}`}>
+                {formatQuantity(ingredient.quantity)} {ingredient.unit}
+                {ingredient.reorder_point !== null && (
+                  <span className="ml-2 text-xs text-slate-500 dark:text-white/60">
+                    reorder at {formatQuantity(ingredient.reorder_point)}
+                  </span>
+                )}
+              </div>
+              <div className="col-span-4 flex items-center gap-4 mt-2 sm:mt-0">
+                <button onClick={() => openStockAction(ingredient, 'receive')} className="text-teal-500 hover:text-teal-400" title="Receive stock">
+                  <FaTruck />
+                </button>
+                <button onClick={() => openStockAction(ingredient, 'adjust')} className="text-teal-500 hover:text-teal-400" title="Count stock">
+                  <FaBalanceScale />
+                </button>
+                <button onClick={() => openHistory(ingredient)} className="text-teal-500 hover:text-teal-400" title="Stock history">
+                  <FaHistory />
+                </button>
+                <button onClick={() => openIngredientForm(ingredient)} className="text-teal-500 hover:text-teal-400" title="Edit ingredient">
+                  <FaEdit />
+                </button>
+                <button onClick={() => handleDeleteIngredient(ingredient)} className="text-red-400 hover:text-red-300" title="Delete ingredient">
+                  <FaTrash />
+                </button>
+              </div>
+            </div>
+          ))}
+
+          {!isLoading && ingredients.length === 0 && (
+            <div className="p-6 sm:p-8 text-center text-slate-600 dark:text-white/60">
+              No ingredients yet. Add them here, then give products a recipe in the Product Manager.
+            </div>
+          )}
+        </motion.div>
+      </div>
+
+      {/* Ingredient Modal */}
+      {editing && (
+        <div className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center p-4 z-50">
+          <motion.div
+            initial={{ opacity: 0, scale: 0.9 }}
+            animate={{ opacity: 1, scale: 1 }}
+            className="bg-white dark:bg-slate-800 rounded-xl p-4 sm:p-6 w-full max-w-md"
+          >
+            <h2 className="text-xl sm:text-2xl font-bold text-slate-900 dark:text-white mb-4">
+              {editing === 'new' ? 'Add Ingredient' : 'Edit Ingredient'}
+            </h2>
+            <div className="space-y-4">
+              <div>
+                <label className="block text-slate-900 dark:text-white mb-2">Name</label>
+                <input
+                  type="text"
+                  value={form.name}
+                  onChange={(e) => setForm({ ...form, name: e.target.value })}
+                  className={inputClass}
+                  disabled={isSubmitting}
+                />
+              </div>
+              <div>
+                <label className="block text-slate-900 dark:text-white mb-2">Unit</label>
+                <UnitSelect
+                  units={units}
+                  value={form.unit}
+                  onChange={(unit) => setForm({ ...form, unit })}
+                  className={inputClass}
+                  disabled={isSubmitting}
+                />
+              </div>
+              <div className="grid grid-cols-2 gap-4">
+                <div>
+                  <label className="block text-slate-900 dark:text-white mb-2">Reorder point</label>
+                  <input
+                    type="number"
+                    min="0"
+                    step="any"
+                    value={form.reorder_point}
+                    onChange={(e) => setForm({ ...form, reorder_point: e.target.value })}
+                    placeholder="Optional"
+                    className={inputClass}
+                    disabled={isSubmitting}
+                  />
+                </div>
+                <div>
+                  <label className="block text-slate-900 dark:text-white mb-2">Par level</label>
+                  <input
+                    type="number"
+                    min="0"
+                    step="any"
+                    value={form.par_level}
+                    onChange={(e) => setForm({ ...form, par_level: e.target.value })}
+                    placeholder="Optional"
+                    className={inputClass}
+                    disabled={isSubmitting}
+                  />
+                </div>
+              </div>
+            </div>
+            <div className="flex gap-4 mt-6">
+              <button
+                onClick={() => setEditing(null)}
+                className="flex-1 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 text-slate-900 dark:text-white
+                  hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+                disabled={isSubmitting}
+              >
+                Cancel
+              </button>
+              <button
+                onClick={handleSaveIngredient}
+                className="flex-1 px-4 py-2 rounded-lg bg-teal-500 text-white hover:bg-teal-400 transition-colors disabled:opacity-50"
+                disabled={isSubmitting}
+              >
+                {isSubmitting ? 'Saving...' : 'Save'}
+              </button>
+            </div>
+          </motion.div>
+        </div>
+      )}
+
+      {/* Receive / Count Modal */}
+      {stockAction && (
+        <div className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center p-4 z-50">
+          <motion.div
+            initial={{ opacity: 0, scale: 0.9 }}
+            animate={{ opacity: 1, scale: 1 }}
+            className="bg-white dark:bg-slate-800 rounded-xl p-4 sm:p-6 w-full max-w-md"
+          >
+            <h2 className="text-xl sm:text-2xl font-bold text-slate-900 dark:text-white mb-4">
+              {stockAction.kind === 'receive' ? 'Receive' : 'Count'} {stockAction.ingredient.name}
+            </h2>
+            <div className="space-y-4">
+              <div>
+                <label className="block text-slate-900 dark:text-white mb-2">
+                  {stockAction.kind === 'receive' ? 'Quantity received' : 'Quantity on hand'} ({stockAction.ingredient.unit})
+                </label>
+                <input
+                  type="number"
+                  min="0"
+                  step="any"
+                  value={stockForm.quantity}
+                  onChange={(e) => setStockForm({ ...stockForm, quantity: e.target.value })}
+                  className={inputClass}
+                  disabled={isSubmitting}
+                />
+              </div>
+              {stockAction.kind === 'receive' && (
+                <div>
+                  <label className="block text-slate-900 dark:text-white mb-2">
+                    Cost per {stockAction.ingredient.unit} ({currency}, optional)
+                  </label>
+                  <input
+                    type="number"
+                    min="0"
+                    step="any"
+                    value={stockForm.unit_cost}
+                    onChange={(e) => setStockForm({ ...stockForm, unit_cost: e.target.value })}
+                    className={inputClass}
+                    disabled={isSubmitting}
+                  />
+                </div>
+              )}
+              <div>
+                <label className="block text-slate-900 dark:text-white mb-2">Note (optional)</label>
+                <input
+                  type="text"
+                  value={stockForm.note}
+                  onChange={(e) => setStockForm({ ...stockForm, note: e.target.value })}
+                  className={inputClass}
+                  disabled={isSubmitting}
+                />
+              </div>
+            </div>
+            <div className="flex gap-4 mt-6">
+              <button
+                onClick={() => setStockAction(null)}
+                className="flex-1 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 text-slate-900 dark:text-white
+                  hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+                disabled={isSubmitting}
+              >
+                Cancel
+              </button>
+              <button
+                onClick={handleStockAction}
+                className="flex-1 px-4 py-2 rounded-lg bg-teal-500 text-white hover:bg-teal-400 transition-colors disabled:opacity-50"
+                disabled={isSubmitting || stockForm.quantity === ''}
+              >
+                {isSubmitting ? 'Saving...' : 'Save'}
+              </button>
+            </div>
+          </motion.div>
+        </div>
+      )}
+
+      {/* Stock History Modal */}
+      {history && (
+        <div className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center p-4 z-50">
+          <motion.div
+            initial={{ opacity: 0, scale: 0.9 }}
+            animate={{ opacity: 1, scale: 1 }}
+            className="bg-white dark:bg-slate-800 rounded-xl p-4 sm:p-6 w-full max-w-lg max-h-[90vh] overflow-y-auto"
+          >
+            <h2 className="text-xl sm:text-2xl font-bold text-slate-900 dark:text-white mb-4">
+              {history.ingredient.name} history
+            </h2>
+            <div className="space-y-2">
+              {history.movements.map(movement => (
+                <div key={movement.id} className="flex justify-between gap-4 text-slate-900 dark:text-white border-b border-slate-200 dark:border-white/10 pb-2">
+                  <div>
+                    <div className="font-medium">
+                      {movementLabels[movement.kind]}
+                      {movement.sale_id !== null && ` #${movement.sale_id}`}
+                      {movement.purchase_order_id !== null && ` · PO #${movement.purchase_order_id}`}
+                      {movement.stock_count_id !== null && ` · stock take #${movement.stock_count_id}`}
+                    </div>
+                    <div className="text-sm text-slate-500 dark:text-white/60">
+                      {new Date(`${movement.created_at.replace(' ', 'T')}Z`).toLocaleString()}
+                      {movement.unit_cost && ` · ${movement.unit_cost.currency} ${formatAmount(movement.unit_cost)}/${history.ingredient.unit}`}
+                      {movement.note && ` · ${movement.note}`}
+                    </div>
+                  </div>
+                  <div className="text-right">
+                    <div className={movement.change < 0 ? 'text-red-500' : 'text-teal-600 dark:text-teal-400'}>
+                      {movement.change > 0 ? '+' : ''}{formatQuantity(movement.change)}
+                    </div>
+                    <div className="text-sm text-slate-500 dark:text-white/60">
+                      {formatQuantity(movement.quantity_after)} {history.ingredient.unit}
+                    </div>
+                  </div>
+                </div>
+              ))}
+              {history.movements.length === 0 && (
+                <p className="text-slate-600 dark:text-white/60">No stock changes yet.</p>
+              )}
+            </div>
+            <button
+              onClick={() => setHistory(null)}
+              className="w-full mt-6 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 text-slate-900 dark:text-white
+                hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+            >
+              Close
+            </button>
+          </motion.div>
+        </div>
+      )}
+    </div>
+  );
+}
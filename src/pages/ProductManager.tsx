@@ -1,11 +1,16 @@
 import { motion } from 'framer-motion';
 import { useState, useEffect } from 'react';
-import { FaPlus, FaTrash, FaCheck, FaExclamationTriangle } from 'react-icons/fa';
-import { MdArrowBack } from 'react-icons/md';
+import { FaPlus, FaTrash, FaCheck, FaExclamationTriangle, FaEdit, FaUndo, FaFolder, FaSlidersH } from 'react-icons/fa';
+import { MdArrowBack, MdLocalOffer } from 'react-icons/md';
 import { useNavigate } from 'react-router-dom';
 import { invoke } from '@tauri-apps/api/core';
-import { Product, NewProduct, Settings } from '../types';
+import { Product, NewProduct, Settings, Category, NewCategory, ModifierGroup, NewModifierGroup, Selection, BundleComponent, NewBundleComponent, Ingredient, RecipeItem, NewRecipeItem, Unit, TaxClass } from '../types';
 import ThemeToggle from '../components/ThemeToggle';
+import UnitSelect from '../components/UnitSelect';
+import { taxClassLabels } from '../tax';
+import { formatAmount, fromMajor, toMajor } from '../money';
+import { errorMessage } from '../errors';
+import { compatibleUnits } from '../units';
 
 interface FormErrors {
   name?: string;
@@ -17,7 +22,7 @@ export default function ProductManager() {
   const navigate = useNavigate();
   const [products, setProducts] = useState<Product[]>([]);
   const [showAddModal, setShowAddModal] = useState(false);
-  const [newProduct, setNewProduct] = useState({ name: '', price: '', unit: 'item' });
+  const [newProduct, setNewProduct] = useState({ name: '', price: '', unit: 'item', category_id: '', tax_class: 'standard' });
   const [currencySymbol, setCurrencySymbol] = useState('PKR');
   const [isNavigating, setIsNavigating] = useState(false);
   const [errors, setErrors] = useState<FormErrors>({});
@@ -27,6 +32,31 @@ export default function ProductManager() {
   const [deletingId, setDeletingId] = useState<number | null>(null);
   const [showDeleteModal, setShowDeleteModal] = useState(false);
   const [productToDelete, setProductToDelete] = useState<Product | null>(null);
+  const [editingProduct, setEditingProduct] = useState<Product | null>(null);
+  const [showArchived, setShowArchived] = useState(false);
+  const [categories, setCategories] = useState<Category[]>([]);
+  const [showCategoryModal, setShowCategoryModal] = useState(false);
+  const [newCategory, setNewCategory] = useState({ name: '', parent_id: '', color: '#14b8a6' });
+  const [modifierGroups, setModifierGroups] = useState<ModifierGroup[]>([]);
+  // Option groups offered on the product in the add/edit modal
+  const [productGroupIds, setProductGroupIds] = useState<number[]>([]);
+  const [showOptionsModal, setShowOptionsModal] = useState(false);
+  // Components of the product in the add/edit modal; none means it isn't a bundle
+  const [bundleRows, setBundleRows] = useState<{ product_id: string; quantity: string; substitutes: number[] }[]>([]);
+  const [ingredients, setIngredients] = useState<Ingredient[]>([]);
+  // Ingredients used per unit of the product in the add/edit modal
+  // `unit` is empty for the ingredient's own unit
+  const [recipeRows, setRecipeRows] = useState<{ ingredient_id: string; quantity: string; unit: string }[]>([]);
+  const [units, setUnits] = useState<Unit[]>([]);
+  const [editingGroup, setEditingGroup] = useState<ModifierGroup | null>(null);
+  const emptyGroupForm = () => ({
+    name: '',
+    selection: 'single' as Selection,
+    min_choices: '0',
+    max_choices: '',
+    modifiers: [{ id: null as number | null, name: '', price_delta: '0' }]
+  });
+  const [groupForm, setGroupForm] = useState(emptyGroupForm());
 
   const handleBackNavigation = () => {
     setIsNavigating(true);
@@ -52,7 +82,7 @@ export default function ProductManager() {
 
   const loadProducts = async () => {
     try {
-      const response = await invoke<Product[]>('get_products');
+      const response = await invoke<Product[]>('get_products', { includeArchived: showArchived });
       setProducts(response);
     } catch (error) {
       console.error('Error loading products:', error);
@@ -61,8 +91,59 @@ export default function ProductManager() {
 
   useEffect(() => {
     loadProducts();
+  }, [showArchived]);
+
+  // Categories come back parents first, so sub-sections can be indented
+  const loadCategories = async () => {
+    try {
+      setCategories(await invoke<Category[]>('get_categories'));
+    } catch (error) {
+      console.error('Error loading categories:', error);
+    }
+  };
+
+  const loadModifierGroups = async () => {
+    try {
+      setModifierGroups(await invoke<ModifierGroup[]>('get_modifier_groups'));
+    } catch (error) {
+      console.error('Error loading option groups:', error);
+    }
+  };
+
+  const loadIngredients = async () => {
+    try {
+      setIngredients(await invoke<Ingredient[]>('get_ingredients'));
+    } catch (error) {
+      console.error('Error loading ingredients:', error);
+    }
+  };
+
+  const loadUnits = async () => {
+    try {
+      setUnits(await invoke<Unit[]>('get_units'));
+    } catch (error) {
+      console.error('Error loading units:', error);
+    }
+  };
+
+  useEffect(() => {
+    loadCategories();
+    loadModifierGroups();
+    loadIngredients();
+    loadUnits();
   }, []);
 
+  const categoryDepth = (category: Category): number => {
+    const parent = categories.find(c => c.id === category.parent_id);
+    return parent ? categoryDepth(parent) + 1 : 0;
+  };
+
+  const categoryLabel = (category: Category) =>
+    `${'\u00a0\u00a0'.repeat(categoryDepth(category))}${category.name}`;
+
+  // Products that can go into the bundle being edited
+  const bundleChoices = products.filter(product => !product.archived_at && product.id !== editingProduct?.id);
+
   const handleInputChange = (field: string, value: string) => {
     setNewProduct({ ...newProduct, [field]: value });
     // Clear error for this field when user starts typing
@@ -83,7 +164,8 @@ export default function ProductManager() {
     } else {
       // Check for duplicate product names (case-insensitive)
       const isDuplicate = products.some(
-        product => product.name.toLowerCase() === newProduct.name.trim().toLowerCase()
+        product => product.id !== editingProduct?.id &&
+          product.name.toLowerCase() === newProduct.name.trim().toLowerCase()
       );
       if (isDuplicate) {
         newErrors.name = 'A product with this name already exists';
@@ -102,7 +184,7 @@ export default function ProductManager() {
 
     // Validate unit
     if (!newProduct.unit.trim()) {
-      newErrors.unit = 'Please enter unit';
+      newErrors.unit = 'Please choose a unit';
     }
 
     setErrors(newErrors);
@@ -120,29 +202,44 @@ export default function ProductManager() {
     setStatusMessage('');
 
     try {
-      await invoke('add_product', {
-        product: {
-          name: newProduct.name.trim(),
-          price: Number(newProduct.price),
-          unit: newProduct.unit.trim() || 'item'
-        } as NewProduct
-      });
+      // An edited product keeps the currency it was priced in
+      const product: NewProduct = {
+        name: newProduct.name.trim(),
+        price: fromMajor(Number(newProduct.price), editingProduct?.price.currency ?? currencySymbol),
+        unit: newProduct.unit.trim() || 'item',
+        category_id: newProduct.category_id ? Number(newProduct.category_id) : null,
+        tax_class: newProduct.tax_class as TaxClass
+      };
+      const saved = editingProduct
+        ? await invoke<Product>('update_product', { id: editingProduct.id, product })
+        : await invoke<Product>('add_product', { product });
+      await invoke('set_product_modifier_groups', { productId: saved.id, groupIds: productGroupIds });
+      const components: NewBundleComponent[] = bundleRows
+        .filter(row => row.product_id)
+        .map(row => ({
+          product_id: Number(row.product_id),
+          quantity: Number(row.quantity) || 1,
+          substitutes: row.substitutes
+        }));
+      await invoke('set_bundle_components', { productId: saved.id, components });
+      const recipe: NewRecipeItem[] = recipeRows
+        .filter(row => row.ingredient_id)
+        .map(row => ({ ingredient_id: Number(row.ingredient_id), quantity: Number(row.quantity), unit: row.unit || null }));
+      await invoke('set_recipe', { productId: saved.id, items: recipe });
       
-      setNewProduct({ name: '', price: '', unit: 'item' });
-      setErrors({});
-      setShowAddModal(false);
+      closeProductModal();
       await loadProducts();
       
       setSubmitStatus('success');
-      setStatusMessage('Product added successfully!');
+      setStatusMessage(editingProduct ? 'Product updated successfully!' : 'Product added successfully!');
       
       setTimeout(() => {
         setSubmitStatus('idle');
       }, 3000);
     } catch (error) {
-      console.error('Error adding product:', error);
+      console.error('Error saving product:', error);
       setSubmitStatus('error');
-      setStatusMessage(error as string || 'Failed to add product. Please try again.');
+      setStatusMessage(errorMessage(error) || 'Failed to save product. Please try again.');
       
       setTimeout(() => {
         setSubmitStatus('idle');
@@ -152,6 +249,53 @@ export default function ProductManager() {
     }
   };
 
+  const openEditModal = (product: Product) => {
+    setEditingProduct(product);
+    setNewProduct({
+      name: product.name,
+      price: String(toMajor(product.price)),
+      unit: product.unit,
+      category_id: product.category_id != null ? String(product.category_id) : '',
+      tax_class: product.tax_class
+    });
+    setErrors({});
+    setProductGroupIds([]);
+    setBundleRows([]);
+    setRecipeRows([]);
+    setShowAddModal(true);
+    invoke<ModifierGroup[]>('get_product_modifier_groups', { productId: product.id })
+      .then(groups => setProductGroupIds(groups.map(group => group.id)))
+      .catch(error => console.error('Error loading product options:', error));
+    invoke<BundleComponent[]>('get_bundle_components', { productId: product.id })
+      .then(components => setBundleRows(components.map(component => ({
+        product_id: String(component.product_id),
+        quantity: String(component.quantity),
+        substitutes: component.substitutes.map(s => s.product_id)
+      }))))
+      .catch(error => console.error('Error loading bundle components:', error));
+    invoke<RecipeItem[]>('get_recipe', { productId: product.id })
+      .then(items => setRecipeRows(items.map(item => ({
+        ingredient_id: String(item.ingredient_id),
+        quantity: String(item.quantity),
+        unit: ''
+      }))))
+      .catch(error => console.error('Error loading recipe:', error));
+  };
+
+  const closeProductModal = () => {
+    setShowAddModal(false);
+    setEditingProduct(null);
+    setErrors({});
+    setNewProduct({ name: '', price: '', unit: 'item', category_id: '', tax_class: 'standard' });
+    setProductGroupIds([]);
+    setBundleRows([]);
+    setRecipeRows([]);
+  };
+
+  const updateBundleRow = (index: number, row: Partial<{ product_id: string; quantity: string; substitutes: number[] }>) => {
+    setBundleRows(prev => prev.map((r, i) => (i === index ? { ...r, ...row } : r)));
+  };
+
   const openDeleteConfirmation = (product: Product) => {
     setProductToDelete(product);
     setShowDeleteModal(true);
@@ -163,12 +307,14 @@ export default function ProductManager() {
     setDeletingId(productToDelete.id);
     setShowDeleteModal(false);
     
+    // Deleting an active product archives it; an archived one is removed for good
+    const purge = Boolean(productToDelete.archived_at);
     try {
-      await invoke('delete_product', { id: productToDelete.id });
+      await invoke(purge ? 'purge_product' : 'delete_product', { id: productToDelete.id });
       await loadProducts();
       
       setSubmitStatus('success');
-      setStatusMessage('Product deleted successfully!');
+      setStatusMessage(purge ? 'Product deleted permanently!' : 'Product archived!');
       
       setTimeout(() => {
         setSubmitStatus('idle');
@@ -176,7 +322,7 @@ export default function ProductManager() {
     } catch (error) {
       console.error('Error deleting product:', error);
       setSubmitStatus('error');
-      setStatusMessage(error as string || 'Failed to delete product. Please try again.');
+      setStatusMessage(errorMessage(error) || 'Failed to delete product. Please try again.');
       
       setTimeout(() => {
         setSubmitStatus('idle');
@@ -187,6 +333,123 @@ export default function ProductManager() {
     }
   };
 
+  const handleRestoreProduct = async (product: Product) => {
+    try {
+      await invoke('restore_product', { id: product.id });
+      await loadProducts();
+
+      setSubmitStatus('success');
+      setStatusMessage('Product restored!');
+    } catch (error) {
+      console.error('Error restoring product:', error);
+      setSubmitStatus('error');
+      setStatusMessage(errorMessage(error) || 'Failed to restore product. Please try again.');
+    } finally {
+      setTimeout(() => {
+        setSubmitStatus('idle');
+      }, 3000);
+    }
+  };
+
+  const handleAddCategory = async () => {
+    if (!newCategory.name.trim()) return;
+    try {
+      const category: NewCategory = {
+        name: newCategory.name.trim(),
+        color: newCategory.color,
+        parent_id: newCategory.parent_id ? Number(newCategory.parent_id) : null
+      };
+      await invoke('add_category', { category });
+      setNewCategory({ ...newCategory, name: '' });
+      await loadCategories();
+    } catch (error) {
+      console.error('Error adding category:', error);
+      setSubmitStatus('error');
+      setStatusMessage(errorMessage(error) || 'Failed to add category. Please try again.');
+      setTimeout(() => {
+        setSubmitStatus('idle');
+      }, 3000);
+    }
+  };
+
+  // Products and sub-sections of a deleted category move up to its parent
+  const handleDeleteCategory = async (category: Category) => {
+    try {
+      await invoke('delete_category', { id: category.id });
+      await Promise.all([loadCategories(), loadProducts()]);
+    } catch (error) {
+      console.error('Error deleting category:', error);
+      setSubmitStatus('error');
+      setStatusMessage(errorMessage(error) || 'Failed to delete category. Please try again.');
+      setTimeout(() => {
+        setSubmitStatus('idle');
+      }, 3000);
+    }
+  };
+
+  const openGroupForm = (group: ModifierGroup | null) => {
+    setEditingGroup(group);
+    setGroupForm(group
+      ? {
+          name: group.name,
+          selection: group.selection,
+          min_choices: String(group.min_choices),
+          max_choices: group.max_choices !== null ? String(group.max_choices) : '',
+          modifiers: group.modifiers.map(m => ({ id: m.id, name: m.name, price_delta: String(toMajor(m.price_delta)) }))
+        }
+      : emptyGroupForm());
+  };
+
+  const handleSaveGroup = async () => {
+    try {
+      const group: NewModifierGroup = {
+        name: groupForm.name.trim(),
+        selection: groupForm.selection,
+        min_choices: Number(groupForm.min_choices) || 0,
+        max_choices: groupForm.max_choices ? Number(groupForm.max_choices) : null,
+        modifiers: groupForm.modifiers
+          .filter(m => m.name.trim())
+          .map(m => ({ id: m.id, name: m.name.trim(), price_delta: fromMajor(Number(m.price_delta) || 0, currencySymbol) }))
+      };
+      if (editingGroup) {
+        await invoke('update_modifier_group', { id: editingGroup.id, group });
+      } else {
+        await invoke('add_modifier_group', { group });
+      }
+      openGroupForm(null);
+      await loadModifierGroups();
+    } catch (error) {
+      console.error('Error saving option group:', error);
+      setSubmitStatus('error');
+      setStatusMessage(errorMessage(error) || 'Failed to save option group. Please try again.');
+      setTimeout(() => {
+        setSubmitStatus('idle');
+      }, 3000);
+    }
+  };
+
+  const handleDeleteGroup = async (group: ModifierGroup) => {
+    try {
+      await invoke('delete_modifier_group', { id: group.id });
+      if (editingGroup?.id === group.id) openGroupForm(null);
+      await loadModifierGroups();
+    } catch (error) {
+      console.error('Error deleting option group:', error);
+      setSubmitStatus('error');
+      setStatusMessage(errorMessage(error) || 'Failed to delete option group. Please try again.');
+      setTimeout(() => {
+        setSubmitStatus('idle');
+      }, 3000);
+    }
+  };
+
+  const updateGroupOption = (index: number, field: 'name' | 'price_delta', value: string) => {
+    setGroupForm(prev => ({
+      ...prev,
+      modifiers: prev.modifiers.map((m, i) => (i === index ? { ...m, [field]: value } : m))
+    }));
+  };
+
   return (
     <div className="min-h-screen bg-linear-to-br from-slate-100 via-purple-100 to-slate-100 
       dark:from-slate-900 dark:via-purple-900 dark:to-slate-900 transition-colors duration-300">
@@ -229,7 +492,17 @@ export default function ProductManager() {
           >
             Product Manager
           </motion.h1>
-          <div className="hidden sm:block w-24" />
+          <motion.button
+            whileHover={{ scale: 1.05 }}
+            whileTap={{ scale: 0.95 }}
+            onClick={() => navigate('/discounts')}
+            className="flex items-center text-slate-900 dark:text-white gap-2 
+              bg-purple-200 dark:bg-purple-600/30 px-4 py-2 rounded-lg
+              transition-colors duration-300"
+          >
+            <MdLocalOffer className="w-5 h-5" />
+            <span>Discounts</span>
+          </motion.button>
         </div>
 
         {/* Stats Cards Row */}
@@ -240,7 +513,18 @@ export default function ProductManager() {
             className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl p-4 sm:p-6 md:w-full transition-colors duration-300"
           >
             <h2 className="text-lg sm:text-xl text-slate-900 dark:text-white mb-2">Total Products</h2>
-            <p className="text-3xl sm:text-4xl font-bold text-teal-600 dark:text-teal-400">{products.length}</p>
+            <p className="text-3xl sm:text-4xl font-bold text-teal-600 dark:text-teal-400">
+              {products.filter(product => !product.archived_at).length}
+            </p>
+            <label className="mt-3 flex items-center gap-2 text-sm text-slate-600 dark:text-gray-300 cursor-pointer">
+              <input
+                type="checkbox"
+                checked={showArchived}
+                onChange={(e) => setShowArchived(e.target.checked)}
+                className="accent-teal-500"
+              />
+              Show archived products
+            </label>
           </motion.div>
         </div>
 
@@ -257,6 +541,32 @@ export default function ProductManager() {
           <span>Add New Product</span>
         </motion.button>
 
+        <div className="grid grid-cols-2 gap-4 -mt-3 sm:-mt-5 mb-6 sm:mb-8">
+          <motion.button
+            whileHover={{ scale: 1.02 }}
+            whileTap={{ scale: 0.98 }}
+            onClick={() => setShowCategoryModal(true)}
+            className="bg-white/70 dark:bg-white/10 text-slate-900 dark:text-white 
+              rounded-xl py-3 px-4 sm:px-6 flex items-center justify-center gap-2 transition-colors duration-300"
+          >
+            <FaFolder />
+            <span>Manage Categories ({categories.length})</span>
+          </motion.button>
+          <motion.button
+            whileHover={{ scale: 1.02 }}
+            whileTap={{ scale: 0.98 }}
+            onClick={() => {
+              openGroupForm(null);
+              setShowOptionsModal(true);
+            }}
+            className="bg-white/70 dark:bg-white/10 text-slate-900 dark:text-white 
+              rounded-xl py-3 px-4 sm:px-6 flex items-center justify-center gap-2 transition-colors duration-300"
+          >
+            <FaSlidersH />
+            <span>Manage Options ({modifierGroups.length})</span>
+          </motion.button>
+        </div>
+
         {/* Products List */}
         <motion.div
           initial={{ opacity: 0 }}
@@ -285,38 +595,66 @@ export default function ProductManager() {
               {/* Mobile Layout */}
               <div className="sm:hidden flex justify-between items-center mb-2">
                 <span className="font-semibold">#{product.id}</span>
-                <motion.button
-                  whileHover={{ scale: 1.1 }}
-                  whileTap={{ scale: 0.9 }}
-                  onClick={() => openDeleteConfirmation(product)}
-                  className="text-red-400 hover:text-red-300 disabled:opacity-50"
-                  disabled={deletingId === product.id}
-                >
-                  {deletingId === product.id ? (
-                    <motion.div
-                      animate={{ rotate: 360 }}
-                      transition={{ duration: 1, repeat: Infinity, ease: "linear" }}
-                      className="w-4 h-4 border-2 border-red-400 border-t-transparent rounded-full"
-                    />
-                  ) : (
-                    <FaTrash />
-                  )}
-                </motion.button>
+                <div className="flex items-center">
+                  <motion.button
+                    whileHover={{ scale: 1.1 }}
+                    whileTap={{ scale: 0.9 }}
+                    onClick={() => product.archived_at ? handleRestoreProduct(product) : openEditModal(product)}
+                    className="text-teal-500 hover:text-teal-400 mr-4"
+                    title={product.archived_at ? 'Restore product' : 'Edit product'}
+                  >
+                    {product.archived_at ? <FaUndo /> : <FaEdit />}
+                  </motion.button>
+                  <motion.button
+                    whileHover={{ scale: 1.1 }}
+                    whileTap={{ scale: 0.9 }}
+                    onClick={() => openDeleteConfirmation(product)}
+                    className="text-red-400 hover:text-red-300 disabled:opacity-50"
+                    disabled={deletingId === product.id}
+                  >
+                    {deletingId === product.id ? (
+                      <motion.div
+                        animate={{ rotate: 360 }}
+                        transition={{ duration: 1, repeat: Infinity, ease: "linear" }}
+                        className="w-4 h-4 border-2 border-red-400 border-t-transparent rounded-full"
+                      />
+                    ) : (
+                      <FaTrash />
+                    )}
+                  </motion.button>
+                </div>
               </div>
               <div className="sm:hidden mb-1">
                 <span className="text-lg font-medium">{product.name}</span>
+                {product.archived_at && (
+                  <span className="ml-2 text-xs px-2 py-0.5 rounded-full bg-slate-300 dark:bg-slate-600">Archived</span>
+                )}
               </div>
               <div className="sm:hidden text-teal-600 dark:text-teal-400 font-medium">
-                {currencySymbol} {product.price.toFixed(2)} per {product.unit}
+                {product.price.currency} {formatAmount(product.price)} per {product.unit}
               </div>
 
               {/* Desktop Layout */}
               <div className="hidden sm:block col-span-2">#{product.id}</div>
-              <div className="hidden sm:block col-span-5">{product.name}</div>
+              <div className="hidden sm:block col-span-5">
+                {product.name}
+                {product.archived_at && (
+                  <span className="ml-2 text-xs px-2 py-0.5 rounded-full bg-slate-300 dark:bg-slate-600">Archived</span>
+                )}
+              </div>
               <div className="hidden sm:block col-span-3">
-                {currencySymbol} {product.price.toFixed(2)} per {product.unit}
+                {product.price.currency} {formatAmount(product.price)} per {product.unit}
               </div>
               <div className="hidden sm:block col-span-2">
+                <motion.button
+                  whileHover={{ scale: 1.1 }}
+                  whileTap={{ scale: 0.9 }}
+                  onClick={() => product.archived_at ? handleRestoreProduct(product) : openEditModal(product)}
+                  className="text-teal-500 hover:text-teal-400 mr-4"
+                  title={product.archived_at ? 'Restore product' : 'Edit product'}
+                >
+                  {product.archived_at ? <FaUndo /> : <FaEdit />}
+                </motion.button>
                 <motion.button
                   whileHover={{ scale: 1.1 }}
                   whileTap={{ scale: 0.9 }}
@@ -354,7 +692,9 @@ export default function ProductManager() {
             animate={{ opacity: 1, scale: 1 }}
             className="bg-white dark:bg-slate-800 rounded-xl p-4 sm:p-6 w-full max-w-md transition-colors duration-300"
           >
-            <h2 className="text-xl sm:text-2xl font-bold text-slate-900 dark:text-white mb-4 sm:mb-6">Add New Product</h2>
+            <h2 className="text-xl sm:text-2xl font-bold text-slate-900 dark:text-white mb-4 sm:mb-6">
+              {editingProduct ? 'Edit Product' : 'Add New Product'}
+            </h2>
             
             <div className="space-y-4">
               <div>
@@ -380,7 +720,7 @@ export default function ProductManager() {
               <div className="grid grid-cols-2 gap-4">
                 <div>
                   <label className="block text-slate-900 dark:text-white mb-2">
-                    Price ({currencySymbol} per {newProduct.unit})
+                    Price ({editingProduct?.price.currency ?? currencySymbol} per {newProduct.unit})
                   </label>
                   <input
                     type="number"
@@ -404,17 +744,16 @@ export default function ProductManager() {
 
                 <div>
                   <label className="block text-slate-900 dark:text-white mb-2">Unit</label>
-                  <input
-                    type="text"
+                  <UnitSelect
+                    units={units}
                     value={newProduct.unit}
-                    onChange={(e) => handleInputChange('unit', e.target.value)}
+                    onChange={(value) => handleInputChange('unit', value)}
                     className={`w-full px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 
                       text-slate-900 dark:text-white border focus:outline-none transition-colors ${
                         errors.unit 
                           ? 'border-red-500 focus:border-red-400' 
                           : 'border-slate-300 dark:border-transparent focus:border-teal-400'
                       }`}
-                    placeholder="e.g., piece, kg, plate"
                     disabled={isSubmitting}
                   />
                   {errors.unit && (
@@ -423,13 +762,204 @@ export default function ProductManager() {
                 </div>
               </div>
 
+              <div>
+                <label className="block text-slate-900 dark:text-white mb-2">Category</label>
+                <select
+                  value={newProduct.category_id}
+                  onChange={(e) => handleInputChange('category_id', e.target.value)}
+                  className="w-full px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                  disabled={isSubmitting}
+                >
+                  <option value="">Uncategorized</option>
+                  {categories.map(category => (
+                    <option key={category.id} value={category.id}>{categoryLabel(category)}</option>
+                  ))}
+                </select>
+              </div>
+
+              <div>
+                <label className="block text-slate-900 dark:text-white mb-2">Tax</label>
+                <select
+                  value={newProduct.tax_class}
+                  onChange={(e) => handleInputChange('tax_class', e.target.value)}
+                  className="w-full px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                  disabled={isSubmitting}
+                >
+                  {(Object.keys(taxClassLabels) as TaxClass[]).map(taxClass => (
+                    <option key={taxClass} value={taxClass}>{taxClassLabels[taxClass]}</option>
+                  ))}
+                </select>
+              </div>
+
+              {modifierGroups.length > 0 && (
+                <div>
+                  <label className="block text-slate-900 dark:text-white mb-2">Options</label>
+                  <div className="flex flex-wrap gap-3">
+                    {modifierGroups.map(group => (
+                      <label key={group.id} className="flex items-center gap-2 text-slate-700 dark:text-gray-300 cursor-pointer">
+                        <input
+                          type="checkbox"
+                          checked={productGroupIds.includes(group.id)}
+                          onChange={(e) => setProductGroupIds(prev =>
+                            e.target.checked ? [...prev, group.id] : prev.filter(id => id !== group.id)
+                          )}
+                          className="accent-teal-500"
+                          disabled={isSubmitting}
+                        />
+                        {group.name}
+                      </label>
+                    ))}
+                  </div>
+                </div>
+              )}
+
+              <div>
+                <label className="block text-slate-900 dark:text-white mb-2">Bundle components</label>
+                <p className="text-sm text-slate-500 dark:text-white/60 mb-2">
+                  Sold at this product's price. Hold Ctrl to pick the products a component can be swapped for.
+                </p>
+                <div className="space-y-3">
+                  {bundleRows.map((row, index) => (
+                    <div key={index} className="space-y-2">
+                      <div className="flex gap-2">
+                        <select
+                          value={row.product_id}
+                          onChange={(e) => updateBundleRow(index, { product_id: e.target.value })}
+                          className="flex-1 px-3 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                            border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                          disabled={isSubmitting}
+                        >
+                          <option value="">Choose a product</option>
+                          {bundleChoices.map(product => (
+                            <option key={product.id} value={product.id}>{product.name}</option>
+                          ))}
+                        </select>
+                        <input
+                          type="number"
+                          min="0"
+                          step="any"
+                          value={row.quantity}
+                          onChange={(e) => updateBundleRow(index, { quantity: e.target.value })}
+                          className="w-20 px-3 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                            border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                          disabled={isSubmitting}
+                        />
+                        <button
+                          onClick={() => setBundleRows(prev => prev.filter((_, i) => i !== index))}
+                          className="text-red-500 hover:text-red-400"
+                          title="Remove component"
+                          disabled={isSubmitting}
+                        >
+                          <FaTrash />
+                        </button>
+                      </div>
+                      {row.product_id && (
+                        <select
+                          multiple
+                          value={row.substitutes.map(String)}
+                          onChange={(e) => updateBundleRow(index, {
+                            substitutes: Array.from(e.target.selectedOptions, option => Number(option.value))
+                          })}
+                          className="w-full h-20 px-3 py-1 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                            border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                          disabled={isSubmitting}
+                        >
+                          {bundleChoices
+                            .filter(product => String(product.id) !== row.product_id)
+                            .map(product => (
+                              <option key={product.id} value={product.id}>{product.name}</option>
+                            ))}
+                        </select>
+                      )}
+                    </div>
+                  ))}
+                </div>
+                <button
+                  onClick={() => setBundleRows(prev => [...prev, { product_id: '', quantity: '1', substitutes: [] }])}
+                  className="mt-2 text-teal-600 dark:text-teal-400 flex items-center gap-2"
+                  disabled={isSubmitting}
+                >
+                  <FaPlus />
+                  Add component
+                </button>
+              </div>
+
+              {ingredients.length > 0 && (
+                <div>
+                  <label className="block text-slate-900 dark:text-white mb-2">Recipe</label>
+                  <p className="text-sm text-slate-500 dark:text-white/60 mb-2">
+                    Ingredients used for one {newProduct.unit || 'item'}, taken out of stock when it is sold.
+                  </p>
+                  <div className="space-y-2">
+                    {recipeRows.map((row, index) => (
+                      <div key={index} className="flex gap-2 items-center">
+                        <select
+                          value={row.ingredient_id}
+                          onChange={(e) => setRecipeRows(prev => prev.map((r, i) => (i === index ? { ...r, ingredient_id: e.target.value, unit: '' } : r)))}
+                          className="flex-1 px-3 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                            border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                          disabled={isSubmitting}
+                        >
+                          <option value="">Choose an ingredient</option>
+                          {ingredients.map(ingredient => (
+                            <option key={ingredient.id} value={ingredient.id}>{ingredient.name} ({ingredient.unit})</option>
+                          ))}
+                        </select>
+                        <input
+                          type="number"
+                          min="0"
+                          step="any"
+                          value={row.quantity}
+                          onChange={(e) => setRecipeRows(prev => prev.map((r, i) => (i === index ? { ...r, quantity: e.target.value } : r)))}
+                          className="w-24 px-3 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                            border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                          disabled={isSubmitting}
+                        />
+                        {/* Quantities in another unit are converted into the ingredient's */}
+                        <select
+                          value={row.unit}
+                          onChange={(e) => setRecipeRows(prev => prev.map((r, i) => (i === index ? { ...r, unit: e.target.value } : r)))}
+                          className="w-24 px-3 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                            border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                          disabled={isSubmitting || !row.ingredient_id}
+                        >
+                          {(() => {
+                            const own = ingredients.find(ingredient => String(ingredient.id) === row.ingredient_id)?.unit ?? '';
+                            return [
+                              <option key="" value="">{own}</option>,
+                              ...compatibleUnits(units, own)
+                                .filter(unit => unit.code !== own)
+                                .map(unit => <option key={unit.code} value={unit.code}>{unit.code}</option>)
+                            ];
+                          })()}
+                        </select>
+                        <button
+                          onClick={() => setRecipeRows(prev => prev.filter((_, i) => i !== index))}
+                          className="text-red-500 hover:text-red-400"
+                          title="Remove ingredient"
+                          disabled={isSubmitting}
+                        >
+                          <FaTrash />
+                        </button>
+                      </div>
+                    ))}
+                  </div>
+                  <button
+                    onClick={() => setRecipeRows(prev => [...prev, { ingredient_id: '', quantity: '', unit: '' }])}
+                    className="mt-2 text-teal-600 dark:text-teal-400 flex items-center gap-2"
+                    disabled={isSubmitting}
+                  >
+                    <FaPlus />
+                    Add ingredient
+                  </button>
+                </div>
+              )}
+
               <div className="flex gap-4 mt-6">
                 <button
-                  onClick={() => {
-                    setShowAddModal(false);
-                    setErrors({});
-                    setNewProduct({ name: '', price: '', unit: 'item' });
-                  }}
+                  onClick={closeProductModal}
                   className="flex-1 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 
                     text-slate-900 dark:text-white hover:bg-slate-300 dark:hover:bg-slate-600 
                     transition-colors disabled:opacity-50"
@@ -450,7 +980,12 @@ export default function ProductManager() {
                         transition={{ duration: 1, repeat: Infinity, ease: "linear" }}
                         className="w-5 h-5 border-2 border-white border-t-transparent rounded-full"
                       />
-                      Adding...
+                      {editingProduct ? 'Saving...' : 'Adding...'}
+                    </>
+                  ) : editingProduct ? (
+                    <>
+                      <FaCheck />
+                      Save Changes
                     </>
                   ) : (
                     <>
@@ -465,6 +1000,245 @@ export default function ProductManager() {
         </div>
       )}
 
+      {/* Options Modal */}
+      {showOptionsModal && (
+        <div className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center p-4 z-50">
+          <motion.div
+            initial={{ opacity: 0, scale: 0.9 }}
+            animate={{ opacity: 1, scale: 1 }}
+            className="bg-white dark:bg-slate-800 rounded-xl p-4 sm:p-6 w-full max-w-lg max-h-[90vh] overflow-y-auto transition-colors duration-300"
+          >
+            <h2 className="text-xl sm:text-2xl font-bold text-slate-900 dark:text-white mb-4">Options</h2>
+
+            <div className="space-y-2 mb-4">
+              {modifierGroups.map(group => (
+                <div
+                  key={group.id}
+                  className={`flex items-center justify-between px-3 py-2 rounded-lg text-slate-900 dark:text-white ${
+                    editingGroup?.id === group.id ? 'bg-teal-100 dark:bg-teal-500/20' : 'bg-slate-100 dark:bg-slate-700'
+                  }`}
+                >
+                  <span>
+                    {group.name}
+                    <span className="ml-2 text-sm text-slate-500 dark:text-white/60">
+                      {group.modifiers.map(m => m.name).join(', ')}
+                    </span>
+                  </span>
+                  <span className="flex gap-3">
+                    <button onClick={() => openGroupForm(group)} className="text-teal-500 hover:text-teal-400" title="Edit group">
+                      <FaEdit />
+                    </button>
+                    <button onClick={() => handleDeleteGroup(group)} className="text-red-500 hover:text-red-400" title="Delete group">
+                      <FaTrash />
+                    </button>
+                  </span>
+                </div>
+              ))}
+              {modifierGroups.length === 0 && (
+                <p className="text-center text-slate-600 dark:text-white/60">No option groups yet.</p>
+              )}
+            </div>
+
+            <div className="space-y-3 border-t border-slate-300 dark:border-white/10 pt-4">
+              <h3 className="text-slate-900 dark:text-white font-semibold">
+                {editingGroup ? `Edit ${editingGroup.name}` : 'New Group'}
+              </h3>
+              <input
+                type="text"
+                value={groupForm.name}
+                onChange={(e) => setGroupForm({ ...groupForm, name: e.target.value })}
+                className="w-full px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                  border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                placeholder="e.g., Size, Add-ons"
+              />
+              <div className="grid grid-cols-3 gap-3">
+                <select
+                  value={groupForm.selection}
+                  onChange={(e) => setGroupForm({ ...groupForm, selection: e.target.value as Selection })}
+                  className="px-3 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                >
+                  <option value="single">Pick one</option>
+                  <option value="multiple">Pick many</option>
+                </select>
+                <input
+                  type="number"
+                  min="0"
+                  value={groupForm.min_choices}
+                  onChange={(e) => setGroupForm({ ...groupForm, min_choices: e.target.value })}
+                  className="px-3 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                  title="Minimum choices"
+                  placeholder="Min"
+                />
+                <input
+                  type="number"
+                  min="1"
+                  value={groupForm.selection === 'single' ? '1' : groupForm.max_choices}
+                  onChange={(e) => setGroupForm({ ...groupForm, max_choices: e.target.value })}
+                  className="px-3 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none disabled:opacity-50"
+                  title="Maximum choices, empty for no limit"
+                  placeholder="Max"
+                  disabled={groupForm.selection === 'single'}
+                />
+              </div>
+              {groupForm.modifiers.map((modifier, index) => (
+                <div key={index} className="flex gap-3">
+                  <input
+                    type="text"
+                    value={modifier.name}
+                    onChange={(e) => updateGroupOption(index, 'name', e.target.value)}
+                    className="flex-1 px-3 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                      border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                    placeholder="Option name"
+                  />
+                  <input
+                    type="number"
+                    step="0.01"
+                    value={modifier.price_delta}
+                    onChange={(e) => updateGroupOption(index, 'price_delta', e.target.value)}
+                    className="w-28 px-3 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                      border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                    title={`Price change (${currencySymbol})`}
+                  />
+                  <button
+                    onClick={() => setGroupForm(prev => ({ ...prev, modifiers: prev.modifiers.filter((_, i) => i !== index) }))}
+                    className="text-red-500 hover:text-red-400"
+                    title="Remove option"
+                  >
+                    <FaTrash />
+                  </button>
+                </div>
+              ))}
+              <button
+                onClick={() => setGroupForm(prev => ({
+                  ...prev,
+                  modifiers: [...prev.modifiers, { id: null, name: '', price_delta: '0' }]
+                }))}
+                className="text-teal-600 dark:text-teal-400 flex items-center gap-2"
+              >
+                <FaPlus />
+                Add option
+              </button>
+            </div>
+
+            <div className="flex gap-4 mt-6">
+              <button
+                onClick={() => {
+                  setShowOptionsModal(false);
+                  openGroupForm(null);
+                }}
+                className="flex-1 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 
+                  text-slate-900 dark:text-white hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+              >
+                Close
+              </button>
+              <button
+                onClick={handleSaveGroup}
+                className="flex-1 px-4 py-2 rounded-lg bg-teal-500 text-white hover:bg-teal-400 
+                  transition-colors disabled:opacity-50 flex items-center justify-center gap-2"
+                disabled={!groupForm.name.trim()}
+              >
+                <FaCheck />
+                {editingGroup ? 'Save Group' : 'Add Group'}
+              </button>
+            </div>
+          </motion.div>
+        </div>
+      )}
+
+      {/* Categories Modal */}
+      {showCategoryModal && (
+        <div className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center p-4 z-50">
+          <motion.div
+            initial={{ opacity: 0, scale: 0.9 }}
+            animate={{ opacity: 1, scale: 1 }}
+            className="bg-white dark:bg-slate-800 rounded-xl p-4 sm:p-6 w-full max-w-md transition-colors duration-300"
+          >
+            <h2 className="text-xl sm:text-2xl font-bold text-slate-900 dark:text-white mb-4">Categories</h2>
+
+            <div className="max-h-64 overflow-y-auto mb-4 space-y-2">
+              {categories.map(category => (
+                <div
+                  key={category.id}
+                  className="flex items-center justify-between px-3 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white"
+                  style={{ marginLeft: `${categoryDepth(category) * 1.5}rem` }}
+                >
+                  <span className="flex items-center gap-2">
+                    <span
+                      className="w-3 h-3 rounded-full"
+                      style={{ backgroundColor: category.color ?? 'transparent' }}
+                    />
+                    {category.name}
+                  </span>
+                  <button
+                    onClick={() => handleDeleteCategory(category)}
+                    className="text-red-500 hover:text-red-400"
+                    title="Delete category"
+                  >
+                    <FaTrash />
+                  </button>
+                </div>
+              ))}
+              {categories.length === 0 && (
+                <p className="text-center text-slate-600 dark:text-white/60">No categories yet.</p>
+              )}
+            </div>
+
+            <div className="space-y-3">
+              <input
+                type="text"
+                value={newCategory.name}
+                onChange={(e) => setNewCategory({ ...newCategory, name: e.target.value })}
+                className="w-full px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                  border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                placeholder="e.g., Starters, Mains, Drinks"
+              />
+              <div className="flex gap-3">
+                <select
+                  value={newCategory.parent_id}
+                  onChange={(e) => setNewCategory({ ...newCategory, parent_id: e.target.value })}
+                  className="flex-1 px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                >
+                  <option value="">Top level</option>
+                  {categories.map(category => (
+                    <option key={category.id} value={category.id}>{categoryLabel(category)}</option>
+                  ))}
+                </select>
+                <input
+                  type="color"
+                  value={newCategory.color}
+                  onChange={(e) => setNewCategory({ ...newCategory, color: e.target.value })}
+                  className="w-12 h-10 rounded-lg bg-transparent cursor-pointer"
+                  title="Category color"
+                />
+              </div>
+            </div>
+
+            <div className="flex gap-4 mt-6">
+              <button
+                onClick={() => setShowCategoryModal(false)}
+                className="flex-1 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 
+                  text-slate-900 dark:text-white hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+              >
+                Close
+              </button>
+              <button
+                onClick={handleAddCategory}
+                className="flex-1 px-4 py-2 rounded-lg bg-teal-500 text-white hover:bg-teal-400 
+                  transition-colors disabled:opacity-50 flex items-center justify-center gap-2"
+                disabled={!newCategory.name.trim()}
+              >
+                <FaPlus />
+                Add Category
+              </button>
+            </div>
+          </motion.div>
+        </div>
+      )}
+
       {/* Delete Confirmation Modal */}
       {showDeleteModal && productToDelete && (
         <div className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center p-4 z-50">
@@ -483,18 +1257,20 @@ export default function ProductManager() {
 
             {/* Title */}
             <h2 className="text-xl sm:text-2xl font-bold text-slate-900 dark:text-white text-center mb-3">
-              Delete Product?
+              {productToDelete.archived_at ? 'Delete Permanently?' : 'Archive Product?'}
             </h2>
 
             {/* Message */}
             <p className="text-slate-600 dark:text-gray-300 text-center mb-2">
-              Are you sure you want to delete
+              {productToDelete.archived_at ? 'Are you sure you want to permanently delete' : 'Are you sure you want to archive'}
             </p>
             <p className="text-slate-900 dark:text-white font-semibold text-center text-lg mb-1">
               {productToDelete.name}
             </p>
             <p className="text-slate-500 dark:text-gray-400 text-center text-sm mb-6">
-              This action cannot be undone.
+              {productToDelete.archived_at
+                ? 'Its price history is removed too. This action cannot be undone.'
+                : 'It will be hidden from the menu and can be restored later.'}
             </p>
 
             {/* Buttons */}
@@ -520,7 +1296,7 @@ export default function ProductManager() {
                   transition-colors font-semibold flex items-center justify-center gap-2"
               >
                 <FaTrash />
-                Yes, Delete
+                {productToDelete.archived_at ? 'Yes, Delete' : 'Yes, Archive'}
               </motion.button>
             </div>
           </motion.div>
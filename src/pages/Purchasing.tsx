@@ -0,0 +1,699 @@
+import { motion } from 'framer-motion';
+import { useState, useEffect } from 'react';
+import { FaPlus, FaTrash, FaEdit, FaPaperPlane, FaTruck } from 'react-icons/fa';
+import { MdArrowBack } from 'react-icons/md';
+import { useNavigate } from 'react-router-dom';
+import { invoke } from '@tauri-apps/api/core';
+import {
+  Ingredient,
+  Product,
+  Settings,
+  Supplier,
+  NewSupplier,
+  PurchaseOrder,
+  PurchaseOrderStatus,
+  NewPurchaseOrder,
+  LineReceipt
+} from '../types';
+import ThemeToggle from '../components/ThemeToggle';
+import { errorMessage } from '../errors';
+import { formatAmount, fromMajor, lineTotal, money, toMajor } from '../money';
+
+const statusLabels: Record<PurchaseOrderStatus, string> = {
+  draft: 'Draft',
+  sent: 'Sent',
+  partially_received: 'Partially received',
+  received: 'Received'
+};
+
+const statusClasses: Record<PurchaseOrderStatus, string> = {
+  draft: 'bg-slate-200 text-slate-700 dark:bg-slate-600 dark:text-white',
+  sent: 'bg-sky-100 text-sky-700 dark:bg-sky-500/30 dark:text-sky-200',
+  partially_received: 'bg-amber-100 text-amber-700 dark:bg-amber-500/30 dark:text-amber-200',
+  received: 'bg-teal-100 text-teal-700 dark:bg-teal-500/30 dark:text-teal-200'
+};
+
+const formatQuantity = (quantity: number) => String(Number(quantity.toFixed(3)));
+
+// An order line's item, as "ingredient:3" or "product:7" in the picker
+interface LineRow {
+  item: string;
+  quantity: string;
+  unit_cost: string;
+}
+
+const emptyLine: LineRow = { item: '', quantity: '', unit_cost: '' };
+
+const orderTotal = (order: PurchaseOrder) =>
+  money(
+    order.lines.reduce((sum, line) => sum + lineTotal(line.unit_cost, line.quantity).minor, 0),
+    order.currency
+  );
+
+export default function Purchasing() {
+  const navigate = useNavigate();
+  const [isNavigating, setIsNavigating] = useState(false);
+  const [suppliers, setSuppliers] = useState<Supplier[]>([]);
+  const [orders, setOrders] = useState<PurchaseOrder[]>([]);
+  const [ingredients, setIngredients] = useState<Ingredient[]>([]);
+  const [products, setProducts] = useState<Product[]>([]);
+  const [currency, setCurrency] = useState('PKR');
+  const [isLoading, setIsLoading] = useState(true);
+  const [isSubmitting, setIsSubmitting] = useState(false);
+  const [editingSupplier, setEditingSupplier] = useState<Supplier | 'new' | null>(null);
+  const [supplierForm, setSupplierForm] = useState({ name: '', contact_name: '', phone: '', email: '', notes: '' });
+  const [editingOrder, setEditingOrder] = useState<PurchaseOrder | 'new' | null>(null);
+  const [orderForm, setOrderForm] = useState<{ supplier_id: string; notes: string; lines: LineRow[] }>({
+    supplier_id: '',
+    notes: '',
+    lines: [emptyLine]
+  });
+  // Quantities and costs arriving per line id
+  const [receiving, setReceiving] = useState<PurchaseOrder | null>(null);
+  const [receiptRows, setReceiptRows] = useState<Record<number, { quantity: string; unit_cost: string }>>({});
+  const [receiptNote, setReceiptNote] = useState('');
+
+  const handleBackNavigation = () => {
+    setIsNavigating(true);
+    setTimeout(() => {
+      navigate('/inventory');
+    }, 300);
+  };
+
+  const loadData = async () => {
+    try {
+      const [suppliersRes, ordersRes, ingredientsRes, productsRes] = await Promise.all([
+        invoke<Supplier[]>('get_suppliers'),
+        invoke<PurchaseOrder[]>('get_purchase_orders'),
+        invoke<Ingredient[]>('get_ingredients'),
+        invoke<Product[]>('get_products')
+      ]);
+      setSuppliers(suppliersRes);
+      setOrders(ordersRes);
+      setIngredients(ingredientsRes);
+      setProducts(productsRes);
+    } catch (error) {
+      console.error('Error loading purchasing data:', error);
+    } finally {
+      setIsLoading(false);
+    }
+  };
+
+  useEffect(() => {
+    const loadCurrency = async () => {
+      try {
+        const response = await invoke<Settings>('get_settings');
+        if (response?.currency) {
+          setCurrency(response.currency);
+        }
+      } catch (error) {
+        console.error('Error loading currency:', error);
+      }
+    };
+
+    loadCurrency();
+    loadData();
+  }, []);
+
+  const openSupplierForm = (supplier: Supplier | null) => {
+    setEditingSupplier(supplier ?? 'new');
+    setSupplierForm({
+      name: supplier?.name ?? '',
+      contact_name: supplier?.contact_name ?? '',
+      phone: supplier?.phone ?? '',
+      email: supplier?.email ?? '',
+      notes: supplier?.notes ?? ''
+    });
+  };
+
+  const handleSaveSupplier = async () => {
+    if (!editingSupplier) return;
+    setIsSubmitting(true);
+    try {
+      const supplier: NewSupplier = { ...supplierForm };
+      if (editingSupplier === 'new') {
+        await invoke('add_supplier', { supplier });
+      } else {
+        await invoke('update_supplier', { id: editingSupplier.id, supplier });
+      }
+      setEditingSupplier(null);
+      await loadData();
+    } catch (error) {
+      alert(`Error saving supplier: ${errorMessage(error)}`);
+    } finally {
+      setIsSubmitting(false);
+    }
+  };
+
+  const handleDeleteSupplier = async (supplier: Supplier) => {
+    if (!confirm(`Delete supplier ${supplier.name}?`)) return;
+    try {
+      await invoke('delete_supplier', { id: supplier.id });
+      await loadData();
+    } catch (error) {
+      alert(`Error deleting supplier: ${errorMessage(error)}`);
+    }
+  };
+
+  const openOrderForm = (order: PurchaseOrder | null) => {
+    setEditingOrder(order ?? 'new');
+    setOrderForm({
+      supplier_id: order ? String(order.supplier_id) : suppliers[0] ? String(suppliers[0].id) : '',
+      notes: order?.notes ?? '',
+      lines: order
+        ? order.lines.map(line => ({
+            item: line.ingredient_id !== null ? `ingredient:${line.ingredient_id}` : `product:${line.product_id}`,
+            quantity: formatQuantity(line.quantity),
+            unit_cost: String(toMajor(line.unit_cost))
+          }))
+        : [emptyLine]
+    });
+  };
+
+  const updateLine = (index: number, changes: Partial<LineRow>) => {
+    setOrderForm({
+      ...orderForm,
+      lines: orderForm.lines.map((line, i) => (i === index ? { ...line, ...changes } : line))
+    });
+  };
+
+  const handleSaveOrder = async () => {
+    if (!editingOrder) return;
+    setIsSubmitting(true);
+    try {
+      // A draft keeps the currency it was started in
+      const orderCurrency = editingOrder === 'new' ? currency : editingOrder.currency;
+      const order: NewPurchaseOrder = {
+        supplier_id: Number(orderForm.supplier_id),
+        notes: orderForm.notes || null,
+        lines: orderForm.lines
+          .filter(line => line.item !== '')
+          .map(line => {
+            const [kind, id] = line.item.split(':');
+            return {
+              ingredient_id: kind === 'ingredient' ? Number(id) : null,
+              product_id: kind === 'product' ? Number(id) : null,
+              quantity: Number(line.quantity),
+              unit_cost: fromMajor(Number(line.unit_cost) || 0, orderCurrency)
+            };
+          })
+      };
+      if (editingOrder === 'new') {
+        await invoke('add_purchase_order', { order });
+      } else {
+        await invoke('update_purchase_order', { id: editingOrder.id, order });
+      }
+      setEditingOrder(null);
+      await loadData();
+    } catch (error) {
+      alert(`Error saving purchase order: ${errorMessage(error)}`);
+    } finally {
+      setIsSubmitting(false);
+    }
+  };
+
+  const handleSendOrder = async (order: PurchaseOrder) => {
+    if (!confirm(`Mark purchase order #${order.id} as sent to ${order.supplier_name}? It can no longer be edited.`)) return;
+    try {
+      await invoke('send_purchase_order', { id: order.id });
+      await loadData();
+    } catch (error) {
+      alert(`Error sending purchase order: ${errorMessage(error)}`);
+    }
+  };
+
+  const handleDeleteOrder = async (order: PurchaseOrder) => {
+    if (!confirm(`Delete purchase order #${order.id}?`)) return;
+    try {
+      await invoke('delete_purchase_order', { id: order.id });
+      await loadData();
+    } catch (error) {
+      alert(`Error deleting purchase order: ${errorMessage(error)}`);
+    }
+  };
+
+  // Pre-fills each line with what is still outstanding at the ordered cost
+  const openReceive = (order: PurchaseOrder) => {
+    const rows: Record<number, { quantity: string; unit_cost: string }> = {};
+    for (const line of order.lines) {
+      const outstanding = Math.max(line.quantity - line.received_quantity, 0);
+      rows[line.id] = {
+        quantity: outstanding > 0 ? formatQuantity(outstanding) : '',
+        unit_cost: String(toMajor(line.unit_cost))
+      };
+    }
+    setReceiptRows(rows);
+    setReceiptNote('');
+    setReceiving(order);
+  };
+
+  const handleReceive = async () => {
+    if (!receiving) return;
+    setIsSubmitting(true);
+    try {
+      const receipts: LineReceipt[] = receiving.lines
+        .filter(line => Number(receiptRows[line.id]?.quantity) > 0)
+        .map(line => ({
+          line_id: line.id,
+          quantity: Number(receiptRows[line.id].quantity),
+          unit_cost: fromMajor(Number(receiptRows[line.id].unit_cost) || 0, receiving.currency)
+        }));
+      await invoke('receive_purchase_order', { id: receiving.id, receipts, note: receiptNote || null });
+      setReceiving(null);
+      await loadData();
+    } catch (error) {
+      alert(`Error receiving purchase order: ${errorMessage(error)}`);
+    } finally {
+      setIsSubmitting(false);
+    }
+  };
+
+  const inputClass = `w-full px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white
+    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none`;
+
+  return (
+    <div className="min-h-screen bg-linear-to-br from-slate-100 via-purple-100 to-slate-100
+      dark:from-slate-900 dark:via-purple-900 dark:to-slate-900 transition-colors duration-300">
+      {/* Theme Toggle */}
+      <div className="absolute safe-top safe-right z-50">
+        <ThemeToggle />
+      </div>
+      <div className="max-w-7xl mx-auto px-4 py-4 sm:py-8">
+        {/* Header */}
+        <div className="flex flex-col sm:flex-row items-center gap-4 sm:gap-0 sm:justify-between mb-6 sm:mb-8">
+          <motion.button
+            whileHover={{ scale: 1.05 }}
+            whileTap={{ scale: 0.95 }}
+            onClick={handleBackNavigation}
+            className="flex items-center text-slate-900 dark:text-white gap-2
+              bg-purple-200 dark:bg-purple-600/30 px-4 py-2 rounded-lg min-w-[120px] justify-center
+              transition-colors duration-300"
+            disabled={isNavigating}
+          >
+            {isNavigating ? (
+              <>
+                <motion.div
+                  animate={{ rotate: 360 }}
+                  transition={{ duration: 1, repeat: Infinity, ease: "linear" }}
+                  className="w-5 h-5 border-2 border-white border-t-transparent rounded-full"
+                />
+                <span>Loading...</span>
+              </>
+            ) : (
+              <>
+                <MdArrowBack className="w-5 h-5" />
+                <span>Inventory</span>
+              </>
+            )}
+          </motion.button>
+          <motion.h1
+            initial={{ opacity: 0, y: -20 }}
+            animate={{ opacity: 1, y: 0 }}
+            className="text-2xl sm:text-3xl font-bold text-slate-900 dark:text-white order-first sm:order-0 transition-colors duration-300"
+          >
+            Purchasing
+          </motion.h1>
+          <div className="hidden sm:block w-24" />
+        </div>
+
+        {/* Purchase Orders */}
+        <div className="flex items-center justify-between mb-4">
+          <h2 className="text-xl sm:text-2xl font-semibold text-slate-900 dark:text-white">Purchase orders</h2>
+          <motion.button
+            whileHover={{ scale: 1.02 }}
+            whileTap={{ scale: 0.98 }}
+            onClick={() => openOrderForm(null)}
+            className="bg-teal-500 text-white rounded-lg py-2 px-4 flex items-center gap-2 hover:bg-teal-400
+              transition-colors disabled:opacity-50"
+            disabled={suppliers.length === 0}
+            title={suppliers.length === 0 ? 'Add a supplier first' : undefined}
+          >
+            <FaPlus />
+            <span>New order</span>
+          </motion.button>
+        </div>
+        <motion.div
+          initial={{ opacity: 0 }}
+          animate={{ opacity: 1 }}
+          className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl overflow-hidden mb-8 transition-colors duration-300"
+        >
+          {orders.map(order => (
+            <div
+              key={order.id}
+              className="p-4 border-b border-slate-300 dark:border-white/10 text-slate-900 dark:text-white"
+            >
+              <div className="flex flex-col sm:flex-row sm:items-center sm:justify-between gap-2">
+                <div className="flex items-center gap-3">
+                  <span className="font-semibold">#{order.id} · {order.supplier_name}</span>
+                  <span className={`text-xs px-2 py-1 rounded-full ${statusClasses[order.status]}`}>
+                    {statusLabels[order.status]}
+                  </span>
+                </div>
+                <div className="flex items-center gap-4">
+                  <span className="font-medium">
+                    {order.currency} {formatAmount(orderTotal(order))}
+                  </span>
+                  {order.status === 'draft' && (
+                    <>
+                      <button onClick={() => openOrderForm(order)} className="text-teal-500 hover:text-teal-400" title="Edit order">
+                        <FaEdit />
+                      </button>
+                      <button onClick={() => handleSendOrder(order)} className="text-teal-500 hover:text-teal-400" title="Mark as sent">
+                        <FaPaperPlane />
+                      </button>
+                    </>
+                  )}
+                  {(order.status === 'sent' || order.status === 'partially_received') && (
+                    <button onClick={() => openReceive(order)} className="text-teal-500 hover:text-teal-400" title="Receive delivery">
+                      <FaTruck />
+                    </button>
+                  )}
+                  {(order.status === 'draft' || order.status === 'sent') && (
+                    <button onClick={() => handleDeleteOrder(order)} className="text-red-400 hover:text-red-300" title="Delete order">
+                      <FaTrash />
+                    </button>
+                  )}
+                </div>
+              </div>
+              <div className="mt-2 text-sm text-slate-600 dark:text-white/70 space-y-1">
+                {order.lines.map(line => (
+                  <div key={line.id}>
+                    {formatQuantity(line.quantity)} {line.unit} {line.name} @ {formatAmount(line.unit_cost)}
+                    {line.received_quantity > 0 && (
+                      <span className="ml-2 text-teal-600 dark:text-teal-400">
+                        received {formatQuantity(line.received_quantity)} for {formatAmount(line.received_cost)}
+                      </span>
+                    )}
+                  </div>
+                ))}
+                {order.notes && <div className="italic">{order.notes}</div>}
+              </div>
+            </div>
+          ))}
+          {!isLoading && orders.length === 0 && (
+            <div className="p-6 sm:p-8 text-center text-slate-600 dark:text-white/60">No purchase orders yet.</div>
+          )}
+        </motion.div>
+
+        {/* Suppliers */}
+        <div className="flex items-center justify-between mb-4">
+          <h2 className="text-xl sm:text-2xl font-semibold text-slate-900 dark:text-white">Suppliers</h2>
+          <motion.button
+            whileHover={{ scale: 1.02 }}
+            whileTap={{ scale: 0.98 }}
+            onClick={() => openSupplierForm(null)}
+            className="bg-teal-500 text-white rounded-lg py-2 px-4 flex items-center gap-2 hover:bg-teal-400 transition-colors"
+          >
+            <FaPlus />
+            <span>Add supplier</span>
+          </motion.button>
+        </div>
+        <motion.div
+          initial={{ opacity: 0 }}
+          animate={{ opacity: 1 }}
+          className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl overflow-hidden transition-colors duration-300"
+        >
+          {suppliers.map(supplier => (
+            <div
+              key={supplier.id}
+              className="flex justify-between items-center gap-4 p-4 border-b border-slate-300 dark:border-white/10
+                text-slate-900 dark:text-white hover:bg-slate-100 dark:hover:bg-white/5 transition-colors"
+            >
+              <div>
+                <div className="font-medium">{supplier.name}</div>
+                <div className="text-sm text-slate-500 dark:text-white/60">
+                  {[supplier.contact_name, supplier.phone, supplier.email].filter(Boolean).join(' · ')}
+                </div>
+              </div>
+              <div className="flex items-center gap-4">
+                <button onClick={() => openSupplierForm(supplier)} className="text-teal-500 hover:text-teal-400" title="Edit supplier">
+                  <FaEdit />
+                </button>
+                <button onClick={() => handleDeleteSupplier(supplier)} className="text-red-400 hover:text-red-300" title="Delete supplier">
+                  <FaTrash />
+                </button>
+              </div>
+            </div>
+          ))}
+          {!isLoading && suppliers.length === 0 && (
+            <div className="p-6 sm:p-8 text-center text-slate-600 dark:text-white/60">
+              No suppliers yet. Add one to start ordering stock.
+            </div>
+          )}
+        </motion.div>
+      </div>
+
+      {/* Supplier Modal */}
+      {editingSupplier && (
+        <div className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center p-4 z-50">
+          <motion.div
+            initial={{ opacity: 0, scale: 0.9 }}
+            animate={{ opacity: 1, scale: 1 }}
+            className="bg-white dark:bg-slate-800 rounded-xl p-4 sm:p-6 w-full max-w-md"
+          >
+            <h2 className="text-xl sm:text-2xl font-bold text-slate-900 dark:text-white mb-4">
+              {editingSupplier === 'new' ? 'Add Supplier' : 'Edit Supplier'}
+            </h2>
+            <div className="space-y-4">
+              {([
+                ['name', 'Name'],
+                ['contact_name', 'Contact (optional)'],
+                ['phone', 'Phone (optional)'],
+                ['email', 'Email (optional)'],
+                ['notes', 'Notes (optional)']
+              ] as const).map(([field, label]) => (
+                <div key={field}>
+                  <label className="block text-slate-900 dark:text-white mb-2">{label}</label>
+                  <input
+                    type={field === 'email' ? 'email' : 'text'}
+                    value={supplierForm[field]}
+                    onChange={(e) => setSupplierForm({ ...supplierForm, [field]: e.target.value })}
+                    className={inputClass}
+                    disabled={isSubmitting}
+                  />
+                </div>
+              ))}
+            </div>
+            <div className="flex gap-4 mt-6">
+              <button
+                onClick={() => setEditingSupplier(null)}
+                className="flex-1 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 text-slate-900 dark:text-white
+                  hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+                disabled={isSubmitting}
+              >
+                Cancel
+              </button>
+              <button
+                onClick={handleSaveSupplier}
+                className="flex-1 px-4 py-2 rounded-lg bg-teal-500 text-white hover:bg-teal-400 transition-colors disabled:opacity-50"
+                disabled={isSubmitting}
+              >
+                {isSubmitting ? 'Saving...' : 'Save'}
+              </button>
+            </div>
+          </motion.div>
+        </div>
+      )}
+
+      {/* Purchase Order Modal */}
+      {editingOrder && (
+        <div className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center p-4 z-50">
+          <motion.div
+            initial={{ opacity: 0, scale: 0.9 }}
+            animate={{ opacity: 1, scale: 1 }}
+            className="bg-white dark:bg-slate-800 rounded-xl p-4 sm:p-6 w-full max-w-2xl max-h-[90vh] overflow-y-auto"
+          >
+            <h2 className="text-xl sm:text-2xl font-bold text-slate-900 dark:text-white mb-4">
+              {editingOrder === 'new' ? 'New Purchase Order' : `Edit Purchase Order #${editingOrder.id}`}
+            </h2>
+            <div className="space-y-4">
+              <div>
+                <label className="block text-slate-900 dark:text-white mb-2">Supplier</label>
+                <select
+                  value={orderForm.supplier_id}
+                  onChange={(e) => setOrderForm({ ...orderForm, supplier_id: e.target.value })}
+                  className={inputClass}
+                  disabled={isSubmitting}
+                >
+                  {suppliers.map(supplier => (
+                    <option key={supplier.id} value={supplier.id}>{supplier.name}</option>
+                  ))}
+                </select>
+              </div>
+              <div>
+                <label className="block text-slate-900 dark:text-white mb-2">
+                  Lines (cost per unit in {editingOrder === 'new' ? currency : editingOrder.currency})
+                </label>
+                <div className="space-y-2">
+                  {orderForm.lines.map((line, index) => (
+                    <div key={index} className="grid grid-cols-12 gap-2 items-center">
+                      <select
+                        value={line.item}
+                        onChange={(e) => updateLine(index, { item: e.target.value })}
+                        className={`${inputClass} col-span-6`}
+                        disabled={isSubmitting}
+                      >
+                        <option value="">Choose an item...</option>
+                        <optgroup label="Ingredients">
+                          {ingredients.map(ingredient => (
+                            <option key={ingredient.id} value={`ingredient:${ingredient.id}`}>
+                              {ingredient.name} ({ingredient.unit})
+                            </option>
+                          ))}
+                        </optgroup>
+                        <optgroup label="Products">
+                          {products.map(product => (
+                            <option key={product.id} value={`product:${product.id}`}>
+                              {product.name} ({product.unit})
+                            </option>
+                          ))}
+                        </optgroup>
+                      </select>
+                      <input
+                        type="number"
+                        min="0"
+                        step="any"
+                        value={line.quantity}
+                        onChange={(e) => updateLine(index, { quantity: e.target.value })}
+                        placeholder="Qty"
+                        className={`${inputClass} col-span-2`}
+                        disabled={isSubmitting}
+                      />
+                      <input
+                        type="number"
+                        min="0"
+                        step="any"
+                        value={line.unit_cost}
+                        onChange={(e) => updateLine(index, { unit_cost: e.target.value })}
+                        placeholder="Cost"
+                        className={`${inputClass} col-span-3`}
+                        disabled={isSubmitting}
+                      />
+                      <button
+                        onClick={() => setOrderForm({ ...orderForm, lines: orderForm.lines.filter((_, i) => i !== index) })}
+                        className="col-span-1 text-red-400 hover:text-red-300 flex justify-center"
+                        title="Remove line"
+                        disabled={isSubmitting}
+                      >
+                        <FaTrash />
+                      </button>
+                    </div>
+                  ))}
+                </div>
+                <button
+                  onClick={() => setOrderForm({ ...orderForm, lines: [...orderForm.lines, emptyLine] })}
+                  className="mt-2 text-teal-500 hover:text-teal-400 flex items-center gap-2"
+                  disabled={isSubmitting}
+                >
+                  <FaPlus /> Add line
+                </button>
+              </div>
+              <div>
+                <label className="block text-slate-900 dark:text-white mb-2">Notes (optional)</label>
+                <input
+                  type="text"
+                  value={orderForm.notes}
+                  onChange={(e) => setOrderForm({ ...orderForm, notes: e.target.value })}
+                  className={inputClass}
+                  disabled={isSubmitting}
+                />
+              </div>
+            </div>
+            <div className="flex gap-4 mt-6">
+              <button
+                onClick={() => setEditingOrder(null)}
+                className="flex-1 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 text-slate-900 dark:text-white
+                  hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+                disabled={isSubmitting}
+              >
+                Cancel
+              </button>
+              <button
+                onClick={handleSaveOrder}
+                className="flex-1 px-4 py-2 rounded-lg bg-teal-500 text-white hover:bg-teal-400 transition-colors disabled:opacity-50"
+                disabled={isSubmitting || orderForm.supplier_id === ''}
+              >
+                {isSubmitting ? 'Saving...' : 'Save draft'}
+              </button>
+            </div>
+          </motion.div>
+        </div>
+      )}
+
+      {/* Receive Modal */}
+      {receiving && (
+        <div className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center p-4 z-50">
+          <motion.div
+            initial={{ opacity: 0, scale: 0.9 }}
+            animate={{ opacity: 1, scale: 1 }}
+            className="bg-white dark:bg-slate-800 rounded-xl p-4 sm:p-6 w-full max-w-2xl max-h-[90vh] overflow-y-auto"
+          >
+            <h2 className="text-xl sm:text-2xl font-bold text-slate-900 dark:text-white mb-4">
+              Receive #{receiving.id} from {receiving.supplier_name}
+            </h2>
+            <div className="space-y-3">
+              {receiving.lines.map(line => (
+                <div key={line.id} className="grid grid-cols-12 gap-2 items-center text-slate-900 dark:text-white">
+                  <div className="col-span-6">
+                    <div className="font-medium">{line.name}</div>
+                    <div className="text-xs text-slate-500 dark:text-white/60">
+                      {formatQuantity(line.received_quantity)} of {formatQuantity(line.quantity)} {line.unit} received
+                    </div>
+                  </div>
+                  <input
+                    type="number"
+                    min="0"
+                    step="any"
+                    value={receiptRows[line.id]?.quantity ?? ''}
+                    onChange={(e) => setReceiptRows({ ...receiptRows, [line.id]: { ...receiptRows[line.id], quantity: e.target.value } })}
+                    placeholder="Qty"
+                    className={`${inputClass} col-span-3`}
+                    disabled={isSubmitting}
+                  />
+                  <input
+                    type="number"
+                    min="0"
+                    step="any"
+                    value={receiptRows[line.id]?.unit_cost ?? ''}
+                    onChange={(e) => setReceiptRows({ ...receiptRows, [line.id]: { ...receiptRows[line.id], unit_cost: e.target.value } })}
+                    placeholder={`Cost (${receiving.currency})`}
+                    className={`${inputClass} col-span-3`}
+                    disabled={isSubmitting}
+                  />
+                </div>
+              ))}
+              <div>
+                <label className="block text-slate-900 dark:text-white mb-2">Note (optional)</label>
+                <input
+                  type="text"
+                  value={receiptNote}
+                  onChange={(e) => setReceiptNote(e.target.value)}
+                  placeholder="Invoice number, driver..."
+                  className={inputClass}
+                  disabled={isSubmitting}
+                />
+              </div>
+            </div>
+            <div className="flex gap-4 mt-6">
+              <button
+                onClick={() => setReceiving(null)}
+                className="flex-1 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 text-slate-900 dark:text-white
+                  hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+                disabled={isSubmitting}
+              >
+                Cancel
+              </button>
+              <button
+                onClick={handleReceive}
+                className="flex-1 px-4 py-2 rounded-lg bg-teal-500 text-white hover:bg-teal-400 transition-colors disabled:opacity-50"
+                disabled={isSubmitting}
+              >
+                {isSubmitting ? 'Saving...' : 'Receive'}
+              </button>
+            </div>
+          </motion.div>
+        </div>
+      )}
+    </div>
+  );
+}
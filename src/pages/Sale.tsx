@@ -1,24 +1,48 @@
 import { motion } from 'framer-motion';
 import { useState, useEffect, useRef } from 'react';
-import { MdArrowBack, MdShoppingCart, MdCheckCircle, MdLocalPrintshop, MdFileDownload } from 'react-icons/md';
+import { MdArrowBack, MdShoppingCart, MdCheckCircle, MdLocalPrintshop, MdFileDownload, MdLocalOffer } from 'react-icons/md';
 import { FaPlus } from 'react-icons/fa';
 import { useNavigate } from 'react-router-dom';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { save } from '@tauri-apps/plugin-dialog';
 import { writeFile } from '@tauri-apps/plugin-fs';
-import { Product, Settings, CartItem, Sale as SaleType } from '../types';
+import { Product, Settings, CartItem, CategoryGroup, LineModifier, ModifierGroup, Modifier, Money, BundleComponent, LineComponent, Substitution, Ingredient, StockLowAlert, Sale as SaleType, Unit, DiscountKind, DiscountReason, NewDiscount, Coupon } from '../types';
 import Receipt from '../components/Receipt';
 import jsPDF from 'jspdf';
 import ThemeToggle from '../components/ThemeToggle';
+import { formatAmount, lineTotal, money, toMajor } from '../money';
+import { lineTax, rateFor, taxRates } from '../tax';
+import { allocate, discountLabel, discountOff, parseDiscountValue } from '../discounts';
+import { errorMessage } from '../errors';
+import { compatibleUnits, convertQuantity, findUnit, isCounted } from '../units';
 
 export default function Sale() {
   const navigate = useNavigate();
   const [cart, setCart] = useState<CartItem[]>([]);
   const [showSuccessDialog, setShowSuccessDialog] = useState(false);
   const [products, setProducts] = useState<Product[]>([]);
+  const [groups, setGroups] = useState<CategoryGroup[]>([]);
+  // Index into `groups`, or null for every product
+  const [activeGroup, setActiveGroup] = useState<number | null>(null);
+  // Modifier groups of each product, fetched the first time it is added
+  const [productOptions, setProductOptions] = useState<Record<number, ModifierGroup[]>>({});
+  // Components of each bundle product, fetched alongside its options
+  const [productComponents, setProductComponents] = useState<Record<number, BundleComponent[]>>({});
+  const [optionPicker, setOptionPicker] = useState<{
+    product: Product;
+    groups: ModifierGroup[];
+    chosen: number[];
+    components: BundleComponent[];
+    // Product chosen for each swappable component, by component id
+    swaps: Record<number, number>;
+  } | null>(null);
   const [receiptData, setReceiptData] = useState<{
     products: { name: string; quantity: number; unit: string; price: number }[];
     totalAmount: number;
+    discounts: { label: string; amount: number }[];
+    taxAmount: number;
+    pricesIncludeTax: boolean;
     date: string;
     time: string;
     receiptNumber: string;
@@ -32,6 +56,23 @@ export default function Sale() {
     receipt_footer: 'Thank you for your business!'
   });
   const [isNavigating, setIsNavigating] = useState(false);
+  // Ingredients recent sales took to or below their reorder point
+  const [lowStock, setLowStock] = useState<Ingredient[]>([]);
+  const [units, setUnits] = useState<Unit[]>([]);
+  const [reasons, setReasons] = useState<DiscountReason[]>([]);
+  // Comes off what the lines add up to after their own discounts
+  const [orderDiscount, setOrderDiscount] = useState<NewDiscount | null>(null);
+  // A coupon the backend has accepted for this order
+  const [coupon, setCoupon] = useState<Coupon | null>(null);
+  const [couponCode, setCouponCode] = useState('');
+  const [isCheckingCoupon, setIsCheckingCoupon] = useState(false);
+  // Discount being edited, on a cart line or, with no line, the whole order
+  const [discountEditor, setDiscountEditor] = useState<{
+    lineKey: string | null;
+    kind: DiscountKind;
+    value: string;
+    reasonId: number | null;
+  } | null>(null);
   const [isLoading, setIsLoading] = useState(true);
   const [isSelling, setIsSelling] = useState(false);
   const [isDownloadingPDF, setIsDownloadingPDF] = useState(false);
@@ -50,19 +91,27 @@ export default function Sale() {
     const loadData = async () => {
       try {
         setIsLoading(true);
-        const [productsRes, settingsRes] = await Promise.all([
-          invoke<Product[]>('get_products'),
-          invoke<Settings>('get_settings')
+        const [groupsRes, settingsRes, unitsRes, reasonsRes] = await Promise.all([
+          invoke<CategoryGroup[]>('get_products_by_category'),
+          invoke<Settings>('get_settings'),
+          invoke<Unit[]>('get_units'),
+          invoke<DiscountReason[]>('get_discount_reasons')
         ]);
+        setUnits(unitsRes);
+        setReasons(reasonsRes);
         
-        setProducts(productsRes);
+        setGroups(groupsRes.filter(group => group.products.length > 0));
+        setProducts(groupsRes.flatMap(group => group.products));
         if (settingsRes) {
           setSettings({
             restaurant_name: settingsRes.restaurant_name || 'Restaurant Management System',
             address: settingsRes.address || '',
             phone: settingsRes.phone || '',
             currency: settingsRes.currency || 'PKR',
-            receipt_footer: settingsRes.receipt_footer || 'Thank you for your business!'
+            receipt_footer: settingsRes.receipt_footer || 'Thank you for your business!',
+            tax_rate: settingsRes.tax_rate,
+            reduced_tax_rate: settingsRes.reduced_tax_rate,
+            prices_include_tax: settingsRes.prices_include_tax
           });
         }
       } catch (error) {
@@ -75,39 +124,212 @@ export default function Sale() {
     loadData();
   }, []);
 
-  const addToCart = (product: Product) => {
+  useEffect(() => {
+    const unlisten = listen<StockLowAlert>('stock-low', (event) => {
+      setLowStock(prev => [
+        ...prev.filter(item => !event.payload.ingredients.some(ingredient => ingredient.id === item.id)),
+        ...event.payload.ingredients
+      ]);
+    });
+    return () => {
+      unlisten.then(stop => stop());
+    };
+  }, []);
+
+  const addToCart = (
+    product: Product,
+    modifiers: LineModifier[] = [],
+    substitutions: Substitution[] = [],
+    components: LineComponent[] = []
+  ) => {
+    const lineKey = [
+      product.id,
+      ...modifiers.map(m => m.modifier_id),
+      ...substitutions.map(s => `${s.component_id}>${s.product_id}`)
+    ].join(':');
     setCart(prev => {
-      const existing = prev.find(item => item.id === product.id);
+      const existing = prev.find(item => item.line_key === lineKey);
       if (existing) {
         return prev.map(item => 
-          item.id === product.id 
-            ? { 
-                ...item, 
-                quantity: item.unit === 'item' || item.unit === 'items' 
-                  ? item.quantity + 1 
-                  : item.quantity + 0.5 
-              }
+          item.line_key === lineKey 
+            ? { ...item, quantity: item.quantity + quantityStep(item.sale_unit) }
             : item
         );
       }
-      return [...prev, { ...product, quantity: 1 }];
+      return [
+        ...prev,
+        { ...product, quantity: 1, sale_unit: product.unit, line_key: lineKey, modifiers, substitutions, components }
+      ];
+    });
+  };
+
+  // Products with options, and bundles with swappable components, open the
+  // picker; the rest go straight into the cart
+  const handleAddProduct = async (product: Product) => {
+    let options = productOptions[product.id];
+    let components = productComponents[product.id];
+    if (!options || !components) {
+      try {
+        [options, components] = await Promise.all([
+          invoke<ModifierGroup[]>('get_product_modifier_groups', { productId: product.id }),
+          invoke<BundleComponent[]>('get_bundle_components', { productId: product.id })
+        ]);
+      } catch (error) {
+        console.error('Error loading product options:', error);
+        options = [];
+        components = [];
+      }
+      setProductOptions(prev => ({ ...prev, [product.id]: options }));
+      setProductComponents(prev => ({ ...prev, [product.id]: components }));
+    }
+    if (options.length === 0 && components.every(c => c.substitutes.length === 0)) {
+      addToCart(product);
+    } else {
+      setOptionPicker({ product, groups: options, chosen: [], components, swaps: {} });
+    }
+  };
+
+  const toggleOption = (group: ModifierGroup, modifier: Modifier) => {
+    setOptionPicker(prev => {
+      if (!prev) return prev;
+      const inGroup = group.modifiers.map(m => m.id);
+      if (prev.chosen.includes(modifier.id)) {
+        return { ...prev, chosen: prev.chosen.filter(id => id !== modifier.id) };
+      }
+      if (group.selection === 'single') {
+        return { ...prev, chosen: [...prev.chosen.filter(id => !inGroup.includes(id)), modifier.id] };
+      }
+      const count = prev.chosen.filter(id => inGroup.includes(id)).length;
+      if (group.max_choices !== null && count >= group.max_choices) return prev;
+      return { ...prev, chosen: [...prev.chosen, modifier.id] };
     });
   };
 
-  const updateQuantity = (productId: number, quantity: number, unit: string) => {
-    if (quantity < (unit === 'item' || unit === 'items' ? 1 : 0.5)) {
-      setCart(prev => prev.filter(item => item.id !== productId));
+  const optionsComplete = optionPicker?.groups.every(group =>
+    group.modifiers.filter(m => optionPicker.chosen.includes(m.id)).length >= group.min_choices
+  ) ?? false;
+
+  const confirmOptions = () => {
+    if (!optionPicker || !optionsComplete) return;
+    const modifiers = optionPicker.groups.flatMap(group =>
+      group.modifiers
+        .filter(m => optionPicker.chosen.includes(m.id))
+        .map(m => ({ modifier_id: m.id, group: group.name, name: m.name, price_delta: m.price_delta }))
+    );
+    const components = optionPicker.components.map(component => {
+      const chosen = optionPicker.swaps[component.id] ?? component.product_id;
+      const substitute = component.substitutes.find(s => s.product_id === chosen);
+      return {
+        product_id: chosen,
+        name: substitute ? substitute.name : component.name,
+        quantity: component.quantity,
+        substituted_for: substitute ? component.product_id : null
+      };
+    });
+    const substitutions = optionPicker.components.flatMap((component, index) =>
+      components[index].substituted_for !== null
+        ? [{ component_id: component.id, product_id: components[index].product_id }]
+        : []
+    );
+    addToCart(optionPicker.product, modifiers, substitutions, components);
+    setOptionPicker(null);
+  };
+
+  // Price of one unit of a cart line, including its options
+  const unitPrice = (item: CartItem): Money =>
+    money(item.price.minor + item.modifiers.reduce((sum, m) => sum + m.price_delta.minor, 0), item.price.currency);
+
+  const lineName = (item: CartItem) => {
+    const extras = [
+      ...item.modifiers.map(m => m.name),
+      ...item.components.filter(c => c.substituted_for !== null).map(c => c.name)
+    ];
+    return extras.length > 0 ? `${item.name} (${extras.join(', ')})` : item.name;
+  };
+
+  // Quantity of a cart line in the product's unit, which its price is per
+  const pricedQuantity = (item: CartItem) => convertQuantity(units, item.quantity, item.sale_unit, item.unit);
+
+  const updateQuantity = (lineKey: string, quantity: number, unit: string) => {
+    if (quantity < quantityStep(unit)) {
+      setCart(prev => prev.filter(item => item.line_key !== lineKey));
       return;
     }
     setCart(prev => 
       prev.map(item => 
-        item.id === productId 
+        item.line_key === lineKey 
           ? { ...item, quantity }
           : item
       )
     );
   };
 
+  // Counted units go one at a time; small units like grams in tens
+  const quantityStep = (unit: string) => {
+    if (isCounted(units, unit) || unit === 'items') return 1;
+    return (findUnit(units, unit)?.factor ?? 100) < 100 ? 10 : 0.5;
+  };
+
+  // Sells a line in another unit of the same kind, keeping the amount
+  const changeSaleUnit = (lineKey: string, saleUnit: string) => {
+    setCart(prev =>
+      prev.map(item =>
+        item.line_key === lineKey
+          ? {
+              ...item,
+              sale_unit: saleUnit,
+              quantity: Number(convertQuantity(units, item.quantity, item.sale_unit, saleUnit).toFixed(3))
+            }
+          : item
+      )
+    );
+  };
+
+  const unitLabel = (unit: string) => (unit === 'item' ? 'item(s)' : unit);
+
+  const reasonName = (reasonId: number) => reasons.find(reason => reason.id === reasonId)?.name ?? 'Discount';
+
+  const editDiscount = (lineKey: string | null) => {
+    const current = lineKey === null ? orderDiscount : cart.find(item => item.line_key === lineKey)?.discount;
+    setDiscountEditor({
+      lineKey,
+      kind: current?.kind ?? 'percentage',
+      value: !current
+        ? ''
+        : current.kind === 'percentage'
+          ? String(current.value / 100)
+          : formatAmount(money(current.value, currency)),
+      reasonId: current?.reason_id ?? reasons[0]?.id ?? null
+    });
+  };
+
+  // Sets or, given null, removes the discount being edited
+  const saveDiscount = (discount: NewDiscount | null) => {
+    if (!discountEditor) return;
+    if (discountEditor.lineKey === null) {
+      setOrderDiscount(discount);
+    } else {
+      const lineKey = discountEditor.lineKey;
+      setCart(prev => prev.map(item => (item.line_key === lineKey ? { ...item, discount } : item)));
+    }
+    setDiscountEditor(null);
+  };
+
+  // Asks the backend whether the code can be used on this order yet
+  const applyCoupon = async () => {
+    const code = couponCode.trim();
+    if (!code) return;
+    try {
+      setIsCheckingCoupon(true);
+      setCoupon(await invoke<Coupon>('check_coupon', { code, spend: money(itemsTotal, currency) }));
+      setCouponCode('');
+    } catch (error) {
+      alert(errorMessage(error));
+    } finally {
+      setIsCheckingCoupon(false);
+    }
+  };
+
   const handleSell = async () => {
     if (cart.length === 0) {
       alert('Please select at least one product');
@@ -119,13 +341,18 @@ export default function Sale() {
       await invoke('add_sale', {
         sale: {
           products: cart.map(item => ({
+            product_id: item.id,
             name: item.name,
             price: item.price,
             quantity: item.quantity,
-            unit: item.unit,
+            unit: item.sale_unit,
+            modifier_ids: item.modifiers.flatMap(m => (m.modifier_id !== null ? [m.modifier_id] : [])),
+            substitutions: item.substitutions,
+            discount: item.discount ?? null,
           })),
           total_amount: totalAmount,
-          currency: settings.currency
+          discount: orderDiscount,
+          coupon_code: coupon?.code ?? null
         } as SaleType
       });
 
@@ -133,12 +360,25 @@ export default function Sale() {
       const now = new Date();
       setReceiptData({
         products: cart.map(item => ({
-          name: item.name,
+          name: lineName(item),
           quantity: item.quantity,
-          unit: item.unit,
-          price: item.price * item.quantity
+          unit: item.sale_unit,
+          price: toMajor(lineTotal(unitPrice(item), pricedQuantity(item)))
         })),
-        totalAmount,
+        totalAmount: toMajor(totalAmount),
+        discounts: [
+          ...cart.flatMap((item, index) => (item.discount && lineDiscounts[index] > 0
+            ? [{ label: `${lineName(item)}: ${reasonName(item.discount.reason_id)}`, amount: toMajor(money(lineDiscounts[index], currency)) }]
+            : [])),
+          ...(orderDiscount && orderDiscountAmount > 0
+            ? [{ label: reasonName(orderDiscount.reason_id), amount: toMajor(money(orderDiscountAmount, currency)) }]
+            : []),
+          ...(coupon && couponAmount > 0
+            ? [{ label: `Coupon ${coupon.code}`, amount: toMajor(money(couponAmount, currency)) }]
+            : [])
+        ],
+        taxAmount: toMajor(taxAmount),
+        pricesIncludeTax: rates.pricesIncludeTax,
         date: now.toLocaleDateString(),
         time: now.toLocaleTimeString(),
         receiptNumber: Math.random().toString(36).substr(2, 9).toUpperCase()
@@ -147,7 +387,7 @@ export default function Sale() {
       setShowSuccessDialog(true);
     } catch (error) {
       console.error('Error saving sale:', error);
-      alert('Error completing sale');
+      alert(`Error completing sale: ${errorMessage(error)}`);
     } finally {
       setIsSelling(false);
     }
@@ -155,6 +395,9 @@ export default function Sale() {
 
   const handleNewSale = () => {
     setCart([]);
+    setOrderDiscount(null);
+    setCoupon(null);
+    setCouponCode('');
     setShowSuccessDialog(false);
     setReceiptData(null);
   };
@@ -252,6 +495,25 @@ export default function Sale() {
       }
       yPos += 5;
 
+      // Discounts
+      pdf.setFontSize(9);
+      pdf.setFont('helvetica', 'normal');
+      receiptData.discounts.forEach(discount => {
+        const label = discount.label.length > 28 ? discount.label.substring(0, 28) + '...' : discount.label;
+        pdf.text(label, margin, yPos);
+        pdf.text(`-${settings.currency} ${discount.amount.toFixed(2)}`, pageWidth - margin, yPos, { align: 'right' });
+        yPos += 5;
+      });
+
+      // Tax
+      if (receiptData.taxAmount > 0) {
+        pdf.setFontSize(9);
+        pdf.setFont('helvetica', 'normal');
+        pdf.text(receiptData.pricesIncludeTax ? 'Tax (included)' : 'Tax', margin, yPos);
+        pdf.text(`${settings.currency} ${receiptData.taxAmount.toFixed(2)}`, pageWidth - margin, yPos, { align: 'right' });
+        yPos += 6;
+      }
+
       // Total
       pdf.setFontSize(11);
       pdf.setFont('helvetica', 'bold');
@@ -300,9 +562,42 @@ export default function Sale() {
     }
   };
 
-  const totalAmount = cart.reduce((sum, item) => 
-    sum + (item.price * item.quantity), 0
+  // Discounts come off in the order the backend takes them: each line's
+  // own, then the order discount from what the lines come to, then the
+  // coupon from what is left. The last two are shared out across the lines.
+  const currency = settings.currency || 'PKR';
+  const formatMinor = (minor: number) => `${currency} ${formatAmount(money(minor, currency))}`;
+  const subtotals = cart.map(item => lineTotal(unitPrice(item), pricedQuantity(item)).minor);
+  const lineDiscounts = cart.map((item, index) =>
+    item.discount ? discountOff(item.discount.kind, item.discount.value, subtotals[index]) : 0
+  );
+  const nets = subtotals.map((subtotal, index) => subtotal - lineDiscounts[index]);
+  const itemsTotal = nets.reduce((sum, net) => sum + net, 0);
+  const orderDiscountAmount = orderDiscount ? discountOff(orderDiscount.kind, orderDiscount.value, itemsTotal) : 0;
+  const couponAmount = coupon ? discountOff(coupon.kind, coupon.value, itemsTotal - orderDiscountAmount) : 0;
+  // The coupon is checked again on sale, so say when the order no longer qualifies
+  const couponShortBy = coupon?.min_spend ? coupon.min_spend.minor - itemsTotal : 0;
+  const shares = allocate(orderDiscountAmount + couponAmount, nets);
+
+  // Each line is taxed on its own, like the backend does, so the total
+  // matches whatever the rates round to
+  const rates = taxRates(settings);
+  const lineAmounts = nets.map((net, index) => net - shares[index]);
+  const taxAmount = money(
+    cart.reduce(
+      (sum, item, index) => sum + lineTax(lineAmounts[index], rateFor(rates, item.tax_class), rates.pricesIncludeTax),
+      0
+    ),
+    currency
+  );
+  const totalAmount = money(
+    lineAmounts.reduce((sum, amount) => sum + amount, 0) + (rates.pricesIncludeTax ? 0 : taxAmount.minor),
+    currency
   );
+  const editorValue = discountEditor
+    ? parseDiscountValue(discountEditor.kind, discountEditor.value, currency)
+    : null;
+  const editingLine = cart.find(item => item.line_key === discountEditor?.lineKey);
 
   return (
     <div className="min-h-screen bg-slate-100 dark:bg-slate-900 transition-colors duration-300">
@@ -362,6 +657,29 @@ export default function Sale() {
           <div className="hidden sm:block w-24" />
         </div>
 
+        {/* Low Stock Warning */}
+        {lowStock.length > 0 && (
+          <motion.div
+            initial={{ opacity: 0, y: -10 }}
+            animate={{ opacity: 1, y: 0 }}
+            className="bg-red-100 dark:bg-red-500/20 text-slate-900 dark:text-white rounded-xl p-4 mb-6 
+              flex justify-between items-start gap-4"
+          >
+            <div>
+              <p className="font-semibold">Running low</p>
+              <p className="text-sm">
+                {lowStock.map(item => `${item.name} (${Number(item.quantity.toFixed(3))} ${item.unit} left)`).join(', ')}
+              </p>
+            </div>
+            <button
+              onClick={() => setLowStock([])}
+              className="text-sm text-slate-600 dark:text-white/70 hover:underline"
+            >
+              Dismiss
+            </button>
+          </motion.div>
+        )}
+
         {/* Total Amount Card */}
         <motion.div
           initial={{ opacity: 0, y: 20 }}
@@ -373,7 +691,7 @@ export default function Sale() {
             <div>
               <h2 className="text-lg sm:text-xl text-slate-900 dark:text-white mb-1">Total Amount</h2>
               <p className="text-3xl sm:text-4xl font-bold text-teal-600 dark:text-teal-400">
-                {settings.currency} {totalAmount.toFixed(2)}
+                {totalAmount.currency} {formatAmount(totalAmount)}
               </p>
             </div>
             <div className="text-slate-600 dark:text-white/60">
@@ -382,10 +700,43 @@ export default function Sale() {
           </div>
         </motion.div>
 
+        {/* Category Tabs */}
+        {groups.length > 1 && (
+          <div className="flex gap-2 overflow-x-auto pb-2 mb-4">
+            {[null, ...groups.map((_, index) => index)].map(index => {
+              const category = index === null ? null : groups[index].category;
+              const parent = category?.parent_id != null
+                ? groups.find(group => group.category?.id === category.parent_id)?.category
+                : null;
+              const label = index === null
+                ? 'All'
+                : category
+                  ? (parent ? `${parent.name} / ${category.name}` : category.name)
+                  : 'Other';
+              return (
+                <button
+                  key={index ?? 'all'}
+                  onClick={() => setActiveGroup(index)}
+                  style={category?.color ? { borderColor: category.color } : undefined}
+                  className={`px-4 py-2 rounded-lg border-2 whitespace-nowrap transition-colors duration-200 ${
+                    activeGroup === index
+                      ? 'bg-teal-500 text-white border-teal-500'
+                      : 'bg-white/70 dark:bg-white/10 text-slate-900 dark:text-white border-transparent hover:bg-white dark:hover:bg-white/20'
+                  }`}
+                >
+                  {label}
+                </button>
+              );
+            })}
+          </div>
+        )}
+
         {/* Products Grid */}
         <div className="grid grid-cols-1 sm:grid-cols-2 lg:grid-cols-3 gap-4 mb-6 sm:mb-8">
-          {products.map((product) => {
-            const cartItem = cart.find(item => item.id === product.id);
+          {(activeGroup === null ? products : groups[activeGroup]?.products ?? []).map((product) => {
+            // Lines with options are adjusted from the cart summary instead
+            const cartItem = cart.find(item => item.line_key === String(product.id));
+            const inCart = cart.some(item => item.id === product.id);
             return (
               <motion.div
                 key={product.id}
@@ -393,7 +744,7 @@ export default function Sale() {
                 animate={{ opacity: 1, scale: 1 }}
                 whileHover={{ scale: 1.02 }}
                 className={`relative p-4 rounded-xl transition-all duration-200 ${
-                  cartItem 
+                  inCart 
                     ? 'bg-teal-100 dark:bg-teal-500/20 border-2 border-teal-500 dark:border-teal-500' 
                     : 'bg-white/70 dark:bg-white/10 border-2 border-transparent'
                 }`}
@@ -403,14 +754,14 @@ export default function Sale() {
                     <div>
                       <h3 className="text-slate-900 dark:text-white font-medium">{product.name}</h3>
                       <p className="text-teal-600 dark:text-teal-400 font-semibold">
-                        {settings.currency} {product.price.toFixed(2)} per {product.unit}
+                        {product.price.currency} {formatAmount(product.price)} per {product.unit}
                       </p>
                     </div>
                     {!cartItem && (
                       <motion.button
                         whileHover={{ scale: 1.1 }}
                         whileTap={{ scale: 0.9 }}
-                        onClick={() => addToCart(product)}
+                        onClick={() => handleAddProduct(product)}
                         className="bg-teal-500 text-white p-2 rounded-lg hover:bg-teal-400 transition-colors"
                       >
                         <FaPlus />
@@ -426,9 +777,9 @@ export default function Sale() {
                     >
                       <button
                         onClick={() => updateQuantity(
-                          product.id, 
-                          (cartItem.quantity || 0) - (product.unit === 'item' || product.unit === 'items' ? 1 : 0.5),
-                          product.unit
+                          cartItem.line_key, 
+                          (cartItem.quantity || 0) - quantityStep(cartItem.sale_unit),
+                          cartItem.sale_unit
                         )}
                         className="w-8 h-8 flex items-center justify-center text-white bg-red-400 dark:bg-red-500/20 
                           hover:bg-red-500 rounded-lg transition-colors"
@@ -437,17 +788,17 @@ export default function Sale() {
                       </button>
                       <div className="flex flex-col items-center">
                         <span className="text-slate-900 dark:text-white font-medium text-lg">
-                          {cartItem.quantity} {product.unit === 'item' ? 'item(s)' : product.unit}
+                          {cartItem.quantity} {unitLabel(cartItem.sale_unit)}
                         </span>
                         <span className="text-slate-600 dark:text-white/60 text-sm">
-                          {settings.currency} {(cartItem.quantity * product.price).toFixed(2)}
+                          {settings.currency} {formatAmount(lineTotal(product.price, pricedQuantity(cartItem)))}
                         </span>
                       </div>
                       <button
                         onClick={() => updateQuantity(
-                          product.id, 
-                          (cartItem.quantity || 0) + (product.unit === 'item' || product.unit === 'items' ? 1 : 0.5),
-                          product.unit
+                          cartItem.line_key, 
+                          (cartItem.quantity || 0) + quantityStep(cartItem.sale_unit),
+                          cartItem.sale_unit
                         )}
                         className="w-8 h-8 flex items-center justify-center text-white bg-teal-400 dark:bg-teal-500/20 
                           hover:bg-teal-500 rounded-lg transition-colors"
@@ -471,18 +822,132 @@ export default function Sale() {
           >
             <h3 className="text-slate-900 dark:text-white font-semibold mb-3">Cart Summary</h3>
             <div className="space-y-2">
-              {cart.map(item => (
-                <div key={item.id} className="flex justify-between items-center text-slate-700 dark:text-white/80">
+              {cart.map((item, index) => (
+                <div key={item.line_key}>
+                  <div className="flex justify-between items-center text-slate-700 dark:text-white/80">
+                    <span className="flex items-center gap-2">
+                      {item.modifiers.length > 0 && (
+                        <span className="flex gap-1">
+                          <button
+                            onClick={() => updateQuantity(item.line_key, item.quantity - quantityStep(item.sale_unit), item.sale_unit)}
+                            className="w-6 h-6 rounded bg-red-400 dark:bg-red-500/20 text-white hover:bg-red-500 transition-colors"
+                          >
+                            -
+                          </button>
+                          <button
+                            onClick={() => updateQuantity(item.line_key, item.quantity + quantityStep(item.sale_unit), item.sale_unit)}
+                            className="w-6 h-6 rounded bg-teal-400 dark:bg-teal-500/20 text-white hover:bg-teal-500 transition-colors"
+                          >
+                            +
+                          </button>
+                        </span>
+                      )}
+                      {lineName(item)} × {item.quantity}
+                      {compatibleUnits(units, item.unit).length > 1 && !isCounted(units, item.unit) ? (
+                        <select
+                          value={item.sale_unit}
+                          onChange={(e) => changeSaleUnit(item.line_key, e.target.value)}
+                          className="px-2 py-1 rounded bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                            border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                        >
+                          {compatibleUnits(units, item.unit).map(unit => (
+                            <option key={unit.code} value={unit.code}>{unit.code}</option>
+                          ))}
+                        </select>
+                      ) : (
+                        ` ${unitLabel(item.sale_unit)}`
+                      )}
+                    </span>
+                    <span className="flex items-center gap-2">
+                      {settings.currency} {formatAmount(lineTotal(unitPrice(item), pricedQuantity(item)))}
+                      <button
+                        onClick={() => editDiscount(item.line_key)}
+                        title="Discount this line"
+                        className="w-6 h-6 flex items-center justify-center rounded bg-purple-200 dark:bg-purple-600/30 
+                          text-slate-900 dark:text-white hover:bg-purple-300 transition-colors"
+                      >
+                        <MdLocalOffer />
+                      </button>
+                    </span>
+                  </div>
+                  {item.discount && (
+                    <div className="flex justify-between items-center text-sm text-slate-600 dark:text-gray-400 pl-4">
+                      <span>
+                        {reasonName(item.discount.reason_id)} ({discountLabel(item.discount.kind, item.discount.value, formatMinor)})
+                      </span>
+                      <span className="mr-8">-{formatMinor(lineDiscounts[index])}</span>
+                    </div>
+                  )}
+                </div>
+              ))}
+              {orderDiscount && (
+                <div className="border-t border-slate-300 dark:border-white/10 pt-2 mt-2 flex justify-between items-center text-slate-600 dark:text-gray-400">
+                  <button onClick={() => editDiscount(null)} className="hover:underline">
+                    {reasonName(orderDiscount.reason_id)} ({discountLabel(orderDiscount.kind, orderDiscount.value, formatMinor)})
+                  </button>
+                  <span>-{formatMinor(orderDiscountAmount)}</span>
+                </div>
+              )}
+              {coupon && (
+                <div className="flex justify-between items-center text-slate-600 dark:text-gray-400">
                   <span>
-                    {item.name} × {item.quantity} {item.unit === 'item' ? 'item(s)' : item.unit}
+                    Coupon {coupon.code}
+                    {couponShortBy > 0 && (
+                      <span className="ml-2 text-sm text-red-500">spend {formatMinor(couponShortBy)} more to use it</span>
+                    )}
+                  </span>
+                  <span className="flex items-center gap-2">
+                    -{formatMinor(couponAmount)}
+                    <button
+                      onClick={() => setCoupon(null)}
+                      title="Remove coupon"
+                      className="w-6 h-6 rounded bg-red-400 dark:bg-red-500/20 text-white hover:bg-red-500 transition-colors"
+                    >
+                      ×
+                    </button>
                   </span>
-                  <span>{settings.currency} {(item.price * item.quantity).toFixed(2)}</span>
                 </div>
-              ))}
+              )}
+              <div className="border-t border-slate-300 dark:border-white/10 pt-2 mt-2 flex flex-col sm:flex-row gap-2">
+                <button
+                  onClick={() => editDiscount(null)}
+                  className="flex items-center justify-center gap-2 px-3 py-1 rounded-lg bg-purple-200 dark:bg-purple-600/30 
+                    text-slate-900 dark:text-white hover:bg-purple-300 transition-colors"
+                >
+                  <MdLocalOffer />
+                  {orderDiscount ? 'Change order discount' : 'Discount order'}
+                </button>
+                {!coupon && (
+                  <div className="flex gap-2 flex-1">
+                    <input
+                      type="text"
+                      value={couponCode}
+                      onChange={(e) => setCouponCode(e.target.value)}
+                      onKeyDown={(e) => e.key === 'Enter' && applyCoupon()}
+                      placeholder="Coupon code"
+                      className="flex-1 min-w-0 px-3 py-1 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                        border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                    />
+                    <button
+                      onClick={applyCoupon}
+                      disabled={!couponCode.trim() || isCheckingCoupon}
+                      className="px-3 py-1 rounded-lg bg-teal-500 text-white hover:bg-teal-400 transition-colors disabled:opacity-50"
+                    >
+                      {isCheckingCoupon ? 'Checking...' : 'Apply'}
+                    </button>
+                  </div>
+                )}
+              </div>
+              {taxAmount.minor > 0 && (
+                <div className="border-t border-slate-300 dark:border-white/10 pt-2 mt-2 flex justify-between items-center text-slate-600 dark:text-gray-400">
+                  <span>{rates.pricesIncludeTax ? 'Tax (included)' : 'Tax'}</span>
+                  <span>{taxAmount.currency} {formatAmount(taxAmount)}</span>
+                </div>
+              )}
               <div className="border-t border-slate-300 dark:border-white/10 pt-2 mt-2 flex justify-between items-center">
                 <span className="text-slate-900 dark:text-white font-semibold">Total</span>
                 <span className="text-teal-600 dark:text-teal-400 font-bold">
-                  {settings.currency} {totalAmount.toFixed(2)}
+                  {totalAmount.currency} {formatAmount(totalAmount)}
                 </span>
               </div>
             </div>
@@ -519,6 +984,188 @@ export default function Sale() {
       </div>
       )}
 
+      {/* Product Options Dialog */}
+      {optionPicker && (
+        <div className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center p-4 z-50">
+          <motion.div
+            initial={{ opacity: 0, scale: 0.9 }}
+            animate={{ opacity: 1, scale: 1 }}
+            className="bg-white dark:bg-slate-800 rounded-xl p-4 sm:p-6 w-full max-w-md max-h-[90vh] overflow-y-auto"
+          >
+            <h2 className="text-xl font-bold text-slate-900 dark:text-white mb-4">{optionPicker.product.name}</h2>
+            {optionPicker.components.filter(component => component.substitutes.length > 0).map(component => {
+              const current = optionPicker.swaps[component.id] ?? component.product_id;
+              const choices = [{ product_id: component.product_id, name: component.name }, ...component.substitutes];
+              return (
+                <div key={`component-${component.id}`} className="mb-4">
+                  <h3 className="text-slate-900 dark:text-white font-semibold mb-2">
+                    {component.name}
+                    <span className="ml-2 text-sm font-normal text-slate-500 dark:text-white/60">
+                      {component.quantity !== 1 ? `× ${component.quantity}, ` : ''}included
+                    </span>
+                  </h3>
+                  <div className="space-y-2">
+                    {choices.map(choice => (
+                      <button
+                        key={choice.product_id}
+                        onClick={() => setOptionPicker(prev => prev && ({
+                          ...prev,
+                          swaps: { ...prev.swaps, [component.id]: choice.product_id }
+                        }))}
+                        className={`w-full flex justify-between px-4 py-2 rounded-lg border-2 transition-colors ${
+                          current === choice.product_id
+                            ? 'border-teal-500 bg-teal-100 dark:bg-teal-500/20 text-slate-900 dark:text-white'
+                            : 'border-transparent bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white'
+                        }`}
+                      >
+                        <span>{choice.name}</span>
+                      </button>
+                    ))}
+                  </div>
+                </div>
+              );
+            })}
+            {optionPicker.groups.map(group => (
+              <div key={group.id} className="mb-4">
+                <h3 className="text-slate-900 dark:text-white font-semibold mb-2">
+                  {group.name}
+                  <span className="ml-2 text-sm font-normal text-slate-500 dark:text-white/60">
+                    {group.min_choices > 0 ? `choose ${group.min_choices}` : 'optional'}
+                    {group.max_choices !== null && group.max_choices !== group.min_choices ? `, up to ${group.max_choices}` : ''}
+                  </span>
+                </h3>
+                <div className="space-y-2">
+                  {group.modifiers.map(modifier => {
+                    const selected = optionPicker.chosen.includes(modifier.id);
+                    return (
+                      <button
+                        key={modifier.id}
+                        onClick={() => toggleOption(group, modifier)}
+                        className={`w-full flex justify-between px-4 py-2 rounded-lg border-2 transition-colors ${
+                          selected
+                            ? 'border-teal-500 bg-teal-100 dark:bg-teal-500/20 text-slate-900 dark:text-white'
+                            : 'border-transparent bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white'
+                        }`}
+                      >
+                        <span>{modifier.name}</span>
+                        {modifier.price_delta.minor !== 0 && (
+                          <span className="text-slate-600 dark:text-white/60">
+                            {modifier.price_delta.minor > 0 ? '+' : ''}{formatAmount(modifier.price_delta)}
+                          </span>
+                        )}
+                      </button>
+                    );
+                  })}
+                </div>
+              </div>
+            ))}
+            <div className="flex gap-4 mt-6">
+              <button
+                onClick={() => setOptionPicker(null)}
+                className="flex-1 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 text-slate-900 dark:text-white 
+                  hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+              >
+                Cancel
+              </button>
+              <button
+                onClick={confirmOptions}
+                disabled={!optionsComplete}
+                className="flex-1 px-4 py-2 rounded-lg bg-teal-500 text-white hover:bg-teal-400 transition-colors 
+                  disabled:opacity-50 flex items-center justify-center gap-2"
+              >
+                <FaPlus />
+                Add to Cart
+              </button>
+            </div>
+          </motion.div>
+        </div>
+      )}
+
+      {/* Discount Dialog */}
+      {discountEditor && (
+        <div className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center p-4 z-50">
+          <motion.div
+            initial={{ opacity: 0, scale: 0.9 }}
+            animate={{ opacity: 1, scale: 1 }}
+            className="bg-white dark:bg-slate-800 rounded-xl p-4 sm:p-6 w-full max-w-md"
+          >
+            <h2 className="text-xl font-bold text-slate-900 dark:text-white mb-4">
+              {editingLine ? `Discount on ${lineName(editingLine)}` : 'Order discount'}
+            </h2>
+            {reasons.length === 0 ? (
+              <p className="text-slate-600 dark:text-white/70">
+                There are no discount reasons yet. Add some under Discounts in the Product Manager first.
+              </p>
+            ) : (
+              <div className="space-y-3">
+                <div className="grid grid-cols-2 gap-2">
+                  {(['percentage', 'fixed'] as DiscountKind[]).map(kind => (
+                    <button
+                      key={kind}
+                      onClick={() => setDiscountEditor({ ...discountEditor, kind })}
+                      className={`px-4 py-2 rounded-lg border-2 transition-colors text-slate-900 dark:text-white ${
+                        discountEditor.kind === kind
+                          ? 'border-teal-500 bg-teal-100 dark:bg-teal-500/20'
+                          : 'border-transparent bg-slate-100 dark:bg-slate-700'
+                      }`}
+                    >
+                      {kind === 'percentage' ? 'Percent off' : `${currency} off`}
+                    </button>
+                  ))}
+                </div>
+                <input
+                  type="text"
+                  inputMode="decimal"
+                  value={discountEditor.value}
+                  onChange={(e) => setDiscountEditor({ ...discountEditor, value: e.target.value })}
+                  placeholder={discountEditor.kind === 'percentage' ? 'Percent, e.g. 10' : 'Amount, e.g. 5.00'}
+                  className="w-full px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                />
+                <select
+                  value={discountEditor.reasonId ?? ''}
+                  onChange={(e) => setDiscountEditor({ ...discountEditor, reasonId: Number(e.target.value) })}
+                  className="w-full px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white 
+                    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                >
+                  {reasons.map(reason => (
+                    <option key={reason.id} value={reason.id}>{reason.name}</option>
+                  ))}
+                </select>
+              </div>
+            )}
+            <div className="flex gap-4 mt-6">
+              <button
+                onClick={() => setDiscountEditor(null)}
+                className="flex-1 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 text-slate-900 dark:text-white 
+                  hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+              >
+                Cancel
+              </button>
+              {(editingLine ? editingLine.discount : orderDiscount) && (
+                <button
+                  onClick={() => saveDiscount(null)}
+                  className="flex-1 px-4 py-2 rounded-lg bg-red-500 text-white hover:bg-red-400 transition-colors"
+                >
+                  Remove
+                </button>
+              )}
+              <button
+                onClick={() => editorValue !== null && discountEditor.reasonId !== null && saveDiscount({
+                  kind: discountEditor.kind,
+                  value: editorValue,
+                  reason_id: discountEditor.reasonId
+                })}
+                disabled={editorValue === null || discountEditor.reasonId === null}
+                className="flex-1 px-4 py-2 rounded-lg bg-teal-500 text-white hover:bg-teal-400 transition-colors disabled:opacity-50"
+              >
+                Apply
+              </button>
+            </div>
+          </motion.div>
+        </div>
+      )}
+
       {/* Success Dialog */}
       {showSuccessDialog && receiptData && (
         <motion.div
@@ -561,6 +1208,9 @@ export default function Sale() {
                   ref={receiptRef}
                   products={receiptData.products}
                   totalAmount={receiptData.totalAmount}
+                  discounts={receiptData.discounts}
+                  taxAmount={receiptData.taxAmount}
+                  pricesIncludeTax={receiptData.pricesIncludeTax}
                   date={receiptData.date}
                   time={receiptData.time}
                   settings={settings}
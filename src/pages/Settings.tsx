@@ -1,19 +1,23 @@
 import { useState, useRef, useEffect, useCallback } from 'react';
 import { motion } from 'framer-motion';
-import { FaCog, FaSave, FaCheck, FaFileImport, FaFileExport, FaChevronDown, FaExclamationTriangle } from 'react-icons/fa';
+import { FaCog, FaSave, FaCheck, FaFileImport, FaFileExport, FaChevronDown, FaExclamationTriangle, FaHistory } from 'react-icons/fa';
 import { MdArrowBack } from 'react-icons/md';
 import { useNavigate } from 'react-router-dom';
 import { invoke } from '@tauri-apps/api/core';
 import { open, save } from '@tauri-apps/plugin-dialog';
-import { readFile, writeFile } from '@tauri-apps/plugin-fs';
-import { Settings as SettingsType } from '../types';
+import { readFile } from '@tauri-apps/plugin-fs';
+import { listen } from '@tauri-apps/api/event';
+import { Settings as SettingsType, BackupSnapshot, BackupProgress } from '../types';
 import ThemeToggle from '../components/ThemeToggle';
+import { errorMessage } from '../errors';
+import { parseRate } from '../tax';
 
 interface FormErrors {
   restaurant_name?: string;
   email?: string;
   phone?: string;
   tax_rate?: string;
+  reduced_tax_rate?: string;
   opening_time?: string;
   closing_time?: string;
 }
@@ -281,6 +285,8 @@ export default function Settings() {
     phone: '',
     email: '',
     tax_rate: '',
+    reduced_tax_rate: '',
+    prices_include_tax: true,
     currency: 'PKR',
     opening_time: '09:00',
     closing_time: '22:00',
@@ -295,6 +301,10 @@ export default function Settings() {
   const [isImporting, setIsImporting] = useState(false);
   const [isExporting, setIsExporting] = useState(false);
   const [isUploadingLogo, setIsUploadingLogo] = useState(false);
+  const [backups, setBackups] = useState<BackupSnapshot[]>([]);
+  const [restoringBackup, setRestoringBackup] = useState<string | null>(null);
+  const [backupPassword, setBackupPassword] = useState('');
+  const [backupProgress, setBackupProgress] = useState<BackupProgress | null>(null);
   const [errors, setErrors] = useState<FormErrors>({});
   const [submitStatus, setSubmitStatus] = useState<'idle' | 'success' | 'error'>('idle');
   const [errorMessage, setErrorMessage] = useState('');
@@ -339,14 +349,12 @@ export default function Settings() {
       newErrors.phone = 'Please enter a valid phone number';
     }
 
-    // Validate tax rate
-    if (settings.tax_rate && settings.tax_rate.trim()) {
-      const taxRate = parseFloat(settings.tax_rate);
-      if (isNaN(taxRate)) {
-        newErrors.tax_rate = 'Tax rate must be a number';
-      } else if (taxRate < 0 || taxRate > 100) {
-        newErrors.tax_rate = 'Tax rate must be between 0 and 100';
-      }
+    // Validate tax rates the way the backend does
+    if (settings.tax_rate && settings.tax_rate.trim() && parseRate(settings.tax_rate) === null) {
+      newErrors.tax_rate = 'Tax rate must be between 0 and 100, with at most two decimals';
+    }
+    if (settings.reduced_tax_rate && settings.reduced_tax_rate.trim() && parseRate(settings.reduced_tax_rate) === null) {
+      newErrors.reduced_tax_rate = 'Tax rate must be between 0 and 100, with at most two decimals';
     }
 
     // Validate times
@@ -407,7 +415,7 @@ export default function Settings() {
       }
     } catch (error) {
       console.error('Error selecting logo:', error);
-      alert(`Error selecting logo: ${error instanceof Error ? error.message : String(error)}`);
+      alert(`Error selecting logo: ${errorMessage(error)}`);
     } finally {
       setIsUploadingLogo(false);
     }
@@ -442,7 +450,7 @@ export default function Settings() {
     } catch (error) {
       console.error('Error saving settings:', error);
       setSubmitStatus('error');
-      setErrorMessage(error instanceof Error ? error.message : 'Error saving settings');
+      setErrorMessage(errorMessage(error) || 'Error saving settings');
       setTimeout(() => {
         setSubmitStatus('idle');
         setErrorMessage('');
@@ -452,38 +460,50 @@ export default function Settings() {
     }
   };
 
+  // Export/import run on a background thread in the backend and report
+  // progress through `backup-progress` events while the command is pending
+  const withBackupProgress = async (job: () => Promise<void>) => {
+    const unlisten = await listen<BackupProgress>('backup-progress', (event) => {
+      setBackupProgress(event.payload);
+    });
+    try {
+      await job();
+    } finally {
+      unlisten();
+      setBackupProgress(null);
+    }
+  };
+
+  const progressLabel = (label: string) => {
+    if (!backupProgress || backupProgress.bytes_total === 0) {
+      return `${label}...`;
+    }
+    const percent = Math.floor((backupProgress.bytes_done / backupProgress.bytes_total) * 100);
+    return `${label}... ${percent}%`;
+  };
+
   const handleImportDatabase = async () => {
     setIsImporting(true);
     try {
       const file = await open({
         multiple: false,
         filters: [{
-          name: 'Database',
-          extensions: ['db']
+          name: 'Backup',
+          extensions: ['rmsbackup', 'db']
         }]
       });
 
       if (file) {
-        const contents = await readFile(file as string);
-        
-        // Convert Uint8Array to base64 using a more efficient method
-        // Process in chunks to avoid stack overflow
-        let binary = '';
-        const chunkSize = 8192;
-        for (let i = 0; i < contents.length; i += chunkSize) {
-          const chunk = contents.slice(i, i + chunkSize);
-          binary += String.fromCharCode.apply(null, Array.from(chunk));
-        }
-        const base64 = btoa(binary);
-        
-        await invoke('import_database_cmd', { data: base64 });
+        await withBackupProgress(() =>
+          invoke('import_database_from_file', { path: file, password: backupPassword })
+        );
         setIsSuccess(true);
         setTimeout(() => setIsSuccess(false), 3000);
         loadSettings();
       }
     } catch (error) {
       console.error('Error importing database:', error);
-      alert(`Error importing database: ${error instanceof Error ? error.message : String(error)}`);
+      alert(`Error importing database: ${errorMessage(error)}`);
     } finally {
       setIsImporting(false);
     }
@@ -492,37 +512,65 @@ export default function Settings() {
   const handleExportDatabase = async () => {
     setIsExporting(true);
     try {
-      const base64Data = await invoke<string>('export_database_cmd');
-      
       const date = new Date().toISOString().split('T')[0];
       const filePath = await save({
-        defaultPath: `restaurant-database-${date}.db`,
+        defaultPath: `restaurant-database-${date}.rmsbackup`,
         filters: [{
-          name: 'Database',
-          extensions: ['db']
+          name: 'Backup',
+          extensions: ['rmsbackup']
         }]
       });
 
       if (filePath) {
-        // Decode base64 to binary
-        const binary = atob(base64Data);
-        const bytes = new Uint8Array(binary.length);
-        for (let i = 0; i < binary.length; i++) {
-          bytes[i] = binary.charCodeAt(i);
-        }
-        
-        await writeFile(filePath, bytes);
+        await withBackupProgress(() =>
+          invoke('export_database_to_file', { path: filePath, password: backupPassword })
+        );
         setIsSuccess(true);
         setTimeout(() => setIsSuccess(false), 3000);
       }
     } catch (error) {
       console.error('Error exporting database:', error);
-      alert(`Error exporting database: ${error instanceof Error ? error.message : String(error)}`);
+      alert(`Error exporting database: ${errorMessage(error)}`);
     } finally {
       setIsExporting(false);
     }
   };
 
+  const handleKeepChange = (e: React.ChangeEvent<HTMLInputElement>) => {
+    const { name, value } = e.target;
+    setSettings(prev => ({
+      ...prev,
+      [name]: value === '' ? null : Math.max(0, Math.floor(Number(value)))
+    }));
+  };
+
+  const loadBackups = useCallback(async () => {
+    try {
+      setBackups(await invoke<BackupSnapshot[]>('list_backups'));
+    } catch (error) {
+      console.error('Error loading backups:', error);
+    }
+  }, []);
+
+  const handleRestoreBackup = async (backup: BackupSnapshot) => {
+    if (!confirm(`Restore the backup from ${backup.created_at}? This will replace current data.`)) {
+      return;
+    }
+    setRestoringBackup(backup.file_name);
+    try {
+      await withBackupProgress(() => invoke('restore_backup', { fileName: backup.file_name }));
+      setIsSuccess(true);
+      setTimeout(() => setIsSuccess(false), 3000);
+      loadSettings();
+      loadBackups();
+    } catch (error) {
+      console.error('Error restoring backup:', error);
+      alert(`Error restoring backup: ${errorMessage(error)}`);
+    } finally {
+      setRestoringBackup(null);
+    }
+  };
+
   const loadSettings = useCallback(async () => {
     try {
       const loadedSettings = await invoke<SettingsType>('get_settings');
@@ -542,7 +590,8 @@ export default function Settings() {
 
   useEffect(() => {
     loadSettings();
-  }, [loadSettings]);
+    loadBackups();
+  }, [loadSettings, loadBackups]);
 
   return (
     <div className="min-h-screen bg-linear-to-br from-slate-100 via-purple-100 to-slate-100 
@@ -766,6 +815,44 @@ export default function Settings() {
                   )}
                 </div>
 
+                <div>
+                  <label className="block text-slate-700 dark:text-gray-300 mb-2">Reduced Tax Rate (%)</label>
+                  <input
+                    type="number"
+                    name="reduced_tax_rate"
+                    value={settings.reduced_tax_rate ?? ''}
+                    onChange={handleChange}
+                    step="0.01"
+                    min="0"
+                    max="100"
+                    className={`w-full px-4 py-2 rounded-lg bg-white/50 dark:bg-white/5 border 
+                      text-slate-900 dark:text-white focus:outline-none transition-colors ${
+                        errors.reduced_tax_rate 
+                          ? 'border-red-500 focus:border-red-500' 
+                          : 'border-slate-300 dark:border-gray-600 focus:border-teal-400'
+                      }`}
+                  />
+                  {errors.reduced_tax_rate && (
+                    <p className="mt-1 text-sm text-red-400 flex items-center gap-1">
+                      <FaExclamationTriangle className="text-xs" />
+                      {errors.reduced_tax_rate}
+                    </p>
+                  )}
+                  <p className="mt-1 text-sm text-slate-500 dark:text-gray-400">
+                    For products set to the reduced rate; exempt products are never taxed.
+                  </p>
+                </div>
+
+                <label className="flex items-center gap-2 text-slate-700 dark:text-gray-300 cursor-pointer">
+                  <input
+                    type="checkbox"
+                    checked={settings.prices_include_tax !== false}
+                    onChange={(e) => setSettings(prev => ({ ...prev, prices_include_tax: e.target.checked }))}
+                    className="accent-teal-500"
+                  />
+                  Prices include tax
+                </label>
+
                 <div>
                   <label className="block text-slate-700 dark:text-gray-300 mb-2">Currency</label>
                   <CurrencyDropdown
@@ -836,6 +923,90 @@ export default function Settings() {
             </div>
           </div>
 
+          {/* Automatic Backups */}
+          <div className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-2xl p-6 md:p-8 mb-8 transition-colors duration-300">
+            <h2 className="text-xl font-semibold text-slate-900 dark:text-white mb-2">Automatic Backups</h2>
+            <p className="text-sm text-slate-500 dark:text-gray-400 mb-6">
+              A backup is taken every hour during opening hours and once a day otherwise.
+            </p>
+
+            <div className="grid grid-cols-1 md:grid-cols-3 gap-6 mb-6">
+              <div className="md:col-span-3">
+                <label className="block text-slate-700 dark:text-gray-300 mb-2">Backup Folder</label>
+                <input
+                  type="text"
+                  name="backup_directory"
+                  value={settings.backup_directory ?? ''}
+                  onChange={handleChange}
+                  className="w-full px-4 py-2 rounded-lg bg-white/50 dark:bg-white/5 border 
+                    border-slate-300 dark:border-gray-600 text-slate-900 dark:text-white 
+                    focus:outline-none focus:border-teal-400 transition-colors
+                    placeholder:text-slate-400 dark:placeholder:text-gray-400"
+                  placeholder="Default: backups folder next to the database"
+                />
+              </div>
+
+              <div>
+                <label className="block text-slate-700 dark:text-gray-300 mb-2">Daily Backups to Keep</label>
+                <input
+                  type="number"
+                  min="0"
+                  name="backup_keep_daily"
+                  value={settings.backup_keep_daily ?? ''}
+                  onChange={handleKeepChange}
+                  className="w-full px-4 py-2 rounded-lg bg-white/50 dark:bg-white/5 border 
+                    border-slate-300 dark:border-gray-600 text-slate-900 dark:text-white 
+                    focus:outline-none focus:border-teal-400 transition-colors
+                    placeholder:text-slate-400 dark:placeholder:text-gray-400"
+                  placeholder="7"
+                />
+              </div>
+
+              <div>
+                <label className="block text-slate-700 dark:text-gray-300 mb-2">Weekly Backups to Keep</label>
+                <input
+                  type="number"
+                  min="0"
+                  name="backup_keep_weekly"
+                  value={settings.backup_keep_weekly ?? ''}
+                  onChange={handleKeepChange}
+                  className="w-full px-4 py-2 rounded-lg bg-white/50 dark:bg-white/5 border 
+                    border-slate-300 dark:border-gray-600 text-slate-900 dark:text-white 
+                    focus:outline-none focus:border-teal-400 transition-colors
+                    placeholder:text-slate-400 dark:placeholder:text-gray-400"
+                  placeholder="4"
+                />
+              </div>
+            </div>
+
+            {backups.length === 0 ? (
+              <p className="text-sm text-slate-500 dark:text-gray-400">No automatic backups yet.</p>
+            ) : (
+              <ul className="divide-y divide-slate-200 dark:divide-gray-700 max-h-64 overflow-y-auto">
+                {backups.map(backup => (
+                  <li key={backup.file_name} className="flex items-center justify-between py-2 gap-4">
+                    <div>
+                      <p className="text-slate-900 dark:text-white">{backup.created_at}</p>
+                      <p className="text-xs text-slate-500 dark:text-gray-400">
+                        {(backup.size / 1024).toFixed(0)} KB
+                      </p>
+                    </div>
+                    <button
+                      type="button"
+                      onClick={() => handleRestoreBackup(backup)}
+                      disabled={restoringBackup !== null}
+                      className="px-3 py-1 rounded-lg bg-blue-500 hover:bg-blue-600 text-white text-sm
+                        flex items-center gap-2 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+                    >
+                      <FaHistory />
+                      {restoringBackup === backup.file_name ? 'Restoring...' : 'Restore'}
+                    </button>
+                  </li>
+                ))}
+              </ul>
+            )}
+          </div>
+
           {/* Save and Back Buttons */}
           <div className="flex gap-4 mb-8">
             <motion.button
@@ -890,6 +1061,22 @@ export default function Settings() {
             </motion.button>
           </div>
 
+          {/* Backup Password */}
+          <div className="mb-4">
+            <label className="block text-slate-700 dark:text-gray-300 mb-2">Backup Password (optional)</label>
+            <input
+              type="password"
+              value={backupPassword}
+              onChange={(e) => setBackupPassword(e.target.value)}
+              autoComplete="new-password"
+              className="w-full px-4 py-2 rounded-lg bg-white/50 dark:bg-white/5 border 
+                border-slate-300 dark:border-gray-600 text-slate-900 dark:text-white 
+                focus:outline-none focus:border-teal-400 transition-colors
+                placeholder:text-slate-400 dark:placeholder:text-gray-400"
+              placeholder="Encrypts exports; needed to import an encrypted backup"
+            />
+          </div>
+
           {/* Import/Export Buttons */}
           <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
             <motion.button
@@ -909,7 +1096,7 @@ export default function Settings() {
                     transition={{ duration: 1, repeat: Infinity, ease: "linear" }}
                     className="w-5 h-5 border-2 border-white border-t-transparent rounded-full"
                   />
-                  <span>Importing...</span>
+                  <span>{progressLabel('Importing')}</span>
                 </>
               ) : (
                 <>
@@ -936,7 +1123,7 @@ export default function Settings() {
                     transition={{ duration: 1, repeat: Infinity, ease: "linear" }}
                     className="w-5 h-5 border-2 border-white border-t-transparent rounded-full"
                   />
-                  <span>Exporting...</span>
+                  <span>{progressLabel('Exporting')}</span>
                 </>
               ) : (
                 <>
@@ -0,0 +1,403 @@
+import { motion } from 'framer-motion';
+import { useState, useEffect } from 'react';
+import { FaPlus, FaTrash, FaClipboardList } from 'react-icons/fa';
+import { MdArrowBack } from 'react-icons/md';
+import { useNavigate } from 'react-router-dom';
+import { invoke } from '@tauri-apps/api/core';
+import { CountEntry, Ingredient, StockCount, StockCountStatus, VarianceReport } from '../types';
+import ThemeToggle from '../components/ThemeToggle';
+import { errorMessage } from '../errors';
+import { formatAmount } from '../money';
+
+const statusLabels: Record<StockCountStatus, string> = {
+  open: 'Counting',
+  closed: 'Closed',
+  applied: 'Applied'
+};
+
+const formatQuantity = (quantity: number) => String(Number(quantity.toFixed(3)));
+
+const formatDate = (timestamp: string) => new Date(`${timestamp.replace(' ', 'T')}Z`).toLocaleString();
+
+export default function StockCounts() {
+  const navigate = useNavigate();
+  const [isNavigating, setIsNavigating] = useState(false);
+  const [counts, setCounts] = useState<StockCount[]>([]);
+  const [ingredients, setIngredients] = useState<Ingredient[]>([]);
+  const [isLoading, setIsLoading] = useState(true);
+  const [isSubmitting, setIsSubmitting] = useState(false);
+  const [note, setNote] = useState('');
+  // What has been typed for the open count, by ingredient id
+  const [counted, setCounted] = useState<Record<number, string>>({});
+  const [report, setReport] = useState<VarianceReport | null>(null);
+
+  const openCount = counts.find(count => count.status === 'open') ?? null;
+
+  const handleBackNavigation = () => {
+    setIsNavigating(true);
+    setTimeout(() => {
+      navigate('/inventory');
+    }, 300);
+  };
+
+  const loadData = async () => {
+    try {
+      const [countsRes, ingredientsRes] = await Promise.all([
+        invoke<StockCount[]>('get_stock_counts'),
+        invoke<Ingredient[]>('get_ingredients')
+      ]);
+      setCounts(countsRes);
+      setIngredients(ingredientsRes);
+      const open = countsRes.find(count => count.status === 'open');
+      const values: Record<number, string> = {};
+      open?.lines.forEach(line => {
+        values[line.ingredient_id] = formatQuantity(line.counted_quantity);
+      });
+      setCounted(values);
+    } catch (error) {
+      console.error('Error loading stock takes:', error);
+    } finally {
+      setIsLoading(false);
+    }
+  };
+
+  useEffect(() => {
+    loadData();
+  }, []);
+
+  const handleOpenCount = async () => {
+    setIsSubmitting(true);
+    try {
+      await invoke('open_stock_count', { note: note || null });
+      setNote('');
+      await loadData();
+    } catch (error) {
+      alert(`Error starting stock take: ${errorMessage(error)}`);
+    } finally {
+      setIsSubmitting(false);
+    }
+  };
+
+  // Sends every ingredient; blank ones are left out of the count
+  const saveCounts = async (count: StockCount) => {
+    const entries: CountEntry[] = ingredients.map(ingredient => {
+      const value = counted[ingredient.id]?.trim() ?? '';
+      return { ingredient_id: ingredient.id, counted: value === '' ? null : Number(value) };
+    });
+    await invoke('set_counted_quantities', { id: count.id, entries });
+  };
+
+  const handleSaveCounts = async () => {
+    if (!openCount) return;
+    setIsSubmitting(true);
+    try {
+      await saveCounts(openCount);
+      await loadData();
+    } catch (error) {
+      alert(`Error saving counts: ${errorMessage(error)}`);
+    } finally {
+      setIsSubmitting(false);
+    }
+  };
+
+  const handleCloseCount = async () => {
+    if (!openCount) return;
+    if (!confirm('Close this stock take? Counts can no longer be changed.')) return;
+    setIsSubmitting(true);
+    try {
+      await saveCounts(openCount);
+      const closed = await invoke<VarianceReport>('close_stock_count', { id: openCount.id });
+      setReport(closed);
+      await loadData();
+    } catch (error) {
+      alert(`Error closing stock take: ${errorMessage(error)}`);
+    } finally {
+      setIsSubmitting(false);
+    }
+  };
+
+  const openReport = async (count: StockCount) => {
+    try {
+      setReport(await invoke<VarianceReport>('get_variance_report', { id: count.id }));
+    } catch (error) {
+      alert(`Error loading variance: ${errorMessage(error)}`);
+    }
+  };
+
+  const handleApply = async () => {
+    if (!report) return;
+    if (!confirm('Correct stock levels by these variances?')) return;
+    setIsSubmitting(true);
+    try {
+      setReport(await invoke<VarianceReport>('apply_stock_count', { id: report.count.id }));
+      await loadData();
+    } catch (error) {
+      alert(`Error applying stock take: ${errorMessage(error)}`);
+    } finally {
+      setIsSubmitting(false);
+    }
+  };
+
+  const handleDelete = async (count: StockCount) => {
+    if (!confirm(`Discard stock take #${count.id}?`)) return;
+    try {
+      await invoke('delete_stock_count', { id: count.id });
+      setReport(null);
+      await loadData();
+    } catch (error) {
+      alert(`Error discarding stock take: ${errorMessage(error)}`);
+    }
+  };
+
+  const inputClass = `w-full px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white
+    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none`;
+
+  return (
+    <div className="min-h-screen bg-linear-to-br from-slate-100 via-purple-100 to-slate-100
+      dark:from-slate-900 dark:via-purple-900 dark:to-slate-900 transition-colors duration-300">
+      {/* Theme Toggle */}
+      <div className="absolute safe-top safe-right z-50">
+        <ThemeToggle />
+      </div>
+      <div className="max-w-7xl mx-auto px-4 py-4 sm:py-8">
+        {/* Header */}
+        <div className="flex flex-col sm:flex-row items-center gap-4 sm:gap-0 sm:justify-between mb-6 sm:mb-8">
+          <motion.button
+            whileHover={{ scale: 1.05 }}
+            whileTap={{ scale: 0.95 }}
+            onClick={handleBackNavigation}
+            className="flex items-center text-slate-900 dark:text-white gap-2
+              bg-purple-200 dark:bg-purple-600/30 px-4 py-2 rounded-lg min-w-[120px] justify-center
+              transition-colors duration-300"
+            disabled={isNavigating}
+          >
+            {isNavigating ? (
+              <>
+                <motion.div
+                  animate={{ rotate: 360 }}
+                  transition={{ duration: 1, repeat: Infinity, ease: "linear" }}
+                  className="w-5 h-5 border-2 border-white border-t-transparent rounded-full"
+                />
+                <span>Loading...</span>
+              </>
+            ) : (
+              <>
+                <MdArrowBack className="w-5 h-5" />
+                <span>Inventory</span>
+              </>
+            )}
+          </motion.button>
+          <motion.h1
+            initial={{ opacity: 0, y: -20 }}
+            animate={{ opacity: 1, y: 0 }}
+            className="text-2xl sm:text-3xl font-bold text-slate-900 dark:text-white order-first sm:order-0 transition-colors duration-300"
+          >
+            Stock Take
+          </motion.h1>
+          <div className="hidden sm:block w-24" />
+        </div>
+
+        {/* Open Count */}
+        {!isLoading && !openCount && (
+          <div className="flex flex-col sm:flex-row gap-4 mb-6 sm:mb-8">
+            <input
+              type="text"
+              value={note}
+              onChange={(e) => setNote(e.target.value)}
+              placeholder="Note (optional), e.g. month-end count"
+              className={`${inputClass} flex-1`}
+              disabled={isSubmitting}
+            />
+            <motion.button
+              whileHover={{ scale: 1.02 }}
+              whileTap={{ scale: 0.98 }}
+              onClick={handleOpenCount}
+              className="bg-linear-to-r from-teal-400 to-teal-500 dark:from-teal-500 dark:to-teal-600
+                text-white rounded-xl py-3 px-6 flex items-center justify-center gap-2 shadow-lg hover:shadow-xl
+                disabled:opacity-50"
+              disabled={isSubmitting}
+            >
+              <FaPlus />
+              <span>Start stock take</span>
+            </motion.button>
+          </div>
+        )}
+
+        {openCount && (
+          <motion.div
+            initial={{ opacity: 0 }}
+            animate={{ opacity: 1 }}
+            className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl p-4 sm:p-6 mb-6 sm:mb-8
+              text-slate-900 dark:text-white transition-colors duration-300"
+          >
+            <h2 className="text-lg sm:text-xl font-semibold">
+              Stock take #{openCount.id}{openCount.note && ` · ${openCount.note}`}
+            </h2>
+            <p className="text-sm text-slate-600 dark:text-white/70 mb-4">
+              Started {formatDate(openCount.opened_at)}. Enter what is on the shelf; leave an ingredient blank to skip it.
+            </p>
+            <div className="space-y-2">
+              {ingredients.map(ingredient => (
+                <div key={ingredient.id} className="grid grid-cols-12 gap-4 items-center">
+                  <div className="col-span-7 sm:col-span-8 font-medium">{ingredient.name}</div>
+                  <div className="col-span-5 sm:col-span-4 flex items-center gap-2">
+                    <input
+                      type="number"
+                      min="0"
+                      step="any"
+                      value={counted[ingredient.id] ?? ''}
+                      onChange={(e) => setCounted({ ...counted, [ingredient.id]: e.target.value })}
+                      className={inputClass}
+                      disabled={isSubmitting}
+                    />
+                    <span className="text-sm text-slate-500 dark:text-white/60">{ingredient.unit}</span>
+                  </div>
+                </div>
+              ))}
+            </div>
+            <div className="flex flex-col sm:flex-row gap-4 mt-6">
+              <button
+                onClick={() => handleDelete(openCount)}
+                className="px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 text-red-500
+                  hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+                disabled={isSubmitting}
+              >
+                Discard
+              </button>
+              <button
+                onClick={handleSaveCounts}
+                className="flex-1 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 text-slate-900 dark:text-white
+                  hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+                disabled={isSubmitting}
+              >
+                Save counts
+              </button>
+              <button
+                onClick={handleCloseCount}
+                className="flex-1 px-4 py-2 rounded-lg bg-teal-500 text-white hover:bg-teal-400 transition-colors disabled:opacity-50"
+                disabled={isSubmitting}
+              >
+                {isSubmitting ? 'Saving...' : 'Close and see variance'}
+              </button>
+            </div>
+          </motion.div>
+        )}
+
+        {/* Past Counts */}
+        <motion.div
+          initial={{ opacity: 0 }}
+          animate={{ opacity: 1 }}
+          className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl overflow-hidden transition-colors duration-300"
+        >
+          {counts.filter(count => count.status !== 'open').map(count => (
+            <div
+              key={count.id}
+              className="flex justify-between items-center gap-4 p-4 border-b border-slate-300 dark:border-white/10
+                text-slate-900 dark:text-white hover:bg-slate-100 dark:hover:bg-white/5 transition-colors"
+            >
+              <div>
+                <div className="font-medium">
+                  #{count.id} · {statusLabels[count.status]}{count.note && ` · ${count.note}`}
+                </div>
+                <div className="text-sm text-slate-500 dark:text-white/60">
+                  {count.lines.length} ingredient(s) · closed {count.closed_at && formatDate(count.closed_at)}
+                </div>
+              </div>
+              <div className="flex items-center gap-4">
+                <button onClick={() => openReport(count)} className="text-teal-500 hover:text-teal-400" title="Variance report">
+                  <FaClipboardList />
+                </button>
+                {count.status === 'closed' && (
+                  <button onClick={() => handleDelete(count)} className="text-red-400 hover:text-red-300" title="Discard">
+                    <FaTrash />
+                  </button>
+                )}
+              </div>
+            </div>
+          ))}
+          {!isLoading && counts.every(count => count.status === 'open') && (
+            <div className="p-6 sm:p-8 text-center text-slate-600 dark:text-white/60">No finished stock takes yet.</div>
+          )}
+        </motion.div>
+      </div>
+
+      {/* Variance Report Modal */}
+      {report && (
+        <div className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center p-4 z-50">
+          <motion.div
+            initial={{ opacity: 0, scale: 0.9 }}
+            animate={{ opacity: 1, scale: 1 }}
+            className="bg-white dark:bg-slate-800 rounded-xl p-4 sm:p-6 w-full max-w-5xl max-h-[90vh] overflow-y-auto"
+          >
+            <h2 className="text-xl sm:text-2xl font-bold text-slate-900 dark:text-white mb-4">
+              Variance · stock take #{report.count.id} ({statusLabels[report.count.status]})
+            </h2>
+            <div className="overflow-x-auto">
+              <table className="w-full text-sm text-slate-900 dark:text-white">
+                <thead>
+                  <tr className="text-left border-b border-slate-300 dark:border-white/10">
+                    <th className="py-2 pr-4">Ingredient</th>
+                    <th className="py-2 pr-4 text-right">Opening</th>
+                    <th className="py-2 pr-4 text-right">Received</th>
+                    <th className="py-2 pr-4 text-right">Used</th>
+                    <th className="py-2 pr-4 text-right">Wasted</th>
+                    <th className="py-2 pr-4 text-right">Adjusted</th>
+                    <th className="py-2 pr-4 text-right">Expected</th>
+                    <th className="py-2 pr-4 text-right">Counted</th>
+                    <th className="py-2 pr-4 text-right">Variance</th>
+                    <th className="py-2 text-right">Value</th>
+                  </tr>
+                </thead>
+                <tbody>
+                  {report.lines.map(line => (
+                    <tr key={line.ingredient_id} className="border-b border-slate-200 dark:border-white/10">
+                      <td className="py-2 pr-4 font-medium">{line.name} <span className="text-slate-500 dark:text-white/60">({line.unit})</span></td>
+                      <td className="py-2 pr-4 text-right">{formatQuantity(line.opening)}</td>
+                      <td className="py-2 pr-4 text-right">{formatQuantity(line.received)}</td>
+                      <td className="py-2 pr-4 text-right">{formatQuantity(line.used)}</td>
+                      <td className="py-2 pr-4 text-right">{formatQuantity(line.wasted)}</td>
+                      <td className="py-2 pr-4 text-right">{formatQuantity(line.adjusted)}</td>
+                      <td className="py-2 pr-4 text-right">{formatQuantity(line.expected)}</td>
+                      <td className="py-2 pr-4 text-right">{formatQuantity(line.counted)}</td>
+                      <td className={`py-2 pr-4 text-right ${line.variance < 0 ? 'text-red-500' : line.variance > 0 ? 'text-teal-600 dark:text-teal-400' : ''}`}>
+                        {line.variance > 0 ? '+' : ''}{formatQuantity(line.variance)}
+                      </td>
+                      <td className="py-2 text-right">
+                        {line.variance_value ? `${line.variance_value.currency} ${formatAmount(line.variance_value)}` : '—'}
+                      </td>
+                    </tr>
+                  ))}
+                </tbody>
+              </table>
+            </div>
+            <div className="mt-4 text-right font-semibold text-slate-900 dark:text-white">
+              Total variance:{' '}
+              {report.total_variance_value.length > 0
+                ? report.total_variance_value.map(total => `${total.currency} ${formatAmount(total)}`).join(' · ')
+                : 'no costed ingredients'}
+            </div>
+            <div className="flex gap-4 mt-6">
+              <button
+                onClick={() => setReport(null)}
+                className="flex-1 px-4 py-2 rounded-lg bg-slate-200 dark:bg-slate-700 text-slate-900 dark:text-white
+                  hover:bg-slate-300 dark:hover:bg-slate-600 transition-colors"
+                disabled={isSubmitting}
+              >
+                Close
+              </button>
+              {report.count.status === 'closed' && (
+                <button
+                  onClick={handleApply}
+                  className="flex-1 px-4 py-2 rounded-lg bg-teal-500 text-white hover:bg-teal-400 transition-colors disabled:opacity-50"
+                  disabled={isSubmitting}
+                >
+                  {isSubmitting ? 'Applying...' : 'Apply adjustments'}
+                </button>
+              )}
+            </div>
+          </motion.div>
+        </div>
+      )}
+    </div>
+  );
+}
@@ -5,6 +5,7 @@ import { MdArrowBack } from 'react-icons/md';
 import { useNavigate } from 'react-router-dom';
 import { invoke } from '@tauri-apps/api/core';
 import ThemeToggle from '../components/ThemeToggle';
+import { errorMessage } from '../errors';
 
 interface SupportMessage {
   name: string;
@@ -122,7 +123,7 @@ export default function Support() {
     } catch (error) {
       console.error('Error sending message:', error);
       setSubmitStatus('error');
-      setErrorMessage(error as string || 'Failed to send message. Please try again.');
+      setErrorMessage(errorMessage(error) || 'Failed to send message. Please try again.');
       
       setTimeout(() => {
         setSubmitStatus('idle');
@@ -0,0 +1,209 @@
+import { motion } from 'framer-motion';
+import { useState, useEffect } from 'react';
+import { MdArrowBack, MdFileDownload } from 'react-icons/md';
+import { useNavigate } from 'react-router-dom';
+import { invoke } from '@tauri-apps/api/core';
+import { save } from '@tauri-apps/plugin-dialog';
+import { TaxReport as TaxReportType } from '../types';
+import ThemeToggle from '../components/ThemeToggle';
+import { errorMessage } from '../errors';
+import { formatAmount } from '../money';
+import { taxClassLabels } from '../tax';
+
+// Dates are UTC, like the database
+const isoDate = (date: Date) => date.toISOString().slice(0, 10);
+
+export default function TaxReport() {
+  const navigate = useNavigate();
+  const [isNavigating, setIsNavigating] = useState(false);
+  const [report, setReport] = useState<TaxReportType | null>(null);
+  // Defaults to the month so far
+  const [range, setRange] = useState(() => {
+    const end = new Date();
+    const start = new Date(Date.UTC(end.getUTCFullYear(), end.getUTCMonth(), 1));
+    return { start: isoDate(start), end: isoDate(end) };
+  });
+  const [isExporting, setIsExporting] = useState(false);
+
+  const handleBackNavigation = () => {
+    setIsNavigating(true);
+    setTimeout(() => {
+      navigate('/transactions');
+    }, 300);
+  };
+
+  useEffect(() => {
+    invoke<TaxReportType>('get_tax_report', { startDate: range.start, endDate: range.end })
+      .then(setReport)
+      .catch(error => alert(`Error loading tax report: ${errorMessage(error)}`));
+  }, [range]);
+
+  const handleExport = async () => {
+    setIsExporting(true);
+    try {
+      const filePath = await save({
+        defaultPath: `tax-report-${range.start}-to-${range.end}.csv`,
+        filters: [{
+          name: 'CSV',
+          extensions: ['csv']
+        }]
+      });
+      if (filePath) {
+        await invoke('export_tax_report_csv', { startDate: range.start, endDate: range.end, path: filePath });
+      }
+    } catch (error) {
+      alert(`Error exporting tax report: ${errorMessage(error)}`);
+    } finally {
+      setIsExporting(false);
+    }
+  };
+
+  const inputClass = `px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white
+    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none`;
+
+  return (
+    <div className="min-h-screen bg-linear-to-br from-slate-100 via-purple-100 to-slate-100
+      dark:from-slate-900 dark:via-purple-900 dark:to-slate-900 transition-colors duration-300">
+      {/* Theme Toggle */}
+      <div className="absolute safe-top safe-right z-50">
+        <ThemeToggle />
+      </div>
+      <div className="max-w-7xl mx-auto px-4 py-4 sm:py-8">
+        {/* Header */}
+        <div className="flex flex-col sm:flex-row items-center gap-4 sm:gap-0 sm:justify-between mb-6 sm:mb-8">
+          <motion.button
+            whileHover={{ scale: 1.05 }}
+            whileTap={{ scale: 0.95 }}
+            onClick={handleBackNavigation}
+            className="flex items-center text-slate-900 dark:text-white gap-2
+              bg-purple-200 dark:bg-purple-600/30 px-4 py-2 rounded-lg min-w-[120px] justify-center
+              transition-colors duration-300"
+            disabled={isNavigating}
+          >
+            {isNavigating ? (
+              <>
+                <motion.div
+                  animate={{ rotate: 360 }}
+                  transition={{ duration: 1, repeat: Infinity, ease: "linear" }}
+                  className="w-5 h-5 border-2 border-white border-t-transparent rounded-full"
+                />
+                <span>Loading...</span>
+              </>
+            ) : (
+              <>
+                <MdArrowBack className="w-5 h-5" />
+                <span>Transactions</span>
+              </>
+            )}
+          </motion.button>
+          <motion.h1
+            initial={{ opacity: 0, y: -20 }}
+            animate={{ opacity: 1, y: 0 }}
+            className="text-2xl sm:text-3xl font-bold text-slate-900 dark:text-white order-first sm:order-0 transition-colors duration-300"
+          >
+            Tax Report
+          </motion.h1>
+          <motion.button
+            whileHover={{ scale: 1.05 }}
+            whileTap={{ scale: 0.95 }}
+            onClick={handleExport}
+            className="flex items-center text-slate-900 dark:text-white gap-2
+              bg-purple-200 dark:bg-purple-600/30 px-4 py-2 rounded-lg
+              transition-colors duration-300 disabled:opacity-50"
+            disabled={isExporting || !report}
+          >
+            <MdFileDownload className="w-5 h-5" />
+            <span>{isExporting ? 'Exporting...' : 'Export CSV'}</span>
+          </motion.button>
+        </div>
+
+        {/* Period */}
+        <div className="flex flex-col sm:flex-row items-center gap-4 mb-6 text-slate-900 dark:text-white">
+          <label className="flex items-center gap-2">
+            From
+            <input
+              type="date"
+              value={range.start}
+              onChange={(e) => e.target.value && setRange({ ...range, start: e.target.value })}
+              className={inputClass}
+            />
+          </label>
+          <label className="flex items-center gap-2">
+            to
+            <input
+              type="date"
+              value={range.end}
+              onChange={(e) => e.target.value && setRange({ ...range, end: e.target.value })}
+              className={inputClass}
+            />
+          </label>
+        </div>
+
+        {/* Report */}
+        {report && (
+          <motion.div
+            initial={{ opacity: 0 }}
+            animate={{ opacity: 1 }}
+            className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl p-4 sm:p-6 text-slate-900 dark:text-white
+              transition-colors duration-300 overflow-x-auto"
+          >
+            <table className="w-full text-left">
+              <thead>
+                <tr className="border-b border-slate-300 dark:border-white/10">
+                  <th className="py-2 pr-4">Tax class</th>
+                  <th className="py-2 pr-4 text-right">Rate</th>
+                  <th className="py-2 pr-4 text-right">Taxable sales</th>
+                  <th className="py-2 pr-4 text-right">Exempt sales</th>
+                  <th className="py-2 pr-4 text-right">Tax collected</th>
+                  <th className="py-2 pr-4 text-right">Refunds</th>
+                  <th className="py-2 text-right">Tax refunded</th>
+                </tr>
+              </thead>
+              <tbody>
+                {report.lines.map(line => (
+                  <tr key={`${line.tax_class}-${line.rate}`} className="border-b border-slate-300 dark:border-white/10">
+                    <td className="py-2 pr-4">{taxClassLabels[line.tax_class]}</td>
+                    <td className="py-2 pr-4 text-right">{line.rate}%</td>
+                    <td className="py-2 pr-4 text-right">{formatAmount(line.taxable_sales)}</td>
+                    <td className="py-2 pr-4 text-right">{formatAmount(line.exempt_sales)}</td>
+                    <td className="py-2 pr-4 text-right">{formatAmount(line.tax)}</td>
+                    <td className="py-2 pr-4 text-right">{formatAmount(line.refunds)}</td>
+                    <td className="py-2 text-right">{formatAmount(line.refunded_tax)}</td>
+                  </tr>
+                ))}
+                {report.lines.length === 0 && (
+                  <tr>
+                    <td colSpan={7} className="py-6 text-center text-slate-600 dark:text-white/60">
+                      No taxed sales in this period.
+                    </td>
+                  </tr>
+                )}
+              </tbody>
+              <tfoot>
+                <tr className="font-semibold">
+                  <td className="py-2 pr-4">Total ({report.tax.currency})</td>
+                  <td />
+                  <td className="py-2 pr-4 text-right">{formatAmount(report.taxable_sales)}</td>
+                  <td className="py-2 pr-4 text-right">{formatAmount(report.exempt_sales)}</td>
+                  <td className="py-2 pr-4 text-right">{formatAmount(report.tax)}</td>
+                  <td className="py-2 pr-4 text-right">{formatAmount(report.refunds)}</td>
+                  <td className="py-2 text-right">{formatAmount(report.refunded_tax)}</td>
+                </tr>
+              </tfoot>
+            </table>
+            <p className="mt-3 text-sm text-slate-500 dark:text-white/60">
+              Sales are shown net of tax and discounts. Refunds given in the period are taken off its sales and
+              tax, whenever the sale was made, and shown alongside. Deleted transactions are left out.
+            </p>
+            {report.untaxed_sales.minor !== 0 && (
+              <p className="mt-1 text-sm text-slate-500 dark:text-white/60">
+                {report.untaxed_sales.currency} {formatAmount(report.untaxed_sales)} was sold before tax was
+                recorded per sale and is not included.
+              </p>
+            )}
+          </motion.div>
+        )}
+      </div>
+    </div>
+  );
+}
@@ -1,13 +1,43 @@
 import { motion } from 'framer-motion';
 import { useState, useMemo, useEffect, useRef } from 'react';
-import { MdArrowBack, MdDelete, MdFilterList, MdLocalPrintshop, MdFileDownload, MdClose } from 'react-icons/md';
+import { MdArrowBack, MdDelete, MdFilterList, MdLocalPrintshop, MdFileDownload, MdClose, MdReceiptLong, MdUndo } from 'react-icons/md';
 import { useNavigate } from 'react-router-dom';
 import { invoke } from '@tauri-apps/api/core';
-import { Transaction, Settings } from '../types';
+import { Transaction, TransactionItem, Settings, AppliedDiscount, Money, NewRefund } from '../types';
 import DatePicker from '../components/DatePicker';
 import Receipt from '../components/Receipt';
 import jsPDF from 'jspdf';
 import ThemeToggle from '../components/ThemeToggle';
+import { formatAmount, money, toMajor } from '../money';
+import { taxClassLabels } from '../tax';
+import { discountLabel } from '../discounts';
+import { errorMessage } from '../errors';
+
+// Item name with the options chosen on it, as printed on receipts
+const itemLabel = (item: TransactionItem) => {
+  const extras = [
+    ...item.modifiers.map(m => m.name),
+    ...item.components.filter(c => c.substituted_for !== null).map(c => c.name)
+  ];
+  return extras.length > 0 ? `${item.name} (${extras.join(', ')})` : item.name;
+};
+
+// Every discount on a sale as printed on receipts: the lines' own, then the
+// order discount, then the coupon
+const discountLines = (transaction: Transaction): { label: string; amount: Money }[] => {
+  const describe = (discount: AppliedDiscount) =>
+    `${discount.reason} (${discountLabel(discount.kind, discount.value, minor =>
+      `${transaction.currency} ${formatAmount(money(minor, transaction.currency))}`)})`;
+  return [
+    ...transaction.items.flatMap(item =>
+      item.discount ? [{ label: `${itemLabel(item)}: ${describe(item.discount)}`, amount: item.discount.amount }] : []
+    ),
+    ...(transaction.order_discount
+      ? [{ label: describe(transaction.order_discount), amount: transaction.order_discount.amount }]
+      : []),
+    ...(transaction.coupon ? [{ label: `Coupon ${transaction.coupon.code}`, amount: transaction.coupon.amount }] : [])
+  ];
+};
 
 export default function Transactions() {
   const navigate = useNavigate();
@@ -24,6 +54,11 @@ export default function Transactions() {
     currency: 'PKR',
     receipt_footer: 'Thank you for your business!'
   });
+  const [refundDialog, setRefundDialog] = useState<Transaction | null>(null);
+  // Quantity to give back of each line, by line id
+  const [refundQuantities, setRefundQuantities] = useState<Record<number, string>>({});
+  const [refundReason, setRefundReason] = useState('');
+  const [isRefunding, setIsRefunding] = useState(false);
   const receiptRef = useRef<HTMLDivElement>(null);
   const [isNavigating, setIsNavigating] = useState(false);
 
@@ -88,7 +123,7 @@ export default function Transactions() {
         const existing = stats.get(item.name) || { count: 0, totalAmount: 0 };
         stats.set(item.name, {
           count: existing.count + item.quantity,
-          totalAmount: existing.totalAmount + (item.price * item.quantity)
+          totalAmount: existing.totalAmount + toMajor(item.subtotal)
         });
       });
     });
@@ -99,8 +134,8 @@ export default function Transactions() {
     }));
   }, [transactions]);
 
-  const totalAllTime = transactions.reduce((sum, t) => sum + t.total_amount, 0);
-  const totalFiltered = filteredTransactions.reduce((sum, t) => sum + t.total_amount, 0);
+  const totalAllTime = transactions.reduce((sum, t) => sum + toMajor(t.total_amount), 0);
+  const totalFiltered = filteredTransactions.reduce((sum, t) => sum + toMajor(t.total_amount), 0);
 
   const handleDeleteTransaction = async (id: number) => {
     if (!confirm('Are you sure you want to delete this transaction?')) return;
@@ -110,7 +145,38 @@ export default function Transactions() {
       setTransactions(prev => prev.filter(t => t.id !== id));
     } catch (error) {
       console.error('Error deleting transaction:', error);
-      alert('Error deleting transaction');
+      alert(`Error deleting transaction: ${errorMessage(error)}`);
+    }
+  };
+
+  const openRefund = (transaction: Transaction) => {
+    setRefundQuantities(Object.fromEntries(
+      transaction.items.map(item => [item.id, String(Math.max(item.quantity - item.refunded_quantity, 0))])
+    ));
+    setRefundReason('');
+    setRefundDialog(transaction);
+  };
+
+  const handleRefund = async () => {
+    if (!refundDialog) return;
+    const lines = refundDialog.items
+      .map(item => ({ item_id: item.id, quantity: parseFloat(refundQuantities[item.id] || '0') }))
+      .filter(line => line.quantity > 0);
+    if (lines.length === 0) {
+      alert('Enter a quantity to refund');
+      return;
+    }
+    const refund: NewRefund = { lines, reason: refundReason.trim() || undefined };
+
+    setIsRefunding(true);
+    try {
+      await invoke<Transaction>('refund_sale', { id: refundDialog.id, refund });
+      setRefundDialog(null);
+      await loadData();
+    } catch (error) {
+      alert(`Error refunding sale: ${errorMessage(error)}`);
+    } finally {
+      setIsRefunding(false);
     }
   };
 
@@ -185,11 +251,11 @@ export default function Transactions() {
       // Items
       pdf.setFont('helvetica', 'normal');
       showReceiptDialog.items.forEach(item => {
-        const itemName = item.name.length > 18 ? item.name.substring(0, 18) + '...' : item.name;
-        const itemPrice = item.subtotal || (item.price * item.quantity);
+        const label = itemLabel(item);
+        const itemName = label.length > 18 ? label.substring(0, 18) + '...' : label;
         pdf.text(itemName, margin, yPos);
         pdf.text(`${item.quantity} ${item.unit}`, pageWidth / 2, yPos, { align: 'center' });
-        pdf.text(`${showReceiptDialog.currency} ${itemPrice.toFixed(2)}`, pageWidth - margin, yPos, { align: 'right' });
+        pdf.text(`${showReceiptDialog.currency} ${formatAmount(item.subtotal)}`, pageWidth - margin, yPos, { align: 'right' });
         yPos += 5;
       });
 
@@ -200,11 +266,30 @@ export default function Transactions() {
       }
       yPos += 5;
 
+      // Discounts
+      pdf.setFontSize(9);
+      pdf.setFont('helvetica', 'normal');
+      discountLines(showReceiptDialog).forEach(discount => {
+        const label = discount.label.length > 28 ? discount.label.substring(0, 28) + '...' : discount.label;
+        pdf.text(label, margin, yPos);
+        pdf.text(`-${showReceiptDialog.currency} ${formatAmount(discount.amount)}`, pageWidth - margin, yPos, { align: 'right' });
+        yPos += 5;
+      });
+
+      // Tax
+      if (showReceiptDialog.tax.minor > 0) {
+        pdf.setFontSize(9);
+        pdf.setFont('helvetica', 'normal');
+        pdf.text(showReceiptDialog.prices_include_tax ? 'Tax (included)' : 'Tax', margin, yPos);
+        pdf.text(`${showReceiptDialog.currency} ${formatAmount(showReceiptDialog.tax)}`, pageWidth - margin, yPos, { align: 'right' });
+        yPos += 6;
+      }
+
       // Total
       pdf.setFontSize(11);
       pdf.setFont('helvetica', 'bold');
       pdf.text('Total', margin, yPos);
-      pdf.text(`${showReceiptDialog.currency} ${showReceiptDialog.total_amount.toFixed(2)}`, pageWidth - margin, yPos, { align: 'right' });
+      pdf.text(`${showReceiptDialog.currency} ${formatAmount(showReceiptDialog.total_amount)}`, pageWidth - margin, yPos, { align: 'right' });
       yPos += 8;
 
       // Footer
@@ -279,17 +364,30 @@ export default function Transactions() {
           >
             Transaction History
           </motion.h1>
-          <motion.button
-            whileHover={{ scale: 1.05 }}
-            whileTap={{ scale: 0.95 }}
-            onClick={() => setShowFilters(!showFilters)}
-            className="flex items-center text-slate-900 dark:text-white gap-2 
-              bg-purple-200 dark:bg-purple-600/30 px-4 py-2 rounded-lg
-              transition-colors duration-300"
-          >
-            <MdFilterList className="w-5 h-5" />
-            <span>Filters</span>
-          </motion.button>
+          <div className="flex gap-2">
+            <motion.button
+              whileHover={{ scale: 1.05 }}
+              whileTap={{ scale: 0.95 }}
+              onClick={() => navigate('/tax-report')}
+              className="flex items-center text-slate-900 dark:text-white gap-2 
+                bg-purple-200 dark:bg-purple-600/30 px-4 py-2 rounded-lg
+                transition-colors duration-300"
+            >
+              <MdReceiptLong className="w-5 h-5" />
+              <span>Tax Report</span>
+            </motion.button>
+            <motion.button
+              whileHover={{ scale: 1.05 }}
+              whileTap={{ scale: 0.95 }}
+              onClick={() => setShowFilters(!showFilters)}
+              className="flex items-center text-slate-900 dark:text-white gap-2 
+                bg-purple-200 dark:bg-purple-600/30 px-4 py-2 rounded-lg
+                transition-colors duration-300"
+            >
+              <MdFilterList className="w-5 h-5" />
+              <span>Filters</span>
+            </motion.button>
+          </div>
         </div>
 
         {/* Stats Cards */}
@@ -415,8 +513,25 @@ export default function Transactions() {
                     })}
                   </div>
                   <div className="text-slate-600 dark:text-white/60 text-sm">{transaction.time}</div>
+                  {transaction.refund_of !== null && (
+                    <div className="text-sm font-medium text-amber-600 dark:text-amber-400">
+                      Refund of #{transaction.refund_of}{transaction.refund_reason ? `: ${transaction.refund_reason}` : ''}
+                    </div>
+                  )}
                 </div>
                 <div className="flex gap-2">
+                  {transaction.refund_of === null &&
+                    transaction.items.some(item => item.quantity - item.refunded_quantity > 0) && (
+                    <motion.button
+                      whileHover={{ scale: 1.1 }}
+                      whileTap={{ scale: 0.9 }}
+                      onClick={() => openRefund(transaction)}
+                      title="Refund"
+                      className="text-amber-600 dark:text-amber-400 hover:text-amber-700 dark:hover:text-amber-300 p-2"
+                    >
+                      <MdUndo className="w-5 h-5" />
+                    </motion.button>
+                  )}
                   <motion.button
                     whileHover={{ scale: 1.1 }}
                     whileTap={{ scale: 0.9 }}
@@ -442,20 +557,69 @@ export default function Transactions() {
                     <div className="flex-1">
                       <span className="font-medium">{item.name}</span>
                       <span className="text-slate-600 dark:text-white/60 ml-2">
-                        ({item.quantity} {item.unit} × {transaction.currency} {(item.price || 0).toFixed(2)})
+                        ({item.quantity} {item.unit} × {transaction.currency} {formatAmount(item.price)})
                       </span>
+                      {item.refunded_quantity > 0 && (
+                        <span className="text-amber-600 dark:text-amber-400 ml-2 text-sm">
+                          {item.refunded_quantity} refunded
+                        </span>
+                      )}
+                      {item.modifiers.length > 0 && (
+                        <div className="text-sm text-slate-600 dark:text-white/60">
+                          {item.modifiers.map(m =>
+                            m.price_delta.minor !== 0
+                              ? `${m.name} (${m.price_delta.minor > 0 ? '+' : ''}${formatAmount(m.price_delta)})`
+                              : m.name
+                          ).join(', ')}
+                        </div>
+                      )}
+                      {item.components.length > 0 && (
+                        <div className="text-sm text-slate-600 dark:text-white/60">
+                          {item.components.map(c =>
+                            `${c.quantity} × ${c.name}${c.substituted_for !== null ? ' (swapped)' : ''}`
+                          ).join(', ')}
+                        </div>
+                      )}
                     </div>
                     <div className="text-teal-600 dark:text-teal-400 sm:ml-4">
-                      {transaction.currency} {(item.subtotal || 0).toFixed(2)}
+                      {transaction.currency} {formatAmount(item.subtotal)}
                     </div>
                   </div>
                 ))}
               </div>
 
+              {transaction.discount.minor > 0 && (
+                <div className="border-t border-slate-300 dark:border-white/10 pt-3 mb-3 space-y-1 text-sm text-slate-600 dark:text-white/60">
+                  {discountLines(transaction).map((discount, index) => (
+                    <div key={index} className="flex justify-between">
+                      <span>{discount.label}</span>
+                      <span>-{transaction.currency} {formatAmount(discount.amount)}</span>
+                    </div>
+                  ))}
+                </div>
+              )}
+
+              {transaction.tax_breakdown.some(group => group.tax.minor !== 0) && (
+                <div className="border-t border-slate-300 dark:border-white/10 pt-3 mb-3 space-y-1 text-sm text-slate-600 dark:text-white/60">
+                  {transaction.tax_breakdown.map(group => (
+                    <div key={`${group.tax_class}-${group.rate}`} className="flex justify-between">
+                      <span>
+                        {taxClassLabels[group.tax_class]} {group.rate}% on {transaction.currency} {formatAmount(group.taxable)}
+                      </span>
+                      <span>{transaction.currency} {formatAmount(group.tax)}</span>
+                    </div>
+                  ))}
+                  <div className="flex justify-between font-medium">
+                    <span>{transaction.prices_include_tax ? 'Tax (included)' : 'Tax'}</span>
+                    <span>{transaction.currency} {formatAmount(transaction.tax)}</span>
+                  </div>
+                </div>
+              )}
+
               <div className="border-t border-slate-300 dark:border-white/10 pt-3 flex justify-between items-center">
                 <span className="text-slate-900 dark:text-white font-semibold">Total:</span>
                 <span className="text-xl font-bold text-teal-600 dark:text-teal-400">
-                  {transaction.currency} {transaction.total_amount.toFixed(2)}
+                  {transaction.currency} {formatAmount(transaction.total_amount)}
                 </span>
               </div>
             </motion.div>
@@ -469,6 +633,90 @@ export default function Transactions() {
         </div>
       </div>
 
+      {/* Refund Dialog */}
+      {refundDialog && (
+        <motion.div
+          initial={{ opacity: 0 }}
+          animate={{ opacity: 1 }}
+          exit={{ opacity: 0 }}
+          className="fixed inset-0 bg-black/50 backdrop-blur-sm flex items-center justify-center p-4 z-50 overflow-y-auto"
+          onClick={() => !isRefunding && setRefundDialog(null)}
+        >
+          <motion.div
+            initial={{ scale: 0.8, opacity: 0 }}
+            animate={{ scale: 1, opacity: 1 }}
+            exit={{ scale: 0.8, opacity: 0 }}
+            className="bg-white dark:bg-slate-800 rounded-2xl p-6 max-w-md w-full my-8 transition-colors duration-300"
+            onClick={(e) => e.stopPropagation()}
+          >
+            <div className="flex justify-between items-center mb-2">
+              <h3 className="text-xl font-bold text-slate-900 dark:text-white">Refund #{refundDialog.id}</h3>
+              <motion.button
+                whileHover={{ scale: 1.1 }}
+                whileTap={{ scale: 0.9 }}
+                onClick={() => setRefundDialog(null)}
+                disabled={isRefunding}
+                className="text-slate-600 dark:text-white/60 hover:text-slate-900 dark:hover:text-white p-2"
+              >
+                <MdClose className="w-6 h-6" />
+              </motion.button>
+            </div>
+            <p className="text-sm text-slate-600 dark:text-white/60 mb-4">
+              The refund is recorded today, with its share of each line's discounts and tax. Stock is not changed.
+            </p>
+
+            <div className="space-y-3 mb-4">
+              {refundDialog.items.map(item => {
+                const remaining = Math.max(item.quantity - item.refunded_quantity, 0);
+                return (
+                  <label key={item.id} className="flex items-center justify-between gap-3 text-slate-900 dark:text-white">
+                    <span className="flex-1">
+                      {itemLabel(item)}
+                      <span className="block text-sm text-slate-600 dark:text-white/60">
+                        {remaining} of {item.quantity} {item.unit} left to refund
+                      </span>
+                    </span>
+                    <input
+                      type="number"
+                      min="0"
+                      max={remaining}
+                      step="any"
+                      value={refundQuantities[item.id] ?? ''}
+                      onChange={(e) => setRefundQuantities({ ...refundQuantities, [item.id]: e.target.value })}
+                      disabled={isRefunding || remaining === 0}
+                      className="w-24 px-3 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white
+                        border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+                    />
+                  </label>
+                );
+              })}
+            </div>
+
+            <input
+              type="text"
+              value={refundReason}
+              onChange={(e) => setRefundReason(e.target.value)}
+              placeholder="Reason (optional)"
+              disabled={isRefunding}
+              className="w-full px-4 py-2 mb-4 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white
+                border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none"
+            />
+
+            <motion.button
+              whileHover={{ scale: 1.02 }}
+              whileTap={{ scale: 0.98 }}
+              onClick={handleRefund}
+              disabled={isRefunding}
+              className="w-full py-3 px-4 bg-amber-500 text-white rounded-xl font-semibold 
+                transition-all duration-300 flex items-center justify-center gap-2 disabled:opacity-50"
+            >
+              <MdUndo className="text-xl" />
+              {isRefunding ? 'Refunding...' : 'Refund'}
+            </motion.button>
+          </motion.div>
+        </motion.div>
+      )}
+
       {/* Receipt Dialog */}
       {showReceiptDialog && (
         <motion.div
@@ -502,12 +750,18 @@ export default function Transactions() {
               <Receipt
                 ref={receiptRef}
                 products={showReceiptDialog.items.map(item => ({
-                  name: item.name,
+                  name: itemLabel(item),
                   quantity: item.quantity,
                   unit: item.unit,
-                  price: item.subtotal || (item.price * item.quantity)
+                  price: toMajor(item.subtotal)
+                }))}
+                totalAmount={toMajor(showReceiptDialog.total_amount)}
+                discounts={discountLines(showReceiptDialog).map(discount => ({
+                  label: discount.label,
+                  amount: toMajor(discount.amount)
                 }))}
-                totalAmount={showReceiptDialog.total_amount}
+                taxAmount={toMajor(showReceiptDialog.tax)}
+                pricesIncludeTax={showReceiptDialog.prices_include_tax}
                 date={showReceiptDialog.date}
                 time={showReceiptDialog.time}
                 settings={settings}
@@ -0,0 +1,376 @@
+import { motion } from 'framer-motion';
+import { useState, useEffect } from 'react';
+import { FaPlus, FaTrash } from 'react-icons/fa';
+import { MdArrowBack } from 'react-icons/md';
+import { useNavigate } from 'react-router-dom';
+import { invoke } from '@tauri-apps/api/core';
+import { Ingredient, Product, WasteEntry, WasteReason, WasteReport, NewWasteEntry, Unit } from '../types';
+import ThemeToggle from '../components/ThemeToggle';
+import { errorMessage } from '../errors';
+import { formatAmount } from '../money';
+import { compatibleUnits } from '../units';
+
+const reasonLabels: Record<WasteReason, string> = {
+  spoiled: 'Spoiled',
+  dropped: 'Dropped',
+  comped: 'Comped',
+  staff_meal: 'Staff meal',
+  other: 'Other'
+};
+
+const formatQuantity = (quantity: number) => String(Number(quantity.toFixed(3)));
+
+// Dates are UTC, like the database
+const isoDate = (date: Date) => date.toISOString().slice(0, 10);
+
+const emptyForm = { item: '', quantity: '', unit: '', reason: 'spoiled' as WasteReason, recorded_by: '', note: '' };
+
+export default function Waste() {
+  const navigate = useNavigate();
+  const [isNavigating, setIsNavigating] = useState(false);
+  const [ingredients, setIngredients] = useState<Ingredient[]>([]);
+  const [products, setProducts] = useState<Product[]>([]);
+  const [entries, setEntries] = useState<WasteEntry[]>([]);
+  const [report, setReport] = useState<WasteReport | null>(null);
+  const [range, setRange] = useState(() => {
+    const end = new Date();
+    const start = new Date(end);
+    start.setUTCDate(start.getUTCDate() - 6);
+    return { start: isoDate(start), end: isoDate(end) };
+  });
+  const [units, setUnits] = useState<Unit[]>([]);
+  // `item` is "ingredient:3" or "product:7"; an empty `unit` is the item's own
+  const [form, setForm] = useState(emptyForm);
+  const [isSubmitting, setIsSubmitting] = useState(false);
+
+  const handleBackNavigation = () => {
+    setIsNavigating(true);
+    setTimeout(() => {
+      navigate('/inventory');
+    }, 300);
+  };
+
+  const loadEntries = async () => {
+    try {
+      const [entriesRes, reportRes] = await Promise.all([
+        invoke<WasteEntry[]>('get_waste_entries', { startDate: range.start, endDate: range.end }),
+        invoke<WasteReport>('get_waste_report', { startDate: range.start, endDate: range.end })
+      ]);
+      setEntries(entriesRes);
+      setReport(reportRes);
+    } catch (error) {
+      alert(`Error loading waste: ${errorMessage(error)}`);
+    }
+  };
+
+  useEffect(() => {
+    const loadItems = async () => {
+      try {
+        const [ingredientsRes, productsRes, unitsRes] = await Promise.all([
+          invoke<Ingredient[]>('get_ingredients'),
+          invoke<Product[]>('get_products'),
+          invoke<Unit[]>('get_units')
+        ]);
+        setIngredients(ingredientsRes);
+        setProducts(productsRes);
+        setUnits(unitsRes);
+      } catch (error) {
+        console.error('Error loading items:', error);
+      }
+    };
+
+    loadItems();
+  }, []);
+
+  useEffect(() => {
+    loadEntries();
+  }, [range]);
+
+  const itemUnit = () => {
+    const [kind, id] = form.item.split(':');
+    const items: { id: number; unit: string }[] = kind === 'ingredient' ? ingredients : products;
+    return items.find(item => item.id === Number(id))?.unit ?? '';
+  };
+
+  const handleLogWaste = async () => {
+    setIsSubmitting(true);
+    try {
+      const [kind, id] = form.item.split(':');
+      const entry: NewWasteEntry = {
+        ingredient_id: kind === 'ingredient' ? Number(id) : null,
+        product_id: kind === 'product' ? Number(id) : null,
+        quantity: Number(form.quantity),
+        unit: form.unit || null,
+        reason: form.reason,
+        recorded_by: form.recorded_by,
+        note: form.note || null
+      };
+      await invoke('log_waste', { entry });
+      // Keep who is logging for the next entry
+      setForm({ ...emptyForm, recorded_by: form.recorded_by });
+      await loadEntries();
+    } catch (error) {
+      alert(`Error logging waste: ${errorMessage(error)}`);
+    } finally {
+      setIsSubmitting(false);
+    }
+  };
+
+  const handleDelete = async (entry: WasteEntry) => {
+    if (!confirm(`Remove this entry for ${entry.name}? Its stock is put back.`)) return;
+    try {
+      await invoke('delete_waste_entry', { id: entry.id });
+      await loadEntries();
+    } catch (error) {
+      alert(`Error removing waste entry: ${errorMessage(error)}`);
+    }
+  };
+
+  const inputClass = `w-full px-4 py-2 rounded-lg bg-slate-100 dark:bg-slate-700 text-slate-900 dark:text-white
+    border border-slate-300 dark:border-transparent focus:border-teal-400 focus:outline-none`;
+
+  return (
+    <div className="min-h-screen bg-linear-to-br from-slate-100 via-purple-100 to-slate-100
+      dark:from-slate-900 dark:via-purple-900 dark:to-slate-900 transition-colors duration-300">
+      {/* Theme Toggle */}
+      <div className="absolute safe-top safe-right z-50">
+        <ThemeToggle />
+      </div>
+      <div className="max-w-7xl mx-auto px-4 py-4 sm:py-8">
+        {/* Header */}
+        <div className="flex flex-col sm:flex-row items-center gap-4 sm:gap-0 sm:justify-between mb-6 sm:mb-8">
+          <motion.button
+            whileHover={{ scale: 1.05 }}
+            whileTap={{ scale: 0.95 }}
+            onClick={handleBackNavigation}
+            className="flex items-center text-slate-900 dark:text-white gap-2
+              bg-purple-200 dark:bg-purple-600/30 px-4 py-2 rounded-lg min-w-[120px] justify-center
+              transition-colors duration-300"
+            disabled={isNavigating}
+          >
+            {isNavigating ? (
+              <>
+                <motion.div
+                  animate={{ rotate: 360 }}
+                  transition={{ duration: 1, repeat: Infinity, ease: "linear" }}
+                  className="w-5 h-5 border-2 border-white border-t-transparent rounded-full"
+                />
+                <span>Loading...</span>
+              </>
+            ) : (
+              <>
+                <MdArrowBack className="w-5 h-5" />
+                <span>Inventory</span>
+              </>
+            )}
+          </motion.button>
+          <motion.h1
+            initial={{ opacity: 0, y: -20 }}
+            animate={{ opacity: 1, y: 0 }}
+            className="text-2xl sm:text-3xl font-bold text-slate-900 dark:text-white order-first sm:order-0 transition-colors duration-300"
+          >
+            Waste Log
+          </motion.h1>
+          <div className="hidden sm:block w-24" />
+        </div>
+
+        {/* Log Waste */}
+        <motion.div
+          initial={{ opacity: 0 }}
+          animate={{ opacity: 1 }}
+          className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl p-4 sm:p-6 mb-6 sm:mb-8
+            text-slate-900 dark:text-white transition-colors duration-300"
+        >
+          <h2 className="text-lg sm:text-xl font-semibold mb-4">Log waste</h2>
+          <div className="grid grid-cols-1 sm:grid-cols-12 gap-4">
+            <select
+              value={form.item}
+              onChange={(e) => setForm({ ...form, item: e.target.value, unit: '' })}
+              className={`${inputClass} sm:col-span-3`}
+              disabled={isSubmitting}
+            >
+              <option value="">Choose an item...</option>
+              <optgroup label="Ingredients">
+                {ingredients.map(ingredient => (
+                  <option key={ingredient.id} value={`ingredient:${ingredient.id}`}>
+                    {ingredient.name} ({ingredient.unit})
+                  </option>
+                ))}
+              </optgroup>
+              <optgroup label="Products">
+                {products.map(product => (
+                  <option key={product.id} value={`product:${product.id}`}>
+                    {product.name} ({product.unit})
+                  </option>
+                ))}
+              </optgroup>
+            </select>
+            <input
+              type="number"
+              min="0"
+              step="any"
+              value={form.quantity}
+              onChange={(e) => setForm({ ...form, quantity: e.target.value })}
+              placeholder="Quantity"
+              className={`${inputClass} sm:col-span-2`}
+              disabled={isSubmitting}
+            />
+            <select
+              value={form.unit}
+              onChange={(e) => setForm({ ...form, unit: e.target.value })}
+              className={`${inputClass} sm:col-span-1`}
+              disabled={isSubmitting || form.item === ''}
+            >
+              <option value="">{itemUnit()}</option>
+              {compatibleUnits(units, itemUnit())
+                .filter(unit => unit.code !== itemUnit())
+                .map(unit => (
+                  <option key={unit.code} value={unit.code}>{unit.code}</option>
+                ))}
+            </select>
+            <select
+              value={form.reason}
+              onChange={(e) => setForm({ ...form, reason: e.target.value as WasteReason })}
+              className={`${inputClass} sm:col-span-2`}
+              disabled={isSubmitting}
+            >
+              {(Object.keys(reasonLabels) as WasteReason[]).map(reason => (
+                <option key={reason} value={reason}>{reasonLabels[reason]}</option>
+              ))}
+            </select>
+            <input
+              type="text"
+              value={form.recorded_by}
+              onChange={(e) => setForm({ ...form, recorded_by: e.target.value })}
+              placeholder="Logged by"
+              className={`${inputClass} sm:col-span-2`}
+              disabled={isSubmitting}
+            />
+            <motion.button
+              whileHover={{ scale: 1.02 }}
+              whileTap={{ scale: 0.98 }}
+              onClick={handleLogWaste}
+              className="sm:col-span-2 bg-teal-500 text-white rounded-lg py-2 px-4 flex items-center justify-center gap-2
+                hover:bg-teal-400 transition-colors disabled:opacity-50"
+              disabled={isSubmitting || form.item === '' || form.quantity === ''}
+            >
+              <FaPlus />
+              <span>Log</span>
+            </motion.button>
+            <input
+              type="text"
+              value={form.note}
+              onChange={(e) => setForm({ ...form, note: e.target.value })}
+              placeholder="Note (optional)"
+              className={`${inputClass} sm:col-span-12`}
+              disabled={isSubmitting}
+            />
+          </div>
+        </motion.div>
+
+        {/* Period */}
+        <div className="flex flex-col sm:flex-row items-center gap-4 mb-6 text-slate-900 dark:text-white">
+          <label className="flex items-center gap-2">
+            From
+            <input
+              type="date"
+              value={range.start}
+              onChange={(e) => e.target.value && setRange({ ...range, start: e.target.value })}
+              className={inputClass}
+            />
+          </label>
+          <label className="flex items-center gap-2">
+            to
+            <input
+              type="date"
+              value={range.end}
+              onChange={(e) => e.target.value && setRange({ ...range, end: e.target.value })}
+              className={inputClass}
+            />
+          </label>
+        </div>
+
+        {/* Report */}
+        {report && (
+          <div className="grid grid-cols-1 lg:grid-cols-2 gap-6 mb-6 sm:mb-8">
+            <motion.div
+              initial={{ opacity: 0 }}
+              animate={{ opacity: 1 }}
+              className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl p-4 sm:p-6 text-slate-900 dark:text-white"
+            >
+              <h2 className="text-lg sm:text-xl font-semibold mb-3">
+                By reason · {report.total.currency} {formatAmount(report.total)}
+              </h2>
+              <div className="space-y-2">
+                {report.by_reason.map(total => (
+                  <div key={total.reason} className="flex justify-between">
+                    <span>{reasonLabels[total.reason]} ({total.entries})</span>
+                    <span className="font-medium">{formatAmount(total.value)}</span>
+                  </div>
+                ))}
+                {report.by_reason.length === 0 && (
+                  <p className="text-slate-600 dark:text-white/60">No waste logged in this period.</p>
+                )}
+              </div>
+              {report.uncosted_entries > 0 && (
+                <p className="mt-3 text-sm text-slate-500 dark:text-white/60">
+                  {report.uncosted_entries} entr{report.uncosted_entries === 1 ? 'y has' : 'ies have'} no cost and
+                  {report.uncosted_entries === 1 ? ' is' : ' are'} not included in the value.
+                </p>
+              )}
+            </motion.div>
+            <motion.div
+              initial={{ opacity: 0 }}
+              animate={{ opacity: 1 }}
+              className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl p-4 sm:p-6 text-slate-900 dark:text-white"
+            >
+              <h2 className="text-lg sm:text-xl font-semibold mb-3">By item</h2>
+              <div className="space-y-2">
+                {report.by_item.map(item => (
+                  <div key={`${item.ingredient_id}:${item.product_id}:${item.name}`} className="flex justify-between">
+                    <span>{item.name} · {formatQuantity(item.quantity)} {item.unit}</span>
+                    <span className="font-medium">{formatAmount(item.value)}</span>
+                  </div>
+                ))}
+              </div>
+            </motion.div>
+          </div>
+        )}
+
+        {/* Entries */}
+        <motion.div
+          initial={{ opacity: 0 }}
+          animate={{ opacity: 1 }}
+          className="bg-white/70 dark:bg-white/10 backdrop-blur-sm rounded-xl overflow-hidden transition-colors duration-300"
+        >
+          {entries.map(entry => (
+            <div
+              key={entry.id}
+              className="flex justify-between items-center gap-4 p-4 border-b border-slate-300 dark:border-white/10
+                text-slate-900 dark:text-white hover:bg-slate-100 dark:hover:bg-white/5 transition-colors"
+            >
+              <div>
+                <div className="font-medium">
+                  {formatQuantity(entry.quantity)} {entry.unit} {entry.name} · {reasonLabels[entry.reason]}
+                </div>
+                <div className="text-sm text-slate-500 dark:text-white/60">
+                  {new Date(`${entry.created_at.replace(' ', 'T')}Z`).toLocaleString()} · {entry.recorded_by}
+                  {entry.note && ` · ${entry.note}`}
+                </div>
+              </div>
+              <div className="flex items-center gap-4">
+                <span>{entry.cost ? `${entry.cost.currency} ${formatAmount(entry.cost)}` : '—'}</span>
+                <button onClick={() => handleDelete(entry)} className="text-red-400 hover:text-red-300" title="Remove entry">
+                  <FaTrash />
+                </button>
+              </div>
+            </div>
+          ))}
+          {entries.length === 0 && (
+            <div className="p-6 sm:p-8 text-center text-slate-600 dark:text-white/60">No waste logged in this period.</div>
+          )}
+        </motion.div>
+      </div>
+    </div>
+  );
+}
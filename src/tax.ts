@@ -0,0 +1,43 @@
+import { Settings, TaxClass } from './types';
+
+// Mirrors src-tauri/src/tax.rs. Rates are in basis points, hundredths of a
+// percent, so 17.5% is 1750.
+
+export const taxClassLabels: Record<TaxClass, string> = {
+  standard: 'Standard rate',
+  reduced: 'Reduced rate',
+  exempt: 'Exempt'
+};
+
+// A percentage such as "17.5" as basis points, or null when it is empty or
+// not a rate the backend would accept
+export const parseRate = (value: string | undefined | null): number | null => {
+  const text = (value ?? '').trim();
+  const match = /^(\d{0,3})(?:\.(\d{0,2}))?$/.exec(text);
+  if (!match || (match[1] === '' && !match[2])) return null;
+  const rate = Number(match[1] || 0) * 100 + Number((match[2] ?? '').padEnd(2, '0'));
+  return rate <= 10000 ? rate : null;
+};
+
+export interface TaxRates {
+  standard: number;
+  reduced: number;
+  pricesIncludeTax: boolean;
+}
+
+// The rates sales are taxed at under `settings`; a rate left empty is zero
+export const taxRates = (settings: Settings): TaxRates => ({
+  standard: parseRate(settings.tax_rate) ?? 0,
+  reduced: parseRate(settings.reduced_tax_rate) ?? 0,
+  pricesIncludeTax: settings.prices_include_tax !== false
+});
+
+export const rateFor = (rates: TaxRates, taxClass: TaxClass): number =>
+  taxClass === 'standard' ? rates.standard : taxClass === 'reduced' ? rates.reduced : 0;
+
+// Tax on a line that came to `amount` minor units, rounded half away from
+// zero. An inclusive amount already contains its tax.
+export const lineTax = (amount: number, rate: number, pricesIncludeTax: boolean): number => {
+  const exact = (amount * rate) / (pricesIncludeTax ? 10000 + rate : 10000);
+  return Math.sign(exact) * Math.round(Math.abs(exact));
+};
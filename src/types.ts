@@ -1,14 +1,66 @@
+// Integer minor units; `amount` is a display string filled in by the backend
+export interface Money {
+  minor: number;
+  currency: string;
+  amount?: string;
+}
+
+export type Dimension = 'mass' | 'volume' | 'count';
+
+// A unit from the backend registry. `factor` is its size in grams,
+// millilitres or single items.
+export interface Unit {
+  code: string;
+  name: string;
+  dimension: Dimension;
+  factor: number;
+}
+
+// Which of the tax rates in settings a product is taxed at
+export type TaxClass = 'standard' | 'reduced' | 'exempt';
+
 export interface Product {
   id: number;
   name: string;
-  price: number;
+  price: Money;
   unit: string;
+  category_id?: number | null;
+  tax_class: TaxClass;
+  archived_at?: string | null;
 }
 
 export interface NewProduct {
   name: string;
-  price: number;
+  price: Money;
   unit: string;
+  category_id?: number | null;
+  tax_class?: TaxClass;
+}
+
+export interface Category {
+  id: number;
+  name: string;
+  sort_order: number;
+  color: string | null;
+  parent_id: number | null;
+}
+
+export interface NewCategory {
+  name: string;
+  sort_order?: number | null;
+  color?: string | null;
+  parent_id?: number | null;
+}
+
+// `category` is null for the group of uncategorized products
+export interface CategoryGroup {
+  category: Category | null;
+  products: Product[];
+}
+
+export interface PriceChange {
+  price: Money;
+  effective_from: string;
 }
 
 export interface Settings {
@@ -17,74 +69,581 @@ export interface Settings {
   phone?: string;
   email?: string;
   tax_rate?: string;
+  reduced_tax_rate?: string;
+  // Prices include tax unless this is false
+  prices_include_tax?: boolean | null;
   currency?: string;
   opening_time?: string;
   closing_time?: string;
   receipt_footer?: string;
   logo?: string;
+  backup_directory?: string | null;
+  backup_keep_daily?: number | null;
+  backup_keep_weekly?: number | null;
 }
 
+// `quantity` is in `unit`, which may be any unit of the same kind as the
+// product's; `price` stays per the product's unit
 export interface SaleItem {
+  product_id?: number | null;
   name: string;
-  price: number;
+  price: Money;
   quantity: number;
   unit: string;
+  modifier_ids?: number[];
+  substitutions?: Substitution[];
+  discount?: NewDiscount | null;
 }
 
+// `discount` comes off the order after line discounts, then the coupon off what is left
 export interface Sale {
   products: SaleItem[];
-  total_amount: number;
+  total_amount: Money;
+  discount?: NewDiscount | null;
+  coupon_code?: string | null;
+}
+
+// A percentage off in basis points (1250 is 12.5%), or a fixed amount off in minor units
+export type DiscountKind = 'percentage' | 'fixed';
+
+export interface DiscountReason {
+  id: number;
+  name: string;
+}
+
+// A discount given at the till; a fixed `value` is in the sale currency
+export interface NewDiscount {
+  kind: DiscountKind;
+  value: number;
+  reason_id: number;
+}
+
+// A discount as it was given, with its reason as named then
+export interface AppliedDiscount {
+  kind: DiscountKind;
+  value: number;
+  reason: string;
+  amount: Money;
+}
+
+// `coupon_id` is null once the coupon is deleted
+export interface AppliedCoupon {
+  coupon_id: number | null;
+  code: string;
+  amount: Money;
+}
+
+// A fixed `value` and `min_spend` are in `currency`; dates are inclusive and
+// `usage_limit` of null means no limit
+export interface Coupon {
+  id: number;
+  code: string;
+  description: string | null;
+  kind: DiscountKind;
+  value: number;
   currency: string;
+  min_spend: Money | null;
+  valid_from: string | null;
+  valid_until: string | null;
+  usage_limit: number | null;
+  times_used: number;
+  active: boolean;
+  created_at: string;
+}
+
+export interface NewCoupon {
+  code: string;
+  description?: string | null;
+  kind: DiscountKind;
+  value: number;
+  currency: string;
+  min_spend?: Money | null;
+  valid_from?: string | null;
+  valid_until?: string | null;
+  usage_limit?: number | null;
+  active?: boolean;
 }
 
 export interface TransactionItem {
+  id: number;
+  product_id: number | null;
   name: string;
-  price: number;
+  price: Money;
   quantity: number;
   unit: string;
-  subtotal: number;
+  modifiers: LineModifier[];
+  components: LineComponent[];
+  // Before the line's own `discount` and its `order_discount` share of the order's discounts
+  subtotal: Money;
+  discount: AppliedDiscount | null;
+  order_discount: Money;
+  // Null on lines sold before tax was worked out; `tax_rate` is a percentage
+  tax_class: TaxClass | null;
+  tax_rate: number | null;
+  tax: Money;
+  // How much of the line later refunds have given back
+  refunded_quantity: number;
+}
+
+// Tax on the lines of one class at one rate; `taxable` is their value without tax
+export interface TaxBreakdown {
+  tax_class: TaxClass;
+  rate: number;
+  taxable: Money;
+  tax: Money;
 }
 
+// `tax` is part of `total_amount` either way: within the prices when
+// `prices_include_tax`, added on top of them otherwise
 export interface Transaction {
   id: number;
+  // Set on refunds, whose amounts and quantities are negative
+  refund_of: number | null;
+  refund_reason: string | null;
   items: TransactionItem[];
-  total_amount: number;
+  total_amount: Money;
+  // Everything taken off the lines and the order, coupon included
+  discount: Money;
+  order_discount: AppliedDiscount | null;
+  coupon: AppliedCoupon | null;
+  tax: Money;
+  prices_include_tax: boolean;
+  tax_breakdown: TaxBreakdown[];
   currency: string;
   date: string;
   time: string;
 }
 
+// How much of a line to give back, in the unit it was sold in
+export interface RefundLine {
+  item_id: number;
+  quantity: number;
+}
+
+// No lines gives back everything not refunded yet
+export interface NewRefund {
+  lines: RefundLine[];
+  reason?: string;
+}
+
 export interface DailyRevenue {
   date: string;
-  revenue: number;
+  revenue: Money;
   orders: number;
 }
 
 export interface TopProduct {
+  product_id: number | null;
   name: string;
   sales: number;
-  revenue: number;
+  revenue: Money;
 }
 
 export interface ProductDistribution {
+  product_id: number | null;
   name: string;
   value: number;
 }
 
+export interface ModifierUsage {
+  modifier_id: number | null;
+  group: string;
+  name: string;
+  sales: number;
+  revenue: Money;
+}
+
+export interface CategoryRevenue {
+  category_id: number | null;
+  name: string;
+  sales: number;
+  revenue: Money;
+}
+
 export interface AnalyticsSummary {
   total_orders: number;
-  total_revenue: number;
-  average_order_value: number;
+  total_revenue: Money;
+  average_order_value: Money;
 }
 
 export interface AnalyticsData {
   daily_revenue: DailyRevenue[];
   top_products: TopProduct[];
   product_distribution: ProductDistribution[];
+  category_revenue: CategoryRevenue[];
+  top_modifiers: ModifierUsage[];
   summary: AnalyticsSummary;
 }
 
+export interface BackupSnapshot {
+  file_name: string;
+  created_at: string;
+  size: number;
+}
+
+// Payload of the `backup-failed` event emitted when automatic backups stop working
+export interface BackupFailed {
+  message: string;
+}
+
+// Payload of the `backup-progress` event emitted during export, import and restore
+export interface BackupProgress {
+  operation: 'export' | 'import' | 'restore';
+  stage: 'snapshot' | 'writing' | 'reading' | 'verifying' | 'replacing' | 'done';
+  bytes_done: number;
+  bytes_total: number;
+}
+
+// Shape of every error returned by a Tauri command
+export interface AppError {
+  code: 'database' | 'validation' | 'not_found' | 'io' | 'email' | 'permission' | 'conflict';
+  message: string;
+  details: Record<string, unknown> | null;
+}
+
+export type Selection = 'single' | 'multiple';
+
+export interface Modifier {
+  id: number;
+  name: string;
+  price_delta: Money;
+}
+
+// `max_choices` of null means no limit
+export interface ModifierGroup {
+  id: number;
+  name: string;
+  selection: Selection;
+  min_choices: number;
+  max_choices: number | null;
+  modifiers: Modifier[];
+}
+
+// Options with an `id` are existing ones being edited
+export interface NewModifier {
+  id?: number | null;
+  name: string;
+  price_delta: Money;
+}
+
+export interface NewModifierGroup {
+  name: string;
+  selection: Selection;
+  min_choices?: number | null;
+  max_choices?: number | null;
+  modifiers: NewModifier[];
+}
+
+// An option as it was chosen on a sale line
+export interface LineModifier {
+  modifier_id: number | null;
+  group: string;
+  name: string;
+  price_delta: Money;
+}
+
+export interface BundleProduct {
+  product_id: number;
+  name: string;
+}
+
+// One slot of a bundle; `quantity` is per bundle sold
+export interface BundleComponent {
+  id: number;
+  product_id: number;
+  name: string;
+  quantity: number;
+  substitutes: BundleProduct[];
+}
+
+export interface NewBundleComponent {
+  product_id: number;
+  quantity: number;
+  substitutes: number[];
+}
+
+export interface Substitution {
+  component_id: number;
+  product_id: number;
+}
+
+// A product served as part of a bundle line, for the whole line quantity
+export interface LineComponent {
+  product_id: number | null;
+  name: string;
+  quantity: number;
+  substituted_for: number | null;
+}
+
+// The same product with different options is a separate line, told apart by `line_key`
 export interface CartItem extends Product {
+  // In `sale_unit`, which starts as the product's unit and can be changed to
+  // another of the same kind, e.g. grams of something sold by the kilogram
+  quantity: number;
+  sale_unit: string;
+  line_key: string;
+  modifiers: LineModifier[];
+  substitutions: Substitution[];
+  components: LineComponent[];
+  discount?: NewDiscount | null;
+}
+
+
+// A stocked item; `quantity` is the current level in `unit`. Stock at or
+// below `reorder_point` is low, and `par_level` is what a delivery restores.
+export interface Ingredient {
+  id: number;
+  name: string;
+  unit: string;
+  quantity: number;
+  reorder_point: number | null;
+  par_level: number | null;
+  // What one unit cost when last received
+  cost_per_unit: Money | null;
+}
+
+export interface NewIngredient {
+  name: string;
+  unit: string;
+  reorder_point?: number | null;
+  par_level?: number | null;
+}
+
+export interface LowStockItem {
+  ingredient: Ingredient;
+  daily_usage: number;
+  suggested_quantity: number;
+}
+
+// Payload of the `stock-low` event emitted when a sale takes ingredients to
+// or below their reorder point
+export interface StockLowAlert {
+  sale_id: number;
+  ingredients: Ingredient[];
+}
+
+// How much of an ingredient one unit of a product uses
+export interface RecipeItem {
+  ingredient_id: number;
+  name: string;
+  unit: string;
   quantity: number;
 }
 
+export interface NewRecipeItem {
+  ingredient_id: number;
+  quantity: number;
+  // Defaults to the ingredient's unit
+  unit?: string | null;
+}
+
+export type MovementKind = 'sale' | 'receive' | 'adjust' | 'waste';
+
+export interface StockMovement {
+  id: number;
+  ingredient_id: number;
+  kind: MovementKind;
+  change: number;
+  quantity_after: number;
+  unit_cost: Money | null;
+  sale_id: number | null;
+  purchase_order_id: number | null;
+  stock_count_id: number | null;
+  waste_entry_id: number | null;
+  note: string | null;
+  created_at: string;
+}
+
+export interface Supplier {
+  id: number;
+  name: string;
+  contact_name: string | null;
+  phone: string | null;
+  email: string | null;
+  notes: string | null;
+}
+
+export interface NewSupplier {
+  name: string;
+  contact_name?: string | null;
+  phone?: string | null;
+  email?: string | null;
+  notes?: string | null;
+}
+
+export type PurchaseOrderStatus = 'draft' | 'sent' | 'partially_received' | 'received';
+
+// A line orders either an ingredient or a product with a recipe. `unit_cost`
+// is per unit; `received_cost` is the total paid for what has arrived.
+export interface PurchaseOrderLine {
+  id: number;
+  ingredient_id: number | null;
+  product_id: number | null;
+  name: string;
+  unit: string;
+  quantity: number;
+  unit_cost: Money;
+  received_quantity: number;
+  received_cost: Money;
+}
+
+export interface PurchaseOrder {
+  id: number;
+  supplier_id: number;
+  supplier_name: string;
+  status: PurchaseOrderStatus;
+  currency: string;
+  notes: string | null;
+  lines: PurchaseOrderLine[];
+  created_at: string;
+  sent_at: string | null;
+  received_at: string | null;
+}
+
+export interface NewPurchaseOrderLine {
+  ingredient_id?: number | null;
+  product_id?: number | null;
+  quantity: number;
+  unit_cost: Money;
+}
+
+export interface NewPurchaseOrder {
+  supplier_id: number;
+  notes?: string | null;
+  lines: NewPurchaseOrderLine[];
+}
+
+// What arrived for one line; `unit_cost` overrides the ordered cost
+export interface LineReceipt {
+  line_id: number;
+  quantity: number;
+  unit_cost?: Money | null;
+}
+
+export type StockCountStatus = 'open' | 'closed' | 'applied';
+
+export interface StockCountLine {
+  ingredient_id: number;
+  name: string;
+  unit: string;
+  counted_quantity: number;
+}
+
+export interface StockCount {
+  id: number;
+  status: StockCountStatus;
+  note: string | null;
+  lines: StockCountLine[];
+  opened_at: string;
+  closed_at: string | null;
+  applied_at: string | null;
+}
+
+// A null count takes the ingredient back out of the stock take
+export interface CountEntry {
+  ingredient_id: number;
+  counted: number | null;
+}
+
+// Expected is opening + received - used - wasted + adjusted since the last
+// applied count; variance is counted - expected, valued at cost when closed
+export interface VarianceLine {
+  ingredient_id: number;
+  name: string;
+  unit: string;
+  opening: number;
+  received: number;
+  used: number;
+  wasted: number;
+  adjusted: number;
+  expected: number;
+  counted: number;
+  variance: number;
+  unit_cost: Money | null;
+  variance_value: Money | null;
+}
+
+export interface VarianceReport {
+  count: StockCount;
+  lines: VarianceLine[];
+  // One total per currency costs were recorded in
+  total_variance_value: Money[];
+}
+
+export type WasteReason = 'spoiled' | 'dropped' | 'comped' | 'staff_meal' | 'other';
+
+// `cost` is the value of the ingredients lost, when they had a cost
+export interface WasteEntry {
+  id: number;
+  ingredient_id: number | null;
+  product_id: number | null;
+  name: string;
+  unit: string;
+  quantity: number;
+  reason: WasteReason;
+  recorded_by: string;
+  note: string | null;
+  cost: Money | null;
+  created_at: string;
+}
+
+export interface NewWasteEntry {
+  ingredient_id?: number | null;
+  product_id?: number | null;
+  quantity: number;
+  // Defaults to the item's unit
+  unit?: string | null;
+  reason: WasteReason;
+  recorded_by: string;
+  note?: string | null;
+}
+
+export interface WasteReasonTotal {
+  reason: WasteReason;
+  entries: number;
+  value: Money;
+}
+
+export interface WasteItemTotal {
+  ingredient_id: number | null;
+  product_id: number | null;
+  name: string;
+  unit: string;
+  quantity: number;
+  value: Money;
+}
+
+// Values are in the report currency; uncosted entries add nothing to them
+export interface WasteReport {
+  start_date: string;
+  end_date: string;
+  total: Money;
+  uncosted_entries: number;
+  by_reason: WasteReasonTotal[];
+  by_item: WasteItemTotal[];
+}
+
+// Sales of one class at one rate, net of tax; `rate` is a percentage
+export interface TaxSummaryLine {
+  tax_class: TaxClass;
+  rate: number;
+  taxable_sales: Money;
+  exempt_sales: Money;
+  tax: Money;
+  // Given back in the period, net of tax; the sales and tax above are what was left after them
+  refunds: Money;
+  refunded_tax: Money;
+}
+
+// `untaxed_sales` were sold before tax was recorded and are in no line
+export interface TaxReport {
+  start_date: string;
+  end_date: string;
+  lines: TaxSummaryLine[];
+  taxable_sales: Money;
+  exempt_sales: Money;
+  tax: Money;
+  refunds: Money;
+  refunded_tax: Money;
+  untaxed_sales: Money;
+}
@@ -0,0 +1,26 @@
+import { Unit } from './types';
+
+// Units come from the `get_units` command, which lists the registry in
+// src-tauri/src/units.rs
+
+export const findUnit = (units: Unit[], code: string): Unit | undefined =>
+  units.find(unit => unit.code === code);
+
+// Units the quantity of something measured in `code` can be entered in
+export const compatibleUnits = (units: Unit[], code: string): Unit[] => {
+  const own = findUnit(units, code);
+  return own ? units.filter(unit => unit.dimension === own.dimension) : [];
+};
+
+// `quantity` of `from` in `to`, left as it is when either is unknown or
+// they measure different things, like the backend
+export const convertQuantity = (units: Unit[], quantity: number, from: string, to: string): number => {
+  const source = findUnit(units, from);
+  const target = findUnit(units, to);
+  if (!source || !target || source.dimension !== target.dimension) return quantity;
+  return (quantity * source.factor) / target.factor;
+};
+
+// Whether quantities in `code` are counted one at a time
+export const isCounted = (units: Unit[], code: string): boolean =>
+  findUnit(units, code)?.dimension === 'count';